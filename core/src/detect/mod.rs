@@ -0,0 +1,143 @@
+//! Recognizes well-known protocols in raw TCP payloads.
+//!
+//! The test server uses the parsers to answer whatever it is sent; the app uses [`detect`]
+//! to suggest what received data is.
+
+mod parsers;
+
+pub use parsers::*;
+use serde::Serialize;
+
+/// Parsed values by name, in the order the parser found them
+pub type Fields = serde_json::Map<String, serde_json::Value>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Kind {
+    ModbusTcp,
+    Http,
+    Redis,
+    Ftp,
+    Smtp,
+    WebSocket,
+    Telnet,
+    CustomHeader,
+}
+
+impl Kind {
+    /// What [`detect`] tries, most specific first. The custom header is left out: its
+    /// magic check matches too much data to be worth suggesting.
+    pub const DETECTED: [Kind; 7] = [
+        Kind::ModbusTcp,
+        Kind::WebSocket,
+        Kind::Http,
+        Kind::Redis,
+        Kind::Ftp,
+        Kind::Smtp,
+        Kind::Telnet,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::ModbusTcp => "Modbus TCP",
+            Kind::Http => "HTTP",
+            Kind::Redis => "Redis RESP",
+            Kind::Ftp => "FTP",
+            Kind::Smtp => "SMTP",
+            Kind::WebSocket => "WebSocket",
+            Kind::Telnet => "Telnet",
+            Kind::CustomHeader => "Custom Header",
+        }
+    }
+}
+
+/// What a payload looks like
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Detection {
+    pub kind: Kind,
+    /// e.g. "Modbus TCP"
+    pub protocol: String,
+    /// What the message does, e.g. "Function Code 0x03 - Read Holding Registers"
+    pub operation: String,
+    pub fields: Fields,
+}
+
+/// The first protocol in [`Kind::DETECTED`] the data parses as
+pub fn detect(data: &[u8]) -> Option<Detection> {
+    Kind::DETECTED.iter().find_map(|&kind| detect_as(data, kind))
+}
+
+/// Parse the data as one protocol, request or reply
+pub fn detect_as(data: &[u8], kind: Kind) -> Option<Detection> {
+    let (operation, fields) = match kind {
+        Kind::ModbusTcp => {
+            let result = parse_modbus_tcp(data)?;
+            let name = result.fields["function_name"].as_str().unwrap_or_default().to_string();
+            (format!("Function Code 0x{:02X} - {}", result.function_code, name), result.fields)
+        }
+        Kind::Http => match parse_http(data) {
+            Some(result) => (format!("{} {}", result.method, result.path), result.fields),
+            None => {
+                let result = parse_http_response(data)?;
+                (format!("{} {}", result.status, result.reason).trim_end().to_string(), result.fields)
+            }
+        },
+        Kind::Redis => match parse_redis_resp(data) {
+            Some(result) => (format!("Command: {}", result.command), result.fields),
+            None => {
+                let result = parse_resp_reply(data)?;
+                (format!("Reply: {}", result.kind), result.fields)
+            }
+        },
+        Kind::Ftp => match parse_ftp(data) {
+            Some(result) => (format!("Command: {}", result.command), result.fields),
+            None => reply(data, is_ftp_reply)?,
+        },
+        Kind::Smtp => match parse_smtp(data) {
+            Some(result) => (format!("Mail from: {}", result.from), result.fields),
+            None => reply(data, is_smtp_reply)?,
+        },
+        Kind::WebSocket => (
+            "Upgrade handshake".to_string(),
+            parse_websocket(data)?.fields,
+        ),
+        Kind::Telnet => ("Option negotiation".to_string(), parse_telnet(data)?.fields),
+        Kind::CustomHeader => {
+            let result = parse_custom_header(data)?;
+            (format!("Message type 0x{:02X}", result.message_type), result.fields)
+        }
+    };
+    Some(Detection {
+        kind,
+        protocol: kind.name().to_string(),
+        operation,
+        fields,
+    })
+}
+
+/// A numbered reply that `belongs` says is of the protocol
+fn reply(data: &[u8], belongs: fn(&ReplyCodeResult) -> bool) -> Option<(String, Fields)> {
+    let result = parse_reply_code(data).filter(belongs)?;
+    let first = result.lines.first().map(String::as_str).unwrap_or_default();
+    Some((format!("Reply {} {}", result.code, first).trim_end().to_string(), result.fields))
+}
+
+/// Codes only FTP uses, or a banner that says so
+fn is_ftp_reply(reply: &ReplyCodeResult) -> bool {
+    matches!(reply.code, 150 | 200 | 211..=215 | 225..=230 | 257 | 331 | 332 | 350 | 425 | 426 | 530 | 532)
+        || reply.lines.iter().any(|line| line.contains("FTP"))
+}
+
+/// Extensions listed in a reply to `EHLO`
+const EHLO_KEYWORDS: [&str; 8] = ["SIZE", "8BITMIME", "PIPELINING", "STARTTLS", "AUTH", "HELP", "ENHANCEDSTATUSCODES", "SMTPUTF8"];
+
+/// Codes only SMTP uses, enhanced status codes, or a banner that says so
+fn is_smtp_reply(reply: &ReplyCodeResult) -> bool {
+    let enhanced = |line: &String| {
+        let code = line.split(' ').next().unwrap_or_default();
+        code.split('.').count() == 3 && code.split('.').all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+    };
+    matches!(reply.code, 251 | 252 | 354 | 421 | 454 | 552 | 554)
+        || reply.lines.iter().any(|line| line.contains("SMTP") || enhanced(line))
+        || (reply.code == 250 && reply.lines.iter().skip(1).any(|line| EHLO_KEYWORDS.contains(&line.split(' ').next().unwrap_or_default())))
+}
@@ -1,3 +1,4 @@
+use super::Fields;
 use std::collections::HashMap;
 
 // 协议解析结果
@@ -10,7 +11,7 @@ pub struct ModbusResult {
     pub function_code: u8,
     pub start_address: u16,
     pub register_count: u16,
-    pub fields: HashMap<String, serde_json::Value>,
+    pub fields: Fields,
 }
 
 pub struct HttpResult {
@@ -20,27 +21,27 @@ pub struct HttpResult {
     pub host: String,
     pub content_length: Option<usize>,
     pub body: Option<String>,
-    pub fields: HashMap<String, serde_json::Value>,
+    pub fields: Fields,
 }
 
 pub struct RedisResult {
     pub command: String,
     pub key: String,
     pub value: String,
-    pub fields: HashMap<String, serde_json::Value>,
+    pub fields: Fields,
 }
 
 pub struct FtpResult {
     pub command: String,
     pub username: String,
-    pub fields: HashMap<String, serde_json::Value>,
+    pub fields: Fields,
 }
 
 pub struct SmtpResult {
     pub from: String,
     pub to: String,
     pub subject: String,
-    pub fields: HashMap<String, serde_json::Value>,
+    pub fields: Fields,
 }
 
 pub struct WebSocketResult {
@@ -49,12 +50,12 @@ pub struct WebSocketResult {
     pub host: String,
     pub key: String,
     pub version: String,
-    pub fields: HashMap<String, serde_json::Value>,
+    pub fields: Fields,
 }
 
 pub struct TelnetResult {
     pub command: String,
-    pub fields: HashMap<String, serde_json::Value>,
+    pub fields: Fields,
 }
 
 pub struct CustomHeaderResult {
@@ -63,7 +64,7 @@ pub struct CustomHeaderResult {
     pub message_type: u8,
     pub sequence: u32,
     pub payload_length: u32,
-    pub fields: HashMap<String, serde_json::Value>,
+    pub fields: Fields,
 }
 
 // Modbus TCP 解析器
@@ -81,6 +82,10 @@ pub fn parse_modbus_tcp(data: &[u8]) -> Option<ModbusResult> {
 
     let transaction_id = u16::from_be_bytes([data[0], data[1]]);
     let length = u16::from_be_bytes([data[4], data[5]]);
+    // 长度字段包含单元ID和PDU，报文不能比它声明的短
+    if length < 2 || data.len() < 6 + length as usize {
+        return None;
+    }
     let unit_id = data[6];
     let function_code = data[7];
 
@@ -93,15 +98,26 @@ pub fn parse_modbus_tcp(data: &[u8]) -> Option<ModbusResult> {
         (0, 0)
     };
 
-    let mut fields = HashMap::new();
+    let mut fields = Fields::new();
     fields.insert("transaction_id".to_string(), serde_json::json!(transaction_id));
     fields.insert("protocol_id".to_string(), serde_json::json!(protocol_id));
     fields.insert("length".to_string(), serde_json::json!(length));
     fields.insert("unit_id".to_string(), serde_json::json!(format!("0x{:02X}", unit_id)));
     fields.insert("function_code".to_string(), serde_json::json!(format!("0x{:02X}", function_code)));
-    fields.insert("function_name".to_string(), serde_json::json!(get_modbus_fn_name(function_code)));
-    fields.insert("start_address".to_string(), serde_json::json!(format!("0x{:04X}", start_address)));
-    fields.insert("register_count".to_string(), serde_json::json!(register_count));
+    fields.insert("function_name".to_string(), serde_json::json!(get_modbus_fn_name(function_code & 0x7F)));
+    let pdu = &data[8..6 + length as usize];
+    if function_code & 0x80 != 0 && pdu.len() == 1 {
+        // 异常响应: 功能码最高位置1，后跟异常码
+        fields.insert("exception_code".to_string(), serde_json::json!(format!("0x{:02X}", pdu[0])));
+    } else if (0x01..=0x04).contains(&function_code) && length != 6 && pdu.len() > 1 && pdu[0] as usize == pdu.len() - 1 {
+        // 读响应: 字节数 + 数据
+        fields.insert("byte_count".to_string(), serde_json::json!(pdu[0]));
+        let values: Vec<String> = pdu[1..].iter().map(|b| format!("{:02X}", b)).collect();
+        fields.insert("data".to_string(), serde_json::json!(values.join(" ")));
+    } else {
+        fields.insert("start_address".to_string(), serde_json::json!(format!("0x{:04X}", start_address)));
+        fields.insert("register_count".to_string(), serde_json::json!(register_count));
+    }
 
     Some(ModbusResult {
         transaction_id,
@@ -185,7 +201,7 @@ pub fn parse_http(data: &[u8]) -> Option<HttpResult> {
         }
     }
 
-    let mut fields = HashMap::new();
+    let mut fields = Fields::new();
     fields.insert("method".to_string(), serde_json::json!(method.clone()));
     fields.insert("path".to_string(), serde_json::json!(path.clone()));
     fields.insert("version".to_string(), serde_json::json!(version));
@@ -268,7 +284,7 @@ pub fn parse_redis_resp(data: &[u8]) -> Option<RedisResult> {
         }
     }
 
-    let mut fields = HashMap::new();
+    let mut fields = Fields::new();
     fields.insert("command".to_string(), serde_json::json!(command.clone()));
     fields.insert("key".to_string(), serde_json::json!(key.clone()));
     fields.insert("value".to_string(), serde_json::json!(value.clone()));
@@ -282,6 +298,8 @@ pub fn parse_redis_resp(data: &[u8]) -> Option<RedisResult> {
     })
 }
 
+const FTP_COMMANDS: [&str; 6] = ["USER", "PASS", "LIST", "RETR", "STOR", "QUIT"];
+
 // FTP 解析器
 pub fn parse_ftp(data: &[u8]) -> Option<FtpResult> {
     let text = String::from_utf8(data.to_vec()).ok()?;
@@ -291,40 +309,23 @@ pub fn parse_ftp(data: &[u8]) -> Option<FtpResult> {
         return None;
     }
 
-    let first_line = lines[0].trim().to_uppercase();
-    let mut command = "UNKNOWN".to_string();
-    let mut username = "anonymous".to_string();
-
-    if first_line.starts_with("USER") {
-        command = "USER".to_string();
-        let parts: Vec<&str> = first_line.split_whitespace().collect();
-        if parts.len() > 1 {
-            username = parts[1].to_string();
-        }
-    } else if first_line.starts_with("PASS") {
-        command = "PASS".to_string();
-    } else if first_line.starts_with("LIST") {
-        command = "LIST".to_string();
-    } else if first_line.starts_with("RETR") {
-        command = "RETR".to_string();
-    } else if first_line.starts_with("STOR") {
-        command = "STOR".to_string();
-    } else if first_line.starts_with("QUIT") {
-        command = "QUIT".to_string();
-    } else {
-        // 检查是否是FTP相关命令
-        for line in &lines {
-            let upper = line.trim().to_uppercase();
-            if upper.starts_with("USER") || upper.starts_with("PASS") || upper.starts_with("LIST") {
-                return parse_ftp(data); // 递归调用处理
-            }
-        }
-    }
-
-    let mut fields = HashMap::new();
+    // 第一条FTP命令，通常是USER
+    let first_line = lines
+        .iter()
+        .map(|line| line.trim().to_uppercase())
+        .find(|line| FTP_COMMANDS.iter().any(|c| line.split_whitespace().next() == Some(c)))?;
+    let command = first_line.split_whitespace().next().unwrap_or_default().to_string();
+    let username = lines
+        .iter()
+        .find_map(|line| line.trim().strip_prefix("USER ").or_else(|| line.trim().strip_prefix("user ")))
+        .unwrap_or("anonymous")
+        .trim()
+        .to_string();
+
+    let mut fields = Fields::new();
     fields.insert("command".to_string(), serde_json::json!(command.clone()));
     fields.insert("username".to_string(), serde_json::json!(username.clone()));
-    fields.insert("raw_command".to_string(), serde_json::json!(lines[0]));
+    fields.insert("raw_command".to_string(), serde_json::json!(first_line));
 
     Some(FtpResult {
         command,
@@ -361,7 +362,7 @@ pub fn parse_smtp(data: &[u8]) -> Option<SmtpResult> {
     for line in &lines {
         let upper = line.trim().to_uppercase();
         if upper.starts_with("MAIL FROM:") {
-            from = line[9..].trim().to_string();
+            from = line[10..].trim().to_string();
         } else if upper.starts_with("RCPT TO:") {
             to = line[8..].trim().to_string();
         } else if upper.starts_with("SUBJECT:") {
@@ -369,7 +370,7 @@ pub fn parse_smtp(data: &[u8]) -> Option<SmtpResult> {
         }
     }
 
-    let mut fields = HashMap::new();
+    let mut fields = Fields::new();
     fields.insert("mail_from".to_string(), serde_json::json!(from.clone()));
     fields.insert("rcpt_to".to_string(), serde_json::json!(to.clone()));
     fields.insert("subject".to_string(), serde_json::json!(subject.clone()));
@@ -424,7 +425,7 @@ pub fn parse_websocket(data: &[u8]) -> Option<WebSocketResult> {
         return None;
     }
 
-    let mut fields = HashMap::new();
+    let mut fields = Fields::new();
     fields.insert("method".to_string(), serde_json::json!("GET"));
     fields.insert("path".to_string(), serde_json::json!(request_line[1]));
     fields.insert("http_version".to_string(), serde_json::json!(request_line.get(2).unwrap_or(&"HTTP/1.1")));
@@ -450,7 +451,7 @@ pub fn parse_telnet(data: &[u8]) -> Option<TelnetResult> {
     }
 
     // 检查是否有IAC字符 (0xFF)
-    let has_iac = data.iter().any(|&b| b == 0xFF);
+    let has_iac = data.contains(&0xFF);
     if !has_iac {
         return None;
     }
@@ -471,7 +472,7 @@ pub fn parse_telnet(data: &[u8]) -> Option<TelnetResult> {
         }
     }
 
-    let mut fields = HashMap::new();
+    let mut fields = Fields::new();
     fields.insert("iac_detected".to_string(), serde_json::json!(true));
     fields.insert("commands".to_string(), serde_json::json!(commands));
     fields.insert("data_length".to_string(), serde_json::json!(data.len()));
@@ -500,7 +501,7 @@ fn get_telnet_option_name(opt: u8) -> &'static str {
         0x03 => "Suppress-Go-Ahead",
         0x18 => "Terminal-Type",
         0x1F => "Negotiate-About-Window-Size",
-        _ => &"Unknown",
+        _ => "Unknown",
     }
 }
 
@@ -525,7 +526,7 @@ pub fn parse_custom_header(data: &[u8]) -> Option<CustomHeaderResult> {
         return None;
     }
 
-    let mut fields = HashMap::new();
+    let mut fields = Fields::new();
     fields.insert("magic_number".to_string(), serde_json::json!(magic.clone()));
     fields.insert("version".to_string(), serde_json::json!(version));
     fields.insert("message_type".to_string(), serde_json::json!(format!("0x{:02X}", message_type)));
@@ -552,3 +553,137 @@ fn get_message_type_name(ty: u8) -> &'static str {
         _ => "Unknown",
     }
 }
+
+pub struct HttpResponseResult {
+    pub version: String,
+    pub status: u16,
+    pub reason: String,
+    pub fields: Fields,
+}
+
+pub struct RespReplyResult {
+    /// RESP 类型名，如 "Simple String"
+    pub kind: &'static str,
+    pub value: String,
+    pub fields: Fields,
+}
+
+pub struct ReplyCodeResult {
+    pub code: u16,
+    /// 每行去掉应答码后的文本
+    pub lines: Vec<String>,
+    pub fields: Fields,
+}
+
+// HTTP 响应解析器
+// 格式: HTTP/1.1 200 OK
+pub fn parse_http_response(data: &[u8]) -> Option<HttpResponseResult> {
+    let text = String::from_utf8_lossy(data);
+    let (head, body) = text.split_once("\r\n\r\n").unwrap_or((&text, ""));
+    let mut lines = head.lines();
+
+    let status_line = lines.next()?;
+    let mut parts = status_line.splitn(3, ' ');
+    let version = parts.next().filter(|v| v.starts_with("HTTP/1.") || *v == "HTTP/2")?.to_string();
+    let status = parts.next().filter(|s| s.len() == 3)?.parse::<u16>().ok().filter(|s| (100..600).contains(s))?;
+    let reason = parts.next().unwrap_or_default().trim().to_string();
+
+    let mut fields = Fields::new();
+    fields.insert("version".to_string(), serde_json::json!(version.clone()));
+    fields.insert("status".to_string(), serde_json::json!(status));
+    fields.insert("reason".to_string(), serde_json::json!(reason.clone()));
+    for line in lines {
+        if let Some((key, value)) = line.split_once(':') {
+            fields.insert(key.trim().to_lowercase(), serde_json::json!(value.trim()));
+        }
+    }
+    if !body.is_empty() {
+        fields.insert("body_length".to_string(), serde_json::json!(body.len()));
+    }
+
+    Some(HttpResponseResult {
+        version,
+        status,
+        reason,
+        fields,
+    })
+}
+
+// Redis RESP 应答解析器，只看第一个应答
+// 格式: +OK\r\n, -ERR ...\r\n, :1\r\n, $5\r\nhello\r\n, _\r\n
+pub fn parse_resp_reply(data: &[u8]) -> Option<RespReplyResult> {
+    let text = String::from_utf8(data.to_vec()).ok()?;
+    let (first, rest) = text.split_once("\r\n")?;
+    let (marker, payload) = first.split_at_checked(1)?;
+
+    let (kind, value) = match marker {
+        "+" => ("Simple String", payload.to_string()),
+        "-" => ("Error", payload.to_string()),
+        ":" => ("Integer", payload.parse::<i64>().ok()?.to_string()),
+        "$" if payload == "-1" => ("Null", String::new()),
+        "$" => {
+            let len = payload.parse::<usize>().ok()?;
+            let value = rest.get(..len).filter(|_| rest[len..].starts_with("\r\n"))?;
+            ("Bulk String", value.to_string())
+        }
+        "*" if payload == "-1" => ("Null", String::new()),
+        "*" => ("Array", format!("{} elements", payload.parse::<usize>().ok()?)),
+        "_" if payload.is_empty() => ("Null", String::new()),
+        "#" if payload == "t" || payload == "f" => ("Boolean", payload.to_string()),
+        "%" => ("Map", format!("{} entries", payload.parse::<usize>().ok()?)),
+        _ => return None,
+    };
+    // 简单字符串和错误不能含控制字符
+    if value.chars().any(|c| c.is_control()) && kind != "Bulk String" {
+        return None;
+    }
+
+    let mut fields = Fields::new();
+    fields.insert("type".to_string(), serde_json::json!(kind));
+    fields.insert("value".to_string(), serde_json::json!(value.clone()));
+
+    Some(RespReplyResult {
+        kind,
+        value,
+        fields,
+    })
+}
+
+// FTP/SMTP 数字应答解析器
+// 格式: 220 ready\r\n 或多行 250-first\r\n250 last\r\n
+pub fn parse_reply_code(data: &[u8]) -> Option<ReplyCodeResult> {
+    let text = String::from_utf8(data.to_vec()).ok()?;
+    if !text.ends_with('\n') {
+        return None;
+    }
+
+    let mut code = None;
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        // FTP 多行应答中间可以有不带应答码的行，如 FEAT 的结果
+        if code.is_some() && line.starts_with(' ') {
+            lines.push(line.trim().to_string());
+            continue;
+        }
+        let (number, rest) = line.split_at_checked(3)?;
+        let number = number.parse::<u16>().ok().filter(|n| (100..600).contains(n))?;
+        if *code.get_or_insert(number) != number {
+            return None;
+        }
+        if !(rest.is_empty() || rest.starts_with(' ') || rest.starts_with('-')) {
+            return None;
+        }
+        lines.push(rest.get(1..).unwrap_or_default().to_string());
+    }
+    let code = code?;
+
+    let mut fields = Fields::new();
+    fields.insert("code".to_string(), serde_json::json!(code));
+    fields.insert("text".to_string(), serde_json::json!(lines.join("\n")));
+
+    Some(ReplyCodeResult {
+        code,
+        lines,
+        fields,
+    })
+}
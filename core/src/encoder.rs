@@ -0,0 +1,200 @@
+use crate::field::{bin_to_bytes, condition_holds, dec_to_bytes, scope_bytes, tlv_spec, uint_to_bytes, Scope};
+use crate::hex::{hex_string_to_bytes, is_hex_value};
+use crate::model::{FieldKind, LengthOf, ProtocolField, RepeatRule};
+use crate::{text, varint, ProtocolError, Result, MAX_DEPTH};
+
+/// Encode protocol fields into the bytes sent on the wire.
+/// Groups referencing saved protocols must be resolved first (see `resolve_references`).
+pub fn encode(fields: &[ProtocolField]) -> Result<Vec<u8>> {
+    encode_fields(fields, &Scope::default(), 0)
+}
+
+/// Auto-length fields are filled in after the rest of the structure is encoded,
+/// so later fields can't refer to their value.
+fn encode_fields(fields: &[ProtocolField], parent: &Scope, depth: usize) -> Result<Vec<u8>> {
+    let mut scope = parent.child();
+    let mut parts: Vec<(&ProtocolField, Option<Vec<u8>>)> = Vec::new();
+
+    for field in fields.iter().filter(|f| f.enabled) {
+        if field.condition.as_ref().is_some_and(|c| !condition_holds(c, &scope)) {
+            continue;
+        }
+        if field.length_of.is_some() {
+            parts.push((field, None));
+            continue;
+        }
+        let bytes = encode_field(field, &scope, depth)?;
+        scope.insert(&field.name, scope_bytes(field, &bytes));
+        parts.push((field, Some(bytes)));
+    }
+
+    // Back to front, so a length covering the following fields includes nested lengths
+    for i in (0..parts.len()).rev() {
+        let (field, None) = parts[i] else { continue };
+        let length = match field.length_of.as_ref() {
+            Some(LengthOf::Field { field: target }) => parts
+                .iter()
+                .find(|(f, _)| f.name == *target)
+                .and_then(|(_, bytes)| bytes.as_ref())
+                .map(Vec::len)
+                .ok_or_else(|| ProtocolError::UnknownReference {
+                    field: field.name.clone(),
+                    target: target.clone(),
+                })?,
+            _ => parts[i + 1..].iter().filter_map(|(_, bytes)| bytes.as_ref()).map(Vec::len).sum(),
+        };
+        parts[i].1 = Some(encode_length(field, length as u64)?);
+    }
+
+    Ok(parts.into_iter().flat_map(|(_, bytes)| bytes.unwrap_or_default()).collect())
+}
+
+/// Encode the computed value of an auto-length field
+fn encode_length(field: &ProtocolField, length: u64) -> Result<Vec<u8>> {
+    match field.kind {
+        FieldKind::Varint => varint::encode(field.varint_format.unwrap_or_default(), length).map_err(|e| invalid(field, e)),
+        FieldKind::Scalar if !field.is_variable => {
+            let len = fixed_length(field);
+            uint_to_bytes(length, len, field.endianness.unwrap_or_default())
+                .ok_or_else(|| invalid(field, format!("length {} does not fit in {} bytes", length, len)))
+        }
+        _ => Err(invalid(field, "an auto length needs a fixed-size number or varint field".to_string())),
+    }
+}
+
+fn encode_field(field: &ProtocolField, scope: &Scope, depth: usize) -> Result<Vec<u8>> {
+    if depth > MAX_DEPTH {
+        return Err(ProtocolError::TooDeep(field.name.clone()));
+    }
+
+    match field.kind {
+        FieldKind::Scalar => encode_scalar(field),
+        FieldKind::Group => encode_fields(&field.fields, scope, depth + 1),
+        FieldKind::Array => encode_array(field, scope, depth),
+        FieldKind::Tlv => encode_tlv(field, scope, depth),
+        FieldKind::Varint => {
+            let format = field.varint_format.unwrap_or_default();
+            varint::parse(format, &field.value)
+                .and_then(|value| varint::encode(format, value))
+                .map_err(|e| invalid(field, e))
+        }
+    }
+}
+
+fn encode_array(field: &ProtocolField, scope: &Scope, depth: usize) -> Result<Vec<u8>> {
+    let mut items: Vec<&ProtocolField> = field.fields.iter().filter(|f| f.enabled).collect();
+
+    // A fixed-count array is topped up with the element template
+    if let Some(RepeatRule::Fixed { count }) = &field.repeat {
+        let count = *count as usize;
+        if items.len() > count {
+            return Err(invalid(
+                field,
+                format!("holds {} elements but the count is fixed at {}", items.len(), count),
+            ));
+        }
+        if items.len() < count {
+            let element = field.element.as_deref().ok_or_else(|| {
+                invalid(field, format!("has {} of {} elements and no element template", items.len(), count))
+            })?;
+            items.resize(count, element);
+        }
+    }
+    // The decoder reads as many elements as the count field says
+    if let Some(RepeatRule::FromField { field: target }) = &field.repeat {
+        let count = scope.get_uint(target).ok_or_else(|| ProtocolError::UnknownReference {
+            field: field.name.clone(),
+            target: target.clone(),
+        })?;
+        if count != items.len() as u64 {
+            return Err(invalid(
+                field,
+                format!("holds {} elements but its count field '{}' is {}", items.len(), target, count),
+            ));
+        }
+    }
+
+    let mut out = Vec::new();
+    for item in items {
+        out.extend(encode_field(item, scope, depth + 1)?);
+    }
+    Ok(out)
+}
+
+fn encode_tlv(field: &ProtocolField, scope: &Scope, depth: usize) -> Result<Vec<u8>> {
+    let spec = tlv_spec(field)?;
+    let tag_width = spec.tag_width as usize;
+    let length_width = spec.length_width as usize;
+    let header = if spec.length_includes_header { tag_width + length_width } else { 0 };
+
+    let mut out = Vec::new();
+    for item in field.fields.iter().filter(|f| f.enabled) {
+        let tag = item.tag.ok_or_else(|| invalid(item, "TLV item has no tag".to_string()))?;
+        let value = encode_field(item, scope, depth + 1)?;
+        let length = (value.len() + header) as u64;
+
+        let tag_bytes = uint_to_bytes(tag, tag_width, spec.endianness)
+            .ok_or_else(|| invalid(item, format!("tag {:#X} does not fit in {} bytes", tag, tag_width)))?;
+        let length_bytes = uint_to_bytes(length, length_width, spec.endianness)
+            .ok_or_else(|| invalid(item, format!("length {} does not fit in {} bytes", length, length_width)))?;
+
+        out.extend(tag_bytes);
+        out.extend(length_bytes);
+        out.extend(value);
+    }
+    Ok(out)
+}
+
+fn encode_scalar(field: &ProtocolField) -> Result<Vec<u8>> {
+    let value = field.value.as_str();
+
+    if field.is_text() {
+        let spec = field.text.clone().unwrap_or_default();
+        let bytes = text::encode(&spec, value).map_err(|e| invalid(field, e))?;
+        return if field.is_variable {
+            Ok(bytes)
+        } else {
+            text::pad(&spec, bytes, fixed_length(field)).map_err(|e| invalid(field, e))
+        };
+    }
+
+    if field.is_variable {
+        return hex_string_to_bytes(value).map_err(|e| invalid(field, e));
+    }
+
+    let len = fixed_length(field);
+    let number = match field.value_format.as_deref() {
+        Some("dec") => dec_to_bytes(value, len),
+        Some("bin") => bin_to_bytes(value, len),
+        _ if is_hex_value(value) => {
+            // Left-pad with zeros and keep the last `len` bytes
+            let clean: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+            let padded = format!("{:0>width$}", clean, width = len * 2);
+            hex_string_to_bytes(&padded[padded.len() - len * 2..])
+        }
+        _ => {
+            // Plain text, zero-padded on the right
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.resize(len, 0);
+            return Ok(bytes);
+        }
+    };
+
+    let mut bytes = number.map_err(|e| invalid(field, e))?;
+    if field.is_little_endian() {
+        bytes.reverse();
+    }
+    Ok(bytes)
+}
+
+/// Byte length of a non-variable field
+pub fn fixed_length(field: &ProtocolField) -> usize {
+    field.length.filter(|l| *l > 0).unwrap_or(1) as usize
+}
+
+fn invalid(field: &ProtocolField, message: String) -> ProtocolError {
+    ProtocolError::InvalidValue {
+        field: field.name.clone(),
+        message,
+    }
+}
@@ -0,0 +1,224 @@
+use crate::model::{Endianness, FieldCondition, FieldKind, ProtocolField, TlvSpec, VarintFormat};
+use crate::{varint, ProtocolError, Result, MAX_DEPTH};
+
+/// Decimal string to big-endian bytes, keeping the low `len` bytes
+pub fn dec_to_bytes(value: &str, len: usize) -> std::result::Result<Vec<u8>, String> {
+    digits_to_bytes(value, 10, len)
+}
+
+/// Binary string to big-endian bytes, keeping the low `len` bytes
+pub fn bin_to_bytes(value: &str, len: usize) -> std::result::Result<Vec<u8>, String> {
+    digits_to_bytes(value, 2, len)
+}
+
+fn digits_to_bytes(value: &str, radix: u32, len: usize) -> std::result::Result<Vec<u8>, String> {
+    let mut bytes = vec![0u8; len];
+
+    for c in value.chars().filter(|c| !c.is_whitespace()) {
+        let digit = c
+            .to_digit(radix)
+            .ok_or_else(|| format!("Invalid digit '{}' for base {}", c, radix))?;
+
+        // bytes = bytes * radix + digit, dropping whatever overflows the field
+        let mut carry = digit;
+        for byte in bytes.iter_mut().rev() {
+            let v = *byte as u32 * radix + carry;
+            *byte = (v & 0xFF) as u8;
+            carry = v >> 8;
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Big-endian bytes to a decimal string of arbitrary size
+pub fn bytes_to_dec(bytes: &[u8]) -> String {
+    let mut number: Vec<u8> = bytes.iter().copied().skip_while(|b| *b == 0).collect();
+    if number.is_empty() {
+        return "0".to_string();
+    }
+
+    let mut digits = Vec::new();
+    while !number.is_empty() {
+        // number = number / 10, collecting the remainder as the next digit
+        let mut rem = 0u32;
+        for byte in number.iter_mut() {
+            let v = (rem << 8) | *byte as u32;
+            *byte = (v / 10) as u8;
+            rem = v % 10;
+        }
+        digits.push(char::from_digit(rem, 10).unwrap());
+        let zeros = number.iter().take_while(|b| **b == 0).count();
+        number.drain(..zeros);
+    }
+
+    digits.iter().rev().collect()
+}
+
+pub fn bytes_to_bin(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:08b}", b)).collect()
+}
+
+/// Big-endian unsigned value, if it fits in 64 bits
+pub fn bytes_to_uint(bytes: &[u8]) -> Option<u64> {
+    let significant: Vec<u8> = bytes.iter().copied().skip_while(|b| *b == 0).collect();
+    if significant.len() > 8 {
+        return None;
+    }
+    Some(significant.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+}
+
+/// Numeric value of a field's encoded bytes. Varints are decoded (zigzag values
+/// as two's complement), anything else is read as an unsigned integer in the
+/// field's byte order.
+pub fn field_uint(field: &ProtocolField, bytes: &[u8]) -> Option<u64> {
+    if field.kind != FieldKind::Varint {
+        return bytes_to_uint(&scope_bytes(field, bytes));
+    }
+    let format = field.varint_format.unwrap_or_default();
+    let (value, _) = varint::decode(format, bytes).ok().flatten()?;
+    Some(match format {
+        VarintFormat::Zigzag => varint::unzigzag(value) as u64,
+        _ => value,
+    })
+}
+
+/// Bytes recorded in the scope for a field, so conditions and counts see varints
+/// and little-endian numbers by value (scope values are read big-endian)
+pub fn scope_bytes(field: &ProtocolField, bytes: &[u8]) -> Vec<u8> {
+    match field.kind {
+        FieldKind::Varint => field_uint(field, bytes)
+            .map(|v| v.to_be_bytes().to_vec())
+            .unwrap_or_default(),
+        _ if field.is_little_endian() => bytes.iter().rev().copied().collect(),
+        _ => bytes.to_vec(),
+    }
+}
+
+/// Unsigned value as `width` bytes, or None if it doesn't fit
+pub fn uint_to_bytes(value: u64, width: usize, endianness: Endianness) -> Option<Vec<u8>> {
+    if width < 8 && value >> (width * 8) != 0 {
+        return None;
+    }
+    let be = value.to_be_bytes();
+    let mut bytes = be[8 - width.min(8)..].to_vec();
+    if endianness == Endianness::Little {
+        bytes.reverse();
+    }
+    Some(bytes)
+}
+
+/// Unsigned value of at most 8 bytes in the given byte order
+pub fn read_uint(bytes: &[u8], endianness: Endianness) -> u64 {
+    let fold = |acc: u64, b: &u8| (acc << 8) | *b as u64;
+    match endianness {
+        Endianness::Big => bytes.iter().fold(0, fold),
+        Endianness::Little => bytes.iter().rev().fold(0, fold),
+    }
+}
+
+/// Header layout of a TLV field, checked for usable widths
+pub fn tlv_spec(field: &ProtocolField) -> Result<&TlvSpec> {
+    let spec = field.tlv.as_ref().ok_or_else(|| ProtocolError::InvalidValue {
+        field: field.name.clone(),
+        message: "TLV field has no item layout".to_string(),
+    })?;
+    if !(1..=8).contains(&spec.tag_width) || !(1..=8).contains(&spec.length_width) {
+        return Err(ProtocolError::InvalidValue {
+            field: field.name.clone(),
+            message: "TLV tag and length widths must be 1 to 8 bytes".to_string(),
+        });
+    }
+    Ok(spec)
+}
+
+/// Raw bytes of the fields processed so far, so later fields can refer to them by name.
+/// Lookups fall back to the enclosing structure.
+#[derive(Default)]
+pub struct Scope<'a> {
+    parent: Option<&'a Scope<'a>>,
+    values: Vec<(String, Vec<u8>)>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn child(&self) -> Scope<'_> {
+        Scope {
+            parent: Some(self),
+            values: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, bytes: Vec<u8>) {
+        self.values.push((name.to_string(), bytes));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, bytes)| bytes.as_slice())
+            .or_else(|| self.parent.and_then(|p| p.get(name)))
+    }
+
+    pub fn get_uint(&self, name: &str) -> Option<u64> {
+        self.get(name).and_then(bytes_to_uint)
+    }
+}
+
+/// Whether a conditional field is present, given the fields before it.
+/// A condition on a field that was itself left out does not hold.
+pub fn condition_holds(condition: &FieldCondition, scope: &Scope) -> bool {
+    let Some(actual) = scope.get_uint(condition.field()) else {
+        return false;
+    };
+
+    match condition {
+        FieldCondition::Equals { value, .. } => actual == *value,
+        FieldCondition::BitmaskSet { mask, .. } => actual & mask == *mask,
+        FieldCondition::InSet { values, .. } => values.contains(&actual),
+    }
+}
+
+/// Fill groups that only reference a saved protocol with that protocol's fields.
+/// `lookup` returns the fields of a saved protocol by id.
+pub fn resolve_references<F>(fields: &mut [ProtocolField], lookup: &mut F) -> Result<()>
+where
+    F: FnMut(&str) -> std::result::Result<Option<Vec<ProtocolField>>, String>,
+{
+    resolve_fields(fields, lookup, 0)
+}
+
+fn resolve_fields<F>(fields: &mut [ProtocolField], lookup: &mut F, depth: usize) -> Result<()>
+where
+    F: FnMut(&str) -> std::result::Result<Option<Vec<ProtocolField>>, String>,
+{
+    for field in fields.iter_mut() {
+        resolve_field(field, lookup, depth)?;
+    }
+    Ok(())
+}
+
+fn resolve_field<F>(field: &mut ProtocolField, lookup: &mut F, depth: usize) -> Result<()>
+where
+    F: FnMut(&str) -> std::result::Result<Option<Vec<ProtocolField>>, String>,
+{
+    if depth > MAX_DEPTH {
+        return Err(ProtocolError::TooDeep(field.name.clone()));
+    }
+
+    if field.kind == FieldKind::Group && field.fields.is_empty() {
+        if let Some(id) = &field.protocol_ref {
+            field.fields = lookup(id)
+                .map_err(ProtocolError::Lookup)?
+                .ok_or_else(|| ProtocolError::ProtocolNotFound(id.clone()))?;
+        }
+    }
+
+    resolve_fields(&mut field.fields, lookup, depth + 1)?;
+    if let Some(element) = field.element.as_mut() {
+        resolve_field(element, lookup, depth + 1)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! Conversions between bytes and the hex text shown and typed in the app
+
+/// Hex text to bytes. Whitespace between digits and a leading `0x` are ignored.
+pub fn hex_string_to_bytes(hex: &str) -> Result<Vec<u8>, String> {
+    let hex = hex.trim();
+    let hex = hex.strip_prefix("0x").or_else(|| hex.strip_prefix("0X")).unwrap_or(hex);
+    let hex: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+
+    // Also keeps the byte slicing below on character boundaries
+    if let Some((i, c)) = hex.chars().enumerate().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid hex at position {}: '{}' is not a hex digit", i, c));
+    }
+    if !hex.len().is_multiple_of(2) {
+        return Err("Hex string must have even length".to_string());
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|e| format!("Invalid hex at position {}: {}", i, e))
+        })
+        .collect()
+}
+
+/// Upper-case hex, bytes separated by spaces
+pub fn bytes_to_hex_string(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Hex without separators, the way field values store it
+pub fn bytes_to_plain_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Same check the editor uses to tell hex values from text
+pub fn is_hex_value(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_hexdigit() || c.is_whitespace())
+}
@@ -0,0 +1,38 @@
+//! Protocol model, encoder and decoder shared by the PacketForge app, the test server and
+//! command-line tools: field definitions, their wire format, hex conversions, and the
+//! recognizers for well-known protocols in [`detect`].
+
+pub mod detect;
+pub mod encoder;
+pub mod field;
+pub mod hex;
+pub mod model;
+pub mod parser;
+pub mod text;
+pub mod varint;
+
+pub use encoder::encode;
+pub use field::resolve_references;
+pub use hex::{bytes_to_hex_string, hex_string_to_bytes};
+pub use parser::{decode, decode_prefix, DecodedField};
+
+/// Nesting limit for groups, arrays and embedded protocols
+pub const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProtocolError {
+    #[error("Field '{field}': {message}")]
+    InvalidValue { field: String, message: String },
+    #[error("Field '{0}': not enough data")]
+    UnexpectedEnd(String),
+    #[error("Field '{field}' refers to unknown field '{target}'")]
+    UnknownReference { field: String, target: String },
+    #[error("Protocol '{0}' not found")]
+    ProtocolNotFound(String),
+    #[error("Field '{0}' is nested too deeply")]
+    TooDeep(String),
+    #[error("{0}")]
+    Lookup(String),
+}
+
+pub type Result<T> = std::result::Result<T, ProtocolError>;
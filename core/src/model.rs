@@ -0,0 +1,372 @@
+//! Protocol field definitions, as saved by the app and exported in protocol libraries
+
+use serde::{Deserialize, Serialize};
+
+/// Layout of a field on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    /// Plain fixed or variable-length value
+    #[default]
+    Scalar,
+    /// Sub-structure made of its own fields (inline or from a saved protocol)
+    Group,
+    /// Repeated element
+    Array,
+    /// Sequence of tag-length-value items
+    Tlv,
+    /// Variable-length integer
+    Varint,
+}
+
+impl FieldKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldKind::Scalar => "scalar",
+            FieldKind::Group => "group",
+            FieldKind::Array => "array",
+            FieldKind::Tlv => "tlv",
+            FieldKind::Varint => "varint",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "group" => FieldKind::Group,
+            "array" => FieldKind::Array,
+            "tlv" => FieldKind::Tlv,
+            "varint" => FieldKind::Varint,
+            _ => FieldKind::Scalar,
+        }
+    }
+}
+
+/// Byte order of multi-byte integers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
+
+impl Endianness {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Endianness::Big => "big",
+            Endianness::Little => "little",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "little" => Endianness::Little,
+            _ => Endianness::Big,
+        }
+    }
+}
+
+/// Header layout of the items in a TLV field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlvSpec {
+    /// Tag size in bytes (1-8)
+    pub tag_width: u8,
+    /// Length size in bytes (1-8)
+    pub length_width: u8,
+    #[serde(default)]
+    pub endianness: Endianness,
+    /// Length counts the tag and length bytes as well as the value
+    #[serde(default)]
+    pub length_includes_header: bool,
+    /// Known tags, used to name decoded items
+    #[serde(default)]
+    pub tags: Vec<TlvTag>,
+}
+
+impl TlvSpec {
+    /// Registered name of a tag
+    pub fn tag_name(&self, tag: u64) -> Option<&str> {
+        self.tags.iter().find(|t| t.tag == tag).map(|t| t.name.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlvTag {
+    pub tag: u64,
+    pub name: String,
+}
+
+/// Wire format of a varint field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VarintFormat {
+    /// Unsigned LEB128, as used by protobuf varints
+    #[default]
+    Leb128,
+    /// Signed value, zigzag mapped then LEB128 (protobuf sint)
+    Zigzag,
+    /// MQTT remaining length: LEB128 limited to four bytes
+    MqttLength,
+}
+
+impl VarintFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VarintFormat::Leb128 => "leb128",
+            VarintFormat::Zigzag => "zigzag",
+            VarintFormat::MqttLength => "mqttLength",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "zigzag" => VarintFormat::Zigzag,
+            "mqttLength" => VarintFormat::MqttLength,
+            _ => VarintFormat::Leb128,
+        }
+    }
+}
+
+/// Character set of a text field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextEncoding {
+    Ascii,
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Gbk,
+    Latin1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PadSide {
+    Left,
+    #[default]
+    Right,
+}
+
+/// How the end of a text value is marked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Terminator {
+    #[default]
+    None,
+    /// Followed by a NUL character
+    Nul,
+    /// Followed by CR LF
+    Crlf,
+    /// Preceded by a one-byte length
+    Pascal,
+}
+
+/// Encoding options of a text field.
+/// Padding only applies to fixed-length fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextSpec {
+    #[serde(default)]
+    pub encoding: TextEncoding,
+    #[serde(default)]
+    pub pad_byte: u8,
+    #[serde(default)]
+    pub pad_side: PadSide,
+    #[serde(default)]
+    pub terminator: Terminator,
+}
+
+/// Value of an auto-length field, computed from the encoded size of other fields
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "camelCase")]
+pub enum LengthOf {
+    /// Byte length of a later field
+    Field { field: String },
+    /// Byte length of everything after this field in the same structure
+    Following,
+}
+
+/// Unit of a Unix time generator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TimeUnit {
+    #[default]
+    #[serde(rename = "s")]
+    Seconds,
+    #[serde(rename = "ms")]
+    Milliseconds,
+}
+
+/// Value produced anew each time the field is sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FieldGenerator {
+    /// start, start + step, ... back to `start` once past `wrap` (or the field's maximum)
+    Counter {
+        #[serde(default)]
+        start: u64,
+        #[serde(default = "default_step")]
+        step: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        wrap: Option<u64>,
+    },
+    /// Current Unix time, written with the field's width and byte order
+    UnixTime {
+        #[serde(default)]
+        unit: TimeUnit,
+    },
+    /// Random bytes filling the field
+    RandomBytes,
+    /// Random number in `min..=max`
+    RandomRange { min: u64, max: u64 },
+}
+
+fn default_step() -> u64 {
+    1
+}
+
+/// How many elements an array field holds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "camelCase")]
+pub enum RepeatRule {
+    /// Always `count` elements
+    Fixed { count: u32 },
+    /// Element count is the value of an earlier field
+    FromField { field: String },
+    /// Repeat until the enclosing data is exhausted
+    UntilEnd,
+}
+
+/// Includes a field only when an earlier field has a matching value.
+/// Field values are compared as big-endian unsigned integers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum FieldCondition {
+    Equals { field: String, value: u64 },
+    /// Every bit of `mask` is set
+    BitmaskSet { field: String, mask: u64 },
+    InSet { field: String, values: Vec<u64> },
+}
+
+impl FieldCondition {
+    /// Name of the field the condition looks at
+    pub fn field(&self) -> &str {
+        match self {
+            FieldCondition::Equals { field, .. }
+            | FieldCondition::BitmaskSet { field, .. }
+            | FieldCondition::InSet { field, .. } => field,
+        }
+    }
+}
+
+/// One field of a protocol. Hand-written definitions (such as mock rule files) may leave
+/// out the id and value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtocolField {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub length: Option<i32>,
+    #[serde(rename = "isVariable", default)]
+    pub is_variable: bool,
+    #[serde(rename = "valueType", default)]
+    pub value_type: String, // "text" or "hex"
+    #[serde(rename = "valueFormat", skip_serializing_if = "Option::is_none")]
+    pub value_format: Option<String>, // "dec" or "hex" for non-variable fields
+    #[serde(default)]
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub kind: FieldKind,
+    /// Saved protocol embedded by a group field
+    #[serde(rename = "protocolRef", skip_serializing_if = "Option::is_none")]
+    pub protocol_ref: Option<String>,
+    /// Group members, or the elements of an array
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<ProtocolField>,
+    /// Shape of one array element, used when decoding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element: Option<Box<ProtocolField>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<RepeatRule>,
+    /// Field is omitted unless the condition holds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<FieldCondition>,
+    /// Item header layout of a TLV field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tlv: Option<TlvSpec>,
+    /// Tag of an item inside a TLV field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<u64>,
+    /// Wire format of a varint field
+    #[serde(rename = "varintFormat", skip_serializing_if = "Option::is_none")]
+    pub varint_format: Option<VarintFormat>,
+    /// Value is filled in with the length of other fields
+    #[serde(rename = "lengthOf", skip_serializing_if = "Option::is_none")]
+    pub length_of: Option<LengthOf>,
+    /// Encoding, padding and terminator of a text field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<TextSpec>,
+    /// Byte order of a fixed-size number (big-endian when unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endianness: Option<Endianness>,
+    /// Value is generated on every send instead of taken from `value`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generator: Option<FieldGenerator>,
+}
+
+impl ProtocolField {
+    /// Variable fields holding text, and fixed fields in text format
+    pub fn is_text(&self) -> bool {
+        if self.is_variable {
+            self.value_type == "text"
+        } else {
+            self.value_format.as_deref() == Some("text")
+        }
+    }
+
+    /// Fixed-size numbers stored least significant byte first
+    pub fn is_little_endian(&self) -> bool {
+        self.endianness == Some(Endianness::Little)
+            && self.kind == FieldKind::Scalar
+            && !self.is_variable
+            && !self.is_text()
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for ProtocolField {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            name: String::new(),
+            length: None,
+            is_variable: false,
+            value_type: "hex".to_string(),
+            value_format: None,
+            value: String::new(),
+            description: None,
+            enabled: true,
+            kind: FieldKind::Scalar,
+            protocol_ref: None,
+            fields: Vec::new(),
+            element: None,
+            repeat: None,
+            condition: None,
+            tlv: None,
+            tag: None,
+            varint_format: None,
+            length_of: None,
+            text: None,
+            endianness: None,
+            generator: None,
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use crate::encoder::fixed_length;
+use crate::field::{bytes_to_bin, bytes_to_dec, condition_holds, field_uint, read_uint, scope_bytes, tlv_spec, Scope};
+use crate::hex::{bytes_to_hex_string, bytes_to_plain_hex};
+use crate::model::{FieldKind, LengthOf, ProtocolField, RepeatRule};
+use crate::{text, varint, ProtocolError, Result, MAX_DEPTH};
+use serde::Serialize;
+
+/// A field read back from received data
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedField {
+    pub name: String,
+    pub offset: usize,
+    pub length: usize,
+    /// Raw bytes, space separated
+    pub hex: String,
+    /// Value in the field's display format (empty for groups, element count for arrays)
+    pub value: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<DecodedField>,
+    /// Tag of a TLV item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<u64>,
+}
+
+/// Read position in the received data; `end` bounds the structure being decoded
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn remaining(&self) -> usize {
+        self.end - self.pos
+    }
+
+    /// Cursor over the next `len` bytes, leaving this one where it is
+    fn window(&self, len: usize, name: &str) -> Result<Cursor<'a>> {
+        if len > self.remaining() {
+            return Err(ProtocolError::UnexpectedEnd(name.to_string()));
+        }
+        Ok(Cursor {
+            data: self.data,
+            pos: self.pos,
+            end: self.pos + len,
+        })
+    }
+
+    fn take(&mut self, len: usize, name: &str) -> Result<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(ProtocolError::UnexpectedEnd(name.to_string()));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+}
+
+/// Decode received data with a protocol definition.
+/// A variable-length field takes the rest of the data when nothing follows it,
+/// otherwise its configured length.
+pub fn decode(fields: &[ProtocolField], data: &[u8]) -> Result<Vec<DecodedField>> {
+    let mut cursor = Cursor {
+        data,
+        pos: 0,
+        end: data.len(),
+    };
+    decode_fields(fields, &mut cursor, &Scope::default(), 0, true)
+}
+
+/// Decode the message at the start of `data`, returning it with the number of bytes it took.
+/// Messages are only delimited by their own definition: fixed sizes and auto-length fields.
+pub fn decode_prefix(fields: &[ProtocolField], data: &[u8]) -> Result<(Vec<DecodedField>, usize)> {
+    let mut cursor = Cursor {
+        data,
+        pos: 0,
+        end: data.len(),
+    };
+    let decoded = decode_fields(fields, &mut cursor, &Scope::default(), 0, true)?;
+    Ok((decoded, cursor.pos))
+}
+
+/// Lengths read from auto-length fields bound the fields they describe:
+/// a targeted field is decoded within exactly that many bytes, and a length of
+/// the following fields ends the structure after that many bytes.
+fn decode_fields(
+    fields: &[ProtocolField],
+    cursor: &mut Cursor,
+    parent: &Scope,
+    depth: usize,
+    at_tail: bool,
+) -> Result<Vec<DecodedField>> {
+    let mut scope = parent.child();
+    let enabled: Vec<&ProtocolField> = fields.iter().filter(|f| f.enabled).collect();
+    let mut lengths: Vec<(&str, usize)> = Vec::new();
+    let outer_end = cursor.end;
+    let mut bounded = false;
+    let mut out = Vec::new();
+
+    for (i, field) in enabled.iter().enumerate() {
+        if field.condition.as_ref().is_some_and(|c| !condition_holds(c, &scope)) {
+            continue;
+        }
+        // Later fields whose condition already fails don't need any data
+        let tail = (at_tail || bounded)
+            && enabled[i + 1..]
+                .iter()
+                .all(|f| f.condition.as_ref().is_some_and(|c| !condition_holds(c, &scope)));
+        let start = cursor.pos;
+        let decoded = match lengths.iter().find(|(target, _)| *target == field.name) {
+            Some((_, len)) => {
+                let mut window = cursor.window(*len, &field.name)?;
+                let decoded = decode_field(field, &field.name, &mut window, &scope, depth, true)?;
+                cursor.pos = window.end;
+                decoded
+            }
+            None => decode_field(field, &field.name, cursor, &scope, depth, tail)?,
+        };
+        let bytes = &cursor.data[start..cursor.pos];
+        scope.insert(&field.name, scope_bytes(field, bytes));
+
+        if let Some(length_of) = &field.length_of {
+            let len = field_uint(field, bytes).ok_or_else(|| ProtocolError::InvalidValue {
+                field: field.name.clone(),
+                message: "length does not fit in 64 bits".to_string(),
+            })? as usize;
+            match length_of {
+                LengthOf::Field { field: target } => lengths.push((target.as_str(), len)),
+                LengthOf::Following => {
+                    if len > cursor.remaining() {
+                        return Err(ProtocolError::UnexpectedEnd(field.name.clone()));
+                    }
+                    cursor.end = cursor.pos + len;
+                    bounded = true;
+                }
+            }
+        }
+        out.push(decoded);
+    }
+
+    if bounded {
+        cursor.pos = cursor.end;
+        cursor.end = outer_end;
+    }
+    Ok(out)
+}
+
+fn decode_field(
+    field: &ProtocolField,
+    name: &str,
+    cursor: &mut Cursor,
+    scope: &Scope,
+    depth: usize,
+    tail: bool,
+) -> Result<DecodedField> {
+    if depth > MAX_DEPTH {
+        return Err(ProtocolError::TooDeep(field.name.clone()));
+    }
+
+    let start = cursor.pos;
+    let (value, fields) = match field.kind {
+        FieldKind::Scalar => {
+            // Terminated or length-prefixed text marks its own end
+            let measured = field
+                .text
+                .as_ref()
+                .filter(|_| field.is_variable && field.is_text())
+                .and_then(|spec| text::measure(spec, &cursor.data[cursor.pos..cursor.end]));
+            let len = if !field.is_variable {
+                fixed_length(field)
+            } else if let Some(len) = measured {
+                len
+            } else if tail {
+                cursor.remaining()
+            } else {
+                field.length.unwrap_or(0).max(0) as usize
+            };
+            let bytes = cursor.take(len, name)?;
+            (format_scalar(field, bytes), Vec::new())
+        }
+        FieldKind::Group => {
+            let fields = decode_fields(&field.fields, cursor, scope, depth + 1, tail)?;
+            (String::new(), fields)
+        }
+        FieldKind::Array => {
+            let items = decode_array(field, cursor, scope, depth)?;
+            (items.len().to_string(), items)
+        }
+        FieldKind::Tlv => {
+            let items = decode_tlv(field, cursor, scope, depth, tail)?;
+            (items.len().to_string(), items)
+        }
+        FieldKind::Varint => {
+            let format = field.varint_format.unwrap_or_default();
+            let (value, len) = varint::decode(format, &cursor.data[cursor.pos..cursor.end])
+                .map_err(|message| ProtocolError::InvalidValue {
+                    field: name.to_string(),
+                    message,
+                })?
+                .ok_or_else(|| ProtocolError::UnexpectedEnd(name.to_string()))?;
+            cursor.take(len, name)?;
+            (varint::display(format, value), Vec::new())
+        }
+    };
+
+    let bytes = &cursor.data[start..cursor.pos];
+    Ok(DecodedField {
+        name: name.to_string(),
+        offset: start,
+        length: bytes.len(),
+        hex: bytes_to_hex_string(bytes),
+        value,
+        fields,
+        tag: None,
+    })
+}
+
+fn decode_array(
+    field: &ProtocolField,
+    cursor: &mut Cursor,
+    scope: &Scope,
+    depth: usize,
+) -> Result<Vec<DecodedField>> {
+    let element = field
+        .element
+        .as_deref()
+        .or(field.fields.first())
+        .ok_or_else(|| ProtocolError::InvalidValue {
+            field: field.name.clone(),
+            message: "array has no element definition".to_string(),
+        })?;
+
+    let count = match &field.repeat {
+        Some(RepeatRule::Fixed { count }) => Some(*count as usize),
+        Some(RepeatRule::FromField { field: target }) => {
+            let count = scope
+                .get_uint(target)
+                .ok_or_else(|| ProtocolError::UnknownReference {
+                    field: field.name.clone(),
+                    target: target.clone(),
+                })?;
+            Some(count as usize)
+        }
+        Some(RepeatRule::UntilEnd) | None => None,
+    };
+
+    let mut items = Vec::new();
+    loop {
+        match count {
+            Some(count) if items.len() >= count => break,
+            None if cursor.remaining() == 0 => break,
+            _ => {}
+        }
+
+        let name = format!("{}[{}]", field.name, items.len());
+        let item = decode_field(element, &name, cursor, scope, depth + 1, false)?;
+        let consumed = item.length;
+        items.push(item);
+
+        // An empty element would repeat forever
+        if consumed == 0 {
+            break;
+        }
+    }
+
+    Ok(items)
+}
+
+/// Read TLV items until the field's data runs out. The field spans the rest of
+/// the data when nothing follows it, otherwise its configured length.
+/// Items whose tag matches a configured item are decoded with that item's format.
+fn decode_tlv(
+    field: &ProtocolField,
+    cursor: &mut Cursor,
+    scope: &Scope,
+    depth: usize,
+    tail: bool,
+) -> Result<Vec<DecodedField>> {
+    let spec = tlv_spec(field)?;
+    let tag_width = spec.tag_width as usize;
+    let length_width = spec.length_width as usize;
+
+    let len = match field.length.filter(|l| *l > 0) {
+        Some(len) if !tail => len as usize,
+        _ => cursor.remaining(),
+    };
+    let mut region = cursor.window(len, &field.name)?;
+
+    let mut items = Vec::new();
+    while region.remaining() > 0 {
+        let start = region.pos;
+        let tag = read_uint(region.take(tag_width, &field.name)?, spec.endianness);
+        let length = read_uint(region.take(length_width, &field.name)?, spec.endianness) as usize;
+        let value_len = if spec.length_includes_header {
+            length
+                .checked_sub(tag_width + length_width)
+                .ok_or_else(|| ProtocolError::InvalidValue {
+                    field: field.name.clone(),
+                    message: format!("item length {} is shorter than its header", length),
+                })?
+        } else {
+            length
+        };
+
+        let name = match spec.tag_name(tag) {
+            Some(name) => name.to_string(),
+            None => format!("{:#0width$X}", tag, width = tag_width * 2 + 2),
+        };
+        let mut value_cursor = region.window(value_len, &name)?;
+        let (value, fields) = match field.fields.iter().find(|f| f.tag == Some(tag)) {
+            Some(item) => {
+                let decoded = decode_field(item, &name, &mut value_cursor, scope, depth + 1, true)?;
+                (decoded.value, decoded.fields)
+            }
+            None => (bytes_to_plain_hex(region.take(value_len, &name)?), Vec::new()),
+        };
+        region.pos = start + tag_width + length_width + value_len;
+
+        let bytes = &region.data[start..region.pos];
+        items.push(DecodedField {
+            name,
+            offset: start,
+            length: bytes.len(),
+            hex: bytes_to_hex_string(bytes),
+            value,
+            fields,
+            tag: Some(tag),
+        });
+    }
+
+    cursor.pos = region.end;
+    Ok(items)
+}
+
+fn format_scalar(field: &ProtocolField, bytes: &[u8]) -> String {
+    if field.is_text() {
+        let spec = field.text.clone().unwrap_or_default();
+        return text::decode(&spec, bytes, !field.is_variable);
+    }
+    if field.is_variable {
+        return bytes_to_plain_hex(bytes);
+    }
+
+    let mut bytes = bytes.to_vec();
+    if field.is_little_endian() {
+        bytes.reverse();
+    }
+    match field.value_format.as_deref() {
+        Some("dec") => bytes_to_dec(&bytes),
+        Some("bin") => bytes_to_bin(&bytes),
+        _ => bytes_to_plain_hex(&bytes),
+    }
+}
@@ -0,0 +1,144 @@
+use crate::model::{PadSide, Terminator, TextEncoding, TextSpec};
+
+/// Bytes per code unit; terminators and padding are matched on unit boundaries
+fn unit_width(encoding: TextEncoding) -> usize {
+    match encoding {
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => 2,
+        _ => 1,
+    }
+}
+
+pub fn encode_str(encoding: TextEncoding, value: &str) -> Result<Vec<u8>, String> {
+    match encoding {
+        TextEncoding::Utf8 => Ok(value.as_bytes().to_vec()),
+        TextEncoding::Ascii => {
+            if value.is_ascii() {
+                Ok(value.as_bytes().to_vec())
+            } else {
+                Err("text contains non-ASCII characters".to_string())
+            }
+        }
+        TextEncoding::Latin1 => value
+            .chars()
+            .map(|c| u8::try_from(c as u32).map_err(|_| format!("'{}' is not a Latin-1 character", c)))
+            .collect(),
+        TextEncoding::Utf16Le => Ok(value.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+        TextEncoding::Utf16Be => Ok(value.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+        TextEncoding::Gbk => {
+            let (bytes, _, had_errors) = encoding_rs::GBK.encode(value);
+            if had_errors {
+                Err("text contains characters GBK can't represent".to_string())
+            } else {
+                Ok(bytes.into_owned())
+            }
+        }
+    }
+}
+
+pub fn decode_str(encoding: TextEncoding, bytes: &[u8]) -> String {
+    match encoding {
+        TextEncoding::Utf8 | TextEncoding::Ascii => String::from_utf8_lossy(bytes).to_string(),
+        TextEncoding::Latin1 => bytes.iter().map(|b| *b as char).collect(),
+        TextEncoding::Utf16Le => encoding_rs::UTF_16LE.decode_without_bom_handling(bytes).0.into_owned(),
+        TextEncoding::Utf16Be => encoding_rs::UTF_16BE.decode_without_bom_handling(bytes).0.into_owned(),
+        TextEncoding::Gbk => encoding_rs::GBK.decode_without_bom_handling(bytes).0.into_owned(),
+    }
+}
+
+fn terminator_bytes(spec: &TextSpec) -> Option<Vec<u8>> {
+    let terminator = match spec.terminator {
+        Terminator::Nul => "\0",
+        Terminator::Crlf => "\r\n",
+        Terminator::None | Terminator::Pascal => return None,
+    };
+    encode_str(spec.encoding, terminator).ok()
+}
+
+/// Offset and size of the first terminator in `data`
+fn find_terminator(spec: &TextSpec, data: &[u8]) -> Option<(usize, usize)> {
+    let term = terminator_bytes(spec)?;
+    (0..data.len())
+        .step_by(unit_width(spec.encoding))
+        .find(|i| data[*i..].starts_with(&term))
+        .map(|i| (i, term.len()))
+}
+
+/// Encoded text followed by its terminator, or preceded by its one-byte Pascal length
+pub fn encode(spec: &TextSpec, value: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = encode_str(spec.encoding, value)?;
+    if spec.terminator == Terminator::Pascal {
+        let len = u8::try_from(bytes.len())
+            .map_err(|_| format!("text is {} bytes, a Pascal length prefix holds at most 255", bytes.len()))?;
+        bytes.insert(0, len);
+    } else if let Some(term) = terminator_bytes(spec) {
+        bytes.extend(term);
+    }
+    Ok(bytes)
+}
+
+/// Fill a fixed-size field with the pad byte
+pub fn pad(spec: &TextSpec, mut bytes: Vec<u8>, len: usize) -> Result<Vec<u8>, String> {
+    if bytes.len() > len {
+        return Err(format!("text takes {} bytes but the field holds {}", bytes.len(), len));
+    }
+    let mut padding = vec![spec.pad_byte; len - bytes.len()];
+    Ok(match spec.pad_side {
+        PadSide::Right => {
+            bytes.extend(padding);
+            bytes
+        }
+        PadSide::Left => {
+            padding.extend(bytes);
+            padding
+        }
+    })
+}
+
+/// Size of a terminated or length-prefixed value at the start of `data`,
+/// including the terminator or prefix. None when the text has no terminator
+/// or it isn't found.
+pub fn measure(spec: &TextSpec, data: &[u8]) -> Option<usize> {
+    match spec.terminator {
+        Terminator::None => None,
+        Terminator::Pascal => data
+            .first()
+            .map(|len| 1 + *len as usize)
+            .filter(|len| *len <= data.len()),
+        Terminator::Nul | Terminator::Crlf => find_terminator(spec, data).map(|(at, len)| at + len),
+    }
+}
+
+/// Text of an encoded value with padding, terminator and length prefix removed
+pub fn decode(spec: &TextSpec, bytes: &[u8], padded: bool) -> String {
+    let unit = unit_width(spec.encoding);
+    let is_pad = |chunk: &[u8]| chunk.iter().all(|b| *b == spec.pad_byte);
+    let mut bytes = bytes;
+
+    if padded && spec.pad_side == PadSide::Left {
+        while bytes.len() >= unit && is_pad(&bytes[..unit]) {
+            bytes = &bytes[unit..];
+        }
+    }
+
+    match spec.terminator {
+        Terminator::None => {
+            if padded && spec.pad_side == PadSide::Right {
+                while bytes.len() >= unit && is_pad(&bytes[bytes.len() - unit..]) {
+                    bytes = &bytes[..bytes.len() - unit];
+                }
+            }
+        }
+        Terminator::Pascal => {
+            if let Some((len, rest)) = bytes.split_first() {
+                bytes = &rest[..(*len as usize).min(rest.len())];
+            }
+        }
+        Terminator::Nul | Terminator::Crlf => {
+            if let Some((at, _)) = find_terminator(spec, bytes) {
+                bytes = &bytes[..at];
+            }
+        }
+    }
+
+    decode_str(spec.encoding, bytes)
+}
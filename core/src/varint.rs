@@ -0,0 +1,76 @@
+use crate::model::VarintFormat;
+
+/// Largest value MQTT's four-byte remaining length can hold
+const MQTT_MAX: u64 = 268_435_455;
+
+fn max_bytes(format: VarintFormat) -> usize {
+    match format {
+        VarintFormat::MqttLength => 4,
+        VarintFormat::Leb128 | VarintFormat::Zigzag => 10,
+    }
+}
+
+pub fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+pub fn unzigzag(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+/// Wire value of a number typed into the editor (signed for zigzag)
+pub fn parse(format: VarintFormat, text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let text = if text.is_empty() { "0" } else { text };
+    match format {
+        VarintFormat::Zigzag => text
+            .parse::<i64>()
+            .map(zigzag)
+            .map_err(|_| format!("'{}' is not a signed 64-bit number", text)),
+        _ => text
+            .parse::<u64>()
+            .map_err(|_| format!("'{}' is not an unsigned 64-bit number", text)),
+    }
+}
+
+/// Encode a wire value, seven bits per byte, least significant group first
+pub fn encode(format: VarintFormat, mut value: u64) -> Result<Vec<u8>, String> {
+    if format == VarintFormat::MqttLength && value > MQTT_MAX {
+        return Err(format!("{} exceeds the MQTT remaining length limit of {}", value, MQTT_MAX));
+    }
+
+    let mut out = Vec::new();
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return Ok(out);
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Read a varint from the start of `data`, returning the wire value and its size.
+/// Ok(None) means the data ends before the last byte.
+pub fn decode(format: VarintFormat, data: &[u8]) -> Result<Option<(u64, usize)>, String> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().enumerate() {
+        if i >= max_bytes(format) || (i == 9 && *byte > 1) {
+            return Err(format!("varint longer than {} bytes", max_bytes(format)));
+        }
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(Some((value, i + 1)));
+        }
+    }
+    Ok(None)
+}
+
+/// Number a wire value stands for, as shown in the editor
+pub fn display(format: VarintFormat, value: u64) -> String {
+    match format {
+        VarintFormat::Zigzag => unzigzag(value).to_string(),
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,45 @@
+#!/usr/bin/env node
+
+/**
+ * Build tcp-test-server and place it where Tauri expects the `externalBin` sidecar:
+ * src-tauri/binaries/tcp-test-server-<target triple>[.exe]
+ * The Tauri CLI passes the target through TAURI_ENV_TARGET_TRIPLE; otherwise the host is used.
+ */
+
+import fs from 'fs';
+import path from 'path';
+import { execFileSync } from 'child_process';
+import { fileURLToPath } from 'url';
+
+const __dirname = path.dirname(fileURLToPath(import.meta.url));
+const rootDir = path.resolve(__dirname, '..');
+
+function hostTriple() {
+  const info = execFileSync('rustc', ['-vV'], { encoding: 'utf-8' });
+  const match = info.match(/^host:\s*(\S+)/m);
+  if (!match) {
+    throw new Error('Could not find the host target in `rustc -vV`');
+  }
+  return match[1];
+}
+
+try {
+  const triple = process.env.TAURI_ENV_TARGET_TRIPLE || hostTriple();
+  const release = process.env.TAURI_ENV_DEBUG !== 'true';
+  const args = ['build', '-p', 'tcp-test-server', '--target', triple];
+  if (release) {
+    args.push('--release');
+  }
+  console.log(`Building tcp-test-server for ${triple}`);
+  execFileSync('cargo', args, { cwd: rootDir, stdio: 'inherit' });
+
+  const ext = triple.includes('windows') ? '.exe' : '';
+  const built = path.join(rootDir, 'target', triple, release ? 'release' : 'debug', `tcp-test-server${ext}`);
+  const binDir = path.join(rootDir, 'src-tauri', 'binaries');
+  fs.mkdirSync(binDir, { recursive: true });
+  fs.copyFileSync(built, path.join(binDir, `tcp-test-server-${triple}${ext}`));
+  console.log('Sidecar ready!');
+} catch (error) {
+  console.error('Error building sidecar:', error.message);
+  process.exit(1);
+}
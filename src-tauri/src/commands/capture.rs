@@ -0,0 +1,109 @@
+use crate::pcap::{self, CaptureContents};
+use crate::protocol::{bytes_to_hex_string, hex_string_to_bytes};
+use crate::tcp::ConnectionManager;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureImport {
+    pub file_name: String,
+    #[serde(flatten)]
+    pub contents: CaptureContents,
+}
+
+/// One message of a replay
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayMessage {
+    /// Payload in hex
+    pub data: String,
+    /// Pause before sending, e.g. the gap seen in the capture
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResult {
+    /// Response in hex; empty when not waiting for responses
+    pub response_data: String,
+    pub response_time_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Read a PCAP or PCAPNG file and list its TCP and UDP conversations with their payloads
+#[tauri::command]
+pub async fn import_capture(app: AppHandle) -> Result<CaptureImport, String> {
+    // Show open dialog
+    let file_path = app
+        .dialog()
+        .file()
+        .add_filter("Capture Files", &["pcap", "pcapng", "cap"])
+        .add_filter("All Files", &["*"])
+        .set_title("Import Capture")
+        .blocking_pick_file();
+
+    let file_path = file_path.ok_or("No file selected")?;
+
+    let path = file_path.as_path().ok_or("Invalid file path")?;
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let packets = pcap::read_capture(&bytes).map_err(|e| e.to_string())?;
+
+    Ok(CaptureImport {
+        file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        contents: pcap::conversations(&packets),
+    })
+}
+
+/// Send captured messages in order on a connection, optionally reading a response after
+/// each one. Stops at the first message that fails.
+#[tauri::command]
+pub async fn replay_capture(
+    manager: State<'_, Arc<ConnectionManager>>,
+    connection_id: String,
+    messages: Vec<ReplayMessage>,
+    wait_for_response: bool,
+) -> Result<Vec<ReplayResult>, String> {
+    let mut results = Vec::with_capacity(messages.len());
+
+    for message in messages {
+        let data = hex_string_to_bytes(&message.data)?;
+        if message.delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(message.delay_ms)).await;
+        }
+
+        let start = Instant::now();
+        let outcome = if wait_for_response {
+            manager.send_and_receive(&connection_id, &data, 4096).await
+        } else {
+            manager.send(&connection_id, &data).await.map(|_| Vec::new())
+        };
+        let response_time_ms = start.elapsed().as_millis() as u64;
+
+        match outcome {
+            Ok(response) => results.push(ReplayResult {
+                response_data: bytes_to_hex_string(&response),
+                response_time_ms,
+                error: None,
+            }),
+            Err(e) => {
+                results.push(ReplayResult {
+                    response_data: String::new(),
+                    response_time_ms,
+                    error: Some(e.to_string()),
+                });
+                break;
+            }
+        }
+    }
+
+    Ok(results)
+}
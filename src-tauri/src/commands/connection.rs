@@ -1,7 +1,12 @@
-use crate::tcp::{ConnectionManager, TcpClientConfig};
+use crate::tcp::{CaptureStatus, ConnectionManager, TcpClientConfig};
+use crate::pcap::CaptureFormat;
+use crate::protocol::generator::GeneratorState;
+use crate::template::Sequences;
 use serde::{Deserialize, Serialize};
+use std::fs;
 use std::sync::Arc;
-use tauri::State;
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConnectionConfig {
@@ -87,12 +92,16 @@ pub async fn check_connection_status(
 #[tauri::command]
 pub async fn remove_connection(
     manager: State<'_, Arc<ConnectionManager>>,
+    sequences: State<'_, Sequences>,
+    generators: State<'_, GeneratorState>,
     connection_id: String,
 ) -> Result<CommandResult, String> {
     manager
         .remove_connection(&connection_id)
         .await
         .map_err(|e| e.to_string())?;
+    sequences.reset(&connection_id);
+    generators.reset(&connection_id);
 
     Ok(CommandResult {
         success: true,
@@ -106,3 +115,59 @@ pub async fn list_connections(
 ) -> Result<Vec<String>, String> {
     Ok(manager.list_connections().await)
 }
+
+/// Start or stop recording a connection's traffic. Starting discards the previous recording.
+#[tauri::command]
+pub async fn set_connection_recording(
+    manager: State<'_, Arc<ConnectionManager>>,
+    connection_id: String,
+    enabled: bool,
+) -> Result<CaptureStatus, String> {
+    if enabled {
+        manager.start_capture(&connection_id).await;
+    } else {
+        manager.stop_capture(&connection_id).await;
+    }
+    Ok(manager.capture_status(&connection_id).await)
+}
+
+#[tauri::command]
+pub async fn get_connection_recording(
+    manager: State<'_, Arc<ConnectionManager>>,
+    connection_id: String,
+) -> Result<CaptureStatus, String> {
+    Ok(manager.capture_status(&connection_id).await)
+}
+
+/// Save the recorded traffic as a PCAPNG (or, for a `.pcap` name, classic pcap) file.
+/// Returns the number of packets written.
+#[tauri::command]
+pub async fn export_connection_capture(
+    manager: State<'_, Arc<ConnectionManager>>,
+    app: AppHandle,
+    connection_id: String,
+) -> Result<usize, String> {
+    let capture = manager
+        .capture(&connection_id)
+        .await
+        .filter(|c| c.status().sessions > 0)
+        .ok_or("Nothing recorded on this connection")?;
+
+    // Show save dialog
+    let file_path = app
+        .dialog()
+        .file()
+        .add_filter("PCAPNG Capture", &["pcapng"])
+        .add_filter("PCAP Capture", &["pcap"])
+        .set_file_name(format!("{}.pcapng", connection_id))
+        .set_title("Export Capture")
+        .blocking_save_file();
+
+    let file_path = file_path.ok_or("No file selected")?;
+    let path = file_path.as_path().ok_or("Invalid file path")?;
+
+    let (contents, packets) = capture.export(CaptureFormat::from_path(path), &connection_id);
+    fs::write(path, contents).map_err(|e| e.to_string())?;
+
+    Ok(packets)
+}
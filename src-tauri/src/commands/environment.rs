@@ -0,0 +1,167 @@
+use crate::database::{Database, DbPool};
+use crate::models::{Environment, EnvironmentVariable, CreateEnvironmentRequest, UpdateEnvironmentRequest};
+use crate::script::SessionVariables;
+use chrono::Utc;
+use rusqlite::params;
+use std::collections::HashMap;
+use tauri::State;
+use uuid::Uuid;
+
+type DbResult<T = ()> = Result<T, String>;
+
+/// List all environments
+#[tauri::command]
+pub fn list_environments(db_pool: State<DbPool>) -> DbResult<Vec<Environment>> {
+    db_pool.with(|db| {
+        let mut stmt = db.conn().prepare(
+            "SELECT id, name, variables, is_active, created_at, updated_at FROM environments ORDER BY created_at"
+        )?;
+
+        let environments = stmt.query_map([], |row| {
+            let variables: String = row.get(2)?;
+            Ok(Environment {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                variables: serde_json::from_str(&variables).unwrap_or_default(),
+                active: row.get::<_, i32>(3)? != 0,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(environments)
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Create a new environment
+#[tauri::command]
+pub fn create_environment(db_pool: State<DbPool>, request: CreateEnvironmentRequest) -> DbResult<Environment> {
+    let variables = variables_json(&request.variables)?;
+
+    db_pool.with(|db| {
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+
+        db.conn().execute(
+            "INSERT INTO environments (id, name, variables, is_active, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 0, ?4, ?5)",
+            params![id, request.name, variables, now, now],
+        )?;
+
+        Ok(Environment {
+            id,
+            name: request.name,
+            variables: request.variables,
+            active: false,
+            created_at: now.clone(),
+            updated_at: now,
+        })
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Update an environment's name and variables
+#[tauri::command]
+pub fn update_environment(db_pool: State<DbPool>, request: UpdateEnvironmentRequest) -> DbResult<Environment> {
+    let variables = variables_json(&request.variables)?;
+
+    db_pool.with(|db| {
+        let now = Utc::now().to_rfc3339();
+
+        db.conn().execute(
+            "UPDATE environments SET name = ?1, variables = ?2, updated_at = ?3 WHERE id = ?4",
+            params![request.name, variables, now, request.id],
+        )?;
+
+        let (active, created_at) = db.conn().query_row(
+            "SELECT is_active, created_at FROM environments WHERE id = ?1",
+            [&request.id],
+            |row| Ok((row.get::<_, i32>(0)? != 0, row.get::<_, String>(1)?)),
+        )?;
+
+        Ok(Environment {
+            id: request.id,
+            name: request.name,
+            variables: request.variables,
+            active,
+            created_at,
+            updated_at: now,
+        })
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Delete an environment
+#[tauri::command]
+pub fn delete_environment(db_pool: State<DbPool>, id: String) -> DbResult<()> {
+    db_pool.with(|db| {
+        db.conn().execute("DELETE FROM environments WHERE id = ?1", [&id])?;
+        Ok(())
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Make an environment the one used when sending (None to send without one)
+#[tauri::command]
+pub fn set_active_environment(db_pool: State<DbPool>, id: Option<String>) -> DbResult<()> {
+    db_pool.with(|db| {
+        db.conn().execute(
+            "UPDATE environments SET is_active = CASE WHEN id = ?1 THEN 1 ELSE 0 END",
+            [&id],
+        )?;
+        Ok(())
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Variables set by protocol scripts; they override environment variables of the same name
+#[tauri::command]
+pub fn list_session_variables(session: State<SessionVariables>) -> Vec<EnvironmentVariable> {
+    session
+        .list()
+        .into_iter()
+        .map(|(key, value)| EnvironmentVariable { key, value })
+        .collect()
+}
+
+/// Forget the variables set by protocol scripts
+#[tauri::command]
+pub fn clear_session_variables(session: State<SessionVariables>) {
+    session.clear();
+}
+
+/// Variables of the active environment (empty when none is active)
+pub(crate) fn active_variables(db: &Database) -> rusqlite::Result<HashMap<String, String>> {
+    let variables = db.conn().query_row(
+        "SELECT variables FROM environments WHERE is_active = 1",
+        [],
+        |row| row.get::<_, String>(0),
+    );
+
+    let variables = match variables {
+        Ok(json) => json,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(HashMap::new()),
+        Err(e) => return Err(e),
+    };
+
+    let variables: Vec<EnvironmentVariable> = serde_json::from_str(&variables).unwrap_or_default();
+    Ok(variables.into_iter().map(|v| (v.key, v.value)).collect())
+}
+
+/// Validate variable names and serialize them for storage
+fn variables_json(variables: &[EnvironmentVariable]) -> Result<String, String> {
+    for variable in variables {
+        let valid = variable.key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && variable.key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+        if !valid {
+            return Err(format!("Invalid variable name '{}': use letters, digits, '_', '-' or '.'", variable.key));
+        }
+    }
+    if let Some(duplicate) = variables.iter().enumerate().find(|(i, v)| variables[..*i].iter().any(|o| o.key == v.key)) {
+        return Err(format!("Variable '{}' is defined twice", duplicate.1.key));
+    }
+
+    serde_json::to_string(variables).map_err(|e| e.to_string())
+}
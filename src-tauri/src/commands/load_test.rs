@@ -0,0 +1,171 @@
+use crate::commands::protocol::{encode_protocol_fields, load_protocol};
+use crate::database::DbPool;
+use crate::models::{LoadTestConfig, LoadTestFinished, LoadTestProgress, LoadTestRun, LoadTestSummary};
+use crate::protocol::hex_string_to_bytes;
+use crate::protocol::generator::GeneratorState;
+use crate::script::SessionVariables;
+use crate::tcp::{load, LoadTests};
+use crate::template::Sequences;
+use chrono::Utc;
+use rusqlite::params;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+type DbResult<T = ()> = Result<T, String>;
+
+/// Start a load test in the background and return its run id.
+/// Progress is emitted as `load-test-progress` once a second and the stored result as
+/// `load-test-finished`. A protocol is encoded once, with placeholders and generators
+/// resolved at the start; its scripts don't run under load.
+#[tauri::command]
+pub fn start_load_test(
+    app: AppHandle,
+    db_pool: State<DbPool>,
+    sequences: State<Sequences>,
+    generators: State<GeneratorState>,
+    session: State<SessionVariables>,
+    tests: State<LoadTests>,
+    config: LoadTestConfig,
+) -> Result<String, String> {
+    load::validate(&config)?;
+    let run_id = Uuid::new_v4().to_string();
+
+    let payload = match &config.protocol_id {
+        Some(protocol_id) => {
+            let protocol = db_pool
+                .with(|db| load_protocol(db, protocol_id))
+                .map_err(|e| e.to_string())?
+                .ok_or("Protocol not found")?;
+            let hex = encode_protocol_fields(
+                db_pool,
+                sequences,
+                generators,
+                session,
+                protocol.fields,
+                Some(format!("load-test:{}", run_id)),
+                None,
+            )?;
+            hex_string_to_bytes(&hex)?
+        }
+        None => hex_string_to_bytes(&config.payload)?,
+    };
+    if payload.is_empty() {
+        return Err("Nothing to send".to_string());
+    }
+
+    let stop = tests.register(&run_id);
+    let id = run_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let db_pool = app.state::<DbPool>();
+        let finished = execute(&db_pool, &id, config, payload, stop, |progress| {
+            let _ = app.emit("load-test-progress", progress);
+        })
+        .await;
+        app.state::<LoadTests>().finish(&id);
+        let _ = app.emit("load-test-finished", finished);
+    });
+
+    Ok(run_id)
+}
+
+/// Run a load test to the end and store its result
+pub(crate) async fn execute(
+    db_pool: &DbPool,
+    run_id: &str,
+    config: LoadTestConfig,
+    payload: Vec<u8>,
+    stop: Arc<AtomicBool>,
+    mut emit: impl FnMut(LoadTestProgress),
+) -> LoadTestFinished {
+    let started_at = Utc::now().to_rfc3339();
+    let summary = load::run(&config, payload, stop, |progress| {
+        emit(LoadTestProgress {
+            run_id: run_id.to_string(),
+            elapsed_ms: progress.elapsed.as_millis() as u64,
+            requests: progress.requests,
+            errors: progress.errors,
+            active_connections: progress.active_connections,
+            sample: progress.sample,
+        })
+    })
+    .await;
+
+    let run = LoadTestRun {
+        id: run_id.to_string(),
+        config,
+        summary,
+        started_at,
+    };
+    // The numbers are still worth showing when they can't be kept
+    let error = store_run(db_pool, &run).err();
+    LoadTestFinished { run, error }
+}
+
+fn store_run(db_pool: &DbPool, run: &LoadTestRun) -> DbResult<()> {
+    let config = serde_json::to_string(&run.config).map_err(|e| e.to_string())?;
+    let summary = serde_json::to_string(&run.summary).map_err(|e| e.to_string())?;
+    db_pool.with(|db| {
+        db.conn().execute(
+            "INSERT INTO load_test_runs (id, config, summary, started_at) VALUES (?1, ?2, ?3, ?4)",
+            params![run.id, config, summary, run.started_at],
+        )?;
+        Ok(())
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Ask a running load test to stop; it finishes as usual with what was measured so far
+#[tauri::command]
+pub fn stop_load_test(tests: State<LoadTests>, run_id: String) -> Result<(), String> {
+    if tests.stop(&run_id) {
+        Ok(())
+    } else {
+        Err("Load test is not running".to_string())
+    }
+}
+
+/// List stored load test runs, newest first
+#[tauri::command]
+pub fn list_load_test_runs(db_pool: State<DbPool>) -> DbResult<Vec<LoadTestRun>> {
+    db_pool.with(|db| {
+        let mut stmt = db.conn().prepare(
+            "SELECT id, config, summary, started_at FROM load_test_runs ORDER BY started_at DESC"
+        )?;
+
+        let runs = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        // Rows written by another version that no longer parse are left out
+        Ok(runs
+            .into_iter()
+            .filter_map(|(id, config, summary, started_at)| {
+                Some(LoadTestRun {
+                    id,
+                    config: serde_json::from_str(&config).ok()?,
+                    summary: serde_json::from_str::<LoadTestSummary>(&summary).ok()?,
+                    started_at,
+                })
+            })
+            .collect())
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Delete a stored load test run
+#[tauri::command]
+pub fn delete_load_test_run(db_pool: State<DbPool>, id: String) -> DbResult<()> {
+    db_pool.with(|db| {
+        db.conn().execute("DELETE FROM load_test_runs WHERE id = ?1", [&id])?;
+        Ok(())
+    })
+    .map_err(|e| e.to_string())
+}
@@ -1,7 +1,15 @@
+use crate::commands::environment::active_variables;
+use crate::commands::protocol::{load_protocol, resolve_fields};
+use crate::database::DbPool;
+use crate::models::ProtocolHooks;
+use crate::protocol::correlation::{correlation_key, ResponseMatcher};
+use crate::protocol::{self, bytes_to_hex_string, hex_string_to_bytes, DecodedField};
+use crate::script::{self, SessionVariables};
 use crate::tcp::ConnectionManager;
+use crate::template::{self, NumberStyle, Sequences};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tauri::State;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -9,6 +17,9 @@ pub struct SendMessageRequest {
     pub connection_id: String,
     pub data: String,
     pub mode: String, // "text" or "hex"
+    /// Protocol the data was encoded with; its hooks run around the exchange
+    #[serde(default)]
+    pub protocol_id: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -16,46 +27,119 @@ pub struct SendMessageResponse {
     pub success: bool,
     pub response_data: String,
     pub response_time_ms: u64,
+    /// Set when a post-receive hook rejected the response
     pub error: Option<String>,
 }
 
-fn hex_string_to_bytes(hex: &str) -> Result<Vec<u8>, String> {
-    let hex = hex.replace(" ", "").replace("\n", "").replace("\r", "");
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CorrelatedSendRequest {
+    pub connection_id: String,
+    /// Protocol whose correlation rule pairs the response with this request
+    pub protocol_id: String,
+    /// Encoded request, in hex
+    pub data: String,
+}
 
-    if hex.len() % 2 != 0 {
-        return Err("Hex string must have even length".to_string());
+#[derive(Debug, Serialize)]
+pub struct CorrelatedSendResponse {
+    pub response_data: String,
+    pub response_time_ms: u64,
+    /// Bytes of the correlation field, in hex
+    pub correlation_value: String,
+    /// Response decoded with the rule's response protocol
+    pub fields: Vec<DecodedField>,
+    /// Set when a post-receive hook rejected the response
+    pub error: Option<String>,
+}
+
+/// Resolve `{{...}}` placeholders with the session variables and active environment;
+/// `{{$seq}}` counts per connection
+fn render_payload(
+    db_pool: &DbPool,
+    sequences: &Sequences,
+    session: &SessionVariables,
+    request: &SendMessageRequest,
+) -> Result<String, String> {
+    if !template::has_placeholders(&request.data) {
+        return Ok(request.data.clone());
     }
 
-    (0..hex.len())
-        .step_by(2)
-        .map(|i| {
-            u8::from_str_radix(&hex[i..i + 2], 16)
-                .map_err(|e| format!("Invalid hex at position {}: {}", i, e))
-        })
-        .collect()
+    let variables = session.overlay(db_pool.with(active_variables).map_err(|e| e.to_string())?);
+    let context = template::Context::new(&variables, || sequences.next(&request.connection_id));
+    let style = if request.mode == "hex" { NumberStyle::Hex(None) } else { NumberStyle::Decimal };
+    template::render(&request.data, &context, style).map_err(|e| e.to_string())
 }
 
-fn bytes_to_hex_string(bytes: &[u8]) -> String {
-    bytes
-        .iter()
-        .map(|b| format!("{:02X}", b))
-        .collect::<Vec<_>>()
-        .join(" ")
+/// Hooks of the protocol a message was encoded with
+fn protocol_hooks(db_pool: &DbPool, protocol_id: Option<&str>) -> Result<ProtocolHooks, String> {
+    let Some(protocol_id) = protocol_id else {
+        return Ok(ProtocolHooks::default());
+    };
+    let protocol = db_pool
+        .with(|db| load_protocol(db, protocol_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Protocol not found")?;
+    Ok(protocol.hooks.unwrap_or_default())
+}
+
+fn run_pre_send(db_pool: &DbPool, session: &SessionVariables, hooks: &ProtocolHooks, data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let Some(script) = &hooks.pre_send else {
+        return Ok(data);
+    };
+    let environment = db_pool.with(active_variables).map_err(|e| e.to_string())?;
+    script::pre_send(script, &data, session, environment).map_err(|e| e.to_string())
+}
+
+/// The hook's complaint about the response, if any
+fn run_post_receive(
+    db_pool: &DbPool,
+    session: &SessionVariables,
+    hooks: &ProtocolHooks,
+    request: &[u8],
+    response: &[u8],
+    fields: &[DecodedField],
+) -> Result<Option<String>, String> {
+    let Some(script) = &hooks.post_receive else {
+        return Ok(None);
+    };
+    let environment = db_pool.with(active_variables).map_err(|e| e.to_string())?;
+    Ok(script::post_receive(script, request, response, fields, session, environment)
+        .err()
+        .map(|e| e.to_string()))
+}
+
+/// Response fields for a post-receive hook; data the protocol can't decode gives none
+fn hook_fields(db_pool: &DbPool, hooks: &ProtocolHooks, protocol_id: Option<&str>, response: &[u8]) -> Result<Vec<DecodedField>, String> {
+    let Some(protocol_id) = protocol_id.filter(|_| hooks.post_receive.is_some()) else {
+        return Ok(Vec::new());
+    };
+    let protocol = db_pool
+        .with(|db| load_protocol(db, protocol_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Protocol not found")?;
+    let fields = resolve_fields(db_pool, protocol.fields)?;
+    Ok(protocol::decode(&fields, response).unwrap_or_default())
 }
 
 #[tauri::command]
 pub async fn send_message(
     manager: State<'_, Arc<ConnectionManager>>,
+    db_pool: State<'_, DbPool>,
+    sequences: State<'_, Sequences>,
+    session: State<'_, SessionVariables>,
     request: SendMessageRequest,
 ) -> Result<SendMessageResponse, String> {
     let start = Instant::now();
+    let data = render_payload(&db_pool, &sequences, &session, &request)?;
 
     // Convert data based on mode
     let data_bytes = match request.mode.as_str() {
-        "text" => request.data.as_bytes().to_vec(),
-        "hex" => hex_string_to_bytes(&request.data)?,
+        "text" => data.into_bytes(),
+        "hex" => hex_string_to_bytes(&data)?,
         _ => return Err(format!("Invalid mode: {}", request.mode)),
     };
+    let hooks = protocol_hooks(&db_pool, request.protocol_id.as_deref())?;
+    let data_bytes = run_pre_send(&db_pool, &session, &hooks, data_bytes)?;
 
     // Send and receive
     let response_bytes = manager
@@ -65,6 +149,9 @@ pub async fn send_message(
 
     let elapsed = start.elapsed();
 
+    let fields = hook_fields(&db_pool, &hooks, request.protocol_id.as_deref(), &response_bytes)?;
+    let error = run_post_receive(&db_pool, &session, &hooks, &data_bytes, &response_bytes, &fields)?;
+
     // Convert response based on mode
     let response_data = match request.mode.as_str() {
         "text" => String::from_utf8_lossy(&response_bytes).to_string(),
@@ -73,26 +160,32 @@ pub async fn send_message(
     };
 
     Ok(SendMessageResponse {
-        success: true,
+        success: error.is_none(),
         response_data,
         response_time_ms: elapsed.as_millis() as u64,
-        error: None,
+        error,
     })
 }
 
 #[tauri::command]
 pub async fn send_only(
     manager: State<'_, Arc<ConnectionManager>>,
+    db_pool: State<'_, DbPool>,
+    sequences: State<'_, Sequences>,
+    session: State<'_, SessionVariables>,
     request: SendMessageRequest,
 ) -> Result<SendMessageResponse, String> {
     let start = Instant::now();
+    let data = render_payload(&db_pool, &sequences, &session, &request)?;
 
     // Convert data based on mode
     let data_bytes = match request.mode.as_str() {
-        "text" => request.data.as_bytes().to_vec(),
-        "hex" => hex_string_to_bytes(&request.data)?,
+        "text" => data.into_bytes(),
+        "hex" => hex_string_to_bytes(&data)?,
         _ => return Err(format!("Invalid mode: {}", request.mode)),
     };
+    let hooks = protocol_hooks(&db_pool, request.protocol_id.as_deref())?;
+    let data_bytes = run_pre_send(&db_pool, &session, &hooks, data_bytes)?;
 
     // Send only
     manager
@@ -140,3 +233,68 @@ pub async fn receive_only(
         error: None,
     })
 }
+
+/// Send an encoded protocol message and wait for the response its protocol's correlation rule
+/// pairs with it. Other requests may be in flight on the same connection meanwhile; each one
+/// times out on its own. The protocol's hooks run as for `send_message`.
+#[tauri::command]
+pub async fn send_correlated(
+    manager: State<'_, Arc<ConnectionManager>>,
+    db_pool: State<'_, DbPool>,
+    session: State<'_, SessionVariables>,
+    request: CorrelatedSendRequest,
+) -> Result<CorrelatedSendResponse, String> {
+    let start = Instant::now();
+
+    let request_protocol = db_pool
+        .with(|db| load_protocol(db, &request.protocol_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Protocol not found")?;
+    let rule = request_protocol
+        .correlation
+        .clone()
+        .ok_or_else(|| format!("Protocol '{}' has no correlation rule", request_protocol.name))?;
+    let response_protocol = match &rule.response_protocol_id {
+        Some(id) if *id != request_protocol.id => db_pool
+            .with(|db| load_protocol(db, id))
+            .map_err(|e| e.to_string())?
+            .ok_or("Response protocol not found")?,
+        _ => request_protocol.clone(),
+    };
+
+    let request_fields = resolve_fields(&db_pool, request_protocol.fields)?;
+    let response_fields = resolve_fields(&db_pool, response_protocol.fields)?;
+
+    let hooks = request_protocol.hooks.clone().unwrap_or_default();
+    let data_bytes = run_pre_send(&db_pool, &session, &hooks, hex_string_to_bytes(&request.data)?)?;
+    let key = correlation_key(&request_fields, &data_bytes, &rule.request_field)
+        .map_err(|e| format!("Request: {}", e))?;
+
+    // A matcher is reused while the response protocol and rule stay the same
+    let matcher_name = format!("{}@{}#{}", response_protocol.id, response_protocol.updated_at, rule.response_field);
+    let matcher = Arc::new(ResponseMatcher::new(response_fields.clone(), rule.response_field));
+
+    let response_bytes = manager
+        .send_correlated(
+            &request.connection_id,
+            &data_bytes,
+            &matcher_name,
+            matcher,
+            key.clone(),
+            Duration::from_millis(rule.timeout_ms),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let elapsed = start.elapsed();
+    let fields = protocol::decode(&response_fields, &response_bytes).map_err(|e| e.to_string())?;
+    let error = run_post_receive(&db_pool, &session, &hooks, &data_bytes, &response_bytes, &fields)?;
+
+    Ok(CorrelatedSendResponse {
+        response_data: bytes_to_hex_string(&response_bytes),
+        response_time_ms: elapsed.as_millis() as u64,
+        correlation_value: bytes_to_hex_string(&key),
+        fields,
+        error,
+    })
+}
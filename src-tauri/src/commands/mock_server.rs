@@ -0,0 +1,220 @@
+use crate::commands::protocol::{load_protocol, resolve_fields};
+use crate::database::DbPool;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// The test server binary, bundled with the app as a sidecar (`externalBin`)
+const SERVER_BINARY: &str = "tcp-test-server";
+/// How long the server gets to start listening
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The test server running in mock mode, when started from the app
+#[derive(Default)]
+pub struct MockServer {
+    /// Process and the port it listens on
+    process: Mutex<Option<(Child, u16)>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartMockServerRequest {
+    /// Mock rules file
+    pub config_path: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockServerStatus {
+    pub running: bool,
+    pub port: Option<u16>,
+}
+
+/// Start the test server in mock mode with a rules file.
+/// Protocols the rules name but the file doesn't define are taken from the saved protocols.
+/// Server output is emitted line by line as `mock-server-log`.
+/// Returns once the server listens, or with its last output line if it exits first.
+#[tauri::command]
+pub fn start_mock_server(
+    app: AppHandle,
+    db_pool: State<DbPool>,
+    server: State<MockServer>,
+    request: StartMockServerRequest,
+) -> Result<MockServerStatus, String> {
+    let mut running = server.process.lock().unwrap();
+    if let Some((child, _)) = running.as_mut() {
+        if child.try_wait().map_err(|e| e.to_string())?.is_none() {
+            return Err("Mock server is already running".to_string());
+        }
+    }
+
+    let text = std::fs::read_to_string(&request.config_path)
+        .map_err(|e| format!("Failed to read {}: {}", request.config_path, e))?;
+    let mut config: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid mock rules: {}", e))?;
+    embed_saved_protocols(&db_pool, &mut config)?;
+
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join("mock-server.json");
+    let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())?;
+
+    let server_path = sidecar_path()?;
+    let mut child = Command::new(&server_path)
+        .arg("--port")
+        .arg(request.port.to_string())
+        .arg("--mock")
+        .arg(&path)
+        // Startup errors are reported from the last output line
+        .env("RUST_LIB_BACKTRACE", "0")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", server_path.display(), e))?;
+
+    let (lines, output) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        forward_output(app.clone(), stdout, lines.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward_output(app, stderr, lines);
+    }
+    wait_until_listening(&mut child, &output)?;
+
+    *running = Some((child, request.port));
+    Ok(MockServerStatus { running: true, port: Some(request.port) })
+}
+
+/// The sidecar is installed next to the app's own executable
+fn sidecar_path() -> Result<PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let dir = exe.parent().ok_or("The app's directory is unknown")?;
+    Ok(dir.join(format!("{}{}", SERVER_BINARY, std::env::consts::EXE_SUFFIX)))
+}
+
+/// Wait for the server's `listening on` line; a port clash or bad rules make it exit instead
+fn wait_until_listening(child: &mut Child, output: &mpsc::Receiver<String>) -> Result<(), String> {
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    let mut last_line = String::new();
+    loop {
+        match output.recv_timeout(Duration::from_millis(50)) {
+            Ok(line) if line.contains("listening on") => return Ok(()),
+            Ok(line) if !line.trim().is_empty() => last_line = line,
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            // Both outputs closed: the server is exiting
+            Err(RecvTimeoutError::Disconnected) => {
+                child.wait().map_err(|e| e.to_string())?;
+            }
+        }
+
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            // The reason is usually the last thing it printed
+            if let Some(line) = output.iter().filter(|l| !l.trim().is_empty()).last() {
+                last_line = line;
+            }
+            return Err(format!("Mock server exited ({}): {}", status, last_line.trim()));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("Mock server didn't start listening within {} s", STARTUP_TIMEOUT.as_secs()));
+        }
+    }
+}
+
+/// Stop the mock server started from the app
+#[tauri::command]
+pub fn stop_mock_server(server: State<MockServer>) -> Result<(), String> {
+    if let Some((mut child, _)) = server.process.lock().unwrap().take() {
+        child.kill().map_err(|e| e.to_string())?;
+        child.wait().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Whether the mock server is still running; it stops by itself on bad rules
+#[tauri::command]
+pub fn mock_server_status(server: State<MockServer>) -> Result<MockServerStatus, String> {
+    let mut running = server.process.lock().unwrap();
+    if let Some((child, port)) = running.as_mut() {
+        if child.try_wait().map_err(|e| e.to_string())?.is_none() {
+            return Ok(MockServerStatus { running: true, port: Some(*port) });
+        }
+        *running = None;
+    }
+    Ok(MockServerStatus { running: false, port: None })
+}
+
+/// Emit each output line, and pass it on to `lines` while anyone still listens there
+fn forward_output(app: AppHandle, output: impl Read + Send + 'static, lines: mpsc::Sender<String>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(output).lines().map_while(Result::ok) {
+            let _ = lines.send(line.clone());
+            let _ = app.emit("mock-server-log", line);
+        }
+    });
+}
+
+/// Add the saved protocols the rules name to the file's own `protocols`,
+/// with references to other protocols resolved
+pub(crate) fn embed_saved_protocols(db_pool: &DbPool, config: &mut Value) -> Result<(), String> {
+    let defined: BTreeSet<String> = config["protocols"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p["name"].as_str().map(str::to_string))
+        .collect();
+
+    let mut replies: Vec<&Value> = Vec::new();
+    let mut named = BTreeSet::new();
+    for rule in config["rules"].as_array().into_iter().flatten() {
+        named.extend(rule["match"]["protocol"].as_str().map(str::to_string));
+        replies.push(&rule["reply"]);
+    }
+    replies.extend(config["timers"].as_array().into_iter().flatten().map(|t| &t["reply"]));
+    replies.push(&config["greeting"]);
+    named.extend(replies.iter().filter_map(|r| r["protocol"].as_str().map(str::to_string)));
+
+    let mut embedded = Vec::new();
+    for name in named.difference(&defined) {
+        let protocol = db_pool
+            .with(|db| {
+                let id = db.conn().query_row(
+                    "SELECT id FROM protocols WHERE name = ?1 ORDER BY created_at LIMIT 1",
+                    [name],
+                    |row| row.get::<_, String>(0),
+                );
+                match id {
+                    Ok(id) => load_protocol(db, &id),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                    Err(e) => Err(e),
+                }
+            })
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("No saved protocol named '{}'", name))?;
+        let fields = resolve_fields(db_pool, protocol.fields)?;
+        embedded.push(serde_json::json!({ "name": protocol.name, "fields": fields }));
+    }
+
+    if !embedded.is_empty() {
+        let protocols = config
+            .as_object_mut()
+            .ok_or("Mock rules must be a JSON object")?
+            .entry("protocols")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        protocols
+            .as_array_mut()
+            .ok_or("`protocols` must be a list")?
+            .extend(embedded);
+    }
+    Ok(())
+}
@@ -1,9 +1,17 @@
 pub mod app;
+pub mod capture;
 pub mod connection;
+pub mod environment;
+pub mod load_test;
+pub mod mock_server;
 pub mod message;
 pub mod protocol;
 
 pub use app::*;
+pub use capture::*;
 pub use connection::*;
+pub use environment::*;
+pub use load_test::*;
+pub use mock_server::*;
 pub use message::*;
 pub use protocol::*;
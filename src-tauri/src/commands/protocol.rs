@@ -1,6 +1,19 @@
-use crate::database::DbPool;
-use crate::models::{Protocol, ProtocolField, CreateProtocolRequest, UpdateProtocolRequest, ProtocolImport, ProtocolFieldExport};
+use crate::database::{Database, DbPool};
+use crate::models::{Protocol, ProtocolField, FieldKind, Endianness, VarintFormat, CreateProtocolRequest, UpdateProtocolRequest, ProtocolImport, ProtocolFieldExport,
+                    ProtocolRevision, ProtocolRevisionSummary,
+                    PROTOCOL_LIBRARY_VERSION, ProtocolLibrary, LibraryProtocol, ConflictReason, LibraryConflict, LibraryPreview,
+                    ConflictResolution, ImportLibraryRequest, LibraryImportSummary};
+use crate::protocol::{self, bytes_to_hex_string, hex_string_to_bytes, DecodedField};
+use crate::commands::environment::active_variables;
+use crate::protocol::diff::RevisionDiff;
+use crate::protocol::dissector::Transport;
+use crate::protocol::generator::GeneratorState;
+use crate::script::SessionVariables;
+use crate::template::{self, Sequences};
+use packetforge_core::detect;
 use chrono::Utc;
+use rusqlite::params;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use tauri::{AppHandle, State};
 use tauri_plugin_dialog::DialogExt;
@@ -14,7 +27,7 @@ type DbResult<T = ()> = Result<T, String>;
 pub fn list_protocols(db_pool: State<DbPool>) -> DbResult<Vec<Protocol>> {
     db_pool.with(|db| {
         let mut stmt = db.conn().prepare(
-            "SELECT id, name, description, created_at, updated_at FROM protocols ORDER BY created_at DESC"
+            "SELECT id, name, description, created_at, updated_at, correlation, hooks FROM protocols ORDER BY created_at DESC"
         )?;
 
         let protocol_map = stmt.query_map([], |row| {
@@ -24,46 +37,26 @@ pub fn list_protocols(db_pool: State<DbPool>) -> DbResult<Vec<Protocol>> {
                 row.get::<_, Option<String>>(2)?, // description
                 row.get::<_, String>(3)?, // created_at
                 row.get::<_, String>(4)?, // updated_at
+                row.get::<_, Option<String>>(5)?, // correlation
+                row.get::<_, Option<String>>(6)?, // hooks
             ))
         })?;
 
         let mut protocols = Vec::new();
 
         for protocol_data in protocol_map {
-            let (id, name, description, created_at, updated_at) = protocol_data?;
+            let (id, name, description, created_at, updated_at, correlation, hooks) = protocol_data?;
 
             // Fetch fields for this protocol
-            let mut field_stmt = db.conn().prepare(
-                "SELECT id, name, length, is_variable, value_type, value_format, value
-                 FROM protocol_fields
-                 WHERE protocol_id = ?1
-                 ORDER BY field_order ASC"
-            )?;
-
-            let fields = field_stmt.query_map([&id], |row| {
-                let value_format: Option<String> = row.get(5)?;
-                Ok(ProtocolField {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    length: row.get(2)?,
-                    is_variable: row.get::<_, i32>(3)? == 1,
-                    value_type: row.get(4)?,
-                    value_format: if value_format.is_some() && !value_format.as_ref().unwrap().is_empty() {
-                        Some(value_format.unwrap())
-                    } else {
-                        None
-                    },
-                    value: row.get(6)?,
-                    description: None,
-                })
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
+            let fields = load_fields(db, &id, None)?;
 
             protocols.push(Protocol {
                 id,
                 name,
                 description,
                 fields,
+                correlation: correlation.and_then(|c| serde_json::from_str(&c).ok()),
+                hooks: hooks.and_then(|h| serde_json::from_str(&h).ok()),
                 created_at,
                 updated_at,
             });
@@ -77,111 +70,220 @@ pub fn list_protocols(db_pool: State<DbPool>) -> DbResult<Vec<Protocol>> {
 /// Get a single protocol by ID
 #[tauri::command]
 pub fn get_protocol(db_pool: State<DbPool>, id: String) -> DbResult<Option<Protocol>> {
-    db_pool.with(|db| {
-        let mut stmt = db.conn().prepare(
-            "SELECT id, name, description, created_at, updated_at
-             FROM protocols WHERE id = ?1"
-        )?;
+    db_pool.with(|db| load_protocol(db, &id))
+        .map_err(|e| e.to_string())
+}
 
-        let protocol_data = stmt.query_row([&id], |row| {
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, Option<String>>(2)?,
-                row.get::<_, String>(3)?,
-                row.get::<_, String>(4)?,
-            ))
-        });
+/// Load a protocol and its field tree
+pub(crate) fn load_protocol(db: &Database, id: &str) -> rusqlite::Result<Option<Protocol>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT id, name, description, created_at, updated_at, correlation, hooks
+         FROM protocols WHERE id = ?1"
+    )?;
 
-        match protocol_data {
-            Ok((id, name, description, created_at, updated_at)) => {
-                // Fetch fields
-                let mut field_stmt = db.conn().prepare(
-                    "SELECT id, name, length, is_variable, value_type, value_format, value
-                     FROM protocol_fields
-                     WHERE protocol_id = ?1
-                     ORDER BY field_order ASC"
-                )?;
+    let protocol_data = stmt.query_row([id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, Option<String>>(5)?,
+            row.get::<_, Option<String>>(6)?,
+        ))
+    });
 
-                let fields = field_stmt.query_map([&id], |row| {
-                    let value_format: Option<String> = row.get(5)?;
-                    Ok(ProtocolField {
-                        id: row.get(0)?,
-                        name: row.get(1)?,
-                        length: row.get(2)?,
-                        is_variable: row.get::<_, i32>(3)? == 1,
-                        value_type: row.get(4)?,
-                        value_format: if value_format.is_some() && !value_format.as_ref().unwrap().is_empty() {
-                            Some(value_format.unwrap())
-                        } else {
-                            None
-                        },
-                        value: row.get(6)?,
-                        description: None,
-                    })
-                })?
-                .collect::<Result<Vec<_>, _>>()?;
+    match protocol_data {
+        Ok((id, name, description, created_at, updated_at, correlation, hooks)) => {
+            // Fetch fields
+            let fields = load_fields(db, &id, None)?;
+
+            Ok(Some(Protocol {
+                id,
+                name,
+                description,
+                fields,
+                correlation: correlation.and_then(|c| serde_json::from_str(&c).ok()),
+                hooks: hooks.and_then(|h| serde_json::from_str(&h).ok()),
+                created_at,
+                updated_at,
+            }))
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Load the fields below `parent_id` (top level when None), including nested groups and arrays
+fn load_fields(db: &Database, protocol_id: &str, parent_id: Option<&str>) -> rusqlite::Result<Vec<ProtocolField>> {
+    load_field_rows(db, protocol_id, parent_id)?
+        .into_iter()
+        .map(|(_, field)| load_children(db, protocol_id, field))
+        .collect()
+}
+
+/// Attach the members and element template of a group or array field
+fn load_children(db: &Database, protocol_id: &str, mut field: ProtocolField) -> rusqlite::Result<ProtocolField> {
+    if field.kind == FieldKind::Scalar {
+        return Ok(field);
+    }
+
+    for (role, child) in load_field_rows(db, protocol_id, Some(&field.id))? {
+        let child = load_children(db, protocol_id, child)?;
+        if role == "element" {
+            field.element = Some(Box::new(child));
+        } else {
+            field.fields.push(child);
+        }
+    }
+
+    Ok(field)
+}
+
+/// Fetch one level of field rows together with their role
+fn load_field_rows(db: &Database, protocol_id: &str, parent_id: Option<&str>) -> rusqlite::Result<Vec<(String, ProtocolField)>> {
+    let mut field_stmt = db.conn().prepare(
+        "SELECT id, name, length, is_variable, value_type, value_format, value,
+                role, kind, protocol_ref, repeat_rule, field_condition, tlv_spec, tlv_tag,
+                varint_format, length_of, text_spec, description, endianness, generator
+         FROM protocol_fields
+         WHERE protocol_id = ?1 AND parent_id IS ?2
+         ORDER BY field_order ASC"
+    )?;
+
+    let rows = field_stmt.query_map(params![protocol_id, parent_id], |row| {
+        let value_format: Option<String> = row.get(5)?;
+        let repeat_rule: Option<String> = row.get(10)?;
+        let condition: Option<String> = row.get(11)?;
+        let tlv_spec: Option<String> = row.get(12)?;
+        let varint_format: Option<String> = row.get(14)?;
+        let length_of: Option<String> = row.get(15)?;
+        let text_spec: Option<String> = row.get(16)?;
+        let endianness: Option<String> = row.get(18)?;
+        let generator: Option<String> = row.get(19)?;
+        Ok((
+            row.get::<_, String>(7)?,
+            ProtocolField {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                length: row.get(2)?,
+                is_variable: row.get::<_, i32>(3)? == 1,
+                value_type: row.get(4)?,
+                value_format: value_format.filter(|f| !f.is_empty()),
+                value: row.get(6)?,
+                kind: FieldKind::parse(&row.get::<_, String>(8)?),
+                protocol_ref: row.get(9)?,
+                repeat: repeat_rule.and_then(|r| serde_json::from_str(&r).ok()),
+                condition: condition.and_then(|c| serde_json::from_str(&c).ok()),
+                tlv: tlv_spec.and_then(|t| serde_json::from_str(&t).ok()),
+                tag: row.get::<_, Option<i64>>(13)?.map(|t| t as u64),
+                varint_format: varint_format.map(|f| VarintFormat::parse(&f)),
+                length_of: length_of.and_then(|l| serde_json::from_str(&l).ok()),
+                text: text_spec.and_then(|t| serde_json::from_str(&t).ok()),
+                description: row.get::<_, Option<String>>(17)?.filter(|d| !d.is_empty()),
+                endianness: endianness.map(|e| Endianness::parse(&e)),
+                generator: generator.and_then(|g| serde_json::from_str(&g).ok()),
+                ..Default::default()
+            },
+        ))
+    })?
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(rows)
+}
+
+/// Insert fields below `parent_id` (top level when None), recursing into groups and arrays
+fn insert_fields(
+    db: &Database,
+    protocol_id: &str,
+    parent_id: Option<&str>,
+    role: &str,
+    fields: &[ProtocolField],
+) -> rusqlite::Result<()> {
+    for (index, field) in fields.iter().enumerate() {
+        let length_str = field.length.map(|l: i32| l.to_string()).unwrap_or_else(|| "0".to_string());
+        let is_var = if field.is_variable { 1 } else { 0 };
+        let value_fmt = field.value_format.as_deref().unwrap_or("");
+        let repeat_rule = field.repeat.as_ref().and_then(|r| serde_json::to_string(r).ok());
+        let condition = field.condition.as_ref().and_then(|c| serde_json::to_string(c).ok());
+        let tlv_spec = field.tlv.as_ref().and_then(|t| serde_json::to_string(t).ok());
+        let length_of = field.length_of.as_ref().and_then(|l| serde_json::to_string(l).ok());
+        let text_spec = field.text.as_ref().and_then(|t| serde_json::to_string(t).ok());
+        let generator = field.generator.as_ref().and_then(|g| serde_json::to_string(g).ok());
+
+        db.conn().execute(
+            "INSERT INTO protocol_fields (id, protocol_id, name, length, is_variable, value_type, value_format, value, field_order,
+                                          parent_id, role, kind, protocol_ref, repeat_rule, field_condition, tlv_spec, tlv_tag,
+                                          varint_format, length_of, text_spec, description, endianness, generator)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+            params![
+                field.id,
+                protocol_id,
+                field.name,
+                length_str,
+                is_var,
+                field.value_type,
+                value_fmt,
+                field.value,
+                index as i32,
+                parent_id,
+                role,
+                field.kind.as_str(),
+                field.protocol_ref,
+                repeat_rule,
+                condition,
+                tlv_spec,
+                field.tag.map(|t| t as i64),
+                field.varint_format.map(|f| f.as_str()),
+                length_of,
+                text_spec,
+                field.description,
+                field.endianness.map(|e| e.as_str()),
+                generator,
+            ],
+        )?;
 
-                Ok(Some(Protocol {
-                    id,
-                    name,
-                    description,
-                    fields,
-                    created_at,
-                    updated_at,
-                }))
+        if field.kind != FieldKind::Scalar {
+            insert_fields(db, protocol_id, Some(&field.id), "field", &field.fields)?;
+            if let Some(element) = &field.element {
+                insert_fields(db, protocol_id, Some(&field.id), "element", std::slice::from_ref(element.as_ref()))?;
             }
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e),
         }
-    })
-    .map_err(|e| e.to_string())
+    }
+
+    Ok(())
 }
 
 /// Create a new protocol
 #[tauri::command]
 pub fn create_protocol(db_pool: State<DbPool>, request: CreateProtocolRequest) -> DbResult<Protocol> {
     db_pool.with(|db| {
+        let tx = db.conn().unchecked_transaction()?;
         let id = Uuid::new_v4().to_string();
         let now = Utc::now().to_rfc3339();
 
         // Insert protocol
         let desc = request.description.as_deref().unwrap_or("").to_string();
+        let correlation = request.correlation.as_ref().and_then(|c| serde_json::to_string(c).ok());
+        let hooks = request.hooks.as_ref().and_then(|h| serde_json::to_string(h).ok());
         db.conn().execute(
-            "INSERT INTO protocols (id, name, description, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            [&id, &request.name, &desc, &now, &now],
+            "INSERT INTO protocols (id, name, description, correlation, hooks, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id, request.name, desc, correlation, hooks, now, now],
         )?;
 
         // Insert fields
-        for (index, field) in request.fields.iter().enumerate() {
-            let length_str = field.length.map(|l: i32| l.to_string()).unwrap_or_else(|| "0".to_string());
-            let is_var = if field.is_variable { 1 } else { 0 };
-            let order = index as i32;
-            let value_fmt = field.value_format.as_deref().unwrap_or("");
-
-            db.conn().execute(
-                "INSERT INTO protocol_fields (id, protocol_id, name, length, is_variable, value_type, value_format, value, field_order)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-                [
-                    &field.id,
-                    &id,
-                    &field.name,
-                    &length_str,
-                    &is_var.to_string(),
-                    &field.value_type,
-                    value_fmt,
-                    &field.value,
-                    &order.to_string(),
-                ],
-            )?;
-        }
+        insert_fields(db, &id, None, "field", &request.fields)?;
+        record_revision(db, &id, &request.name, request.description.as_deref(), &request.fields, &now)?;
+        tx.commit()?;
 
         Ok(Protocol {
             id,
             name: request.name,
             description: request.description,
             fields: request.fields,
+            correlation: request.correlation,
+            hooks: request.hooks,
             created_at: now.clone(),
             updated_at: now,
         })
@@ -193,63 +295,196 @@ pub fn create_protocol(db_pool: State<DbPool>, request: CreateProtocolRequest) -
 #[tauri::command]
 pub fn update_protocol(db_pool: State<DbPool>, request: UpdateProtocolRequest) -> DbResult<Protocol> {
     db_pool.with(|db| {
+        let tx = db.conn().unchecked_transaction()?;
         let now = Utc::now().to_rfc3339();
+        ensure_baseline_revision(db, &request.id)?;
 
         // Update protocol
         let desc = request.description.as_deref().unwrap_or("").to_string();
+        let correlation = request.correlation.as_ref().and_then(|c| serde_json::to_string(c).ok());
+        let hooks = request.hooks.as_ref().and_then(|h| serde_json::to_string(h).ok());
         db.conn().execute(
-            "UPDATE protocols SET name = ?1, description = ?2, updated_at = ?3 WHERE id = ?4",
-            [&request.name, &desc, &now, &request.id],
+            "UPDATE protocols SET name = ?1, description = ?2, correlation = ?3, hooks = ?4, updated_at = ?5 WHERE id = ?6",
+            params![request.name, desc, correlation, hooks, now, request.id],
+        )?;
+        let created_at: String = db.conn().query_row(
+            "SELECT created_at FROM protocols WHERE id = ?1",
+            [&request.id],
+            |row| row.get(0),
         )?;
 
-        // Delete existing fields
+        // Delete existing fields (nested fields share the protocol_id)
         db.conn().execute(
             "DELETE FROM protocol_fields WHERE protocol_id = ?1",
             [&request.id],
         )?;
 
         // Insert new fields
-        for (index, field) in request.fields.iter().enumerate() {
-            let length_str = field.length.map(|l: i32| l.to_string()).unwrap_or_else(|| "0".to_string());
-            let is_var = if field.is_variable { 1 } else { 0 };
-            let order = index as i32;
-            let value_fmt = field.value_format.as_deref().unwrap_or("");
-
-            db.conn().execute(
-                "INSERT INTO protocol_fields (id, protocol_id, name, length, is_variable, value_type, value_format, value, field_order)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-                [
-                    &field.id,
-                    &request.id,
-                    &field.name,
-                    &length_str,
-                    &is_var.to_string(),
-                    &field.value_type,
-                    value_fmt,
-                    &field.value,
-                    &order.to_string(),
-                ],
-            )?;
-        }
+        insert_fields(db, &request.id, None, "field", &request.fields)?;
+        record_revision(db, &request.id, &request.name, request.description.as_deref(), &request.fields, &now)?;
+        tx.commit()?;
 
         Ok(Protocol {
             id: request.id,
             name: request.name,
             description: request.description,
             fields: request.fields,
-            created_at: String::new(), // Will be populated if needed
+            correlation: request.correlation,
+            hooks: request.hooks,
+            created_at,
             updated_at: now,
         })
     })
     .map_err(|e| e.to_string())
 }
 
+/// Snapshot a protocol as its next numbered revision
+fn record_revision(
+    db: &Database,
+    protocol_id: &str,
+    name: &str,
+    description: Option<&str>,
+    fields: &[ProtocolField],
+    created_at: &str,
+) -> rusqlite::Result<u32> {
+    let revision: u32 = db.conn().query_row(
+        "SELECT COALESCE(MAX(revision), 0) + 1 FROM protocol_revisions WHERE protocol_id = ?1",
+        [protocol_id],
+        |row| row.get(0),
+    )?;
+    let snapshot: Vec<ProtocolFieldExport> = fields.iter().cloned().map(Into::into).collect();
+    let snapshot = serde_json::to_string(&snapshot).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    db.conn().execute(
+        "INSERT INTO protocol_revisions (protocol_id, revision, name, description, fields, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![protocol_id, revision, name, description.filter(|d| !d.is_empty()), snapshot, created_at],
+    )?;
+    Ok(revision)
+}
+
+/// Protocols saved before revisions existed get their current state recorded before it is replaced
+fn ensure_baseline_revision(db: &Database, protocol_id: &str) -> rusqlite::Result<()> {
+    let count: u32 = db.conn().query_row(
+        "SELECT COUNT(*) FROM protocol_revisions WHERE protocol_id = ?1",
+        [protocol_id],
+        |row| row.get(0),
+    )?;
+    if count == 0 {
+        if let Some(current) = load_protocol(db, protocol_id)? {
+            record_revision(db, protocol_id, &current.name, current.description.as_deref(), &current.fields, &current.updated_at)?;
+        }
+    }
+    Ok(())
+}
+
+fn load_revision(db: &Database, protocol_id: &str, revision: u32) -> rusqlite::Result<Option<ProtocolRevision>> {
+    let row = db.conn().query_row(
+        "SELECT name, description, fields, created_at FROM protocol_revisions WHERE protocol_id = ?1 AND revision = ?2",
+        params![protocol_id, revision],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        },
+    );
+
+    match row {
+        Ok((name, description, fields, created_at)) => {
+            let fields: Vec<ProtocolFieldExport> = serde_json::from_str(&fields)
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?;
+            Ok(Some(ProtocolRevision {
+                protocol_id: protocol_id.to_string(),
+                revision,
+                name,
+                description,
+                fields: fields.into_iter().map(Into::into).collect(),
+                created_at,
+            }))
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// List the revisions of a protocol, newest first
+#[tauri::command]
+pub fn list_protocol_revisions(db_pool: State<DbPool>, protocol_id: String) -> DbResult<Vec<ProtocolRevisionSummary>> {
+    db_pool.with(|db| {
+        let mut stmt = db.conn().prepare(
+            "SELECT revision, name, fields, created_at FROM protocol_revisions
+             WHERE protocol_id = ?1 ORDER BY revision DESC"
+        )?;
+
+        let revisions = stmt.query_map([&protocol_id], |row| {
+            let fields: String = row.get(2)?;
+            Ok(ProtocolRevisionSummary {
+                revision: row.get(0)?,
+                name: row.get(1)?,
+                field_count: serde_json::from_str::<Vec<serde_json::Value>>(&fields).map(|f| f.len()).unwrap_or(0),
+                created_at: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(revisions)
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Get one revision of a protocol
+#[tauri::command]
+pub fn get_protocol_revision(db_pool: State<DbPool>, protocol_id: String, revision: u32) -> DbResult<Option<ProtocolRevision>> {
+    db_pool.with(|db| load_revision(db, &protocol_id, revision))
+        .map_err(|e| e.to_string())
+}
+
+/// Compare two revisions of a protocol field by field
+#[tauri::command]
+pub fn diff_protocol_revisions(db_pool: State<DbPool>, protocol_id: String, from: u32, to: u32) -> DbResult<RevisionDiff> {
+    let (before, after) = db_pool
+        .with(|db| Ok((load_revision(db, &protocol_id, from)?, load_revision(db, &protocol_id, to)?)))
+        .map_err(|e| e.to_string())?;
+    let before = before.ok_or_else(|| format!("Revision {} not found", from))?;
+    let after = after.ok_or_else(|| format!("Revision {} not found", to))?;
+
+    Ok(protocol::diff::diff_revisions(&before, &after))
+}
+
+/// Make an old revision current again; this is saved as a new revision.
+/// Revisions only hold the field tree, so the current correlation rule and hooks are kept.
+#[tauri::command]
+pub fn restore_protocol_revision(db_pool: State<DbPool>, protocol_id: String, revision: u32) -> DbResult<Protocol> {
+    let (old, current) = db_pool
+        .with(|db| Ok((load_revision(db, &protocol_id, revision)?, load_protocol(db, &protocol_id)?)))
+        .map_err(|e| e.to_string())?;
+    let old = old.ok_or_else(|| format!("Revision {} not found", revision))?;
+
+    let mut fields = old.fields;
+    refresh_field_ids(&mut fields, &HashMap::new());
+    update_protocol(db_pool, UpdateProtocolRequest {
+        id: protocol_id,
+        name: old.name,
+        description: old.description,
+        fields,
+        correlation: current.as_ref().and_then(|p| p.correlation.clone()),
+        hooks: current.and_then(|p| p.hooks),
+    })
+}
+
 /// Delete a protocol
 #[tauri::command]
 pub fn delete_protocol(db_pool: State<DbPool>, id: String) -> DbResult<()> {
     db_pool.with(|db| {
-        // Fields will be deleted automatically due to FOREIGN KEY ON DELETE CASCADE
+        // foreign_keys is not enabled on the connection, so ON DELETE CASCADE never fires
+        let tx = db.conn().unchecked_transaction()?;
+        db.conn().execute("DELETE FROM protocol_fields WHERE protocol_id = ?1", [&id])?;
+        db.conn().execute("DELETE FROM protocol_revisions WHERE protocol_id = ?1", [&id])?;
         db.conn().execute("DELETE FROM protocols WHERE id = ?1", [&id])?;
+        tx.commit()?;
         Ok(())
     })
     .map_err(|e| e.to_string())
@@ -267,12 +502,7 @@ pub async fn export_protocol_to_file(
 
     let protocol = protocol.ok_or("Protocol not found")?;
 
-    // Generate safe filename from protocol name
-    let safe_name = protocol.name
-        .chars()
-        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
-        .collect::<String>();
-    let default_filename = format!("{}.json", safe_name);
+    let default_filename = format!("{}.json", safe_file_name(&protocol.name));
 
     // Show save dialog
     let file_path = app
@@ -314,6 +544,87 @@ pub async fn export_protocol_to_file(
     Ok(())
 }
 
+/// Generate safe filename from protocol name
+fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Export protocol as a C header ("c") or Rust source ("rust") file
+#[tauri::command]
+pub async fn export_protocol_code(
+    protocol_id: String,
+    language: String,
+    app: AppHandle,
+    db_pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let mut protocol = db_pool
+        .with(|db| load_protocol(db, &protocol_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Protocol not found")?;
+    protocol.fields = resolve_fields(&db_pool, protocol.fields)?;
+
+    let (code, filter, extension) = match language.as_str() {
+        "c" => (protocol::codegen::c_header(&protocol), "C Header", "h"),
+        "rust" => (protocol::codegen::rust_source(&protocol), "Rust Source", "rs"),
+        other => return Err(format!("Unsupported language: {}", other)),
+    };
+
+    // Show save dialog
+    let file_path = app
+        .dialog()
+        .file()
+        .add_filter(filter, &[extension])
+        .add_filter("All Files", &["*"])
+        .set_file_name(format!("{}.{}", safe_file_name(&protocol.name).to_lowercase(), extension))
+        .set_title("Generate Code")
+        .blocking_save_file();
+
+    let file_path = file_path.ok_or("No file selected")?;
+
+    let path = file_path.as_path().ok_or("Invalid file path")?;
+    fs::write(path, code).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Export protocol as a Wireshark Lua dissector registered on a TCP or UDP port
+#[tauri::command]
+pub async fn export_protocol_dissector(
+    protocol_id: String,
+    transport: String,
+    port: u16,
+    app: AppHandle,
+    db_pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let transport = Transport::parse(&transport).ok_or_else(|| format!("Unsupported transport: {}", transport))?;
+    let mut protocol = db_pool
+        .with(|db| load_protocol(db, &protocol_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Protocol not found")?;
+    protocol.fields = resolve_fields(&db_pool, protocol.fields)?;
+
+    let script = protocol::dissector::lua_dissector(&protocol, transport, port);
+
+    // Show save dialog
+    let file_path = app
+        .dialog()
+        .file()
+        .add_filter("Lua Scripts", &["lua"])
+        .add_filter("All Files", &["*"])
+        .set_file_name(format!("{}.lua", safe_file_name(&protocol.name).to_lowercase()))
+        .set_title("Export Wireshark Dissector")
+        .blocking_save_file();
+
+    let file_path = file_path.ok_or("No file selected")?;
+
+    let path = file_path.as_path().ok_or("Invalid file path")?;
+    fs::write(path, script).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
 /// Import protocol from JSON file
 #[tauri::command]
 pub async fn import_protocol_from_file(app: AppHandle) -> Result<ProtocolImport, String> {
@@ -321,7 +632,9 @@ pub async fn import_protocol_from_file(app: AppHandle) -> Result<ProtocolImport,
     let file_path = app
         .dialog()
         .file()
+        .add_filter("Protocol Files", &["json", "ksy"])
         .add_filter("JSON Files", &["json"])
+        .add_filter("Kaitai Struct", &["ksy"])
         .add_filter("All Files", &["*"])
         .set_title("Import Protocol")
         .blocking_pick_file();
@@ -332,9 +645,471 @@ pub async fn import_protocol_from_file(app: AppHandle) -> Result<ProtocolImport,
     let path = file_path.as_path().ok_or("Invalid file path")?;
     let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
 
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ksy")) {
+        return protocol::kaitai::import(&content);
+    }
+
     // Parse JSON directly into ProtocolImport
     let import_data: ProtocolImport = serde_json::from_str(&content)
         .map_err(|e| format!("Failed to parse JSON: {}", e))?;
 
     Ok(import_data)
 }
+
+/// Export protocols (all when `protocol_ids` is empty) as a library bundle.
+/// Protocols embedded by group fields are bundled too, so references still resolve after import.
+#[tauri::command]
+pub async fn export_protocol_library(
+    protocol_ids: Vec<String>,
+    app: AppHandle,
+    db_pool: State<'_, DbPool>,
+) -> Result<usize, String> {
+    let protocols = db_pool
+        .with(|db| {
+            let mut pending: VecDeque<String> = if protocol_ids.is_empty() {
+                let mut stmt = db.conn().prepare("SELECT id FROM protocols ORDER BY created_at")?;
+                let ids = stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<Result<_, _>>()?;
+                ids
+            } else {
+                protocol_ids.into()
+            };
+
+            let mut seen = HashSet::new();
+            let mut protocols = Vec::new();
+            while let Some(id) = pending.pop_front() {
+                if !seen.insert(id.clone()) {
+                    continue;
+                }
+                if let Some(protocol) = load_protocol(db, &id)? {
+                    collect_protocol_refs(&protocol.fields, &mut pending);
+                    protocols.push(protocol);
+                }
+            }
+            Ok(protocols)
+        })
+        .map_err(|e| e.to_string())?;
+
+    if protocols.is_empty() {
+        return Err("No protocols to export".to_string());
+    }
+
+    // Show save dialog
+    let file_path = app
+        .dialog()
+        .file()
+        .add_filter("Protocol Library", &["json"])
+        .add_filter("All Files", &["*"])
+        .set_file_name("protocol-library.json")
+        .set_title("Export Protocol Library")
+        .blocking_save_file();
+
+    let file_path = file_path.ok_or("No file selected")?;
+
+    let count = protocols.len();
+    let library = ProtocolLibrary {
+        version: PROTOCOL_LIBRARY_VERSION,
+        exported_at: Some(Utc::now().to_rfc3339()),
+        protocols: protocols
+            .into_iter()
+            .map(|p| LibraryProtocol {
+                id: p.id,
+                name: p.name,
+                description: p.description,
+                fields: p.fields.into_iter().map(Into::into).collect(),
+                correlation: p.correlation,
+                hooks: p.hooks,
+            })
+            .collect(),
+    };
+
+    let json_string = serde_json::to_string_pretty(&library).map_err(|e| e.to_string())?;
+
+    let path = file_path.as_path().ok_or("Invalid file path")?;
+    fs::write(path, json_string).map_err(|e| e.to_string())?;
+
+    Ok(count)
+}
+
+/// Queue the protocols embedded anywhere in a field tree
+fn collect_protocol_refs(fields: &[ProtocolField], out: &mut VecDeque<String>) {
+    for field in fields {
+        if let Some(protocol_ref) = &field.protocol_ref {
+            out.push_back(protocol_ref.clone());
+        }
+        collect_protocol_refs(&field.fields, out);
+        if let Some(element) = &field.element {
+            collect_protocol_refs(std::slice::from_ref(element.as_ref()), out);
+        }
+    }
+}
+
+/// Read a library bundle and report which of its protocols clash with saved ones
+#[tauri::command]
+pub async fn open_protocol_library(app: AppHandle, db_pool: State<'_, DbPool>) -> Result<LibraryPreview, String> {
+    // Show open dialog
+    let file_path = app
+        .dialog()
+        .file()
+        .add_filter("Protocol Library", &["json"])
+        .add_filter("All Files", &["*"])
+        .set_title("Import Protocol Library")
+        .blocking_pick_file();
+
+    let file_path = file_path.ok_or("No file selected")?;
+
+    let path = file_path.as_path().ok_or("Invalid file path")?;
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let library: ProtocolLibrary = serde_json::from_str(&content)
+        .map_err(|e| format!("Not a protocol library: {}", e))?;
+    check_library(&library)?;
+
+    let existing = db_pool.with(saved_protocol_names).map_err(|e| e.to_string())?;
+    let conflicts = library_conflicts(&library, &existing);
+
+    Ok(LibraryPreview { library, conflicts })
+}
+
+/// Import a library bundle in one transaction, resolving each conflict as requested
+#[tauri::command]
+pub fn import_protocol_library(db_pool: State<DbPool>, request: ImportLibraryRequest) -> DbResult<LibraryImportSummary> {
+    check_library(&request.library)?;
+
+    db_pool.with(|db| {
+        let tx = db.conn().unchecked_transaction()?;
+
+        // Conflicts are recomputed here; the library may have been previewed against an older state
+        let existing = saved_protocol_names(db)?;
+        let conflicts = library_conflicts(&request.library, &existing);
+        let mut names: HashSet<String> = existing.into_iter().map(|(_, name)| name).collect();
+
+        let mut summary = LibraryImportSummary::default();
+        // Bundled protocol id -> id it ends up with, used to rewrite group references
+        let mut id_map = HashMap::new();
+        // (protocol, target id, name, replaces a saved protocol)
+        let mut plan = Vec::new();
+
+        for protocol in request.library.protocols {
+            let Some(conflict) = conflicts.iter().find(|c| c.id == protocol.id) else {
+                // The name may still be taken by a saved protocol another one replaces, or a rename
+                let name = if names.contains(&protocol.name) {
+                    unique_name(&protocol.name, &names)
+                } else {
+                    protocol.name.clone()
+                };
+                names.insert(name.clone());
+                id_map.insert(protocol.id.clone(), protocol.id.clone());
+                let id = protocol.id.clone();
+                plan.push((protocol, id, name, false));
+                summary.created += 1;
+                continue;
+            };
+
+            match request.resolutions.get(&protocol.id).copied().unwrap_or(ConflictResolution::Skip) {
+                ConflictResolution::Skip => {
+                    id_map.insert(protocol.id.clone(), conflict.existing_id.clone());
+                    summary.skipped += 1;
+                }
+                ConflictResolution::Rename => {
+                    let id = Uuid::new_v4().to_string();
+                    let name = unique_name(&protocol.name, &names);
+                    names.insert(name.clone());
+                    id_map.insert(protocol.id.clone(), id.clone());
+                    plan.push((protocol, id, name, false));
+                    summary.renamed += 1;
+                }
+                ConflictResolution::Overwrite => {
+                    let id = conflict.existing_id.clone();
+                    id_map.insert(protocol.id.clone(), id.clone());
+                    let name = protocol.name.clone();
+                    names.insert(name.clone());
+                    plan.push((protocol, id, name, true));
+                    summary.overwritten += 1;
+                }
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+        for (protocol, id, name, overwrite) in plan {
+            let mut fields: Vec<ProtocolField> = protocol.fields.into_iter().map(Into::into).collect();
+            refresh_field_ids(&mut fields, &id_map);
+
+            let desc = protocol.description.unwrap_or_default();
+            let correlation = protocol.correlation.map(|mut rule| {
+                if let Some(target) = rule.response_protocol_id.as_ref().and_then(|r| id_map.get(r)) {
+                    rule.response_protocol_id = Some(target.clone());
+                }
+                rule
+            });
+            let correlation = correlation.as_ref().and_then(|c| serde_json::to_string(c).ok());
+            let hooks = protocol.hooks.as_ref().and_then(|h| serde_json::to_string(h).ok());
+            if overwrite {
+                ensure_baseline_revision(db, &id)?;
+                db.conn().execute(
+                    "UPDATE protocols SET name = ?1, description = ?2, correlation = ?3, hooks = ?4, updated_at = ?5 WHERE id = ?6",
+                    params![name, desc, correlation, hooks, now, id],
+                )?;
+                db.conn().execute("DELETE FROM protocol_fields WHERE protocol_id = ?1", [&id])?;
+            } else {
+                db.conn().execute(
+                    "INSERT INTO protocols (id, name, description, correlation, hooks, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![id, name, desc, correlation, hooks, now, now],
+                )?;
+            }
+            insert_fields(db, &id, None, "field", &fields)?;
+            record_revision(db, &id, &name, Some(&desc), &fields, &now)?;
+        }
+
+        tx.commit()?;
+        Ok(summary)
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Reject bundles written by a newer schema or holding the same protocol twice
+fn check_library(library: &ProtocolLibrary) -> Result<(), String> {
+    if library.version == 0 || library.version > PROTOCOL_LIBRARY_VERSION {
+        return Err(format!(
+            "Unsupported library version {} (supported up to {})",
+            library.version, PROTOCOL_LIBRARY_VERSION
+        ));
+    }
+
+    let mut ids = HashSet::new();
+    if let Some(duplicate) = library.protocols.iter().find(|p| !ids.insert(&p.id)) {
+        return Err(format!("Protocol id {} appears more than once in the library", duplicate.id));
+    }
+    let mut names = HashSet::new();
+    if let Some(duplicate) = library.protocols.iter().find(|p| !names.insert(&p.name)) {
+        return Err(format!("Protocol name '{}' appears more than once in the library", duplicate.name));
+    }
+
+    Ok(())
+}
+
+/// (id, name) of every saved protocol
+fn saved_protocol_names(db: &Database) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut stmt = db.conn().prepare("SELECT id, name FROM protocols")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Match bundled protocols to saved ones, by id first and by name otherwise.
+/// A saved protocol matched by id isn't matched again by name, so each has one conflict at most.
+fn library_conflicts(library: &ProtocolLibrary, existing: &[(String, String)]) -> Vec<LibraryConflict> {
+    let bundled_ids: HashSet<&String> = library.protocols.iter().map(|p| &p.id).collect();
+    library
+        .protocols
+        .iter()
+        .filter_map(|protocol| {
+            let (reason, (existing_id, existing_name)) = existing
+                .iter()
+                .find(|(id, _)| *id == protocol.id)
+                .map(|saved| (ConflictReason::Id, saved))
+                .or_else(|| {
+                    existing
+                        .iter()
+                        .find(|(id, name)| *name == protocol.name && !bundled_ids.contains(id))
+                        .map(|saved| (ConflictReason::Name, saved))
+                })?;
+
+            Some(LibraryConflict {
+                id: protocol.id.clone(),
+                name: protocol.name.clone(),
+                existing_id: existing_id.clone(),
+                existing_name: existing_name.clone(),
+                reason,
+            })
+        })
+        .collect()
+}
+
+/// First of "name (2)", "name (3)", ... that isn't taken
+fn unique_name(name: &str, taken: &HashSet<String>) -> String {
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Give fields fresh ids (they are unique across protocols) and point group references found in `id_map` at their new targets
+fn refresh_field_ids(fields: &mut [ProtocolField], id_map: &HashMap<String, String>) {
+    for field in fields {
+        field.id = Uuid::new_v4().to_string();
+        if let Some(target) = field.protocol_ref.as_ref().and_then(|r| id_map.get(r)) {
+            field.protocol_ref = Some(target.clone());
+        }
+        refresh_field_ids(&mut field.fields, id_map);
+        if let Some(element) = &mut field.element {
+            refresh_field_ids(std::slice::from_mut(element.as_mut()), id_map);
+        }
+    }
+}
+
+/// Resolve groups that embed saved protocols
+pub(crate) fn resolve_fields(db_pool: &DbPool, mut fields: Vec<ProtocolField>) -> Result<Vec<ProtocolField>, String> {
+    db_pool
+        .with(|db| {
+            Ok(protocol::resolve_references(&mut fields, &mut |id: &str| {
+                load_protocol(db, id)
+                    .map(|p| p.map(|p| p.fields))
+                    .map_err(|e| e.to_string())
+            }))
+        })
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    Ok(fields)
+}
+
+/// Encode protocol fields into a hex string ready for `send_message`.
+/// Placeholders in field values are resolved with the session variables and active environment,
+/// and generator fields get fresh values. `{{$seq}}` and counters advance per `connection_id`;
+/// a `preview` shows the next values without using them up.
+#[tauri::command]
+pub fn encode_protocol_fields(
+    db_pool: State<DbPool>,
+    sequences: State<Sequences>,
+    generators: State<GeneratorState>,
+    session: State<SessionVariables>,
+    fields: Vec<ProtocolField>,
+    connection_id: Option<String>,
+    preview: Option<bool>,
+) -> Result<String, String> {
+    let mut fields = resolve_fields(&db_pool, fields)?;
+    let connection_id = connection_id.unwrap_or_default();
+    let preview = preview.unwrap_or(false);
+    if template::fields_have_placeholders(&fields) {
+        let variables = session.overlay(db_pool.with(active_variables).map_err(|e| e.to_string())?);
+        let context = template::Context::new(&variables, || {
+            if preview { sequences.peek(&connection_id) } else { sequences.next(&connection_id) }
+        });
+        template::render_fields(&mut fields, &context).map_err(|e| e.to_string())?;
+    }
+    protocol::generator::apply(&mut fields, &generators, &connection_id, preview).map_err(|e| e.to_string())?;
+    let bytes = protocol::encode(&fields).map_err(|e| e.to_string())?;
+    Ok(bytes_to_hex_string(&bytes))
+}
+
+/// Decode hex data with a saved protocol
+#[tauri::command]
+pub fn decode_protocol_data(db_pool: State<DbPool>, protocol_id: String, data: String) -> Result<Vec<DecodedField>, String> {
+    let protocol = db_pool
+        .with(|db| load_protocol(db, &protocol_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Protocol not found")?;
+
+    let fields = resolve_fields(&db_pool, protocol.fields)?;
+    let bytes = hex_string_to_bytes(&data)?;
+    protocol::decode(&fields, &bytes).map_err(|e| e.to_string())
+}
+
+/// A guess at what received data is
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolSuggestion {
+    /// Built-in protocol or saved protocol name
+    pub name: String,
+    /// Saved protocol that decodes the data; None for built-in protocols
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_id: Option<String>,
+    pub summary: String,
+    /// Values parsed by a built-in protocol
+    #[serde(skip_serializing_if = "detect::Fields::is_empty")]
+    pub values: detect::Fields,
+    /// Fields decoded with the saved protocol
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub decoded: Vec<DecodedField>,
+}
+
+/// Saved protocols suggested for one message at most
+const MAX_SAVED_SUGGESTIONS: usize = 3;
+
+/// Suggest what hex data is: a well-known protocol first, then saved protocols that decode
+/// all of it and whose fixed fields mostly hold their saved values, best match first
+#[tauri::command]
+pub fn detect_protocols(db_pool: State<DbPool>, data: String) -> Result<Vec<ProtocolSuggestion>, String> {
+    let bytes = hex_string_to_bytes(&data)?;
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut suggestions: Vec<ProtocolSuggestion> = detect::detect(&bytes)
+        .map(|detection| ProtocolSuggestion {
+            name: detection.protocol,
+            protocol_id: None,
+            summary: detection.operation,
+            values: detection.fields,
+            decoded: Vec::new(),
+        })
+        .into_iter()
+        .collect();
+
+    let protocols = db_pool
+        .with(|db| {
+            let mut stmt = db.conn().prepare("SELECT id FROM protocols ORDER BY created_at")?;
+            let ids = stmt.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut protocols = Vec::new();
+            for id in ids {
+                protocols.extend(load_protocol(db, &id)?);
+            }
+            Ok(protocols)
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut matches = Vec::new();
+    for saved in protocols {
+        // A protocol that cannot be resolved or decoded just isn't suggested
+        let Ok(fields) = resolve_fields(&db_pool, saved.fields) else { continue };
+        let Ok((decoded, used)) = protocol::parser::decode_prefix(&fields, &bytes) else { continue };
+        if used != bytes.len() {
+            continue;
+        }
+        let (matched, constants) = constant_matches(&fields, &decoded);
+        if matched == 0 || matched * 2 < constants {
+            continue;
+        }
+        let summary = format!("Decodes all {} bytes, {} of {} fixed fields match", used, matched, constants);
+        matches.push((matched, constants, ProtocolSuggestion {
+            name: saved.name,
+            protocol_id: Some(saved.id),
+            summary,
+            values: Default::default(),
+            decoded,
+        }));
+    }
+    // Most matching fixed fields first, then fewest mismatches
+    matches.sort_by_key(|(matched, constants, _)| (std::cmp::Reverse(*matched), constants - matched));
+    suggestions.extend(matches.into_iter().take(MAX_SAVED_SUGGESTIONS).map(|(_, _, s)| s));
+    Ok(suggestions)
+}
+
+/// (fields holding their saved value, fields with a constant value) among the top-level fields.
+/// Constant means a fixed-length scalar whose value is not computed when sending.
+fn constant_matches(fields: &[ProtocolField], decoded: &[DecodedField]) -> (usize, usize) {
+    let mut matched = 0;
+    let mut constants = 0;
+    for field in fields {
+        let constant = field.enabled
+            && field.kind == FieldKind::Scalar
+            && !field.is_variable
+            && field.condition.is_none()
+            && field.length_of.is_none()
+            && field.generator.is_none()
+            && !field.value.is_empty()
+            && !template::has_placeholders(&field.value);
+        if !constant {
+            continue;
+        }
+        let Ok(expected) = protocol::encode(std::slice::from_ref(field)) else { continue };
+        constants += 1;
+        if decoded.iter().any(|d| d.name == field.name && d.hex == bytes_to_hex_string(&expected)) {
+            matched += 1;
+        }
+    }
+    (matched, constants)
+}
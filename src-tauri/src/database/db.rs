@@ -36,6 +36,8 @@ impl Database {
                 id TEXT PRIMARY KEY,
                 name TEXT NOT NULL,
                 description TEXT,
+                correlation TEXT,
+                hooks TEXT,
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL
             )",
@@ -54,6 +56,20 @@ impl Database {
                 value_format TEXT,
                 value TEXT NOT NULL DEFAULT '',
                 field_order INTEGER NOT NULL DEFAULT 0,
+                parent_id TEXT,
+                role TEXT NOT NULL DEFAULT 'field',
+                kind TEXT NOT NULL DEFAULT 'scalar',
+                protocol_ref TEXT,
+                repeat_rule TEXT,
+                field_condition TEXT,
+                tlv_spec TEXT,
+                tlv_tag INTEGER,
+                varint_format TEXT,
+                length_of TEXT,
+                text_spec TEXT,
+                description TEXT,
+                endianness TEXT,
+                generator TEXT,
                 FOREIGN KEY (protocol_id) REFERENCES protocols(id) ON DELETE CASCADE
             )",
             [],
@@ -65,6 +81,67 @@ impl Database {
         self.migrate_value_format_column()?;
         // Migrate: fix preset protocols value_format
         self.migrate_preset_protocols_value_format()?;
+        // Migrate: add columns for nested groups and arrays
+        self.migrate_field_tree_columns()?;
+        // Migrate: add field_condition column if it doesn't exist
+        self.add_column_if_missing("protocol_fields", "field_condition", "TEXT")?;
+        // Migrate: add TLV columns if they don't exist
+        self.add_column_if_missing("protocol_fields", "tlv_spec", "TEXT")?;
+        self.add_column_if_missing("protocol_fields", "tlv_tag", "INTEGER")?;
+        // Migrate: add varint and auto-length columns if they don't exist
+        self.add_column_if_missing("protocol_fields", "varint_format", "TEXT")?;
+        self.add_column_if_missing("protocol_fields", "length_of", "TEXT")?;
+        // Migrate: add text_spec column if it doesn't exist
+        self.add_column_if_missing("protocol_fields", "text_spec", "TEXT")?;
+        // Migrate: add field description column if it doesn't exist
+        self.add_column_if_missing("protocol_fields", "description", "TEXT")?;
+        // Migrate: add endianness column if it doesn't exist
+        self.add_column_if_missing("protocol_fields", "endianness", "TEXT")?;
+        // Migrate: add generator column if it doesn't exist
+        self.add_column_if_missing("protocol_fields", "generator", "TEXT")?;
+        // Migrate: add protocol correlation column if it doesn't exist
+        self.add_column_if_missing("protocols", "correlation", "TEXT")?;
+        // Migrate: add protocol hooks column if it doesn't exist
+        self.add_column_if_missing("protocols", "hooks", "TEXT")?;
+
+        // Create protocol_revisions table (one snapshot per save)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS protocol_revisions (
+                protocol_id TEXT NOT NULL,
+                revision INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                fields TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (protocol_id, revision),
+                FOREIGN KEY (protocol_id) REFERENCES protocols(id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Create environments table (variables stored as JSON)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS environments (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                variables TEXT NOT NULL DEFAULT '[]',
+                is_active INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Create load_test_runs table (config and summary stored as JSON)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS load_test_runs (
+                id TEXT PRIMARY KEY,
+                config TEXT NOT NULL,
+                summary TEXT NOT NULL,
+                started_at TEXT NOT NULL
+            )",
+            [],
+        )?;
 
         // Insert preset protocols if none exist
         self.insert_preset_protocols()?;
@@ -112,6 +189,36 @@ impl Database {
         Ok(())
     }
 
+    /// Migrate existing databases to the tree-shaped field schema.
+    /// Child fields point at their group/array through parent_id; `role` tells
+    /// member fields apart from an array's element template.
+    fn migrate_field_tree_columns(&self) -> Result<()> {
+        self.add_column_if_missing("protocol_fields", "parent_id", "TEXT")?;
+        self.add_column_if_missing("protocol_fields", "role", "TEXT NOT NULL DEFAULT 'field'")?;
+        self.add_column_if_missing("protocol_fields", "kind", "TEXT NOT NULL DEFAULT 'scalar'")?;
+        self.add_column_if_missing("protocol_fields", "protocol_ref", "TEXT")?;
+        self.add_column_if_missing("protocol_fields", "repeat_rule", "TEXT")?;
+        Ok(())
+    }
+
+    /// Add a column to an existing table unless it is already there
+    fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists: i64 = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1", table),
+            [column],
+            |row| row.get(0),
+        ).unwrap_or(0);
+
+        if exists == 0 {
+            self.conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+        }
+
+        Ok(())
+    }
+
     /// Migrate preset protocols to fix value_format for non-variable fields
     fn migrate_preset_protocols_value_format(&self) -> Result<()> {
         // Update all preset protocol fields where value_format is NULL or empty
@@ -2,6 +2,10 @@ mod commands;
 mod tcp;
 mod database;
 mod models;
+mod pcap;
+mod protocol;
+mod script;
+mod template;
 
 use std::sync::Arc;
 use tcp::ConnectionManager;
@@ -17,6 +21,11 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .manage(connection_manager)
         .manage(DbPool::new())
+        .manage(template::Sequences::default())
+        .manage(script::SessionVariables::default())
+        .manage(protocol::generator::GeneratorState::default())
+        .manage(tcp::LoadTests::default())
+        .manage(commands::MockServer::default())
         .setup(|app| {
             // Initialize database
             let db = database::Database::open(app.handle())?;
@@ -25,9 +34,13 @@ pub fn run() {
 
             // Disconnect all connections on app shutdown
             let manager = app.state::<Arc<ConnectionManager>>().inner().clone();
+            let handle = app.handle().clone();
             let main_window = app.get_webview_window("main").unwrap();
             main_window.on_window_event(move |event| {
                 if let tauri::WindowEvent::CloseRequested { .. } = event {
+                    // A mock server started from the app would outlive it otherwise
+                    let _ = commands::stop_mock_server(handle.state());
+
                     // Disconnect all connections when window is closing
                     let manager = manager.clone();
                     tauri::async_runtime::spawn(async move {
@@ -46,8 +59,14 @@ pub fn run() {
             commands::check_connection_status,
             commands::remove_connection,
             commands::list_connections,
+            commands::set_connection_recording,
+            commands::get_connection_recording,
+            commands::export_connection_capture,
+            commands::import_capture,
+            commands::replay_capture,
             commands::send_message,
             commands::send_only,
+            commands::send_correlated,
             commands::receive_only,
             // Protocol commands
             commands::list_protocols,
@@ -55,8 +74,34 @@ pub fn run() {
             commands::create_protocol,
             commands::update_protocol,
             commands::delete_protocol,
+            commands::list_protocol_revisions,
+            commands::get_protocol_revision,
+            commands::diff_protocol_revisions,
+            commands::restore_protocol_revision,
             commands::export_protocol_to_file,
+            commands::export_protocol_code,
+            commands::export_protocol_dissector,
             commands::import_protocol_from_file,
+            commands::export_protocol_library,
+            commands::open_protocol_library,
+            commands::import_protocol_library,
+            commands::list_environments,
+            commands::create_environment,
+            commands::update_environment,
+            commands::delete_environment,
+            commands::set_active_environment,
+            commands::list_session_variables,
+            commands::clear_session_variables,
+            commands::start_load_test,
+            commands::stop_load_test,
+            commands::list_load_test_runs,
+            commands::delete_load_test_run,
+            commands::start_mock_server,
+            commands::stop_mock_server,
+            commands::mock_server_status,
+            commands::encode_protocol_fields,
+            commands::decode_protocol_data,
+            commands::detect_protocols,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
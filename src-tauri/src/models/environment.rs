@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// A key/value pair substituted for `{{key}}` in payloads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentVariable {
+    pub key: String,
+    pub value: String,
+}
+
+/// Named set of variables, e.g. one per dev/test/prod device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Environment {
+    pub id: String,
+    pub name: String,
+    pub variables: Vec<EnvironmentVariable>,
+    /// Variables of the active environment are used when sending
+    pub active: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateEnvironmentRequest {
+    pub name: String,
+    #[serde(default)]
+    pub variables: Vec<EnvironmentVariable>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateEnvironmentRequest {
+    pub id: String,
+    pub name: String,
+    pub variables: Vec<EnvironmentVariable>,
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+/// What a load test sends, where, and for how long
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestConfig {
+    pub host: String,
+    pub port: u16,
+    /// Concurrent connections, each sending one request at a time
+    pub connections: u32,
+    pub duration_secs: u64,
+    /// Requests per second over all connections; as fast as possible when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<u32>,
+    /// Request in hex; ignored when `protocol_id` is set
+    #[serde(default)]
+    pub payload: String,
+    /// Saved protocol to send instead of `payload`, encoded once when the test starts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_id: Option<String>,
+    /// Wait for a response to each request; otherwise requests are only written
+    #[serde(default = "default_expect_response")]
+    pub expect_response: bool,
+    /// Connect and response timeout
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_expect_response() -> bool {
+    true
+}
+
+fn default_timeout_secs() -> u64 {
+    5
+}
+
+/// Distribution of durations, in milliseconds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    pub count: u64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// Failures by kind
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestErrors {
+    pub connect: u64,
+    pub timeout: u64,
+    /// Send or receive failures, including the peer closing the connection
+    pub io: u64,
+}
+
+impl LoadTestErrors {
+    pub fn total(&self) -> u64 {
+        self.connect + self.timeout + self.io
+    }
+}
+
+/// One second of a load test, to see how the target degrades over time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestSample {
+    /// Seconds since the start, counting from 1
+    pub second: u64,
+    pub requests: u64,
+    pub errors: u64,
+    pub p50: f64,
+    pub p99: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestSummary {
+    pub duration_ms: u64,
+    /// Requests that completed (answered, or written when no response is expected)
+    pub requests: u64,
+    /// Completed requests per second
+    pub throughput: f64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub latency: LatencyStats,
+    pub connect_time: LatencyStats,
+    pub errors: LoadTestErrors,
+    pub timeline: Vec<LoadTestSample>,
+    /// Stopped before the configured duration
+    pub stopped: bool,
+}
+
+/// Emitted as `load-test-progress` about once a second while a test runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestProgress {
+    pub run_id: String,
+    pub elapsed_ms: u64,
+    pub requests: u64,
+    pub errors: u64,
+    /// Connections currently open
+    pub active_connections: u32,
+    pub sample: LoadTestSample,
+}
+
+/// A finished load test as stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestRun {
+    pub id: String,
+    pub config: LoadTestConfig,
+    pub summary: LoadTestSummary,
+    pub started_at: String,
+}
+
+/// Emitted as `load-test-finished` when a load test ends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestFinished {
+    pub run: LoadTestRun,
+    /// Set when the result couldn't be stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
@@ -1,3 +1,8 @@
+pub mod environment;
+pub mod load_test;
 pub mod protocol;
 
-pub use protocol::{Protocol, ProtocolField, CreateProtocolRequest, UpdateProtocolRequest, ProtocolImport, ProtocolFieldExport};
+pub use packetforge_core::model::{ProtocolField, FieldKind, RepeatRule, FieldCondition, Endianness, TlvSpec, TlvTag, VarintFormat, LengthOf, TimeUnit, FieldGenerator, TextEncoding, PadSide, Terminator, TextSpec};
+pub use protocol::{Protocol, CorrelationRule, ProtocolHooks, CreateProtocolRequest, UpdateProtocolRequest, ProtocolImport, ProtocolFieldExport, ProtocolRevision, ProtocolRevisionSummary, PROTOCOL_LIBRARY_VERSION, ProtocolLibrary, LibraryProtocol, ConflictReason, LibraryConflict, LibraryPreview, ConflictResolution, ImportLibraryRequest, LibraryImportSummary};
+pub use environment::{Environment, EnvironmentVariable, CreateEnvironmentRequest, UpdateEnvironmentRequest};
+pub use load_test::{LoadTestConfig, LatencyStats, LoadTestErrors, LoadTestSample, LoadTestSummary, LoadTestProgress, LoadTestRun, LoadTestFinished};
@@ -1,36 +1,8 @@
+use packetforge_core::model::{
+    Endianness, FieldCondition, FieldGenerator, FieldKind, LengthOf, ProtocolField, RepeatRule, TextSpec, TlvSpec, VarintFormat,
+};
 use serde::{Deserialize, Serialize};
-use std::default::Default;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProtocolField {
-    pub id: String,
-    pub name: String,
-    pub length: Option<i32>,
-    #[serde(rename = "isVariable", default)]
-    pub is_variable: bool,
-    #[serde(rename = "valueType", default)]
-    pub value_type: String, // "text" or "hex"
-    #[serde(rename = "valueFormat", skip_serializing_if = "Option::is_none")]
-    pub value_format: Option<String>, // "dec" or "hex" for non-variable fields
-    pub value: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub description: Option<String>,
-}
-
-impl Default for ProtocolField {
-    fn default() -> Self {
-        Self {
-            id: String::new(),
-            name: String::new(),
-            length: None,
-            is_variable: false,
-            value_type: "hex".to_string(),
-            value_format: None,
-            value: String::new(),
-            description: None,
-        }
-    }
-}
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Protocol {
@@ -39,18 +11,57 @@ pub struct Protocol {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub fields: Vec<ProtocolField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation: Option<CorrelationRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<ProtocolHooks>,
     #[serde(rename = "createdAt")]
     pub created_at: String,
     #[serde(rename = "updatedAt")]
     pub updated_at: String,
 }
 
+/// Pairs responses with requests when several are in flight on one connection:
+/// a response answers the request whose `request_field` bytes equal its `response_field` bytes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorrelationRule {
+    pub request_field: String,
+    pub response_field: String,
+    /// Protocol responses are decoded with; the request's own protocol when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_protocol_id: Option<String>,
+    /// How long each request waits for its response
+    #[serde(default = "default_correlation_timeout")]
+    pub timeout_ms: u64,
+}
+
+fn default_correlation_timeout() -> u64 {
+    5000
+}
+
+/// Lua scripts run around every exchange that uses the protocol (see `script`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolHooks {
+    /// May rewrite the encoded request and set variables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_send: Option<String>,
+    /// May check the response and set variables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_receive: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateProtocolRequest {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub fields: Vec<ProtocolField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation: Option<CorrelationRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<ProtocolHooks>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -60,6 +71,10 @@ pub struct UpdateProtocolRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub fields: Vec<ProtocolField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation: Option<CorrelationRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<ProtocolHooks>,
 }
 
 /// Protocol data for import/export (without id)
@@ -69,6 +84,112 @@ pub struct ProtocolImport {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub fields: Vec<ProtocolFieldExport>,
+    /// Parts of the source that couldn't be represented (never written on export)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// A numbered snapshot of a protocol, taken each time it is saved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolRevision {
+    pub protocol_id: String,
+    pub revision: u32,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub fields: Vec<ProtocolField>,
+    pub created_at: String,
+}
+
+/// Revision list entry, without the field tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolRevisionSummary {
+    pub revision: u32,
+    pub name: String,
+    pub field_count: usize,
+    pub created_at: String,
+}
+
+/// Schema version written into protocol library bundles
+pub const PROTOCOL_LIBRARY_VERSION: u32 = 1;
+
+/// Bundle of protocols shared as a single file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolLibrary {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exported_at: Option<String>,
+    pub protocols: Vec<LibraryProtocol>,
+}
+
+/// Protocol inside a library bundle; the id is kept so group references survive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryProtocol {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub fields: Vec<ProtocolFieldExport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation: Option<CorrelationRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<ProtocolHooks>,
+}
+
+/// What clashes between a bundled protocol and a saved one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictReason {
+    Id,
+    Name,
+}
+
+/// Bundled protocol whose id or name is already taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryConflict {
+    pub id: String,
+    pub name: String,
+    pub existing_id: String,
+    pub existing_name: String,
+    pub reason: ConflictReason,
+}
+
+/// Library read from disk, with the conflicts to resolve before importing it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryPreview {
+    pub library: ProtocolLibrary,
+    pub conflicts: Vec<LibraryConflict>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictResolution {
+    /// Keep the saved protocol; references in the bundle point to it
+    Skip,
+    /// Import under a new id and a free name
+    Rename,
+    /// Replace the saved protocol, keeping its id
+    Overwrite,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportLibraryRequest {
+    pub library: ProtocolLibrary,
+    /// Keyed by bundled protocol id; conflicts without an entry are skipped
+    #[serde(default)]
+    pub resolutions: HashMap<String, ConflictResolution>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LibraryImportSummary {
+    pub created: usize,
+    pub renamed: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
 }
 
 /// Field for export - conditional fields based on isVariable
@@ -92,6 +213,36 @@ pub struct ProtocolFieldExport {
     /// Track enabled state
     #[serde(default)]
     pub enabled: bool,
+    #[serde(default, skip_serializing_if = "is_scalar")]
+    pub kind: FieldKind,
+    #[serde(rename = "protocolRef", skip_serializing_if = "Option::is_none")]
+    pub protocol_ref: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<ProtocolFieldExport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element: Option<Box<ProtocolFieldExport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<RepeatRule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<FieldCondition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tlv: Option<TlvSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<u64>,
+    #[serde(rename = "varintFormat", skip_serializing_if = "Option::is_none")]
+    pub varint_format: Option<VarintFormat>,
+    #[serde(rename = "lengthOf", skip_serializing_if = "Option::is_none")]
+    pub length_of: Option<LengthOf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<TextSpec>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endianness: Option<Endianness>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generator: Option<FieldGenerator>,
+}
+
+fn is_scalar(kind: &FieldKind) -> bool {
+    *kind == FieldKind::Scalar
 }
 
 impl From<ProtocolField> for ProtocolFieldExport {
@@ -105,7 +256,20 @@ impl From<ProtocolField> for ProtocolFieldExport {
             value_format: if !field.is_variable { field.value_format } else { None },
             value: field.value,
             description: field.description,
-            enabled: true,
+            enabled: field.enabled,
+            kind: field.kind,
+            protocol_ref: field.protocol_ref,
+            fields: field.fields.into_iter().map(Into::into).collect(),
+            element: field.element.map(|e| Box::new((*e).into())),
+            repeat: field.repeat,
+            condition: field.condition,
+            tlv: field.tlv,
+            tag: field.tag,
+            varint_format: field.varint_format,
+            length_of: field.length_of,
+            text: field.text,
+            endianness: field.endianness,
+            generator: field.generator,
         }
     }
 }
@@ -121,6 +285,20 @@ impl From<ProtocolFieldExport> for ProtocolField {
             value_format: field.value_format,
             value: field.value,
             description: field.description,
+            enabled: true,
+            kind: field.kind,
+            protocol_ref: field.protocol_ref,
+            fields: field.fields.into_iter().map(Into::into).collect(),
+            element: field.element.map(|e| Box::new((*e).into())),
+            repeat: field.repeat,
+            condition: field.condition,
+            tlv: field.tlv,
+            tag: field.tag,
+            varint_format: field.varint_format,
+            length_of: field.length_of,
+            text: field.text,
+            endianness: field.endianness,
+            generator: field.generator,
         }
     }
 }
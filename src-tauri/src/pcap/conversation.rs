@@ -0,0 +1,254 @@
+//! Conversations found in an imported capture.
+//!
+//! TCP streams are reassembled by sequence number, so retransmissions and reordered
+//! segments don't show up twice or out of place. Consecutive data from one side forms one
+//! message; the other side sending data starts the next. Each UDP datagram is a message.
+
+use super::frame::{parse_frame, Segment, Transport};
+use super::reader::Packet;
+use crate::protocol::bytes_to_hex_string;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+/// Everything between two endpoints over one transport; for TCP, one connection
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversation {
+    pub transport: Transport,
+    /// Side that opened the connection (or sent first, or uses the higher port)
+    pub client: String,
+    pub server: String,
+    pub packets: usize,
+    pub client_bytes: usize,
+    pub server_bytes: usize,
+    /// Some stream data was never captured; messages after the gap may be cut
+    pub incomplete: bool,
+    pub messages: Vec<CaptureMessage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureMessage {
+    pub from_client: bool,
+    /// Milliseconds since the first packet of the conversation
+    pub offset_ms: f64,
+    /// Payload in hex
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureContents {
+    pub packets: usize,
+    /// Packets that aren't TCP or UDP over IP, or are IP fragments
+    pub ignored: usize,
+    pub conversations: Vec<Conversation>,
+}
+
+/// Conversations in order of their first packet
+pub fn conversations(packets: &[Packet]) -> CaptureContents {
+    let mut builders: Vec<Builder> = Vec::new();
+    // Open conversation by transport and endpoints, in either order
+    let mut open: HashMap<(Transport, SocketAddr, SocketAddr), usize> = HashMap::new();
+    let mut ignored = 0;
+
+    for packet in packets {
+        let Some(segment) = parse_frame(packet.linktype, &packet.data) else {
+            ignored += 1;
+            continue;
+        };
+        let key = (segment.transport, segment.src.min(segment.dst), segment.src.max(segment.dst));
+
+        // A new connection on the same ports starts a new conversation
+        let reused = open.get(&key).is_some_and(|&index| builders[index].is_restarted_by(&segment));
+        let index = match open.get(&key) {
+            Some(&index) if !reused => index,
+            _ => {
+                builders.push(Builder::new(&segment, packet.timestamp));
+                open.insert(key, builders.len() - 1);
+                builders.len() - 1
+            }
+        };
+        builders[index].add(&segment, packet.timestamp);
+    }
+
+    CaptureContents {
+        packets: packets.len(),
+        ignored,
+        conversations: builders.into_iter().map(Builder::finish).collect(),
+    }
+}
+
+/// Reassembly state of one direction of a TCP stream
+#[derive(Default)]
+struct Stream {
+    /// Sequence number of the next byte expected
+    next: Option<u32>,
+    /// Segments ahead of `next`, waiting for the data before them, with their capture time
+    pending: Vec<(u32, u64, Vec<u8>)>,
+    /// Initial sequence number, when the SYN was captured
+    syn: Option<u32>,
+}
+
+struct Builder {
+    transport: Transport,
+    client: SocketAddr,
+    server: SocketAddr,
+    started: u64,
+    packets: usize,
+    client_bytes: usize,
+    server_bytes: usize,
+    incomplete: bool,
+    /// Client to server, then server to client
+    streams: [Stream; 2],
+    /// Whether the client sent it, capture time and payload
+    messages: Vec<(bool, u64, Vec<u8>)>,
+}
+
+impl Builder {
+    fn new(first: &Segment, timestamp: u64) -> Self {
+        let src_is_client = match first.transport {
+            Transport::Tcp if first.is_syn() => !first.is_ack(),
+            // Mid-stream: clients usually connect from an ephemeral, higher port
+            Transport::Tcp if first.src.port() != first.dst.port() => first.src.port() > first.dst.port(),
+            _ => true,
+        };
+        let (client, server) = if src_is_client { (first.src, first.dst) } else { (first.dst, first.src) };
+
+        Self {
+            transport: first.transport,
+            client,
+            server,
+            started: timestamp,
+            packets: 0,
+            client_bytes: 0,
+            server_bytes: 0,
+            incomplete: false,
+            streams: Default::default(),
+            messages: Vec::new(),
+        }
+    }
+
+    /// A client SYN with another initial sequence number, or one after data was exchanged
+    /// without a captured handshake
+    fn is_restarted_by(&self, segment: &Segment) -> bool {
+        if self.transport != Transport::Tcp || !segment.is_syn() || segment.is_ack() {
+            return false;
+        }
+        match self.streams[0].syn {
+            Some(syn) => syn != segment.seq,
+            None => !self.messages.is_empty(),
+        }
+    }
+
+    fn add(&mut self, segment: &Segment, timestamp: u64) {
+        let from_client = segment.src == self.client;
+        self.packets += 1;
+        if segment.payload.is_empty() && !segment.is_syn() {
+            return;
+        }
+
+        if self.transport == Transport::Udp {
+            self.append(from_client, timestamp, &segment.payload);
+            return;
+        }
+
+        let stream = &mut self.streams[usize::from(!from_client)];
+        let mut seq = segment.seq;
+        if segment.is_syn() {
+            stream.syn = Some(seq);
+            seq = seq.wrapping_add(1);
+            stream.next = Some(seq);
+            if segment.payload.is_empty() {
+                return;
+            }
+        }
+        let next = *stream.next.get_or_insert(seq);
+
+        // Data ahead of what has arrived so far waits for the gap to fill
+        if (seq.wrapping_sub(next) as i32) > 0 {
+            stream.pending.push((seq, timestamp, segment.payload.clone()));
+            return;
+        }
+        // The other side answering means what it answered has all been captured that will be
+        self.flush(usize::from(from_client), true);
+        self.accept(from_client, timestamp, seq, &segment.payload);
+        self.flush(usize::from(!from_client), false);
+    }
+
+    /// Deliver waiting segments of one direction that are now in order, or with `bridge`,
+    /// all of them, skipping over the data that's missing
+    fn flush(&mut self, index: usize, bridge: bool) {
+        let from_client = index == 0;
+        loop {
+            let stream = &mut self.streams[index];
+            let next = stream.next.unwrap_or_default();
+            let Some(position) = (0..stream.pending.len()).min_by_key(|&i| stream.pending[i].0.wrapping_sub(next) as i32) else {
+                break;
+            };
+            let seq = stream.pending[position].0;
+            if (seq.wrapping_sub(next) as i32) > 0 {
+                if !bridge {
+                    break;
+                }
+                stream.next = Some(seq);
+                self.incomplete = true;
+            }
+            let (seq, timestamp, payload) = self.streams[index].pending.remove(position);
+            self.accept(from_client, timestamp, seq, &payload);
+        }
+    }
+
+    /// Append the part of an in-order segment that wasn't seen before
+    fn accept(&mut self, from_client: bool, timestamp: u64, seq: u32, payload: &[u8]) {
+        let stream = &mut self.streams[usize::from(!from_client)];
+        let next = stream.next.unwrap_or(seq);
+        let seen = next.wrapping_sub(seq) as usize;
+        if seen >= payload.len() {
+            return;
+        }
+        stream.next = Some(seq.wrapping_add(payload.len() as u32));
+        self.append(from_client, timestamp, &payload[seen..]);
+    }
+
+    /// TCP data continues the message of the same side; every UDP datagram is its own
+    fn append(&mut self, from_client: bool, timestamp: u64, data: &[u8]) {
+        if from_client {
+            self.client_bytes += data.len();
+        } else {
+            self.server_bytes += data.len();
+        }
+        match self.messages.last_mut() {
+            Some((side, _, message)) if *side == from_client && self.transport == Transport::Tcp => {
+                message.extend_from_slice(data)
+            }
+            _ => self.messages.push((from_client, timestamp, data.to_vec())),
+        }
+    }
+
+    fn finish(mut self) -> Conversation {
+        self.flush(0, true);
+        self.flush(1, true);
+
+        let started = self.started;
+        Conversation {
+            transport: self.transport,
+            client: self.client.to_string(),
+            server: self.server.to_string(),
+            packets: self.packets,
+            client_bytes: self.client_bytes,
+            server_bytes: self.server_bytes,
+            incomplete: self.incomplete,
+            messages: self
+                .messages
+                .into_iter()
+                .map(|(from_client, timestamp, data)| CaptureMessage {
+                    from_client,
+                    offset_ms: timestamp.saturating_sub(started) as f64 / 1000.0,
+                    data: bytes_to_hex_string(&data),
+                })
+                .collect(),
+        }
+    }
+}
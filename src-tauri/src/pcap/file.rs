@@ -0,0 +1,132 @@
+//! Writers for the classic pcap and the PCAPNG file formats.
+//! Both are written little-endian with microsecond timestamps and Ethernet link type.
+
+use std::path::Path;
+
+/// LINKTYPE_ETHERNET
+const LINKTYPE_ETHERNET: u16 = 1;
+const SNAPLEN: u32 = 262_144;
+
+const BLOCK_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const BLOCK_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const BLOCK_ENHANCED_PACKET: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+const OPT_END: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const SHB_USER_APPL: u16 = 4;
+const IF_NAME: u16 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    Pcap,
+    Pcapng,
+}
+
+impl CaptureFormat {
+    /// Classic pcap for `.pcap` (and `.cap`) files, PCAPNG otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()) {
+            Some(ext) if ext == "pcap" || ext == "cap" => CaptureFormat::Pcap,
+            _ => CaptureFormat::Pcapng,
+        }
+    }
+}
+
+/// One captured link-layer frame
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Microseconds since the Unix epoch
+    pub timestamp: u64,
+    pub data: Vec<u8>,
+    /// Shown by Wireshark as a packet comment (PCAPNG only)
+    pub comment: Option<String>,
+}
+
+/// Encode `frames` as a capture file; `interface` names the capture interface in PCAPNG
+pub fn write_capture(format: CaptureFormat, interface: &str, frames: &[Frame]) -> Vec<u8> {
+    match format {
+        CaptureFormat::Pcap => write_pcap(frames),
+        CaptureFormat::Pcapng => write_pcapng(interface, frames),
+    }
+}
+
+fn write_pcap(frames: &[Frame]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&0xA1B2_C3D4u32.to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&4u16.to_le_bytes());
+    out.extend_from_slice(&0i32.to_le_bytes()); // thiszone
+    out.extend_from_slice(&0u32.to_le_bytes()); // sigfigs
+    out.extend_from_slice(&SNAPLEN.to_le_bytes());
+    out.extend_from_slice(&(LINKTYPE_ETHERNET as u32).to_le_bytes());
+
+    for frame in frames {
+        out.extend_from_slice(&((frame.timestamp / 1_000_000) as u32).to_le_bytes());
+        out.extend_from_slice(&((frame.timestamp % 1_000_000) as u32).to_le_bytes());
+        out.extend_from_slice(&(frame.data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(frame.data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&frame.data);
+    }
+    out
+}
+
+fn write_pcapng(interface: &str, frames: &[Frame]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    let mut body = Vec::new();
+    body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+    body.extend_from_slice(&1u16.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&(-1i64).to_le_bytes()); // section length not specified
+    push_option(&mut body, SHB_USER_APPL, b"PacketForge");
+    push_option(&mut body, OPT_END, &[]);
+    push_block(&mut out, BLOCK_SECTION_HEADER, &body);
+
+    let mut body = Vec::new();
+    body.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&SNAPLEN.to_le_bytes());
+    push_option(&mut body, IF_NAME, interface.as_bytes());
+    push_option(&mut body, OPT_END, &[]);
+    push_block(&mut out, BLOCK_INTERFACE_DESCRIPTION, &body);
+
+    for frame in frames {
+        let mut body = Vec::new();
+        body.extend_from_slice(&0u32.to_le_bytes()); // interface id
+        body.extend_from_slice(&((frame.timestamp >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.timestamp as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&frame.data);
+        pad(&mut body);
+        if let Some(comment) = &frame.comment {
+            push_option(&mut body, OPT_COMMENT, comment.as_bytes());
+            push_option(&mut body, OPT_END, &[]);
+        }
+        push_block(&mut out, BLOCK_ENHANCED_PACKET, &body);
+    }
+    out
+}
+
+/// Block type, total length, body, total length again
+fn push_block(out: &mut Vec<u8>, block_type: u32, body: &[u8]) {
+    let total = (body.len() + 12) as u32;
+    out.extend_from_slice(&block_type.to_le_bytes());
+    out.extend_from_slice(&total.to_le_bytes());
+    out.extend_from_slice(body);
+    out.extend_from_slice(&total.to_le_bytes());
+}
+
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    let value = &value[..value.len().min(u16::MAX as usize)];
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    pad(body);
+}
+
+/// Blocks and options are aligned to 32 bits
+fn pad(body: &mut Vec<u8>) {
+    body.resize(body.len().div_ceil(4) * 4, 0);
+}
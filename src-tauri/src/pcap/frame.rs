@@ -0,0 +1,347 @@
+//! Ethernet/IP/TCP frames: synthesized around a recorded TCP stream, and taken apart
+//! when a capture is imported.
+//!
+//! Only payload bytes are recorded, so the handshake, acknowledgements and teardown are
+//! made up around them. Sequence and acknowledgement numbers follow the payload exactly,
+//! which is all Wireshark needs to reassemble the stream.
+
+use super::file::Frame;
+use rand::Rng;
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+
+const FIN: u8 = 0x01;
+const SYN: u8 = 0x02;
+const PSH: u8 = 0x08;
+const ACK: u8 = 0x10;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+const WINDOW: u16 = 0xFFFF;
+
+/// Locally administered MAC addresses for the two ends
+const CLIENT_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+const SERVER_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x02];
+
+/// One TCP connection between the app (client) and a server
+pub struct TcpFlow {
+    client: SocketAddr,
+    server: SocketAddr,
+    /// Next sequence number of each side
+    client_seq: u32,
+    server_seq: u32,
+    ip_id: u16,
+    frames: Vec<Frame>,
+}
+
+impl TcpFlow {
+    pub fn new(client: SocketAddr, server: SocketAddr) -> Self {
+        // Both ends must share an address family; an IPv4 end is mapped into IPv6
+        let (client, server) = match (client.ip(), server.ip()) {
+            (IpAddr::V4(c), IpAddr::V6(_)) => (SocketAddr::new(c.to_ipv6_mapped().into(), client.port()), server),
+            (IpAddr::V6(_), IpAddr::V4(s)) => (client, SocketAddr::new(s.to_ipv6_mapped().into(), server.port())),
+            _ => (client, server),
+        };
+        let mut rng = rand::thread_rng();
+
+        Self {
+            client,
+            server,
+            client_seq: rng.gen(),
+            server_seq: rng.gen(),
+            ip_id: rng.gen(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Three-way handshake
+    pub fn open(&mut self, timestamp: u64) {
+        self.segment(timestamp, true, SYN, &[]);
+        self.segment(timestamp, false, SYN | ACK, &[]);
+        self.segment(timestamp, true, ACK, &[]);
+    }
+
+    /// Payload sent by one side, split into segments of at most one MSS and acknowledged by the other
+    pub fn data(&mut self, timestamp: u64, from_client: bool, payload: &[u8]) {
+        if payload.is_empty() {
+            return;
+        }
+        let mss = if self.client.is_ipv4() { 1460 } else { 1440 };
+        let mut chunks = payload.chunks(mss).peekable();
+        while let Some(chunk) = chunks.next() {
+            let flags = if chunks.peek().is_none() { PSH | ACK } else { ACK };
+            self.segment(timestamp, from_client, flags, chunk);
+        }
+        self.segment(timestamp, !from_client, ACK, &[]);
+    }
+
+    /// Orderly teardown started by the client
+    pub fn close(&mut self, timestamp: u64) {
+        self.segment(timestamp, true, FIN | ACK, &[]);
+        self.segment(timestamp, false, FIN | ACK, &[]);
+        self.segment(timestamp, true, ACK, &[]);
+    }
+
+    /// Attach a comment to the last frame written
+    pub fn comment(&mut self, text: impl Into<String>) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.comment = Some(text.into());
+        }
+    }
+
+    pub fn into_frames(self) -> Vec<Frame> {
+        self.frames
+    }
+
+    fn segment(&mut self, timestamp: u64, from_client: bool, flags: u8, payload: &[u8]) {
+        let (src, dst, seq, ack) = if from_client {
+            (self.client, self.server, self.client_seq, self.server_seq)
+        } else {
+            (self.server, self.client, self.server_seq, self.client_seq)
+        };
+
+        let mut tcp = Vec::with_capacity(20 + payload.len());
+        tcp.extend_from_slice(&src.port().to_be_bytes());
+        tcp.extend_from_slice(&dst.port().to_be_bytes());
+        tcp.extend_from_slice(&seq.to_be_bytes());
+        tcp.extend_from_slice(&(if flags & ACK != 0 { ack } else { 0 }).to_be_bytes());
+        tcp.push(5 << 4); // data offset: 20 bytes, no options
+        tcp.push(flags);
+        tcp.extend_from_slice(&WINDOW.to_be_bytes());
+        tcp.extend_from_slice(&[0, 0, 0, 0]); // checksum, urgent pointer
+        tcp.extend_from_slice(payload);
+
+        let mut frame = Vec::with_capacity(14 + 40 + tcp.len());
+        let (src_mac, dst_mac) = if from_client { (CLIENT_MAC, SERVER_MAC) } else { (SERVER_MAC, CLIENT_MAC) };
+        frame.extend_from_slice(&dst_mac);
+        frame.extend_from_slice(&src_mac);
+
+        match (src.ip(), dst.ip()) {
+            (IpAddr::V4(s), IpAddr::V4(d)) => {
+                let mut pseudo = Vec::with_capacity(12);
+                pseudo.extend_from_slice(&s.octets());
+                pseudo.extend_from_slice(&d.octets());
+                pseudo.extend_from_slice(&[0, IPPROTO_TCP]);
+                pseudo.extend_from_slice(&(tcp.len() as u16).to_be_bytes());
+                let sum = checksum(&[&pseudo, &tcp]);
+                tcp[16..18].copy_from_slice(&sum.to_be_bytes());
+
+                let mut ip = Vec::with_capacity(20);
+                ip.push(0x45);
+                ip.push(0);
+                ip.extend_from_slice(&((20 + tcp.len()) as u16).to_be_bytes());
+                ip.extend_from_slice(&self.ip_id.to_be_bytes());
+                ip.extend_from_slice(&0x4000u16.to_be_bytes()); // don't fragment
+                ip.push(64);
+                ip.push(IPPROTO_TCP);
+                ip.extend_from_slice(&[0, 0]);
+                ip.extend_from_slice(&s.octets());
+                ip.extend_from_slice(&d.octets());
+                let sum = checksum(&[&ip]);
+                ip[10..12].copy_from_slice(&sum.to_be_bytes());
+                self.ip_id = self.ip_id.wrapping_add(1);
+
+                frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+                frame.extend_from_slice(&ip);
+            }
+            (s, d) => {
+                let s = to_ipv6(s).octets();
+                let d = to_ipv6(d).octets();
+                let mut pseudo = Vec::with_capacity(40);
+                pseudo.extend_from_slice(&s);
+                pseudo.extend_from_slice(&d);
+                pseudo.extend_from_slice(&(tcp.len() as u32).to_be_bytes());
+                pseudo.extend_from_slice(&[0, 0, 0, IPPROTO_TCP]);
+                let sum = checksum(&[&pseudo, &tcp]);
+                tcp[16..18].copy_from_slice(&sum.to_be_bytes());
+
+                frame.extend_from_slice(&ETHERTYPE_IPV6.to_be_bytes());
+                frame.extend_from_slice(&0x6000_0000u32.to_be_bytes());
+                frame.extend_from_slice(&(tcp.len() as u16).to_be_bytes());
+                frame.push(IPPROTO_TCP);
+                frame.push(64);
+                frame.extend_from_slice(&s);
+                frame.extend_from_slice(&d);
+            }
+        }
+        frame.extend_from_slice(&tcp);
+
+        let advance = payload.len() as u32 + u32::from(flags & (SYN | FIN) != 0);
+        if from_client {
+            self.client_seq = self.client_seq.wrapping_add(advance);
+        } else {
+            self.server_seq = self.server_seq.wrapping_add(advance);
+        }
+
+        self.frames.push(Frame {
+            timestamp,
+            data: frame,
+            comment: None,
+        });
+    }
+}
+
+fn to_ipv6(ip: IpAddr) -> std::net::Ipv6Addr {
+    match ip {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+        IpAddr::V6(v6) => v6,
+    }
+}
+
+/// Internet checksum over the concatenation of `parts` (each but the last of even length)
+fn checksum(parts: &[&[u8]]) -> u16 {
+    let mut sum: u32 = 0;
+    for part in parts {
+        let mut words = part.chunks_exact(2);
+        for word in &mut words {
+            sum += u32::from(u16::from_be_bytes([word[0], word[1]]));
+        }
+        if let [last] = words.remainder() {
+            sum += u32::from(*last) << 8;
+        }
+    }
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+/// TCP segment or UDP datagram found in a captured frame
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub transport: Transport,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    /// Sequence number and flags; zero for UDP
+    pub seq: u32,
+    pub flags: u8,
+    pub payload: Vec<u8>,
+}
+
+impl Segment {
+    pub fn is_syn(&self) -> bool {
+        self.flags & SYN != 0
+    }
+
+    pub fn is_ack(&self) -> bool {
+        self.flags & ACK != 0
+    }
+
+    pub fn is_fin(&self) -> bool {
+        self.flags & FIN != 0
+    }
+}
+
+/// TCP or UDP content of a frame with link-layer header type `linktype`.
+/// Other protocols and IP fragments give None.
+pub fn parse_frame(linktype: u32, data: &[u8]) -> Option<Segment> {
+    let ip = match linktype {
+        // BSD loopback: a four-byte address family in either byte order
+        0 | 108 => data.get(4..)?,
+        1 => {
+            let mut ethertype = u16::from_be_bytes([*data.get(12)?, *data.get(13)?]);
+            let mut at = 14;
+            // 802.1Q and 802.1ad VLAN tags
+            while ethertype == 0x8100 || ethertype == 0x88A8 {
+                ethertype = u16::from_be_bytes([*data.get(at + 2)?, *data.get(at + 3)?]);
+                at += 4;
+            }
+            if ethertype != ETHERTYPE_IPV4 && ethertype != ETHERTYPE_IPV6 {
+                return None;
+            }
+            data.get(at..)?
+        }
+        // Raw IP
+        12 | 14 | 101 | 228 | 229 => data,
+        // Linux cooked capture v1 and v2
+        113 => data.get(16..)?,
+        276 => data.get(20..)?,
+        _ => return None,
+    };
+
+    match ip.first()? >> 4 {
+        4 => parse_ipv4(ip),
+        6 => parse_ipv6(ip),
+        _ => None,
+    }
+}
+
+fn parse_ipv4(ip: &[u8]) -> Option<Segment> {
+    let header = ((ip.first()? & 0x0F) as usize) * 4;
+    let total = u16::from_be_bytes([*ip.get(2)?, *ip.get(3)?]) as usize;
+    let fragment = u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]);
+    // More fragments, or a fragment offset
+    if fragment & 0x3FFF != 0 {
+        return None;
+    }
+    let src = IpAddr::from(<[u8; 4]>::try_from(ip.get(12..16)?).ok()?);
+    let dst = IpAddr::from(<[u8; 4]>::try_from(ip.get(16..20)?).ok()?);
+    // Ethernet padding follows short packets; segmentation offload may leave the length zero
+    let end = if total >= header { total.min(ip.len()) } else { ip.len() };
+    parse_transport(*ip.get(9)?, src, dst, ip.get(header..end)?)
+}
+
+fn parse_ipv6(ip: &[u8]) -> Option<Segment> {
+    let length = u16::from_be_bytes([*ip.get(4)?, *ip.get(5)?]) as usize;
+    let src = IpAddr::from(<[u8; 16]>::try_from(ip.get(8..24)?).ok()?);
+    let dst = IpAddr::from(<[u8; 16]>::try_from(ip.get(24..40)?).ok()?);
+    let end = if length > 0 { (40 + length).min(ip.len()) } else { ip.len() };
+    let mut next = *ip.get(6)?;
+    let mut at = 40;
+
+    loop {
+        match next {
+            // Hop-by-hop, routing and destination options
+            0 | 43 | 60 => {
+                next = *ip.get(at)?;
+                at += (*ip.get(at + 1)? as usize + 1) * 8;
+            }
+            // Authentication header
+            51 => {
+                next = *ip.get(at)?;
+                at += (*ip.get(at + 1)? as usize + 2) * 4;
+            }
+            // Fragments are not reassembled
+            44 => return None,
+            _ => break,
+        }
+    }
+    parse_transport(next, src, dst, ip.get(at..end)?)
+}
+
+fn parse_transport(protocol: u8, src: IpAddr, dst: IpAddr, data: &[u8]) -> Option<Segment> {
+    let src_port = u16::from_be_bytes([*data.first()?, *data.get(1)?]);
+    let dst_port = u16::from_be_bytes([*data.get(2)?, *data.get(3)?]);
+
+    let (transport, seq, flags, payload) = match protocol {
+        IPPROTO_TCP => {
+            let seq = u32::from_be_bytes(data.get(4..8)?.try_into().ok()?);
+            let offset = ((*data.get(12)? >> 4) as usize) * 4;
+            (Transport::Tcp, seq, *data.get(13)?, data.get(offset..)?)
+        }
+        IPPROTO_UDP => {
+            let length = u16::from_be_bytes([*data.get(4)?, *data.get(5)?]) as usize;
+            let end = if length >= 8 { length.min(data.len()) } else { data.len() };
+            (Transport::Udp, 0, 0, data.get(8..end)?)
+        }
+        _ => return None,
+    };
+
+    Some(Segment {
+        transport,
+        src: SocketAddr::new(src, src_port),
+        dst: SocketAddr::new(dst, dst_port),
+        seq,
+        flags,
+        payload: payload.to_vec(),
+    })
+}
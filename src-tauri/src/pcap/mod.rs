@@ -0,0 +1,15 @@
+//! Packet capture files
+//!
+//! Recorded connections only hold the payload bytes, so exports wrap them in synthesized
+//! Ethernet, IP and TCP headers that Wireshark can reassemble into the original stream.
+//! Imports go the other way, from captured frames back to the payloads of each conversation.
+
+pub mod conversation;
+pub mod file;
+pub mod frame;
+pub mod reader;
+
+pub use conversation::{conversations, CaptureContents, CaptureMessage, Conversation};
+pub use file::{write_capture, CaptureFormat, Frame};
+pub use frame::{parse_frame, Segment, TcpFlow, Transport};
+pub use reader::{read_capture, Packet};
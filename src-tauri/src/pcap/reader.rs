@@ -0,0 +1,180 @@
+//! Reader for classic pcap (either byte order, micro- or nanosecond timestamps) and PCAPNG
+//! files. A file cut short ends at its last complete packet.
+
+use anyhow::{bail, Result};
+
+/// One captured packet as stored in the file
+#[derive(Debug, Clone)]
+pub struct Packet {
+    /// Microseconds since the Unix epoch
+    pub timestamp: u64,
+    /// Link-layer header type (LINKTYPE_*) of `data`
+    pub linktype: u32,
+    pub data: Vec<u8>,
+}
+
+const BLOCK_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const BLOCK_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const BLOCK_PACKET: u32 = 0x0000_0002;
+const BLOCK_SIMPLE_PACKET: u32 = 0x0000_0003;
+const BLOCK_ENHANCED_PACKET: u32 = 0x0000_0006;
+const IF_TSRESOL: u16 = 9;
+
+pub fn read_capture(bytes: &[u8]) -> Result<Vec<Packet>> {
+    if bytes.len() < 4 {
+        bail!("Not a capture file");
+    }
+    match bytes[..4] {
+        [0xD4, 0xC3, 0xB2, 0xA1] => read_pcap(bytes, false, false),
+        [0xA1, 0xB2, 0xC3, 0xD4] => read_pcap(bytes, true, false),
+        [0x4D, 0x3C, 0xB2, 0xA1] => read_pcap(bytes, false, true),
+        [0xA1, 0xB2, 0x3C, 0x4D] => read_pcap(bytes, true, true),
+        [0x0A, 0x0D, 0x0D, 0x0A] => read_pcapng(bytes),
+        _ => bail!("Not a pcap or pcapng file"),
+    }
+}
+
+/// Fixed-width integers in the byte order of the file
+#[derive(Clone, Copy)]
+struct Order {
+    big: bool,
+}
+
+impl Order {
+    fn u16(self, bytes: &[u8], at: usize) -> u16 {
+        let b = [bytes[at], bytes[at + 1]];
+        if self.big { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) }
+    }
+
+    fn u32(self, bytes: &[u8], at: usize) -> u32 {
+        let b = [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]];
+        if self.big { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+    }
+}
+
+fn read_pcap(bytes: &[u8], big: bool, nanos: bool) -> Result<Vec<Packet>> {
+    if bytes.len() < 24 {
+        bail!("Truncated pcap header");
+    }
+    let order = Order { big };
+    // The upper bits may carry FCS information
+    let linktype = order.u32(bytes, 20) & 0x0FFF_FFFF;
+
+    let mut packets = Vec::new();
+    let mut at = 24;
+    while at + 16 <= bytes.len() {
+        let seconds = order.u32(bytes, at) as u64;
+        let fraction = order.u32(bytes, at + 4) as u64;
+        let captured = order.u32(bytes, at + 8) as usize;
+        let start = at + 16;
+        let Some(data) = bytes.get(start..start + captured) else {
+            break;
+        };
+        packets.push(Packet {
+            timestamp: seconds * 1_000_000 + if nanos { fraction / 1000 } else { fraction },
+            linktype,
+            data: data.to_vec(),
+        });
+        at = start + captured;
+    }
+    Ok(packets)
+}
+
+/// Link type and timestamp resolution of a PCAPNG interface
+struct Interface {
+    linktype: u32,
+    /// if_tsresol: a power of ten, or of two when the high bit is set
+    resolution: u8,
+}
+
+impl Interface {
+    fn micros(&self, ticks: u64) -> u64 {
+        let exponent = (self.resolution & 0x7F) as u32;
+        if self.resolution & 0x80 != 0 {
+            ((ticks as u128 * 1_000_000) >> exponent.min(127)) as u64
+        } else if exponent >= 6 {
+            ticks / 10u64.saturating_pow(exponent - 6)
+        } else {
+            ticks.saturating_mul(10u64.pow(6 - exponent))
+        }
+    }
+}
+
+fn read_pcapng(bytes: &[u8]) -> Result<Vec<Packet>> {
+    let mut order = Order { big: false };
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut packets = Vec::new();
+    let mut at = 0;
+
+    while at + 12 <= bytes.len() {
+        if u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) == BLOCK_SECTION_HEADER {
+            // Every section states its own byte order and interfaces
+            order = Order { big: bytes[at + 8..at + 12] == [0x1A, 0x2B, 0x3C, 0x4D] };
+            interfaces.clear();
+        }
+        let block_type = order.u32(bytes, at);
+        let length = order.u32(bytes, at + 4) as usize;
+        if length < 12 || at + length > bytes.len() {
+            break;
+        }
+        let body = &bytes[at + 8..at + length - 4];
+
+        match block_type {
+            BLOCK_INTERFACE_DESCRIPTION if body.len() >= 8 => {
+                interfaces.push(Interface {
+                    linktype: order.u16(body, 0) as u32,
+                    resolution: interface_resolution(order, &body[8..]),
+                });
+            }
+            BLOCK_ENHANCED_PACKET | BLOCK_PACKET if body.len() >= 20 => {
+                // The obsolete packet block has a 16-bit interface id followed by a drop count
+                let interface = if block_type == BLOCK_PACKET {
+                    order.u16(body, 0) as usize
+                } else {
+                    order.u32(body, 0) as usize
+                };
+                let ticks = (order.u32(body, 4) as u64) << 32 | order.u32(body, 8) as u64;
+                let captured = order.u32(body, 12) as usize;
+                if let (Some(interface), Some(data)) = (interfaces.get(interface), body.get(20..20 + captured)) {
+                    packets.push(Packet {
+                        timestamp: interface.micros(ticks),
+                        linktype: interface.linktype,
+                        data: data.to_vec(),
+                    });
+                }
+            }
+            // No timestamp; the captured length is whatever fits in the block
+            BLOCK_SIMPLE_PACKET if body.len() >= 4 => {
+                let original = order.u32(body, 0) as usize;
+                if let Some(interface) = interfaces.first() {
+                    let data = &body[4..];
+                    packets.push(Packet {
+                        timestamp: 0,
+                        linktype: interface.linktype,
+                        data: data[..original.min(data.len())].to_vec(),
+                    });
+                }
+            }
+            _ => {}
+        }
+        at += length;
+    }
+    Ok(packets)
+}
+
+/// if_tsresol option of an interface description; microseconds when absent
+fn interface_resolution(order: Order, mut options: &[u8]) -> u8 {
+    while options.len() >= 4 {
+        let code = order.u16(options, 0);
+        let length = order.u16(options, 2) as usize;
+        let padded = length.div_ceil(4) * 4;
+        if code == IF_TSRESOL && length >= 1 && options.len() > 4 {
+            return options[4];
+        }
+        if code == 0 || options.len() < 4 + padded {
+            break;
+        }
+        options = &options[4 + padded..];
+    }
+    6
+}
@@ -0,0 +1,810 @@
+use super::encoder::fixed_length;
+use super::text;
+use crate::models::{
+    Endianness, FieldCondition, FieldKind, LengthOf, PadSide, Protocol, ProtocolField, RepeatRule, Terminator,
+    TextEncoding, TlvSpec, VarintFormat,
+};
+use std::fmt::Write;
+
+/// Wire layout of a field as the generated code sees it
+pub(super) enum Shape {
+    /// Integer of 1, 2, 4 or 8 bytes
+    Uint { len: usize, little: bool },
+    /// Fixed number of bytes, padded with `pad` on the left or right
+    Bytes { len: usize, pad: u8, left: bool },
+    /// Bytes whose size is only known on the wire (`fallback` is the configured length)
+    Variable { fallback: usize },
+    /// Bytes followed by a terminator
+    Terminated { terminator: Vec<u8>, unit: usize },
+    /// Bytes preceded by a one-byte length
+    Prefixed,
+    Varint(VarintFormat),
+    Struct(usize),
+    List { element: Box<Shape>, count: Count },
+    Tlv(TlvSpec),
+}
+
+pub(super) enum Count {
+    Fixed(u32),
+    Field(String),
+    UntilEnd,
+}
+
+pub(super) struct Member<'a> {
+    pub(super) ident: String,
+    pub(super) field: &'a ProtocolField,
+    pub(super) shape: Shape,
+}
+
+pub(super) struct StructDef<'a> {
+    rust_name: String,
+    c_name: String,
+    /// Member identifiers leading to this struct, starting with the protocol
+    pub(super) path: Vec<String>,
+    description: Option<String>,
+    pub(super) members: Vec<Member<'a>>,
+}
+
+/// Structs of a protocol, nested ones before the structs that use them
+pub(super) struct Layout<'a> {
+    pub(super) structs: Vec<StructDef<'a>>,
+}
+
+impl<'a> Layout<'a> {
+    pub(super) fn new(protocol: &'a Protocol) -> Self {
+        let mut layout = Layout { structs: Vec::new() };
+        let words = words(&protocol.name);
+        layout.add_struct(&words, vec![words.join("_")], protocol.description.clone(), &protocol.fields);
+        layout
+    }
+
+    fn add_struct(
+        &mut self,
+        words: &[String],
+        path: Vec<String>,
+        description: Option<String>,
+        fields: &'a [ProtocolField],
+    ) -> usize {
+        let mut members: Vec<Member> = Vec::new();
+        for field in fields.iter().filter(|f| f.enabled) {
+            let mut ident = snake_ident(&field.name);
+            let base = ident.clone();
+            let mut n = 2;
+            while members.iter().any(|m| m.ident == ident) {
+                ident = format!("{}_{}", base, n);
+                n += 1;
+            }
+            let shape = self.shape(field, words, &[path.as_slice(), &[ident.clone()]].concat());
+            members.push(Member { ident, field, shape });
+        }
+
+        // Different paths can still spell the same name, e.g. `a` > `b_c` and `a_b` > `c`
+        let mut words = words.to_vec();
+        let base = words.clone();
+        let mut n = 2;
+        while self.structs.iter().any(|s| s.c_name == format!("{}_t", words.join("_"))) {
+            words = [base.as_slice(), &[n.to_string()]].concat();
+            n += 1;
+        }
+
+        self.structs.push(StructDef {
+            rust_name: words.iter().map(|w| capitalize(w)).collect(),
+            c_name: format!("{}_t", words.join("_")),
+            path,
+            description: description.filter(|d| !d.is_empty()),
+            members,
+        });
+        self.structs.len() - 1
+    }
+
+    fn shape(&mut self, field: &'a ProtocolField, parent: &[String], path: &[String]) -> Shape {
+        match field.kind {
+            FieldKind::Scalar => scalar_shape(field),
+            FieldKind::Varint => Shape::Varint(field.varint_format.unwrap_or_default()),
+            FieldKind::Group => {
+                let index = self.add_struct(
+                    &child_words(parent, path),
+                    path.to_vec(),
+                    field.description.clone(),
+                    &field.fields,
+                );
+                Shape::Struct(index)
+            }
+            FieldKind::Array => {
+                let Some(element) = field.element.as_deref().or(field.fields.first()) else {
+                    return Shape::Variable { fallback: 0 };
+                };
+                let count = match &field.repeat {
+                    Some(RepeatRule::Fixed { count }) => Count::Fixed(*count),
+                    Some(RepeatRule::FromField { field }) => Count::Field(field.clone()),
+                    Some(RepeatRule::UntilEnd) | None => Count::UntilEnd,
+                };
+                Shape::List {
+                    element: Box::new(self.shape(
+                        element,
+                        &child_words(parent, path),
+                        &[path, &[snake_ident(&element.name)]].concat(),
+                    )),
+                    count,
+                }
+            }
+            FieldKind::Tlv => match &field.tlv {
+                Some(spec) => Shape::Tlv(spec.clone()),
+                None => Shape::Variable { fallback: 0 },
+            },
+        }
+    }
+
+    /// Whether every member has a size known up front (nothing flexible)
+    fn is_fixed(&self, shape: &Shape) -> bool {
+        match shape {
+            Shape::Uint { .. } | Shape::Bytes { .. } => true,
+            Shape::Struct(index) => self.structs[*index].members.iter().all(|m| self.is_fixed(&m.shape)),
+            Shape::List { element, count: Count::Fixed(_) } => self.is_fixed(element),
+            _ => false,
+        }
+    }
+}
+
+fn scalar_shape(field: &ProtocolField) -> Shape {
+    let spec = field.text.clone().unwrap_or_default();
+    if field.is_variable {
+        if !field.is_text() {
+            return Shape::Variable { fallback: field.length.unwrap_or(0).max(0) as usize };
+        }
+        return match spec.terminator {
+            Terminator::None => Shape::Variable { fallback: field.length.unwrap_or(0).max(0) as usize },
+            Terminator::Pascal => Shape::Prefixed,
+            Terminator::Nul | Terminator::Crlf => Shape::Terminated {
+                terminator: text::encode(&spec, "").unwrap_or_default(),
+                unit: match spec.encoding {
+                    TextEncoding::Utf16Le | TextEncoding::Utf16Be => 2,
+                    _ => 1,
+                },
+            },
+        };
+    }
+
+    let len = fixed_length(field);
+    if field.is_text() {
+        Shape::Bytes { len, pad: spec.pad_byte, left: spec.pad_side == PadSide::Left }
+    } else if matches!(len, 1 | 2 | 4 | 8) {
+        Shape::Uint { len, little: field.is_little_endian() }
+    } else {
+        Shape::Bytes { len, pad: 0, left: true }
+    }
+}
+
+/// Lowercase words of a name, used to build identifiers in each language's style
+pub(super) fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && prev_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        current.push(c.to_ascii_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    if words.is_empty() {
+        words.push("field".to_string());
+    }
+    if words[0].starts_with(|c: char| c.is_ascii_digit()) {
+        words.insert(0, "f".to_string());
+    }
+    words
+}
+
+/// Struct name words for a member, from its deduplicated ident (the last part of its path)
+fn child_words(parent: &[String], path: &[String]) -> Vec<String> {
+    let mut words = parent.to_vec();
+    words.extend(path.last().map(|ident| self::words(ident)).unwrap_or_default());
+    words
+}
+
+/// Text inside a C block comment, which `*/` would end early
+fn c_comment(text: &str) -> String {
+    text.replace("*/", "* /")
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "static",
+    "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while", "char", "int", "long", "short",
+    "signed", "unsigned", "float", "double", "void", "union", "default", "switch", "case", "goto", "register",
+    "sizeof", "typedef", "volatile", "data", "pos",
+];
+
+pub(super) fn snake_ident(name: &str) -> String {
+    let ident = words(name).join("_");
+    if KEYWORDS.contains(&ident.as_str()) {
+        format!("{}_", ident)
+    } else {
+        ident
+    }
+}
+
+fn condition_text(condition: &FieldCondition) -> String {
+    match condition {
+        FieldCondition::Equals { field, value } => format!("{} == {:#X}", field, value),
+        FieldCondition::BitmaskSet { field, mask } => format!("{} & {:#X}", field, mask),
+        FieldCondition::InSet { field, values } => format!(
+            "{} in {{{}}}",
+            field,
+            values.iter().map(|v| format!("{:#X}", v)).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+fn length_of_text(length_of: &LengthOf) -> String {
+    match length_of {
+        LengthOf::Field { field } => format!("length of {}", field),
+        LengthOf::Following => "length of the following fields".to_string(),
+    }
+}
+
+fn varint_text(format: VarintFormat) -> &'static str {
+    match format {
+        VarintFormat::Leb128 => "LEB128 varint",
+        VarintFormat::Zigzag => "zigzag varint",
+        VarintFormat::MqttLength => "MQTT remaining length varint",
+    }
+}
+
+/// Comment notes shared by both languages
+fn member_notes(member: &Member, endian_macro: Option<&str>) -> Vec<String> {
+    let field = member.field;
+    let mut notes = Vec::new();
+    if let Some(description) = field.description.as_deref().filter(|d| !d.is_empty()) {
+        notes.push(description.to_string());
+    }
+    if let Some(length_of) = &field.length_of {
+        notes.push(length_of_text(length_of));
+    }
+    if let Some(condition) = &field.condition {
+        notes.push(format!("present only when {}", condition_text(condition)));
+    }
+    match &member.shape {
+        Shape::Uint { len, little } if *len > 1 => match endian_macro {
+            Some(prefix) if *little => notes.push(format!("little-endian, use {}_LE{}()", prefix, len * 8)),
+            Some(prefix) => notes.push(format!("big-endian, use {}_BE{}()", prefix, len * 8)),
+            None if *little => notes.push("little-endian".to_string()),
+            None => notes.push("big-endian".to_string()),
+        },
+        Shape::Varint(format) => notes.push(varint_text(*format).to_string()),
+        Shape::Terminated { .. } => notes.push("terminated text".to_string()),
+        Shape::Prefixed => notes.push("text with a one-byte length prefix".to_string()),
+        Shape::Tlv(spec) => notes.push(format!(
+            "TLV items: {}-byte tag, {}-byte length, {}",
+            spec.tag_width,
+            spec.length_width,
+            if spec.endianness == Endianness::Little { "little-endian" } else { "big-endian" }
+        )),
+        _ => {}
+    }
+    notes
+}
+
+/// Packed C struct header for a protocol
+pub fn c_header(protocol: &Protocol) -> String {
+    let layout = Layout::new(protocol);
+    let prefix = words(&protocol.name).join("_").to_uppercase();
+    let mut out = String::new();
+
+    let _ = writeln!(out, "/* Generated by PacketForge from protocol \"{}\". */", c_comment(&protocol.name));
+    if let Some(description) = protocol.description.as_deref().filter(|d| !d.is_empty()) {
+        let _ = writeln!(out, "/* {} */", c_comment(description));
+    }
+    let _ = writeln!(out, "#ifndef {}_H", prefix);
+    let _ = writeln!(out, "#define {}_H\n", prefix);
+    let _ = writeln!(out, "#include <stdint.h>\n");
+    let _ = writeln!(out, "/* Multi-byte fields are big-endian (network byte order) unless noted. */");
+    let _ = writeln!(out, "#if defined(__BYTE_ORDER__) && __BYTE_ORDER__ == __ORDER_LITTLE_ENDIAN__");
+    for bits in [16, 32, 64] {
+        let _ = writeln!(out, "#define {}_BE{}(x) __builtin_bswap{}(x)", prefix, bits, bits);
+        let _ = writeln!(out, "#define {}_LE{}(x) (x)", prefix, bits);
+    }
+    let _ = writeln!(out, "#else");
+    for bits in [16, 32, 64] {
+        let _ = writeln!(out, "#define {}_BE{}(x) (x)", prefix, bits);
+        let _ = writeln!(out, "#define {}_LE{}(x) __builtin_bswap{}(x)", prefix, bits, bits);
+    }
+    let _ = writeln!(out, "#endif\n");
+    let _ = writeln!(out, "#pragma pack(push, 1)\n");
+
+    for def in &layout.structs {
+        if let Some(description) = &def.description {
+            let _ = writeln!(out, "/* {} */", c_comment(description));
+        }
+        let _ = writeln!(out, "typedef struct {{");
+        // Only the first variable-size member fits, as a flexible array member
+        let mut flexible = false;
+        for member in &def.members {
+            let notes = member_notes(member, Some(&prefix));
+            let decl = if layout.is_fixed(&member.shape) {
+                c_declaration(&layout, member)
+            } else {
+                format!("uint8_t {}[]", member.ident)
+            };
+            let notes = c_comment(&notes.join("; "));
+            if flexible {
+                let suffix = if notes.is_empty() { String::new() } else { format!(" ({})", notes) };
+                let _ = writeln!(out, "    /* then: {}{} */", decl, suffix);
+            } else {
+                let suffix = if notes.is_empty() { String::new() } else { format!(" /* {} */", notes) };
+                let _ = writeln!(out, "    {};{}", decl, suffix);
+            }
+            if !layout.is_fixed(&member.shape) {
+                flexible = true;
+            }
+        }
+        if def.members.is_empty() {
+            let _ = writeln!(out, "    uint8_t empty[1]; /* protocol has no fields */");
+        }
+        let _ = writeln!(out, "}} {};\n", def.c_name);
+    }
+
+    let _ = writeln!(out, "#pragma pack(pop)\n");
+    let _ = writeln!(out, "#endif /* {}_H */", prefix);
+    out
+}
+
+fn c_declaration(layout: &Layout, member: &Member) -> String {
+    c_type(layout, &member.shape, &member.ident, member.field.is_text())
+}
+
+fn c_type(layout: &Layout, shape: &Shape, name: &str, text: bool) -> String {
+    match shape {
+        Shape::Uint { len, .. } => format!("uint{}_t {}", len * 8, name),
+        Shape::Bytes { len, .. } => format!("{} {}[{}]", if text { "char" } else { "uint8_t" }, name, len),
+        Shape::Struct(index) => format!("{} {}", layout.structs[*index].c_name, name),
+        Shape::List { element, count: Count::Fixed(count) } => {
+            format!("{}[{}]", c_type(layout, element, name, false), count)
+        }
+        _ => format!("uint8_t {}[]", name),
+    }
+}
+
+/// Helper functions the generated Rust code needs
+#[derive(Default)]
+struct Helpers {
+    pad_left: bool,
+    pad_right: bool,
+    varint: bool,
+    tlv: bool,
+    terminated: bool,
+}
+
+/// Rust structs with `to_bytes` / `from_bytes` for a protocol
+pub fn rust_source(protocol: &Protocol) -> String {
+    let layout = Layout::new(protocol);
+    let mut helpers = Helpers::default();
+    let mut out = String::new();
+
+    let _ = writeln!(out, "// Generated by PacketForge from protocol \"{}\".", protocol.name);
+    let _ = writeln!(out, "// Multi-byte integers are big-endian on the wire unless noted.\n");
+
+    for def in &layout.structs {
+        rust_struct(&mut out, &layout, def, &mut helpers);
+    }
+    rust_helpers(&mut out, &helpers);
+    out
+}
+
+fn rust_type(layout: &Layout, shape: &Shape) -> String {
+    match shape {
+        Shape::Uint { len, .. } => format!("u{}", len * 8),
+        Shape::Bytes { .. } | Shape::Variable { .. } | Shape::Terminated { .. } | Shape::Prefixed => {
+            "Vec<u8>".to_string()
+        }
+        Shape::Varint(VarintFormat::Zigzag) => "i64".to_string(),
+        Shape::Varint(_) => "u64".to_string(),
+        Shape::Struct(index) => layout.structs[*index].rust_name.clone(),
+        Shape::List { element, .. } => format!("Vec<{}>", rust_type(layout, element)),
+        Shape::Tlv(_) => "Vec<(u64, Vec<u8>)>".to_string(),
+    }
+}
+
+fn rust_struct(out: &mut String, layout: &Layout, def: &StructDef, helpers: &mut Helpers) {
+    if let Some(description) = &def.description {
+        for line in description.lines() {
+            let _ = writeln!(out, "/// {}", line);
+        }
+    }
+    let _ = writeln!(out, "#[derive(Debug, Clone, Default, PartialEq)]");
+    let _ = writeln!(out, "pub struct {} {{", def.rust_name);
+    for member in &def.members {
+        for note in member_notes(member, None) {
+            for line in note.lines() {
+                let _ = writeln!(out, "    /// {}", line);
+            }
+        }
+        let ty = rust_type(layout, &member.shape);
+        let ty = if member.field.condition.is_some() { format!("Option<{}>", ty) } else { ty };
+        let _ = writeln!(out, "    pub {}: {},", member.ident, ty);
+    }
+    let _ = writeln!(out, "}}\n");
+
+    let _ = writeln!(out, "impl {} {{", def.rust_name);
+
+    // to_bytes: encode every member, then fill in lengths back to front
+    let _ = writeln!(out, "    /// Encode to wire bytes; length fields are filled in automatically");
+    let _ = writeln!(out, "    pub fn to_bytes(&self) -> Vec<u8> {{");
+    for member in def.members.iter().filter(|m| m.field.length_of.is_none()) {
+        let value = format!("&self.{}", member.ident);
+        let expr = if member.field.condition.is_some() {
+            format!(
+                "match {} {{ Some(v) => {}, None => Vec::new() }}",
+                value,
+                rust_encode(&member.shape, "v", helpers)
+            )
+        } else {
+            rust_encode(&member.shape, &value, helpers)
+        };
+        let _ = writeln!(out, "        let b_{} = {};", member.ident, expr);
+    }
+    for (i, member) in def.members.iter().enumerate().rev() {
+        let Some(length_of) = &member.field.length_of else { continue };
+        let length = match length_of {
+            LengthOf::Field { field } => def
+                .members
+                .iter()
+                .find(|m| m.field.name == *field)
+                .map(|m| format!("b_{}.len()", m.ident))
+                .unwrap_or_else(|| "0".to_string()),
+            LengthOf::Following => {
+                let parts: Vec<String> = def.members[i + 1..].iter().map(|m| format!("b_{}.len()", m.ident)).collect();
+                if parts.is_empty() { "0".to_string() } else { parts.join(" + ") }
+            }
+        };
+        let expr = match &member.shape {
+            Shape::Uint { len, little } => format!(
+                "(({}) as u{}).{}().to_vec()",
+                length,
+                len * 8,
+                if *little { "to_le_bytes" } else { "to_be_bytes" }
+            ),
+            Shape::Varint(_) => {
+                helpers.varint = true;
+                format!("write_varint(({}) as u64)", length)
+            }
+            shape => rust_encode(shape, &format!("&self.{}", member.ident), helpers),
+        };
+        let _ = writeln!(out, "        let b_{} = {};", member.ident, expr);
+    }
+    let parts: Vec<String> = def.members.iter().map(|m| format!("b_{}", m.ident)).collect();
+    if parts.is_empty() {
+        let _ = writeln!(out, "        Vec::new()");
+    } else {
+        let _ = writeln!(out, "        [{}].concat()", parts.join(", "));
+    }
+    let _ = writeln!(out, "    }}\n");
+
+    let _ = writeln!(out, "    /// Decode from wire bytes; None if the data is too short");
+    let _ = writeln!(out, "    pub fn from_bytes(data: &[u8]) -> Option<Self> {{");
+    let _ = writeln!(out, "        Self::read(data, &mut 0)");
+    let _ = writeln!(out, "    }}\n");
+
+    let _ = writeln!(out, "    fn read(data: &[u8], pos: &mut usize) -> Option<Self> {{");
+    let mut lengths: Vec<(&str, String)> = Vec::new();
+    for (i, member) in def.members.iter().enumerate() {
+        let is_last = i + 1 == def.members.len();
+        let size = lengths
+            .iter()
+            .find(|(target, _)| *target == member.field.name)
+            .map(|(_, local)| format!("{} as usize", local))
+            .unwrap_or_else(|| match &member.shape {
+                Shape::Variable { fallback } if !is_last && *fallback > 0 => fallback.to_string(),
+                Shape::Tlv(_) if !is_last && member.field.length.unwrap_or(0) > 0 => {
+                    member.field.length.unwrap_or(0).to_string()
+                }
+                _ => "data.len() - *pos".to_string(),
+            });
+        let read = rust_read(layout, def, &member.shape, &size, helpers);
+        let expr = match &member.field.condition {
+            Some(condition) => match rust_condition(def, condition) {
+                Some(test) => format!("if {} {{ Some({}) }} else {{ None }}", test, read),
+                None => format!("Some({})", read),
+            },
+            None => read,
+        };
+        let _ = writeln!(out, "        let f_{} = {};", member.ident, expr);
+
+        match &member.field.length_of {
+            Some(LengthOf::Field { field }) => lengths.push((field.as_str(), format!("f_{}", member.ident))),
+            Some(LengthOf::Following) => {
+                let _ = writeln!(
+                    out,
+                    "        let data = &data[..(*pos + f_{} as usize).min(data.len())];",
+                    member.ident
+                );
+            }
+            None => {}
+        }
+    }
+    let fields: Vec<String> = def.members.iter().map(|m| format!("{}: f_{}", m.ident, m.ident)).collect();
+    if def.members.iter().any(|m| matches!(m.field.length_of, Some(LengthOf::Following))) {
+        // The bounded `data` may have no readers left when the following fields are fixed
+        let _ = writeln!(out, "        let _ = data;");
+    }
+    let _ = writeln!(out, "        Some(Self {{ {} }})", fields.join(", "));
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}\n");
+}
+
+/// Expression turning `value` (a reference) into wire bytes
+fn rust_encode(shape: &Shape, value: &str, helpers: &mut Helpers) -> String {
+    // `&self.x` is used as-is in argument position and without the `&` elsewhere
+    let (place, value) = match value.strip_prefix('&') {
+        Some(place) => (place.to_string(), value),
+        None => (format!("*{}", value), value),
+    };
+    let recv = place.trim_start_matches('*');
+    match shape {
+        Shape::Uint { little: false, .. } => format!("{}.to_be_bytes().to_vec()", recv),
+        Shape::Uint { little: true, .. } => format!("{}.to_le_bytes().to_vec()", recv),
+        Shape::Bytes { len, pad, left } => {
+            let func = if *left {
+                helpers.pad_left = true;
+                "pad_left"
+            } else {
+                helpers.pad_right = true;
+                "pad_right"
+            };
+            format!("{}({}, {}, {:#04X})", func, value, len, pad)
+        }
+        Shape::Variable { .. } => format!("{}.to_vec()", recv),
+        Shape::Terminated { terminator, .. } => {
+            format!("[{}.as_slice(), &{:?}[..]].concat()", recv, terminator)
+        }
+        Shape::Prefixed => format!("[&[{r}.len() as u8][..], {r}.as_slice()].concat()", r = recv),
+        Shape::Varint(VarintFormat::Zigzag) => {
+            helpers.varint = true;
+            format!("write_varint((({p} << 1) ^ ({p} >> 63)) as u64)", p = place)
+        }
+        Shape::Varint(_) => {
+            helpers.varint = true;
+            format!("write_varint({})", place)
+        }
+        Shape::Struct(_) => format!("{}.to_bytes()", recv),
+        Shape::List { element, .. } => format!(
+            "{}.iter().flat_map(|v| {}).collect::<Vec<u8>>()",
+            recv,
+            rust_encode(element, "v", helpers)
+        ),
+        Shape::Tlv(spec) => {
+            helpers.tlv = true;
+            format!("tlv_to_bytes({}, {})", value, tlv_args(spec))
+        }
+    }
+}
+
+fn tlv_args(spec: &TlvSpec) -> String {
+    let header = if spec.length_includes_header { spec.tag_width + spec.length_width } else { 0 };
+    format!(
+        "{}, {}, {}, {}",
+        spec.tag_width,
+        spec.length_width,
+        spec.endianness == Endianness::Little,
+        header
+    )
+}
+
+/// Expression reading one value at `*pos`; `size` is the byte count of variable data
+fn rust_read(layout: &Layout, def: &StructDef, shape: &Shape, size: &str, helpers: &mut Helpers) -> String {
+    match shape {
+        Shape::Uint { len: 1, .. } => "take(data, pos, 1)?[0]".to_string(),
+        Shape::Uint { len, little } => format!(
+            "u{}::{}(take(data, pos, {})?.try_into().ok()?)",
+            len * 8,
+            if *little { "from_le_bytes" } else { "from_be_bytes" },
+            len
+        ),
+        Shape::Bytes { len, .. } => format!("take(data, pos, {})?.to_vec()", len),
+        Shape::Variable { .. } => format!("take(data, pos, {})?.to_vec()", size),
+        Shape::Terminated { terminator, unit } => {
+            helpers.terminated = true;
+            format!("take_terminated(data, pos, &{:?}, {})?", terminator, unit)
+        }
+        Shape::Prefixed => "{ let n = take(data, pos, 1)?[0] as usize; take(data, pos, n)?.to_vec() }".to_string(),
+        Shape::Varint(format) => {
+            helpers.varint = true;
+            let max = if *format == VarintFormat::MqttLength { 4 } else { 10 };
+            if *format == VarintFormat::Zigzag {
+                format!("{{ let n = read_varint(data, pos, {})?; ((n >> 1) as i64) ^ -((n & 1) as i64) }}", max)
+            } else {
+                format!("read_varint(data, pos, {})?", max)
+            }
+        }
+        Shape::Struct(index) => format!("{}::read(data, pos)?", layout.structs[*index].rust_name),
+        Shape::List { element, count } => {
+            let element_size = match element.as_ref() {
+                Shape::Variable { fallback } if *fallback > 0 => fallback.to_string(),
+                _ => "data.len() - *pos".to_string(),
+            };
+            let item = rust_read(layout, def, element, &element_size, helpers);
+            let count = match count {
+                Count::Fixed(n) => Some(n.to_string()),
+                Count::Field(field) => def
+                    .members
+                    .iter()
+                    .find(|m| m.field.name == *field && m.field.condition.is_none())
+                    .filter(|m| matches!(m.shape, Shape::Uint { .. } | Shape::Varint(_)))
+                    .map(|m| format!("f_{} as usize", m.ident)),
+                Count::UntilEnd => None,
+            };
+            match count {
+                Some(count) => format!(
+                    "{{ let mut items = Vec::new(); for _ in 0..{} {{ items.push({}); }} items }}",
+                    count, item
+                ),
+                None => format!(
+                    "{{ let mut items = Vec::new(); while *pos < data.len() {{ items.push({}); }} items }}",
+                    item
+                ),
+            }
+        }
+        Shape::Tlv(spec) => {
+            helpers.tlv = true;
+            format!("tlv_read(take(data, pos, {})?, {})?", size, tlv_args(spec))
+        }
+    }
+}
+
+/// Test on an earlier integer member, or None if the condition can't be checked in code
+fn rust_condition(def: &StructDef, condition: &FieldCondition) -> Option<String> {
+    let target = def
+        .members
+        .iter()
+        .find(|m| m.field.name == condition.field() && m.field.condition.is_none())
+        .filter(|m| matches!(m.shape, Shape::Uint { .. } | Shape::Varint(_)))?;
+    let value = format!("(f_{} as u64)", target.ident);
+    Some(match condition {
+        FieldCondition::Equals { value: expected, .. } => format!("{} == {:#X}", value, expected),
+        FieldCondition::BitmaskSet { mask, .. } => format!("{} & {:#X} == {:#X}", value, mask, mask),
+        FieldCondition::InSet { values, .. } => format!(
+            "[{}].contains(&{})",
+            values.iter().map(|v| format!("{:#X}", v)).collect::<Vec<_>>().join(", "),
+            value
+        ),
+    })
+}
+
+fn rust_helpers(out: &mut String, helpers: &Helpers) {
+    out.push_str(
+        "fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Option<&'a [u8]> {
+    let bytes = data.get(*pos..pos.checked_add(len)?)?;
+    *pos += len;
+    Some(bytes)
+}
+",
+    );
+    if helpers.pad_left {
+        out.push_str(
+            "
+fn pad_left(bytes: &[u8], len: usize, pad: u8) -> Vec<u8> {
+    let keep = &bytes[bytes.len().saturating_sub(len)..];
+    let mut out = vec![pad; len - keep.len()];
+    out.extend_from_slice(keep);
+    out
+}
+",
+        );
+    }
+    if helpers.pad_right {
+        out.push_str(
+            "
+fn pad_right(bytes: &[u8], len: usize, pad: u8) -> Vec<u8> {
+    let mut out = bytes.to_vec();
+    out.resize(len, pad);
+    out
+}
+",
+        );
+    }
+    if helpers.terminated {
+        out.push_str(
+            "
+fn take_terminated(data: &[u8], pos: &mut usize, terminator: &[u8], unit: usize) -> Option<Vec<u8>> {
+    let rest = data.get(*pos..)?;
+    let at = (0..rest.len()).step_by(unit).find(|i| rest[*i..].starts_with(terminator))?;
+    *pos += at + terminator.len();
+    Some(rest[..at].to_vec())
+}
+",
+        );
+    }
+    if helpers.varint {
+        out.push_str(
+            "
+fn write_varint(mut value: u64) -> Vec<u8> {
+    let mut out = Vec::new();
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return out;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize, max_bytes: usize) -> Option<u64> {
+    let mut value = 0u64;
+    for i in 0..max_bytes {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+",
+        );
+    }
+    if helpers.tlv {
+        out.push_str(
+            "
+fn uint_bytes(value: u64, width: usize, little: bool) -> Vec<u8> {
+    let mut bytes = value.to_be_bytes()[8 - width..].to_vec();
+    if little {
+        bytes.reverse();
+    }
+    bytes
+}
+
+fn uint_value(bytes: &[u8], little: bool) -> u64 {
+    let fold = |acc: u64, b: &u8| (acc << 8) | *b as u64;
+    if little {
+        bytes.iter().rev().fold(0, fold)
+    } else {
+        bytes.iter().fold(0, fold)
+    }
+}
+
+fn tlv_to_bytes(items: &[(u64, Vec<u8>)], tag_width: usize, length_width: usize, little: bool, header: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (tag, value) in items {
+        out.extend(uint_bytes(*tag, tag_width, little));
+        out.extend(uint_bytes((value.len() + header) as u64, length_width, little));
+        out.extend_from_slice(value);
+    }
+    out
+}
+
+fn tlv_read(data: &[u8], tag_width: usize, length_width: usize, little: bool, header: usize) -> Option<Vec<(u64, Vec<u8>)>> {
+    let mut pos = 0;
+    let mut items = Vec::new();
+    while pos < data.len() {
+        let tag = uint_value(take(data, &mut pos, tag_width)?, little);
+        let len = (uint_value(take(data, &mut pos, length_width)?, little) as usize).checked_sub(header)?;
+        items.push((tag, take(data, &mut pos, len)?.to_vec()));
+    }
+    Some(items)
+}
+",
+        );
+    }
+}
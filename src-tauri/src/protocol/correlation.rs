@@ -0,0 +1,54 @@
+//! Correlation keys: the raw bytes of the field that pairs a response with its request
+
+use super::parser::{decode_prefix, DecodedField};
+use super::{hex_string_to_bytes, ProtocolError, Result};
+use crate::models::ProtocolField;
+use crate::tcp::FrameMatcher;
+
+/// Bytes of the field called `name` in a message decoded with `fields`
+pub fn correlation_key(fields: &[ProtocolField], data: &[u8], name: &str) -> Result<Vec<u8>> {
+    let (decoded, _) = decode_prefix(fields, data)?;
+    key_of(&decoded, name)
+}
+
+/// First field called `name`, searched depth first through groups and arrays
+fn key_of(decoded: &[DecodedField], name: &str) -> Result<Vec<u8>> {
+    find(decoded, name)
+        .ok_or_else(|| ProtocolError::Lookup(format!("Message has no field '{}'", name)))
+        .and_then(|field| {
+            hex_string_to_bytes(&field.hex).map_err(|message| ProtocolError::InvalidValue {
+                field: name.to_string(),
+                message,
+            })
+        })
+}
+
+fn find<'a>(decoded: &'a [DecodedField], name: &str) -> Option<&'a DecodedField> {
+    decoded
+        .iter()
+        .find_map(|field| if field.name == name { Some(field) } else { find(&field.fields, name) })
+}
+
+/// Splits responses of a protocol into frames keyed by the response field of a correlation rule.
+/// The protocol must delimit its own messages with fixed sizes or auto-length fields.
+pub struct ResponseMatcher {
+    fields: Vec<ProtocolField>,
+    key_field: String,
+}
+
+impl ResponseMatcher {
+    /// `fields` must have their protocol references resolved
+    pub fn new(fields: Vec<ProtocolField>, key_field: String) -> Self {
+        Self { fields, key_field }
+    }
+}
+
+impl FrameMatcher for ResponseMatcher {
+    fn next_frame(&self, buffer: &[u8]) -> std::result::Result<Option<(usize, Vec<u8>)>, String> {
+        match decode_prefix(&self.fields, buffer) {
+            Ok((decoded, len)) => Ok(Some((len, key_of(&decoded, &self.key_field).map_err(|e| e.to_string())?))),
+            Err(ProtocolError::UnexpectedEnd(_)) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Field-by-field comparison of two protocol revisions
+//!
+//! Field ids change on every edit, so fields are matched by their path of names
+//! (`header.flags`, `records[].id`). Siblings sharing a name get a `#n` suffix.
+
+use crate::models::{ProtocolField, ProtocolFieldExport, ProtocolRevision};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// One property that differs; a missing side means the property is unset there
+#[derive(Debug, Clone, Serialize)]
+pub struct PropertyChange {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Changed properties of a modified field; `position` marks a reordered field
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<PropertyChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisionDiff {
+    pub from: u32,
+    pub to: u32,
+    /// Protocol name and description
+    pub properties: Vec<PropertyChange>,
+    pub fields: Vec<FieldChange>,
+}
+
+pub fn diff_revisions(from: &ProtocolRevision, to: &ProtocolRevision) -> RevisionDiff {
+    let before = Map::from_iter([
+        ("name".to_string(), Value::from(from.name.clone())),
+        ("description".to_string(), from.description.clone().map_or(Value::Null, Value::from)),
+    ]);
+    let after = Map::from_iter([
+        ("name".to_string(), Value::from(to.name.clone())),
+        ("description".to_string(), to.description.clone().map_or(Value::Null, Value::from)),
+    ]);
+
+    RevisionDiff {
+        from: from.revision,
+        to: to.revision,
+        properties: diff_properties(&before, &after),
+        fields: diff_fields(&from.fields, &to.fields),
+    }
+}
+
+/// Added, removed and modified fields, in the order they appear in `after` (removals last)
+pub fn diff_fields(before: &[ProtocolField], after: &[ProtocolField]) -> Vec<FieldChange> {
+    let mut old = Vec::new();
+    flatten(before, "", &mut old);
+    let mut new = Vec::new();
+    flatten(after, "", &mut new);
+
+    let old_index: HashMap<&str, &Flat> = old.iter().map(|f| (f.path.as_str(), f)).collect();
+    let new_index: HashMap<&str, &Flat> = new.iter().map(|f| (f.path.as_str(), f)).collect();
+    let old_positions = sibling_positions(&old, &new_index);
+    let new_positions = sibling_positions(&new, &old_index);
+
+    let mut changes = Vec::new();
+    for field in &new {
+        let Some(previous) = old_index.get(field.path.as_str()) else {
+            changes.push(FieldChange { path: field.path.clone(), kind: ChangeKind::Added, changes: Vec::new() });
+            continue;
+        };
+
+        let mut properties = diff_properties(&previous.properties, &field.properties);
+        let (from, to) = (old_positions[&field.path], new_positions[&field.path]);
+        if from != to {
+            properties.push(PropertyChange {
+                key: "position".to_string(),
+                before: Some(Value::from(from + 1)),
+                after: Some(Value::from(to + 1)),
+            });
+        }
+        if !properties.is_empty() {
+            changes.push(FieldChange { path: field.path.clone(), kind: ChangeKind::Modified, changes: properties });
+        }
+    }
+
+    changes.extend(
+        old.iter()
+            .filter(|f| !new_index.contains_key(f.path.as_str()))
+            .map(|f| FieldChange { path: f.path.clone(), kind: ChangeKind::Removed, changes: Vec::new() }),
+    );
+    changes
+}
+
+/// A field with its own properties, children excluded
+struct Flat {
+    path: String,
+    parent: String,
+    properties: Map<String, Value>,
+}
+
+fn flatten(fields: &[ProtocolField], parent: &str, out: &mut Vec<Flat>) {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for field in fields {
+        let count = seen.entry(field.name.as_str()).or_default();
+        *count += 1;
+        let name = if *count > 1 { format!("{}#{}", field.name, count) } else { field.name.clone() };
+        let path = if parent.is_empty() { name } else { format!("{}.{}", parent, name) };
+
+        out.push(Flat { path: path.clone(), parent: parent.to_string(), properties: field_properties(field) });
+
+        flatten(&field.fields, &path, out);
+        if let Some(element) = &field.element {
+            let element_path = format!("{}[]", path);
+            out.push(Flat { path: element_path.clone(), parent: path, properties: field_properties(element) });
+            flatten(&element.fields, &element_path, out);
+        }
+    }
+}
+
+/// Serialized form of a field (as in exported files) minus ids and children
+fn field_properties(field: &ProtocolField) -> Map<String, Value> {
+    let mut shallow = field.clone();
+    shallow.fields = Vec::new();
+    shallow.element = None;
+    match serde_json::to_value(ProtocolFieldExport::from(shallow)) {
+        Ok(Value::Object(mut map)) => {
+            for key in ["id", "name", "enabled"] {
+                map.remove(key);
+            }
+            map
+        }
+        _ => Map::new(),
+    }
+}
+
+/// Index of each field among the siblings that exist on both sides, so an insertion
+/// doesn't make every later field look moved
+fn sibling_positions(fields: &[Flat], other: &HashMap<&str, &Flat>) -> HashMap<String, usize> {
+    let mut next: HashMap<&str, usize> = HashMap::new();
+    fields
+        .iter()
+        .filter(|f| other.contains_key(f.path.as_str()))
+        .map(|f| {
+            let index = next.entry(f.parent.as_str()).or_default();
+            *index += 1;
+            (f.path.clone(), *index - 1)
+        })
+        .collect()
+}
+
+fn diff_properties(before: &Map<String, Value>, after: &Map<String, Value>) -> Vec<PropertyChange> {
+    let mut keys: Vec<&String> = before.keys().chain(after.keys().filter(|k| !before.contains_key(*k))).collect();
+    keys.sort();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let old = before.get(key).filter(|v| !v.is_null());
+            let new = after.get(key).filter(|v| !v.is_null());
+            (old != new).then(|| PropertyChange { key: key.clone(), before: old.cloned(), after: new.cloned() })
+        })
+        .collect()
+}
@@ -0,0 +1,489 @@
+use super::codegen::{snake_ident, words, Count, Layout, Shape, StructDef};
+use crate::models::{
+    Endianness, FieldCondition, LengthOf, Protocol, ProtocolField, TextEncoding, TlvSpec, VarintFormat,
+};
+use std::fmt::Write;
+
+/// Port table a dissector registers on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+impl Transport {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "tcp" => Some(Transport::Tcp),
+            "udp" => Some(Transport::Udp),
+            _ => None,
+        }
+    }
+
+    fn port_table(&self) -> &'static str {
+        match self {
+            Transport::Tcp => "tcp.port",
+            Transport::Udp => "udp.port",
+        }
+    }
+}
+
+/// Lua string literal; bytes outside printable ASCII are written as escapes
+fn lua_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for b in s.bytes() {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7E => out.push(b as char),
+            _ => {
+                let _ = write!(out, "\\{:03}", b);
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn lua_opt_str(s: Option<&str>) -> String {
+    s.filter(|s| !s.is_empty()).map(lua_str).unwrap_or_else(|| "nil".to_string())
+}
+
+fn encoding(encoding: TextEncoding) -> &'static str {
+    match encoding {
+        TextEncoding::Ascii => "ENC_ASCII",
+        TextEncoding::Utf8 => "ENC_UTF_8",
+        TextEncoding::Utf16Le => "ENC_UTF_16 + ENC_LITTLE_ENDIAN",
+        TextEncoding::Utf16Be => "ENC_UTF_16 + ENC_BIG_ENDIAN",
+        TextEncoding::Gbk => "ENC_GB18030",
+        TextEncoding::Latin1 => "ENC_ISO_8859_1",
+    }
+}
+
+/// `:uint()` style reader for an unsigned integer of `width` bytes
+fn uint_reader(width: usize, little: bool) -> &'static str {
+    match (width <= 4, little) {
+        (true, false) => ":uint()",
+        (true, true) => ":le_uint()",
+        (false, false) => ":uint64():tonumber()",
+        (false, true) => ":le_uint64():tonumber()",
+    }
+}
+
+fn uint_field_type(width: usize) -> &'static str {
+    match width {
+        1 => "uint8",
+        2 => "uint16",
+        3 => "uint24",
+        4 => "uint32",
+        _ => "uint64",
+    }
+}
+
+struct Dissector<'a> {
+    layout: &'a Layout<'a>,
+    /// Lua name of the Proto object
+    proto: String,
+    /// `ProtoField` declarations
+    fields: String,
+    /// Value-name tables for TLV tags
+    tables: String,
+    /// Struct whose dissect function is being emitted
+    current: usize,
+}
+
+impl Dissector<'_> {
+    fn declare(&mut self, key: &str, constructor: String) {
+        let _ = writeln!(self.fields, "f[{}] = {}", lua_str(key), constructor);
+    }
+
+    /// Declare the field(s) a shape needs under `key` and return the code dissecting one value
+    fn emit(&mut self, shape: &Shape, field: &ProtocolField, ident: &str, key: &str, size: &str, indent: &str) -> String {
+        let name = lua_str(&field.name);
+        let abbr = lua_str(key);
+        let desc = lua_opt_str(field.description.as_deref());
+        let mut code = String::new();
+
+        match shape {
+            Shape::Uint { len: width, little } => {
+                let base = if field.value_format.as_deref() == Some("hex") { "base.HEX" } else { "base.DEC" };
+                self.declare(
+                    key,
+                    format!("ProtoField.{}({}, {}, {}, nil, nil, {})", uint_field_type(*width), abbr, name, base, desc),
+                );
+                let add = if *little { "add_le" } else { "add" };
+                let _ = writeln!(code, "{}tree:{}(f[{}], buffer(offset, {}))", indent, add, abbr, width);
+                let _ = writeln!(
+                    code,
+                    "{}v[{}] = buffer(offset, {}){}",
+                    indent,
+                    lua_str(ident),
+                    width,
+                    uint_reader(*width, *little)
+                );
+                let _ = writeln!(code, "{}offset = offset + {}", indent, width);
+            }
+            Shape::Bytes { len, .. } => {
+                self.add_bytes(&mut code, field, key, &len.to_string(), indent);
+            }
+            Shape::Variable { .. } => {
+                let _ = writeln!(code, "{}local size = {}", indent, size);
+                self.add_bytes(&mut code, field, key, "size", indent);
+            }
+            Shape::Terminated { terminator, unit } => {
+                let bytes: Vec<String> = terminator.iter().map(|b| b.to_string()).collect();
+                let _ = writeln!(
+                    code,
+                    "{}local size = find_terminator(buffer, offset, limit, {{ {} }}, {})",
+                    indent,
+                    bytes.join(", "),
+                    unit
+                );
+                self.add_bytes(&mut code, field, key, "size", indent);
+            }
+            Shape::Prefixed => {
+                let _ = writeln!(code, "{}local size = buffer(offset, 1):uint()", indent);
+                let _ = writeln!(code, "{}offset = offset + 1", indent);
+                self.add_bytes(&mut code, field, key, "size", indent);
+            }
+            Shape::Varint(format) => {
+                let (kind, wrap) = if *format == VarintFormat::Zigzag { ("int64", "Int64") } else { ("uint64", "UInt64") };
+                self.declare(key, format!("ProtoField.{}({}, {}, base.DEC, nil, nil, {})", kind, abbr, name, desc));
+                let max = if *format == VarintFormat::MqttLength { 4 } else { 10 };
+                let _ = writeln!(code, "{}local value, size = read_varint(buffer, offset, limit, {})", indent, max);
+                if *format == VarintFormat::Zigzag {
+                    let _ = writeln!(
+                        code,
+                        "{}if value % 2 == 0 then value = math.floor(value / 2) else value = -math.floor((value + 1) / 2) end",
+                        indent
+                    );
+                }
+                let _ = writeln!(
+                    code,
+                    "{}if size > 0 then tree:add(f[{}], buffer(offset, size), {}.new(value)) end",
+                    indent, abbr, wrap
+                );
+                let _ = writeln!(code, "{}v[{}] = value", indent, lua_str(ident));
+                let _ = writeln!(code, "{}offset = offset + size", indent);
+            }
+            Shape::Struct(index) => {
+                self.declare(key, format!("ProtoField.none({}, {}, {})", abbr, name, desc));
+                let _ = writeln!(
+                    code,
+                    "{}offset = dissect_{}(buffer, offset, tree:add(f[{}], buffer(offset, 0)), limit)",
+                    indent,
+                    self.layout.structs[*index].path.join("_"),
+                    abbr
+                );
+            }
+            Shape::List { element, count } => {
+                self.declare(key, format!("ProtoField.none({}, {}, {})", abbr, name, desc));
+                let element_field = field.element.as_deref().or(field.fields.first()).unwrap_or(field);
+                let element_key = format!("{}.{}", key, snake_ident(&element_field.name));
+                let element_size = match element.as_ref() {
+                    Shape::Variable { fallback } if *fallback > 0 => fallback.to_string(),
+                    _ => "limit - offset".to_string(),
+                };
+                let inner = format!("{}        ", indent);
+                let body = self.emit(element, element_field, ident, &element_key, &element_size, &inner);
+                let header = match count {
+                    Count::Fixed(n) => format!("for _ = 1, {} do", n),
+                    Count::Field(target) => match self.numeric_sibling(target) {
+                        Some(ident) => format!("for _ = 1, (v[{}] or 0) do", lua_str(&ident)),
+                        None => "while offset < limit do".to_string(),
+                    },
+                    Count::UntilEnd => "while offset < limit do".to_string(),
+                };
+                let _ = writeln!(code, "{}do", indent);
+                let _ = writeln!(code, "{}    local start = offset", indent);
+                let _ = writeln!(code, "{}    local list = tree:add(f[{}], buffer(offset, 0))", indent, abbr);
+                let _ = writeln!(code, "{}    local tree = list", indent);
+                let _ = writeln!(code, "{}    {}", indent, header);
+                code.push_str(&body);
+                let _ = writeln!(code, "{}    end", indent);
+                let _ = writeln!(code, "{}    list:set_len(offset - start)", indent);
+                let _ = writeln!(code, "{}end", indent);
+            }
+            Shape::Tlv(spec) => {
+                self.emit_tlv(&mut code, field, key, spec, size, indent);
+            }
+        }
+        code
+    }
+
+    /// Raw bytes, or a string in the field's encoding for text fields
+    fn add_bytes(&mut self, code: &mut String, field: &ProtocolField, key: &str, size: &str, indent: &str) {
+        let abbr = lua_str(key);
+        let name = lua_str(&field.name);
+        let desc = lua_opt_str(field.description.as_deref());
+        let range = format!("buffer(offset, {})", size);
+        if field.is_text() {
+            let spec = field.text.clone().unwrap_or_default();
+            self.declare(key, format!("ProtoField.string({}, {}, nil, {})", abbr, name, desc));
+            let _ = writeln!(
+                code,
+                "{}if {} > 0 then tree:add_packet_field(f[{}], {}, {}) end",
+                indent,
+                size,
+                abbr,
+                range,
+                encoding(spec.encoding)
+            );
+        } else {
+            self.declare(key, format!("ProtoField.bytes({}, {}, nil, {})", abbr, name, desc));
+            let _ = writeln!(code, "{}if {} > 0 then tree:add(f[{}], {}) end", indent, size, abbr, range);
+        }
+        let _ = writeln!(code, "{}offset = offset + {}", indent, size);
+    }
+
+    fn emit_tlv(&mut self, code: &mut String, field: &ProtocolField, key: &str, spec: &TlvSpec, size: &str, indent: &str) {
+        let abbr = lua_str(key);
+        let little = spec.endianness == Endianness::Little;
+        let (tw, lw) = (spec.tag_width as usize, spec.length_width as usize);
+        let header = if spec.length_includes_header { tw + lw } else { 0 };
+        let add = if little { "add_le" } else { "add" };
+
+        // Tag names double as the value string of the tag field when it fits in 32 bits
+        let table = format!("tags_{}", key.replace('.', "_"));
+        let _ = write!(self.tables, "local {} = {{", table);
+        for (i, tag) in spec.tags.iter().enumerate() {
+            let _ = write!(self.tables, "{}[{}] = {}", if i == 0 { " " } else { ", " }, tag.tag, lua_str(&tag.name));
+        }
+        let _ = writeln!(self.tables, "{}}}", if spec.tags.is_empty() { "" } else { " " });
+        let names = if tw <= 4 { table.clone() } else { "nil".to_string() };
+
+        self.declare(
+            key,
+            format!("ProtoField.none({}, {}, {})", abbr, lua_str(&field.name), lua_opt_str(field.description.as_deref())),
+        );
+        self.declare(&format!("{}.item", key), format!("ProtoField.none({}, \"Item\")", lua_str(&format!("{}.item", key))));
+        self.declare(
+            &format!("{}.tag", key),
+            format!(
+                "ProtoField.{}({}, \"Tag\", base.HEX, {})",
+                uint_field_type(tw),
+                lua_str(&format!("{}.tag", key)),
+                names
+            ),
+        );
+        self.declare(
+            &format!("{}.length", key),
+            format!("ProtoField.{}({}, \"Length\", base.DEC)", uint_field_type(lw), lua_str(&format!("{}.length", key))),
+        );
+        self.declare(&format!("{}.value", key), format!("ProtoField.bytes({}, \"Value\")", lua_str(&format!("{}.value", key))));
+
+        let lines = [
+            "do".to_string(),
+            format!("    local size = {}", size),
+            format!("    local items = tree:add(f[{}], buffer(offset, size))", abbr),
+            "    local stop = offset + size".to_string(),
+            "    while offset < stop do".to_string(),
+            format!("        local tag = buffer(offset, {}){}", tw, uint_reader(tw, little)),
+            format!(
+                "        local len = buffer(offset + {}, {}){} - {}",
+                tw,
+                lw,
+                uint_reader(lw, little),
+                header
+            ),
+            format!(
+                "        local item = items:add(f[{}], buffer(offset, {} + len))",
+                lua_str(&format!("{}.item", key)),
+                tw + lw
+            ),
+            format!("        item:{}(f[{}], buffer(offset, {}))", add, lua_str(&format!("{}.tag", key)), tw),
+            format!(
+                "        item:{}(f[{}], buffer(offset + {}, {}))",
+                add,
+                lua_str(&format!("{}.length", key)),
+                tw,
+                lw
+            ),
+            format!(
+                "        if len > 0 then item:add(f[{}], buffer(offset + {}, len)) end",
+                lua_str(&format!("{}.value", key)),
+                tw + lw
+            ),
+            format!(
+                "        item:append_text(\": \" .. ({}[tag] or string.format(\"0x%X\", tag)))",
+                table
+            ),
+            format!("        offset = offset + {} + len", tw + lw),
+            "    end".to_string(),
+            "end".to_string(),
+        ];
+        for line in lines {
+            let _ = writeln!(code, "{}{}", indent, line);
+        }
+    }
+
+    /// Identifier of an unconditional integer member of the current struct
+    fn numeric_sibling(&self, target: &str) -> Option<String> {
+        self.layout.structs[self.current]
+            .members
+            .iter()
+            .find(|m| m.field.name == target && m.field.condition.is_none())
+            .filter(|m| matches!(m.shape, Shape::Uint { .. } | Shape::Varint(_)))
+            .map(|m| m.ident.clone())
+    }
+
+    fn condition(&self, condition: &FieldCondition) -> Option<String> {
+        let value = format!("v[{}]", lua_str(&self.numeric_sibling(condition.field())?));
+        Some(match condition {
+            FieldCondition::Equals { value: expected, .. } => format!("{} == {}", value, expected),
+            FieldCondition::BitmaskSet { mask, .. } => format!("bit.band({}, {}) == {}", value, mask, mask),
+            FieldCondition::InSet { values, .. } => {
+                let tests: Vec<String> = values.iter().map(|v| format!("{} == {}", value, v)).collect();
+                if tests.is_empty() { "false".to_string() } else { tests.join(" or ") }
+            }
+        })
+    }
+
+    fn emit_struct(&mut self, index: usize) -> String {
+        self.current = index;
+        let layout = self.layout;
+        let def: &StructDef = &layout.structs[index];
+        let prefix = def.path.join(".");
+        let mut code = String::new();
+        let _ = writeln!(code, "local function dissect_{}(buffer, offset, tree, limit)", def.path.join("_"));
+        let _ = writeln!(code, "    local start = offset");
+        let _ = writeln!(code, "    local v = {{}}");
+
+        let mut lengths: Vec<(&str, String)> = Vec::new();
+        for (i, member) in def.members.iter().enumerate() {
+            let is_last = i + 1 == def.members.len();
+            let size = lengths
+                .iter()
+                .find(|(target, _)| *target == member.field.name)
+                .map(|(_, ident)| format!("(v[{}] or 0)", lua_str(ident)))
+                .unwrap_or_else(|| match &member.shape {
+                    Shape::Variable { fallback } if !is_last && *fallback > 0 => fallback.to_string(),
+                    Shape::Tlv(_) if !is_last && member.field.length.unwrap_or(0) > 0 => {
+                        member.field.length.unwrap_or(0).to_string()
+                    }
+                    _ => "limit - offset".to_string(),
+                });
+
+            let key = format!("{}.{}", prefix, member.ident);
+            let _ = writeln!(code, "    -- {}", member.field.name.replace('\n', " "));
+            match member.field.condition.as_ref().and_then(|c| self.condition(c)) {
+                Some(test) => {
+                    let body = self.emit(&member.shape, member.field, &member.ident, &key, &size, "        ");
+                    let _ = writeln!(code, "    if {} then", test);
+                    code.push_str(&body);
+                    let _ = writeln!(code, "    end");
+                }
+                None => {
+                    let body = self.emit(&member.shape, member.field, &member.ident, &key, &size, "    ");
+                    code.push_str(&body);
+                }
+            }
+
+            match &member.field.length_of {
+                Some(LengthOf::Field { field }) => lengths.push((field.as_str(), member.ident.clone())),
+                Some(LengthOf::Following) => {
+                    let _ = writeln!(
+                        code,
+                        "    limit = math.min(limit, offset + (v[{}] or 0))",
+                        lua_str(&member.ident)
+                    );
+                }
+                None => {}
+            }
+        }
+
+        let _ = writeln!(code, "    tree:set_len(offset - start)");
+        let _ = writeln!(code, "    return offset");
+        let _ = writeln!(code, "end\n");
+        code
+    }
+}
+
+/// Wireshark Lua dissector for a protocol, registered on a TCP or UDP port
+pub fn lua_dissector(protocol: &Protocol, transport: Transport, port: u16) -> String {
+    let layout = Layout::new(protocol);
+    let abbr = words(&protocol.name).join("_");
+    let mut dissector = Dissector {
+        layout: &layout,
+        proto: format!("proto_{}", abbr),
+        fields: String::new(),
+        tables: String::new(),
+        current: 0,
+    };
+
+    let functions: Vec<String> = (0..layout.structs.len()).map(|i| dissector.emit_struct(i)).collect();
+    let top = layout.structs.len() - 1;
+    let proto = dissector.proto.clone();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "-- Generated by PacketForge from protocol {}.", lua_str(&protocol.name));
+    let _ = writeln!(
+        out,
+        "-- Copy into Wireshark's personal plugins folder (Help > About > Folders) and reload Lua plugins.\n"
+    );
+    let _ = writeln!(
+        out,
+        "local {} = Proto({}, {})\n",
+        proto,
+        lua_str(&abbr),
+        lua_str(&protocol.name)
+    );
+    let _ = writeln!(out, "local f = {{}}");
+    out.push_str(&dissector.fields);
+    let _ = writeln!(out, "{}.fields = f\n", proto);
+    if !dissector.tables.is_empty() {
+        out.push_str(&dissector.tables);
+        out.push('\n');
+    }
+
+    out.push_str(
+        "-- Returns the value and size of a base-128 varint, least significant group first
+local function read_varint(buffer, offset, limit, max_bytes)
+    local value, scale, size = 0, 1, 0
+    while size < max_bytes and offset + size < limit do
+        local byte = buffer(offset + size, 1):uint()
+        value = value + (byte % 128) * scale
+        scale = scale * 128
+        size = size + 1
+        if byte < 128 then break end
+    end
+    return value, size
+end
+
+-- Returns the size of text up to and including its terminator
+local function find_terminator(buffer, offset, limit, terminator, unit)
+    local pos = offset
+    while pos + #terminator <= limit do
+        local found = true
+        for i, byte in ipairs(terminator) do
+            if buffer(pos + i - 1, 1):uint() ~= byte then
+                found = false
+                break
+            end
+        end
+        if found then return pos - offset + #terminator end
+        pos = pos + unit
+    end
+    return limit - offset
+end
+
+",
+    );
+
+    for function in &functions {
+        out.push_str(function);
+    }
+
+    let _ = writeln!(out, "function {}.dissector(buffer, pinfo, tree)", proto);
+    let _ = writeln!(out, "    if buffer:len() == 0 then return end");
+    let _ = writeln!(out, "    pinfo.cols.protocol = {}", lua_str(&protocol.name));
+    let _ = writeln!(
+        out,
+        "    return dissect_{}(buffer, 0, tree:add({}, buffer()), buffer:len())",
+        layout.structs[top].path.join("_"),
+        proto
+    );
+    let _ = writeln!(out, "end\n");
+    let _ = writeln!(out, "DissectorTable.get({}):add({}, {})", lua_str(transport.port_table()), port, proto);
+    out
+}
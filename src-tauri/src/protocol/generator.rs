@@ -0,0 +1,130 @@
+//! Values of generator fields (counters, Unix time, random data), produced on every send
+
+use super::encoder::fixed_length;
+use super::{ProtocolError, Result};
+use crate::models::{FieldGenerator, FieldKind, ProtocolField, TimeUnit};
+use chrono::Utc;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Next counter value per (connection, counter key): the field id, prefixed for element
+/// templates with the array they belong to
+#[derive(Default)]
+pub struct GeneratorState(Mutex<HashMap<(String, String), u64>>);
+
+impl GeneratorState {
+    /// Start the counters of a connection over
+    pub fn reset(&self, connection_id: &str) {
+        self.0.lock().unwrap().retain(|(connection, _), _| connection != connection_id);
+    }
+}
+
+/// Fill in the value of every generator field in the tree.
+/// A `preview` shows the next counter values without moving the counters on.
+pub fn apply(fields: &mut [ProtocolField], state: &GeneratorState, connection_id: &str, preview: bool) -> Result<()> {
+    apply_scoped(fields, state, connection_id, preview, "")
+}
+
+fn apply_scoped(
+    fields: &mut [ProtocolField],
+    state: &GeneratorState,
+    connection_id: &str,
+    preview: bool,
+    scope: &str,
+) -> Result<()> {
+    for field in fields {
+        let key = format!("{}{}", scope, field.id);
+        if let Some(generator) = field.generator.clone() {
+            generate(field, &generator, state, connection_id, &key, preview)?;
+        }
+        apply_scoped(&mut field.fields, state, connection_id, preview, scope)?;
+        // An element template counts apart from elements copied from it with the same ids
+        if let Some(element) = field.element.as_deref_mut() {
+            let scope = format!("{}/element/", key);
+            apply_scoped(std::slice::from_mut(element), state, connection_id, preview, &scope)?;
+        }
+    }
+    Ok(())
+}
+
+fn generate(
+    field: &mut ProtocolField,
+    generator: &FieldGenerator,
+    state: &GeneratorState,
+    connection_id: &str,
+    key: &str,
+    preview: bool,
+) -> Result<()> {
+    let invalid = |message: &str| ProtocolError::InvalidValue { field: field.name.clone(), message: message.to_string() };
+    let max = max_value(field);
+    // Checked first so a failing send doesn't use up a counter value
+    if !matches!(generator, FieldGenerator::RandomBytes) && field.is_variable && !field.is_text() {
+        return Err(invalid("numeric generators need a fixed-size or text field"));
+    }
+
+    let number = match *generator {
+        FieldGenerator::Counter { start, step, wrap } => {
+            let limit = wrap.unwrap_or(max).min(max);
+            if start > limit {
+                return Err(invalid("counter start is past its wrap value"));
+            }
+            let mut counters = state.0.lock().unwrap();
+            let key = (connection_id.to_string(), key.to_string());
+            let current = counters.get(&key).copied().unwrap_or(start);
+            if !preview {
+                let next = current.checked_add(step).filter(|n| *n <= limit).unwrap_or(start);
+                counters.insert(key, next);
+            }
+            current
+        }
+        FieldGenerator::UnixTime { unit } => {
+            let now = match unit {
+                TimeUnit::Seconds => Utc::now().timestamp(),
+                TimeUnit::Milliseconds => Utc::now().timestamp_millis(),
+            };
+            // Narrow fields keep the low bytes
+            now as u64 & max
+        }
+        FieldGenerator::RandomRange { min, max: upper } => {
+            if min > upper {
+                return Err(invalid("random range minimum is above its maximum"));
+            }
+            if upper > max {
+                return Err(invalid("random range maximum doesn't fit the field"));
+            }
+            rand::thread_rng().gen_range(min..=upper)
+        }
+        FieldGenerator::RandomBytes => {
+            if field.is_text() {
+                return Err(invalid("random bytes need a hex field"));
+            }
+            let mut bytes = vec![0u8; field.length.unwrap_or(0).max(0) as usize];
+            rand::thread_rng().fill(bytes.as_mut_slice());
+            field.value = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+            if field.is_variable {
+                field.value_type = "hex".to_string();
+            } else {
+                field.value_format = Some("hex".to_string());
+            }
+            return Ok(());
+        }
+    };
+
+    field.value = number.to_string();
+    // Text fields get the digits; numbers are written through the decimal format
+    if !field.is_text() {
+        field.value_format = Some("dec".to_string());
+    }
+    Ok(())
+}
+
+/// Largest value the field can hold, at the width the encoder writes it
+fn max_value(field: &ProtocolField) -> u64 {
+    let bytes = fixed_length(field) as u32;
+    if field.kind == FieldKind::Varint || field.is_text() || bytes >= 8 {
+        u64::MAX
+    } else {
+        (1u64 << (bytes * 8)) - 1
+    }
+}
@@ -0,0 +1,470 @@
+use super::hex::bytes_to_plain_hex;
+use super::MAX_DEPTH;
+use crate::models::{
+    Endianness, FieldCondition, FieldKind, LengthOf, ProtocolField, ProtocolImport, RepeatRule, Terminator,
+    TextEncoding, TextSpec,
+};
+use serde_yaml::{Mapping, Value};
+use uuid::Uuid;
+
+/// Attribute keys the importer understands; anything else is reported
+const KNOWN_KEYS: &[&str] = &[
+    "id", "doc", "doc-ref", "type", "size", "size-eos", "contents", "enum", "repeat", "repeat-expr", "if",
+    "encoding", "terminator", "include", "consume",
+];
+
+struct Importer<'a> {
+    /// `types` and `enums` of the enclosing specs, innermost last
+    types: Vec<&'a Mapping>,
+    enums: Vec<&'a Mapping>,
+    encoding: Option<String>,
+    warnings: Vec<String>,
+}
+
+fn get<'a>(map: &'a Mapping, key: &str) -> Option<&'a Value> {
+    map.get(Value::String(key.to_string()))
+}
+
+fn get_str<'a>(map: &'a Mapping, key: &str) -> Option<&'a str> {
+    get(map, key).and_then(Value::as_str)
+}
+
+fn get_map<'a>(map: &'a Mapping, key: &str) -> Option<&'a Mapping> {
+    get(map, key).and_then(Value::as_mapping)
+}
+
+/// Numbers may be written in decimal, hex (0x..) or binary (0b..)
+fn as_int(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => {
+            let s = s.trim().replace('_', "");
+            if let Some(hex) = s.strip_prefix("0x") {
+                u64::from_str_radix(hex, 16).ok()
+            } else if let Some(bin) = s.strip_prefix("0b") {
+                u64::from_str_radix(bin, 2).ok()
+            } else {
+                s.parse().ok()
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && s.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+fn endianness_of(meta: Option<&Mapping>) -> Option<Endianness> {
+    match meta.and_then(|m| get_str(m, "endian")) {
+        Some("le") => Some(Endianness::Little),
+        Some("be") => Some(Endianness::Big),
+        _ => None,
+    }
+}
+
+fn text_encoding(name: &str) -> Option<TextEncoding> {
+    match name.to_ascii_uppercase().replace('_', "-").as_str() {
+        "UTF-8" | "UTF8" => Some(TextEncoding::Utf8),
+        "ASCII" | "US-ASCII" => Some(TextEncoding::Ascii),
+        "UTF-16LE" => Some(TextEncoding::Utf16Le),
+        "UTF-16BE" => Some(TextEncoding::Utf16Be),
+        "GBK" | "GB2312" | "GB18030" | "CP936" => Some(TextEncoding::Gbk),
+        "ISO-8859-1" | "LATIN1" | "LATIN-1" => Some(TextEncoding::Latin1),
+        _ => None,
+    }
+}
+
+fn new_field(name: &str) -> ProtocolField {
+    ProtocolField {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        ..Default::default()
+    }
+}
+
+impl<'a> Importer<'a> {
+    fn warn(&mut self, path: &str, message: impl std::fmt::Display) {
+        self.warnings.push(format!("{}: {}", path, message));
+    }
+
+    /// Fields for a `seq`, with sizes taken from sibling fields turned into auto lengths
+    fn seq(&mut self, path: &str, spec: &'a Mapping, endian: Option<Endianness>, depth: usize) -> Vec<ProtocolField> {
+        let endian = endianness_of(get_map(spec, "meta")).or(endian);
+        for key in ["instances", "params"] {
+            if get(spec, key).is_some() {
+                self.warn(path, format!("`{}` are not supported and were ignored", key));
+            }
+        }
+
+        let (types, enums) = (self.types.len(), self.enums.len());
+        self.types.extend(get_map(spec, "types"));
+        self.enums.extend(get_map(spec, "enums"));
+        let fields = self.seq_fields(path, spec, endian, depth);
+        self.types.truncate(types);
+        self.enums.truncate(enums);
+        fields
+    }
+
+    fn seq_fields(&mut self, path: &str, spec: &'a Mapping, endian: Option<Endianness>, depth: usize) -> Vec<ProtocolField> {
+        let Some(seq) = get(spec, "seq").and_then(Value::as_sequence) else {
+            return Vec::new();
+        };
+
+        let mut fields: Vec<ProtocolField> = Vec::new();
+        let mut sizes: Vec<(String, String)> = Vec::new();
+        for (i, attr) in seq.iter().enumerate() {
+            let Some(attr) = attr.as_mapping() else {
+                self.warn(path, format!("seq entry {} is not a mapping", i));
+                continue;
+            };
+            let id = get_str(attr, "id").map(str::to_string).unwrap_or_else(|| format!("field_{}", i));
+            let attr_path = format!("{}.{}", path, id);
+            for key in attr.keys().filter_map(Value::as_str) {
+                if !KNOWN_KEYS.contains(&key) {
+                    self.warn(&attr_path, format!("`{}` is not supported and was ignored", key));
+                }
+            }
+
+            let Some(mut field) = self.attribute(&attr_path, &id, attr, endian, depth, &mut sizes) else {
+                continue;
+            };
+            field.description = get_str(attr, "doc").map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+            self.apply_enum(&attr_path, attr, &mut field);
+            self.apply_condition(&attr_path, attr, &fields, &mut field);
+            let field = self.apply_repeat(&attr_path, attr, &fields, field);
+            fields.push(field);
+        }
+
+        for (target, source) in sizes {
+            match fields.iter_mut().find(|f| f.name == source && f.kind == FieldKind::Scalar && !f.is_variable) {
+                Some(length) => length.length_of = Some(LengthOf::Field { field: target }),
+                None => self.warn(
+                    &format!("{}.{}", path, target),
+                    format!("size `{}` is not an earlier integer field; the length must be set by hand", source),
+                ),
+            }
+        }
+        fields
+    }
+
+    fn attribute(
+        &mut self,
+        path: &str,
+        id: &str,
+        attr: &'a Mapping,
+        endian: Option<Endianness>,
+        depth: usize,
+        sizes: &mut Vec<(String, String)>,
+    ) -> Option<ProtocolField> {
+        let mut field = new_field(id);
+
+        if let Some(contents) = get(attr, "contents") {
+            let bytes = match contents {
+                Value::String(s) => Some(s.as_bytes().to_vec()),
+                Value::Sequence(items) => items
+                    .iter()
+                    .map(|item| match item {
+                        Value::String(s) => Some(s.as_bytes().to_vec()),
+                        other => as_int(other).and_then(|b| u8::try_from(b).ok()).map(|b| vec![b]),
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .map(|parts| parts.concat()),
+                _ => None,
+            };
+            let Some(bytes) = bytes.filter(|b| !b.is_empty()) else {
+                self.warn(path, "`contents` could not be read and the field was skipped");
+                return None;
+            };
+            field.length = Some(bytes.len() as i32);
+            field.value_format = Some("hex".to_string());
+            field.value = bytes_to_plain_hex(&bytes);
+            return Some(field);
+        }
+
+        let ty = get(attr, "type");
+        if let Some(ty) = ty.filter(|t| !t.is_string()) {
+            if ty.get("switch-on").is_some() {
+                self.warn(path, "`switch-on` types are not supported; imported as raw bytes");
+            } else {
+                self.warn(path, "unrecognised type; imported as raw bytes");
+            }
+            self.size(path, id, attr, &mut field, sizes);
+            return Some(field);
+        }
+
+        match ty.and_then(Value::as_str) {
+            None => self.size(path, id, attr, &mut field, sizes),
+            Some(ty @ ("str" | "strz")) => {
+                let encoding_name = get_str(attr, "encoding").map(str::to_string).or_else(|| self.encoding.clone());
+                let encoding = match encoding_name.as_deref() {
+                    Some(name) => text_encoding(name).unwrap_or_else(|| {
+                        self.warn(path, format!("encoding `{}` is not supported; using UTF-8", name));
+                        TextEncoding::Utf8
+                    }),
+                    None => TextEncoding::Utf8,
+                };
+                let terminator = match (ty, get(attr, "terminator").and_then(as_int)) {
+                    ("strz", _) | (_, Some(0)) => Terminator::Nul,
+                    (_, Some(other)) => {
+                        self.warn(path, format!("terminator {:#04X} is not supported; the text is unterminated", other));
+                        Terminator::None
+                    }
+                    _ => Terminator::None,
+                };
+                field.text = Some(TextSpec { encoding, terminator, ..Default::default() });
+                if terminator == Terminator::None {
+                    self.size(path, id, attr, &mut field, sizes);
+                } else {
+                    field.is_variable = true;
+                }
+                if field.is_variable {
+                    field.value_type = "text".to_string();
+                } else {
+                    field.value_format = Some("text".to_string());
+                }
+            }
+            Some(ty) => {
+                if let Some(number) = self.number(path, ty, endian, &mut field) {
+                    return number.then_some(field);
+                }
+                let Some(user_type) = lookup(&self.types, ty) else {
+                    self.warn(path, format!("type `{}` is not defined in this file; imported as raw bytes", ty));
+                    self.size(path, id, attr, &mut field, sizes);
+                    return Some(field);
+                };
+                if depth >= MAX_DEPTH {
+                    self.warn(path, format!("type `{}` nests too deeply and was skipped", ty));
+                    return None;
+                }
+                if get(attr, "size").is_some() || get(attr, "size-eos").is_some() {
+                    self.warn(path, "`size` on a user type is not supported and was ignored");
+                }
+                field.kind = FieldKind::Group;
+                field.fields = self.seq(path, user_type, endian, depth + 1);
+            }
+        }
+        Some(field)
+    }
+
+    /// Built-in numeric types. None when `ty` isn't one; Some(false) when it's
+    /// recognised but can't be represented.
+    fn number(&mut self, path: &str, ty: &str, endian: Option<Endianness>, field: &mut ProtocolField) -> Option<bool> {
+        let (base, order) = match ty.strip_suffix("le").or_else(|| ty.strip_suffix("be")) {
+            Some(base) if ty.ends_with("le") => (base, Some(Endianness::Little)),
+            Some(base) => (base, Some(Endianness::Big)),
+            None => (ty, None),
+        };
+        let mut chars = base.chars();
+        let (kind, width) = (chars.next()?, chars.as_str().parse::<usize>().ok()?);
+
+        match kind {
+            'b' => {
+                self.warn(path, format!("bit-sized integer `{}` is not supported and was skipped", ty));
+                return Some(false);
+            }
+            'u' | 's' if matches!(width, 1 | 2 | 4 | 8) => {
+                if kind == 's' {
+                    self.warn(path, format!("signed `{}` is imported as unsigned", ty));
+                }
+                field.value_format = Some("dec".to_string());
+                field.value = "0".to_string();
+            }
+            'f' if matches!(width, 4 | 8) => {
+                self.warn(path, format!("float `{}` is imported as raw bytes", ty));
+                field.value_format = Some("hex".to_string());
+            }
+            _ => return None,
+        }
+
+        field.length = Some(width as i32);
+        if width > 1 {
+            match order.or(endian) {
+                Some(Endianness::Little) => field.endianness = Some(Endianness::Little),
+                Some(Endianness::Big) => {}
+                None => self.warn(path, format!("`{}` has no endianness; assuming big-endian", ty)),
+            }
+        }
+        Some(true)
+    }
+
+    /// Raw or text byte count from `size` / `size-eos`
+    fn size(
+        &mut self,
+        path: &str,
+        id: &str,
+        attr: &Mapping,
+        field: &mut ProtocolField,
+        sizes: &mut Vec<(String, String)>,
+    ) {
+        if field.value_format.is_none() {
+            field.value_format = Some("hex".to_string());
+        }
+        match get(attr, "size") {
+            Some(size) if as_int(size).is_some() || size.as_i64().is_some() => {
+                match as_int(size).and_then(|n| i32::try_from(n).ok()).filter(|&n| n >= 1) {
+                    Some(length) => field.length = Some(length),
+                    None => {
+                        field.is_variable = true;
+                        self.warn(
+                            path,
+                            format!("size {} is out of range (1 to {}); the field is variable", value_text(size), i32::MAX),
+                        );
+                    }
+                }
+            }
+            Some(Value::String(expr)) => {
+                field.is_variable = true;
+                if is_identifier(expr.trim()) {
+                    sizes.push((id.to_string(), expr.trim().to_string()));
+                } else {
+                    self.warn(path, format!("size expression `{}` is not supported; the field is variable", expr));
+                }
+            }
+            Some(_) => {
+                field.is_variable = true;
+                self.warn(path, "unreadable `size`; the field is variable");
+            }
+            None => {
+                field.is_variable = true;
+                if get(attr, "size-eos").and_then(Value::as_bool) != Some(true) {
+                    self.warn(path, "no size given; the field is variable");
+                }
+            }
+        }
+        if field.is_variable {
+            field.value_format = None;
+            field.value_type = "hex".to_string();
+        }
+    }
+
+    /// Enum names are listed in the description since fields have no value names
+    fn apply_enum(&mut self, path: &str, attr: &Mapping, field: &mut ProtocolField) {
+        let Some(name) = get_str(attr, "enum") else { return };
+        let Some(values) = lookup(&self.enums, name) else {
+            self.warn(path, format!("enum `{}` is not defined in this file", name));
+            return;
+        };
+        let mut entries: Vec<(u64, String)> = values
+            .iter()
+            .filter_map(|(key, value)| {
+                let label = match value {
+                    Value::String(s) => s.clone(),
+                    Value::Mapping(m) => get_str(m, "id")?.to_string(),
+                    _ => return None,
+                };
+                Some((as_int(key)?, label))
+            })
+            .collect();
+        entries.sort();
+        if let Some((first, _)) = entries.first() {
+            field.value = first.to_string();
+        }
+        let list: Vec<String> = entries.iter().map(|(value, label)| format!("{} = {}", value, label)).collect();
+        let summary = format!("{}: {}", name, list.join(", "));
+        field.description = Some(match field.description.take() {
+            Some(doc) => format!("{} ({})", doc, summary),
+            None => summary,
+        });
+    }
+
+    /// `if: <field> == <value>` on an earlier field
+    fn apply_condition(&mut self, path: &str, attr: &Mapping, fields: &[ProtocolField], field: &mut ProtocolField) {
+        let Some(expr) = get(attr, "if") else { return };
+        let parsed = expr.as_str().and_then(|expr| {
+            let (left, right) = expr.split_once("==")?;
+            let (left, right) = (left.trim(), right.trim());
+            let value = as_int(&Value::String(right.to_string()))?;
+            fields.iter().any(|f| f.name == left).then(|| FieldCondition::Equals { field: left.to_string(), value })
+        });
+        match parsed {
+            Some(condition) => field.condition = Some(condition),
+            None => self.warn(
+                path,
+                format!("condition `{}` is not supported; the field is always present", value_text(expr)),
+            ),
+        }
+    }
+
+    fn apply_repeat(&mut self, path: &str, attr: &Mapping, fields: &[ProtocolField], field: ProtocolField) -> ProtocolField {
+        let Some(repeat) = get_str(attr, "repeat") else { return field };
+        let rule = match repeat {
+            "eos" => RepeatRule::UntilEnd,
+            "expr" => match get(attr, "repeat-expr") {
+                Some(count) if as_int(count).is_some_and(|n| n <= u32::MAX as u64) => {
+                    RepeatRule::Fixed { count: as_int(count).unwrap_or(0) as u32 }
+                }
+                Some(Value::String(expr)) if fields.iter().any(|f| f.name == expr.trim()) => {
+                    RepeatRule::FromField { field: expr.trim().to_string() }
+                }
+                other => {
+                    self.warn(
+                        path,
+                        format!(
+                            "repeat count `{}` is not supported; repeating until the end",
+                            other.map(value_text).unwrap_or_default()
+                        ),
+                    );
+                    RepeatRule::UntilEnd
+                }
+            },
+            other => {
+                self.warn(path, format!("`repeat: {}` is not supported; repeating until the end", other));
+                RepeatRule::UntilEnd
+            }
+        };
+
+        let mut array = new_field(&field.name);
+        array.kind = FieldKind::Array;
+        array.description = field.description.clone();
+        array.condition = field.condition.clone();
+        array.repeat = Some(rule);
+        array.element = Some(Box::new(ProtocolField { condition: None, ..field }));
+        array
+    }
+}
+
+/// Innermost definition of a type or enum; `a::b` paths are matched on their last part
+fn lookup<'a>(scopes: &[&'a Mapping], name: &str) -> Option<&'a Mapping> {
+    let name = name.rsplit("::").next().unwrap_or(name);
+    scopes.iter().rev().find_map(|scope| get_map(scope, name))
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other).unwrap_or_default().trim().to_string(),
+    }
+}
+
+/// Convert a Kaitai Struct (.ksy) definition into a protocol import,
+/// listing every construct that couldn't be represented
+pub fn import(source: &str) -> Result<ProtocolImport, String> {
+    let root: Value = serde_yaml::from_str(source).map_err(|e| format!("Failed to parse KSY: {}", e))?;
+    let root = root.as_mapping().ok_or("A .ksy file must be a YAML mapping")?;
+    let meta = get_map(root, "meta");
+    let name = meta
+        .and_then(|m| get_str(m, "title").or_else(|| get_str(m, "id")))
+        .unwrap_or("Imported")
+        .to_string();
+
+    let mut importer = Importer {
+        types: Vec::new(),
+        enums: Vec::new(),
+        encoding: meta.and_then(|m| get_str(m, "encoding")).map(str::to_string),
+        warnings: Vec::new(),
+    };
+    if meta.and_then(|m| get(m, "endian")).is_some_and(|e| !e.is_string()) {
+        importer.warn("meta", "switchable endianness is not supported; assuming big-endian");
+    }
+    let root_id = meta.and_then(|m| get_str(m, "id")).unwrap_or("root");
+    let fields = importer.seq(root_id, root, None, 0);
+
+    Ok(ProtocolImport {
+        name,
+        description: get_str(root, "doc").map(|d| d.trim().to_string()).filter(|d| !d.is_empty()),
+        fields: fields.into_iter().map(|f| f.into()).collect(),
+        warnings: importer.warnings,
+    })
+}
@@ -1,4 +1,12 @@
-pub mod encoder;
-pub mod parser;
-pub mod field;
-pub mod validator;
+pub mod codegen;
+pub mod correlation;
+pub mod diff;
+pub mod dissector;
+pub mod generator;
+pub mod kaitai;
+
+// The wire format itself is shared with the test server
+pub use packetforge_core::{
+    bytes_to_hex_string, decode, encode, encoder, field, hex, hex_string_to_bytes, parser, resolve_references, text,
+    varint, DecodedField, ProtocolError, Result, MAX_DEPTH,
+};
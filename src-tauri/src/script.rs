@@ -0,0 +1,238 @@
+//! Lua hooks attached to a protocol, run by the message commands around each exchange.
+//!
+//! The pre-send hook gets the encoded request as `payload` and may assign a new one. The
+//! post-receive hook gets `request`, `response` and the decoded response as `fields`, and
+//! fails the exchange by raising an error, e.g. with `assert`. Both can read and write `vars`:
+//! values written there become session variables, which take precedence over the active
+//! environment in `{{name}}` placeholders until the app exits.
+//!
+//! Helpers: `hex(bytes)`, `unhex(text)`, `sha256(bytes)` and `hmac_sha256(key, bytes)`;
+//! `string.pack` and `string.unpack` handle binary integers. Scripts get no file or OS access.
+
+use crate::protocol::{hex_string_to_bytes, DecodedField};
+use mlua::{HookTriggers, Lua, LuaOptions, StdLib, Table, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A hook running longer than this is stopped
+const TIME_LIMIT: Duration = Duration::from_secs(2);
+const MEMORY_LIMIT: usize = 32 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptError {
+    #[error("Pre-send script: {0}")]
+    PreSend(String),
+    #[error("Post-receive script: {0}")]
+    PostReceive(String),
+}
+
+pub type Result<T> = std::result::Result<T, ScriptError>;
+
+/// Variables set by scripts, shared by all connections
+#[derive(Default)]
+pub struct SessionVariables(Mutex<HashMap<String, String>>);
+
+impl SessionVariables {
+    /// `environment` with the session variables laid over it
+    pub fn overlay(&self, mut environment: HashMap<String, String>) -> HashMap<String, String> {
+        let session = self.0.lock().unwrap();
+        environment.extend(session.iter().map(|(k, v)| (k.clone(), v.clone())));
+        environment
+    }
+
+    /// Variables set by scripts so far, by name
+    pub fn list(&self) -> Vec<(String, String)> {
+        let mut variables: Vec<_> = self.0.lock().unwrap().iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        variables.sort();
+        variables
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// Run a pre-send hook and return the bytes to send
+pub fn pre_send(
+    script: &str,
+    payload: &[u8],
+    session: &SessionVariables,
+    environment: HashMap<String, String>,
+) -> Result<Vec<u8>> {
+    run(script, session, environment, |lua, globals| {
+        globals.set("payload", lua.create_string(payload)?)
+    }, |globals| {
+        globals.get::<_, mlua::String>("payload").map(|p| p.as_bytes().to_vec())
+    })
+    .map_err(|e| ScriptError::PreSend(message(e)))
+}
+
+/// Run a post-receive hook; an error raised by the script fails the exchange
+pub fn post_receive(
+    script: &str,
+    request: &[u8],
+    response: &[u8],
+    fields: &[DecodedField],
+    session: &SessionVariables,
+    environment: HashMap<String, String>,
+) -> Result<()> {
+    run(script, session, environment, |lua, globals| {
+        globals.set("request", lua.create_string(request)?)?;
+        globals.set("response", lua.create_string(response)?)?;
+        globals.set("fields", fields_table(lua, fields)?)
+    }, |_| Ok(()))
+    .map_err(|e| ScriptError::PostReceive(message(e)))
+}
+
+fn run<T>(
+    script: &str,
+    session: &SessionVariables,
+    environment: HashMap<String, String>,
+    setup: impl FnOnce(&Lua, &Table) -> mlua::Result<()>,
+    finish: impl FnOnce(&Table) -> mlua::Result<T>,
+) -> mlua::Result<T> {
+    let lua = sandbox()?;
+    let globals = lua.globals();
+    let variables = session.overlay(environment);
+
+    let vars = lua.create_table()?;
+    for (key, value) in &variables {
+        vars.set(key.as_str(), value.as_str())?;
+    }
+    globals.set("vars", vars)?;
+    setup(&lua, &globals)?;
+
+    lua.load(script).set_name("hook").exec()?;
+    let result = finish(&globals)?;
+
+    // Only what the script changed is kept, so environment values aren't frozen into the session
+    let mut changed = HashMap::new();
+    for pair in globals.get::<_, Table>("vars")?.pairs::<String, Value>() {
+        let (key, value) = pair?;
+        let value = match value {
+            Value::String(s) => s.to_str()?.to_string(),
+            Value::Integer(n) => n.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Boolean(b) => b.to_string(),
+            other => {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "vars.{} must be a string or number, not {}",
+                    key,
+                    other.type_name()
+                )))
+            }
+        };
+        if variables.get(&key) != Some(&value) {
+            changed.insert(key, value);
+        }
+    }
+    session.0.lock().unwrap().extend(changed);
+
+    Ok(result)
+}
+
+/// Interpreter with only the string, table, math and utf8 libraries, bounded in time and memory
+fn sandbox() -> mlua::Result<Lua> {
+    let lua = Lua::new_with(
+        StdLib::STRING | StdLib::TABLE | StdLib::MATH | StdLib::UTF8,
+        LuaOptions::default(),
+    )?;
+    lua.set_memory_limit(MEMORY_LIMIT)?;
+    let started = Instant::now();
+    lua.set_hook(HookTriggers::new().every_nth_instruction(10_000), move |_, _| {
+        if started.elapsed() > TIME_LIMIT {
+            return Err(mlua::Error::RuntimeError(format!(
+                "stopped after {} s",
+                TIME_LIMIT.as_secs()
+            )));
+        }
+        Ok(())
+    });
+
+    let globals = lua.globals();
+    // The base library is always loaded; drop what it has for loading files and chunks
+    for name in ["dofile", "loadfile", "load", "require", "collectgarbage"] {
+        globals.set(name, Value::Nil)?;
+    }
+    globals.set(
+        "hex",
+        lua.create_function(|_, data: mlua::String| {
+            Ok(data.as_bytes().iter().map(|b| format!("{:02X}", b)).collect::<String>())
+        })?,
+    )?;
+    globals.set(
+        "unhex",
+        lua.create_function(|lua, text: String| {
+            let bytes = hex_string_to_bytes(&text).map_err(mlua::Error::RuntimeError)?;
+            lua.create_string(bytes)
+        })?,
+    )?;
+    globals.set(
+        "sha256",
+        lua.create_function(|lua, data: mlua::String| lua.create_string(Sha256::digest(data.as_bytes())))?,
+    )?;
+    globals.set(
+        "hmac_sha256",
+        lua.create_function(|lua, (key, data): (mlua::String, mlua::String)| {
+            lua.create_string(hmac_sha256(key.as_bytes(), data.as_bytes()))
+        })?,
+    )?;
+    drop(globals);
+
+    Ok(lua)
+}
+
+/// Decoded fields by name; groups become nested tables and arrays sequences
+fn fields_table<'lua>(lua: &'lua Lua, fields: &[DecodedField]) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    for field in fields {
+        table.set(field.name.as_str(), field_value(lua, field)?)?;
+    }
+    Ok(table)
+}
+
+fn field_value<'lua>(lua: &'lua Lua, field: &DecodedField) -> mlua::Result<Value<'lua>> {
+    if field.fields.is_empty() {
+        return Ok(Value::String(lua.create_string(&field.value)?));
+    }
+
+    let element_prefix = format!("{}[", field.name);
+    if field.fields.iter().all(|f| f.name.starts_with(&element_prefix)) {
+        let items = lua.create_table()?;
+        for item in &field.fields {
+            items.push(field_value(lua, item)?)?;
+        }
+        return Ok(Value::Table(items));
+    }
+
+    Ok(Value::Table(fields_table(lua, &field.fields)?))
+}
+
+/// HMAC (RFC 2104) over SHA-256
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    const BLOCK: usize = 64;
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    let outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    let inner_hash = Sha256::new().chain_update(&inner).chain_update(data).finalize();
+    Sha256::new().chain_update(&outer).chain_update(inner_hash).finalize().to_vec()
+}
+
+/// Lua error text without the traceback, located by line
+fn message(error: mlua::Error) -> String {
+    let text = match error {
+        mlua::Error::CallbackError { cause, .. } => return message((*cause).clone()),
+        mlua::Error::RuntimeError(text) | mlua::Error::SyntaxError { message: text, .. } => text,
+        other => other.to_string(),
+    };
+    let text = text.split("\nstack traceback:").next().unwrap_or_default();
+    text.replace("[string \"hook\"]:", "line ")
+}
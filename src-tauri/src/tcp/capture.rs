@@ -0,0 +1,182 @@
+//! Recording of the bytes a connection sends and receives, for export as a capture file.
+//!
+//! A recording belongs to a connection id rather than to one socket, so it keeps growing
+//! across reconnects (each one a separate TCP session) and can be exported after the
+//! connection is gone.
+
+use crate::pcap::{write_capture, CaptureFormat, TcpFlow};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone)]
+struct Chunk {
+    timestamp: u64,
+    direction: Direction,
+    data: Vec<u8>,
+}
+
+/// One TCP connection seen while recording
+#[derive(Debug, Clone)]
+struct Session {
+    local: SocketAddr,
+    peer: SocketAddr,
+    opened_at: u64,
+    /// Recording started after the connection was opened; its handshake is made up
+    joined: bool,
+    chunks: Vec<Chunk>,
+    closed_at: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Recording {
+    active: bool,
+    sessions: Vec<Session>,
+}
+
+impl Recording {
+    /// Session data is appended to, if recording and the connection is still open
+    fn current(&mut self) -> Option<&mut Session> {
+        if !self.active {
+            return None;
+        }
+        self.sessions.last_mut().filter(|s| s.closed_at.is_none())
+    }
+}
+
+/// What the UI shows about a recording
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureStatus {
+    pub recording: bool,
+    pub sessions: usize,
+    pub bytes_sent: usize,
+    pub bytes_received: usize,
+}
+
+/// Shared handle to a recording; clones record into the same one
+#[derive(Debug, Clone, Default)]
+pub struct Capture(Arc<Mutex<Recording>>);
+
+impl Capture {
+    /// Discard what was recorded and start over. `connection` holds the local and peer
+    /// addresses of an already open connection, which is recorded from now on.
+    pub fn start(&self, connection: Option<(SocketAddr, SocketAddr)>) {
+        let mut recording = self.0.lock().unwrap();
+        recording.active = true;
+        recording.sessions.clear();
+        if let Some((local, peer)) = connection {
+            recording.sessions.push(Session::new(local, peer, true));
+        }
+    }
+
+    /// Stop recording; what was recorded stays available for export
+    pub fn stop(&self) {
+        self.0.lock().unwrap().active = false;
+    }
+
+    /// A new connection replaces the previous one, which is closed if it wasn't yet
+    pub fn opened(&self, local: SocketAddr, peer: SocketAddr) {
+        let mut recording = self.0.lock().unwrap();
+        if let Some(previous) = recording.current() {
+            previous.closed_at = Some(now());
+        }
+        if recording.active {
+            recording.sessions.push(Session::new(local, peer, false));
+        }
+    }
+
+    pub fn record(&self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let mut recording = self.0.lock().unwrap();
+        if let Some(session) = recording.current() {
+            session.chunks.push(Chunk {
+                timestamp: now(),
+                direction,
+                data: data.to_vec(),
+            });
+        }
+    }
+
+    pub fn closed(&self) {
+        let mut recording = self.0.lock().unwrap();
+        if let Some(session) = recording.current() {
+            session.closed_at = Some(now());
+        }
+    }
+
+    pub fn status(&self) -> CaptureStatus {
+        let recording = self.0.lock().unwrap();
+        let bytes = |direction| -> usize {
+            recording
+                .sessions
+                .iter()
+                .flat_map(|s| &s.chunks)
+                .filter(|c| c.direction == direction)
+                .map(|c| c.data.len())
+                .sum()
+        };
+
+        CaptureStatus {
+            recording: recording.active,
+            sessions: recording.sessions.len(),
+            bytes_sent: bytes(Direction::Sent),
+            bytes_received: bytes(Direction::Received),
+        }
+    }
+
+    /// Capture file of every recorded session, with the app as the client side.
+    /// Returns the file contents and the number of packets in it.
+    pub fn export(&self, format: CaptureFormat, interface: &str) -> (Vec<u8>, usize) {
+        let recording = self.0.lock().unwrap();
+        let mut frames = Vec::new();
+
+        for session in &recording.sessions {
+            let mut flow = TcpFlow::new(session.local, session.peer);
+            flow.open(session.opened_at);
+            if session.joined {
+                flow.comment("Recording started on an open connection; handshake synthesized");
+            }
+            for chunk in &session.chunks {
+                flow.data(chunk.timestamp, chunk.direction == Direction::Sent, &chunk.data);
+            }
+            if let Some(closed_at) = session.closed_at {
+                flow.close(closed_at);
+            }
+            frames.extend(flow.into_frames());
+        }
+
+        let count = frames.len();
+        (write_capture(format, interface, &frames), count)
+    }
+}
+
+impl Session {
+    fn new(local: SocketAddr, peer: SocketAddr, joined: bool) -> Self {
+        Self {
+            local,
+            peer,
+            opened_at: now(),
+            joined,
+            chunks: Vec::new(),
+            closed_at: None,
+        }
+    }
+}
+
+/// Microseconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
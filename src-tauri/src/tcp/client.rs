@@ -1,4 +1,8 @@
-use anyhow::{Context, Result};
+use super::capture::{Capture, Direction};
+use super::correlator::{Correlator, FrameMatcher, PendingResponse};
+use anyhow::{bail, Context, Result};
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt, Interest};
 use tokio::net::TcpStream;
@@ -14,17 +18,36 @@ pub struct TcpClientConfig {
 
 pub struct TcpClient {
     stream: Option<TcpStream>,
+    /// Takes over the stream once responses are correlated
+    correlator: Option<Correlator>,
     config: TcpClientConfig,
+    /// Local and peer address of the open connection
+    addresses: Option<(SocketAddr, SocketAddr)>,
+    capture: Capture,
 }
 
 impl TcpClient {
     pub fn new(config: TcpClientConfig) -> Self {
         Self {
             stream: None,
+            correlator: None,
             config,
+            addresses: None,
+            capture: Capture::default(),
         }
     }
 
+    /// Record traffic into `capture` whenever it is started
+    pub fn with_capture(mut self, capture: Capture) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// Local and peer address, while connected
+    pub fn addresses(&self) -> Option<(SocketAddr, SocketAddr)> {
+        self.addresses
+    }
+
     pub async fn connect(&mut self) -> Result<()> {
         let addr = format!("{}:{}", self.config.host, self.config.port);
 
@@ -35,6 +58,11 @@ impl TcpClient {
         .await
         .context("Connection timeout")?
         .context(format!("Failed to connect to {}", addr))?;
+        self.correlator = None;
+
+        let addresses = (stream.local_addr()?, stream.peer_addr()?);
+        self.addresses = Some(addresses);
+        self.capture.opened(addresses.0, addresses.1);
 
         if self.config.keep_alive {
             let socket = socket2::Socket::from(stream.into_std()?);
@@ -48,6 +76,12 @@ impl TcpClient {
     }
 
     pub async fn send(&mut self, data: &[u8]) -> Result<()> {
+        if let Some(correlator) = self.correlator.as_mut() {
+            correlator.send(data, Duration::from_secs(self.config.timeout_secs)).await?;
+            self.capture.record(Direction::Sent, data);
+            return Ok(());
+        }
+
         let stream = self
             .stream
             .as_mut()
@@ -62,11 +96,13 @@ impl TcpClient {
         .context("Failed to send data")?;
 
         stream.flush().await.context("Failed to flush stream")?;
+        self.capture.record(Direction::Sent, data);
 
         Ok(())
     }
 
     pub async fn receive(&mut self, buffer_size: usize) -> Result<Vec<u8>> {
+        self.ensure_uncorrelated()?;
         let stream = self
             .stream
             .as_mut()
@@ -83,15 +119,56 @@ impl TcpClient {
         .context("Failed to read data")?;
 
         buffer.truncate(n);
+        if n == 0 {
+            self.capture.closed();
+        }
+        self.capture.record(Direction::Received, &buffer);
         Ok(buffer)
     }
 
     pub async fn send_and_receive(&mut self, data: &[u8], buffer_size: usize) -> Result<Vec<u8>> {
+        self.ensure_uncorrelated()?;
         self.send(data).await?;
         self.receive(buffer_size).await
     }
 
+    /// Register for the response carrying `key`, decoded with `matcher`.
+    /// The first call hands the stream to a correlator for the rest of the connection.
+    pub fn expect_response(
+        &mut self,
+        matcher_name: &str,
+        matcher: Arc<dyn FrameMatcher>,
+        key: Vec<u8>,
+    ) -> Result<PendingResponse> {
+        match self.correlator.as_ref() {
+            Some(correlator) => correlator.use_matcher(matcher_name, matcher)?,
+            None => {
+                let stream = self
+                    .stream
+                    .take()
+                    .context("Not connected. Call connect() first")?;
+                self.correlator = Some(Correlator::start(stream, matcher_name, matcher, self.capture.clone()));
+            }
+        }
+
+        self.correlator.as_ref().unwrap().expect(key)
+    }
+
+    /// Plain reads would steal frames from the correlator
+    fn ensure_uncorrelated(&self) -> Result<()> {
+        if self.correlator.is_some() {
+            bail!("Responses on this connection are correlated; reconnect to read it directly");
+        }
+        Ok(())
+    }
+
     pub async fn disconnect(&mut self) -> Result<()> {
+        if self.addresses.take().is_some() {
+            self.capture.closed();
+        }
+        if let Some(correlator) = self.correlator.take() {
+            correlator.shutdown().await?;
+        }
         if let Some(mut stream) = self.stream.take() {
             stream.shutdown().await.context("Failed to shutdown connection")?;
         }
@@ -99,12 +176,22 @@ impl TcpClient {
     }
 
     pub fn is_connected(&self) -> bool {
-        self.stream.is_some()
+        self.stream.is_some() || self.correlator.as_ref().is_some_and(|c| !c.is_closed())
     }
 
     /// Check if the TCP connection is still alive by testing the socket state.
     /// Returns true if the connection is active, false if disconnected.
     pub async fn check_connection(&mut self) -> bool {
+        // The correlator's reader notices a closed connection by itself
+        if let Some(correlator) = self.correlator.as_ref() {
+            if correlator.is_closed() {
+                self.correlator = None;
+                self.addresses = None;
+                return false;
+            }
+            return true;
+        }
+
         let stream = match self.stream.as_mut() {
             Some(s) => s,
             None => return false,
@@ -121,10 +208,13 @@ impl TcpClient {
                     Ok(0) => {
                         // EOF - connection closed by peer
                         self.stream = None;
+                        self.addresses = None;
+                        self.capture.closed();
                         false
                     }
-                    Ok(_) => {
+                    Ok(n) => {
                         // Data available - connection is alive
+                        self.capture.record(Direction::Received, &buf[..n]);
                         true
                     }
                     Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -134,6 +224,8 @@ impl TcpClient {
                     Err(_) => {
                         // Connection error
                         self.stream = None;
+                        self.addresses = None;
+                        self.capture.closed();
                         false
                     }
                 }
@@ -141,6 +233,8 @@ impl TcpClient {
             Ok(Err(_)) => {
                 // Error polling readiness
                 self.stream = None;
+                self.addresses = None;
+                self.capture.closed();
                 false
             }
             Err(_) => {
@@ -1,19 +1,25 @@
+use super::capture::{Capture, CaptureStatus};
 use super::client::{TcpClient, TcpClientConfig};
+use super::correlator::FrameMatcher;
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
 pub type ConnectionId = String;
 
 pub struct ConnectionManager {
     connections: Arc<Mutex<HashMap<ConnectionId, TcpClient>>>,
+    /// Traffic recordings by connection id; they outlive the connections themselves
+    captures: Mutex<HashMap<ConnectionId, Capture>>,
 }
 
 impl ConnectionManager {
     pub fn new() -> Self {
         Self {
             connections: Arc::new(Mutex::new(HashMap::new())),
+            captures: Mutex::new(HashMap::new()),
         }
     }
 
@@ -28,7 +34,8 @@ impl ConnectionManager {
             anyhow::bail!("Connection with id '{}' already exists", id);
         }
 
-        let client = TcpClient::new(config);
+        let capture = self.captures.lock().await.entry(id.clone()).or_default().clone();
+        let client = TcpClient::new(config).with_capture(capture);
         connections.insert(id, client);
 
         Ok(())
@@ -89,6 +96,32 @@ impl ConnectionManager {
         client.send_and_receive(data, buffer_size).await
     }
 
+    /// Send a request and wait for the response whose correlation key is `key`.
+    /// The connection is only locked while sending, so many requests can be in flight at once.
+    pub async fn send_correlated(
+        &self,
+        id: &ConnectionId,
+        data: &[u8],
+        matcher_name: &str,
+        matcher: Arc<dyn FrameMatcher>,
+        key: Vec<u8>,
+        limit: Duration,
+    ) -> Result<Vec<u8>> {
+        let pending = {
+            let mut connections = self.connections.lock().await;
+
+            let client = connections
+                .get_mut(id)
+                .context(format!("Connection '{}' not found", id))?;
+
+            let pending = client.expect_response(matcher_name, matcher, key)?;
+            client.send(data).await?;
+            pending
+        };
+
+        pending.wait(limit).await
+    }
+
     pub async fn is_connected(&self, id: &ConnectionId) -> Result<bool> {
         let connections = self.connections.lock().await;
 
@@ -122,6 +155,29 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// Start recording the traffic of a connection, discarding an earlier recording.
+    /// The connection doesn't have to exist yet.
+    pub async fn start_capture(&self, id: &ConnectionId) {
+        let connections = self.connections.lock().await;
+        let capture = self.captures.lock().await.entry(id.clone()).or_default().clone();
+        capture.start(connections.get(id).and_then(|c| c.addresses()));
+    }
+
+    pub async fn stop_capture(&self, id: &ConnectionId) {
+        if let Some(capture) = self.captures.lock().await.get(id) {
+            capture.stop();
+        }
+    }
+
+    /// Recording of a connection, if one was ever started
+    pub async fn capture(&self, id: &ConnectionId) -> Option<Capture> {
+        self.captures.lock().await.get(id).cloned()
+    }
+
+    pub async fn capture_status(&self, id: &ConnectionId) -> CaptureStatus {
+        self.captures.lock().await.get(id).cloned().unwrap_or_default().status()
+    }
+
     pub async fn list_connections(&self) -> Vec<ConnectionId> {
         let connections = self.connections.lock().await;
         connections.keys().cloned().collect()
@@ -0,0 +1,202 @@
+//! Routes responses to their requests when several are in flight on one connection.
+//!
+//! Once a connection correlates, a reader task owns the read half of the socket. It splits
+//! the incoming data into frames with a `FrameMatcher` and hands each frame to the request
+//! waiting for its correlation key. Frames no request is waiting for are dropped.
+
+use super::capture::{Capture, Direction};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+/// Splits a response stream into frames
+pub trait FrameMatcher: Send + Sync {
+    /// Length and correlation key of the frame at the start of `buffer`,
+    /// or None while the frame is incomplete
+    fn next_frame(&self, buffer: &[u8]) -> std::result::Result<Option<(usize, Vec<u8>)>, String>;
+}
+
+/// Waiting requests by correlation key, tagged so a finished request only removes its own entry
+type Waiting = HashMap<Vec<u8>, (u64, oneshot::Sender<Vec<u8>>)>;
+
+struct Shared {
+    waiting: Mutex<Waiting>,
+    /// Name of the matcher in use (protocol and rule it was built from) and the matcher itself
+    matcher: Mutex<(String, Arc<dyn FrameMatcher>)>,
+    next_ticket: AtomicU64,
+    closed: AtomicBool,
+}
+
+impl Shared {
+    /// Fail every waiting request; dropping the senders wakes them
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.waiting.lock().unwrap().clear();
+    }
+}
+
+pub struct Correlator {
+    writer: OwnedWriteHalf,
+    shared: Arc<Shared>,
+    reader: JoinHandle<()>,
+}
+
+impl Correlator {
+    /// Take over a connected stream; received data is also recorded into `capture`
+    pub fn start(stream: TcpStream, name: &str, matcher: Arc<dyn FrameMatcher>, capture: Capture) -> Self {
+        let (read_half, writer) = stream.into_split();
+        let shared = Arc::new(Shared {
+            waiting: Mutex::new(HashMap::new()),
+            matcher: Mutex::new((name.to_string(), matcher)),
+            next_ticket: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+        });
+        let reader = tokio::spawn(read_frames(read_half, shared.clone(), capture));
+
+        Self { writer, shared, reader }
+    }
+
+    /// Switch to another matcher; only possible while no request is waiting
+    pub fn use_matcher(&self, name: &str, matcher: Arc<dyn FrameMatcher>) -> Result<()> {
+        let mut current = self.shared.matcher.lock().unwrap();
+        if current.0 == name {
+            return Ok(());
+        }
+        if !self.shared.waiting.lock().unwrap().is_empty() {
+            bail!("Requests of another protocol are still waiting for responses on this connection");
+        }
+        *current = (name.to_string(), matcher);
+        Ok(())
+    }
+
+    /// Register interest in the response with `key`; call before sending the request
+    pub fn expect(&self, key: Vec<u8>) -> Result<PendingResponse> {
+        if self.is_closed() {
+            bail!("Connection closed");
+        }
+
+        let mut waiting = self.shared.waiting.lock().unwrap();
+        if waiting.contains_key(&key) {
+            bail!("A request with correlation value {} is already waiting for its response", hex(&key));
+        }
+        let ticket = self.shared.next_ticket.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        waiting.insert(key.clone(), (ticket, sender));
+
+        Ok(PendingResponse {
+            receiver,
+            key,
+            ticket,
+            shared: self.shared.clone(),
+        })
+    }
+
+    pub async fn send(&mut self, data: &[u8], limit: Duration) -> Result<()> {
+        timeout(limit, self.writer.write_all(data))
+            .await
+            .context("Send timeout")?
+            .context("Failed to send data")?;
+
+        self.writer.flush().await.context("Failed to flush stream")?;
+
+        Ok(())
+    }
+
+    /// Whether the peer closed the connection or reading failed
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::Relaxed)
+    }
+
+    pub async fn shutdown(mut self) -> Result<()> {
+        self.reader.abort();
+        self.shared.close();
+        self.writer.shutdown().await.context("Failed to shutdown connection")
+    }
+}
+
+impl Drop for Correlator {
+    fn drop(&mut self) {
+        // Requests still waiting hold on to the shared state; without this they would wait
+        // out their whole timeout after a disconnect or reconnect
+        self.reader.abort();
+        self.shared.close();
+    }
+}
+
+/// A sent request waiting for its response; dropping it stops waiting
+pub struct PendingResponse {
+    receiver: oneshot::Receiver<Vec<u8>>,
+    key: Vec<u8>,
+    ticket: u64,
+    shared: Arc<Shared>,
+}
+
+impl PendingResponse {
+    pub async fn wait(mut self, limit: Duration) -> Result<Vec<u8>> {
+        match timeout(limit, &mut self.receiver).await {
+            Ok(Ok(frame)) => Ok(frame),
+            Ok(Err(_)) => bail!("Connection closed before the response arrived"),
+            Err(_) => bail!(
+                "No response with correlation value {} within {} ms",
+                hex(&self.key),
+                limit.as_millis()
+            ),
+        }
+    }
+}
+
+impl Drop for PendingResponse {
+    fn drop(&mut self) {
+        let mut waiting = self.shared.waiting.lock().unwrap();
+        if waiting.get(&self.key).is_some_and(|(ticket, _)| *ticket == self.ticket) {
+            waiting.remove(&self.key);
+        }
+    }
+}
+
+async fn read_frames(mut read_half: OwnedReadHalf, shared: Arc<Shared>, capture: Capture) {
+    let mut buffer = Vec::new();
+    let mut chunk = vec![0u8; 4096];
+
+    loop {
+        let n = match read_half.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        capture.record(Direction::Received, &chunk[..n]);
+        buffer.extend_from_slice(&chunk[..n]);
+
+        let matcher = shared.matcher.lock().unwrap().1.clone();
+        loop {
+            match matcher.next_frame(&buffer) {
+                Ok(Some((len, key))) if len > 0 && len <= buffer.len() => {
+                    let frame: Vec<u8> = buffer.drain(..len).collect();
+                    if let Some((_, sender)) = shared.waiting.lock().unwrap().remove(&key) {
+                        let _ = sender.send(frame);
+                    }
+                }
+                Ok(None) => break,
+                // Data that doesn't decode can't be split into frames; start over with the next read
+                _ => {
+                    buffer.clear();
+                    break;
+                }
+            }
+        }
+    }
+
+    capture.closed();
+    shared.close();
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
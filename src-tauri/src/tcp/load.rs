@@ -0,0 +1,408 @@
+//! Load generator: many connections sending the same request, measuring how the target keeps up.
+//!
+//! Each connection sends one request at a time and, unless told otherwise, waits for the response
+//! before the next. With a target rate the requests are spread evenly over the connections;
+//! without one every connection sends as fast as the target answers. A connection that fails is
+//! dropped and opened again, so connect times are measured throughout the run.
+
+use super::client::{TcpClient, TcpClientConfig};
+use crate::models::{LatencyStats, LoadTestConfig, LoadTestErrors, LoadTestSample, LoadTestSummary};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::error::Elapsed;
+use tokio::time::{sleep_until, Instant};
+
+const MAX_CONNECTIONS: u32 = 1000;
+/// Read size for one response
+const RESPONSE_BUFFER: usize = 64 * 1024;
+/// Wait before reconnecting after a failed connect
+const RECONNECT_DELAY: Duration = Duration::from_millis(100);
+/// How often a waiting connection checks whether the run was stopped
+const STOP_POLL: Duration = Duration::from_millis(100);
+/// Histogram buckets per power of two; percentiles are off by less than 1/64 of the value
+const SUB_BUCKETS: u32 = 64;
+const SUB_BUCKET_BITS: u32 = SUB_BUCKETS.trailing_zeros();
+/// Exact buckets below `SUB_BUCKETS`, then `SUB_BUCKETS` for each power of two up to `u32::MAX`
+const BUCKETS: usize = ((32 - SUB_BUCKET_BITS + 1) * SUB_BUCKETS) as usize;
+
+/// Stop flags of the load tests that are running, by run id
+#[derive(Default)]
+pub struct LoadTests(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+impl LoadTests {
+    pub fn register(&self, run_id: &str) -> Arc<AtomicBool> {
+        let stop = Arc::new(AtomicBool::new(false));
+        self.0.lock().unwrap().insert(run_id.to_string(), stop.clone());
+        stop
+    }
+
+    pub fn finish(&self, run_id: &str) {
+        self.0.lock().unwrap().remove(run_id);
+    }
+
+    /// Ask a run to stop; false if it isn't running
+    pub fn stop(&self, run_id: &str) -> bool {
+        match self.0.lock().unwrap().get(run_id) {
+            Some(stop) => {
+                stop.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Totals so far, passed to the progress callback once a second
+pub struct Progress {
+    pub elapsed: Duration,
+    pub requests: u64,
+    pub errors: u64,
+    pub active_connections: u32,
+    pub sample: LoadTestSample,
+}
+
+/// Microsecond timings in fixed log-linear buckets, so long runs take no more memory
+struct Histogram {
+    buckets: Vec<u64>,
+    count: u64,
+    total: u64,
+    max: u32,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self { buckets: vec![0; BUCKETS], count: 0, total: 0, max: 0 }
+    }
+}
+
+impl Histogram {
+    fn record(&mut self, micros: u32) {
+        self.buckets[bucket(micros)] += 1;
+        self.count += 1;
+        self.total += micros as u64;
+        self.max = self.max.max(micros);
+    }
+
+    /// Nearest-rank percentile in milliseconds, taking the top of the bucket it falls in
+    fn percentile(&self, p: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let rank = (((p / 100.0) * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (index, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return bucket_top(index).min(self.max) as f64 / 1000.0;
+            }
+        }
+        self.max as f64 / 1000.0
+    }
+}
+
+fn bucket(micros: u32) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let shift = 31 - micros.leading_zeros() - SUB_BUCKET_BITS;
+    ((shift + 1) * SUB_BUCKETS + (micros >> shift) - SUB_BUCKETS) as usize
+}
+
+/// Largest value that falls in a bucket
+fn bucket_top(index: usize) -> u32 {
+    let index = index as u32;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let base = (index % SUB_BUCKETS + SUB_BUCKETS) as u64;
+    (((base + 1) << shift) - 1).min(u32::MAX as u64) as u32
+}
+
+#[derive(Default)]
+struct Stats {
+    /// Microseconds, for the whole run
+    latencies: Histogram,
+    connect_times: Histogram,
+    /// Latencies and errors since the last sample
+    window: Histogram,
+    window_errors: u64,
+    errors: LoadTestErrors,
+    bytes_sent: u64,
+    bytes_received: u64,
+}
+
+impl Stats {
+    fn error(&mut self, kind: fn(&mut LoadTestErrors) -> &mut u64) {
+        *kind(&mut self.errors) += 1;
+        self.window_errors += 1;
+    }
+}
+
+/// Check a configuration before anything is opened
+pub fn validate(config: &LoadTestConfig) -> Result<(), String> {
+    if config.connections == 0 || config.connections > MAX_CONNECTIONS {
+        return Err(format!("Connections must be between 1 and {}", MAX_CONNECTIONS));
+    }
+    if config.duration_secs == 0 {
+        return Err("Duration must be at least 1 second".to_string());
+    }
+    if config.rate == Some(0) {
+        return Err("Rate must be at least 1 request per second".to_string());
+    }
+    if config.timeout_secs == 0 {
+        return Err("Timeout must be at least 1 second".to_string());
+    }
+    Ok(())
+}
+
+/// Run a load test sending `payload` until the duration is over or `stop` is set
+pub async fn run(
+    config: &LoadTestConfig,
+    payload: Vec<u8>,
+    stop: Arc<AtomicBool>,
+    mut on_progress: impl FnMut(Progress),
+) -> LoadTestSummary {
+    let stats = Arc::new(Mutex::new(Stats::default()));
+    let active = Arc::new(AtomicU32::new(0));
+    let payload = Arc::new(payload);
+    let start = Instant::now();
+    let deadline = start + Duration::from_secs(config.duration_secs);
+
+    // Each connection sends every `connections / rate` seconds, offset so they don't send together
+    let interval = config
+        .rate
+        .map(|rate| Duration::from_secs_f64(config.connections as f64 / rate as f64));
+
+    let workers: Vec<_> = (0..config.connections)
+        .map(|index| {
+            let worker = Worker {
+                client_config: TcpClientConfig {
+                    host: config.host.clone(),
+                    port: config.port,
+                    timeout_secs: config.timeout_secs,
+                    keep_alive: false,
+                },
+                payload: payload.clone(),
+                expect_response: config.expect_response,
+                interval,
+                first_send: start + interval.map_or(Duration::ZERO, |i| i * index / config.connections),
+                deadline,
+                stop: stop.clone(),
+                stats: stats.clone(),
+                active: active.clone(),
+            };
+            tokio::spawn(worker.run())
+        })
+        .collect();
+
+    let mut timeline = Vec::new();
+    let mut next_sample = start + Duration::from_secs(1);
+    loop {
+        let finished = workers.iter().all(|w| w.is_finished());
+        if !finished {
+            sleep_until(next_sample.min(Instant::now() + Duration::from_millis(50))).await;
+            if Instant::now() < next_sample {
+                continue;
+            }
+        }
+
+        let sample = take_sample(&stats, timeline.len() as u64 + 1);
+        // The last, partial second only counts if something happened in it
+        if !finished || sample.requests > 0 || sample.errors > 0 {
+            let (requests, errors) = {
+                let stats = stats.lock().unwrap();
+                (stats.latencies.count, stats.errors.total())
+            };
+            on_progress(Progress {
+                elapsed: start.elapsed(),
+                requests,
+                errors,
+                active_connections: active.load(Ordering::Relaxed),
+                sample: sample.clone(),
+            });
+            timeline.push(sample);
+        }
+        if finished {
+            break;
+        }
+        next_sample += Duration::from_secs(1);
+    }
+
+    let duration = start.elapsed();
+    let stats = stats.lock().unwrap();
+    let requests = stats.latencies.count;
+    LoadTestSummary {
+        duration_ms: duration.as_millis() as u64,
+        requests,
+        throughput: requests as f64 / duration.as_secs_f64().max(0.001),
+        bytes_sent: stats.bytes_sent,
+        bytes_received: stats.bytes_received,
+        latency: latency_stats(&stats.latencies),
+        connect_time: latency_stats(&stats.connect_times),
+        errors: stats.errors.clone(),
+        timeline,
+        stopped: stop.load(Ordering::Relaxed),
+    }
+}
+
+struct Worker {
+    client_config: TcpClientConfig,
+    payload: Arc<Vec<u8>>,
+    expect_response: bool,
+    interval: Option<Duration>,
+    first_send: Instant,
+    deadline: Instant,
+    stop: Arc<AtomicBool>,
+    stats: Arc<Mutex<Stats>>,
+    active: Arc<AtomicU32>,
+}
+
+impl Worker {
+    fn running(&self) -> bool {
+        !self.stop.load(Ordering::Relaxed) && Instant::now() < self.deadline
+    }
+
+    /// Sleep until `at`, waking early when the run ends; false if it ended first
+    async fn wait_until(&self, at: Instant) -> bool {
+        while self.running() {
+            let now = Instant::now();
+            if now >= at {
+                return true;
+            }
+            sleep_until(at.min(now + STOP_POLL)).await;
+        }
+        false
+    }
+
+    /// Resolves once the run is stopped
+    async fn stopped(&self) {
+        while !self.stop.load(Ordering::Relaxed) {
+            tokio::time::sleep(STOP_POLL).await;
+        }
+    }
+
+    async fn run(self) {
+        let mut client: Option<TcpClient> = None;
+        let mut next_send = self.first_send;
+
+        while self.running() {
+            let Some(connection) = client.as_mut() else {
+                client = self.connect().await;
+                if client.is_none() {
+                    self.wait_until(Instant::now() + RECONNECT_DELAY).await;
+                }
+                continue;
+            };
+
+            if let Some(interval) = self.interval {
+                if !self.wait_until(next_send).await {
+                    break;
+                }
+                // A connection that fell behind catches up gradually instead of in a burst
+                next_send = (next_send + interval).max(Instant::now());
+            }
+
+            let sent_at = Instant::now();
+            let exchange = async {
+                match connection.send(&self.payload).await {
+                    Ok(()) if self.expect_response => connection.receive(RESPONSE_BUFFER).await,
+                    Ok(()) => Ok(Vec::new()),
+                    Err(e) => Err(e),
+                }
+            };
+            // A request still in flight when the run is stopped isn't counted
+            let result = tokio::select! {
+                result = exchange => result,
+                _ = self.stopped() => break,
+            };
+            let elapsed = sent_at.elapsed();
+
+            if self.record(result, elapsed) {
+                continue;
+            }
+
+            // The connection can't be trusted after a failure, e.g. a late response would
+            // be taken for the next one
+            if let Some(mut failed) = client.take() {
+                self.active.fetch_sub(1, Ordering::Relaxed);
+                let _ = failed.disconnect().await;
+            }
+        }
+
+        if let Some(mut client) = client {
+            self.active.fetch_sub(1, Ordering::Relaxed);
+            let _ = client.disconnect().await;
+        }
+    }
+
+    /// Count the outcome of one request; true if it succeeded
+    fn record(&self, result: anyhow::Result<Vec<u8>>, elapsed: Duration) -> bool {
+        let mut stats = self.stats.lock().unwrap();
+        match result {
+            // An empty read means the target closed the connection
+            Ok(response) if self.expect_response && response.is_empty() => stats.error(|e| &mut e.io),
+            Ok(response) => {
+                let micros = elapsed.as_micros().min(u32::MAX as u128) as u32;
+                stats.latencies.record(micros);
+                stats.window.record(micros);
+                stats.bytes_sent += self.payload.len() as u64;
+                stats.bytes_received += response.len() as u64;
+                return true;
+            }
+            Err(e) if e.downcast_ref::<Elapsed>().is_some() => stats.error(|e| &mut e.timeout),
+            Err(_) => stats.error(|e| &mut e.io),
+        }
+        false
+    }
+
+    async fn connect(&self) -> Option<TcpClient> {
+        let mut client = TcpClient::new(self.client_config.clone());
+        let started = Instant::now();
+        let result = client.connect().await;
+        let mut stats = self.stats.lock().unwrap();
+        match result {
+            Ok(()) => {
+                let micros = started.elapsed().as_micros().min(u32::MAX as u128) as u32;
+                stats.connect_times.record(micros);
+                self.active.fetch_add(1, Ordering::Relaxed);
+                Some(client)
+            }
+            Err(_) => {
+                stats.error(|e| &mut e.connect);
+                None
+            }
+        }
+    }
+}
+
+fn take_sample(stats: &Mutex<Stats>, second: u64) -> LoadTestSample {
+    let mut stats = stats.lock().unwrap();
+    let window = std::mem::take(&mut stats.window);
+    let errors = std::mem::take(&mut stats.window_errors);
+    drop(stats);
+
+    LoadTestSample {
+        second,
+        requests: window.count,
+        errors,
+        p50: window.percentile(50.0),
+        p99: window.percentile(99.0),
+    }
+}
+
+fn latency_stats(micros: &Histogram) -> LatencyStats {
+    if micros.count == 0 {
+        return LatencyStats::default();
+    }
+    LatencyStats {
+        count: micros.count,
+        mean: micros.total as f64 / micros.count as f64 / 1000.0,
+        p50: micros.percentile(50.0),
+        p90: micros.percentile(90.0),
+        p99: micros.percentile(99.0),
+        max: micros.max as f64 / 1000.0,
+    }
+}
@@ -1,5 +1,11 @@
+pub mod capture;
 pub mod client;
 pub mod connection_manager;
+pub mod correlator;
+pub mod load;
 
+pub use capture::{Capture, CaptureStatus};
 pub use client::{TcpClient, TcpClientConfig};
 pub use connection_manager::{ConnectionId, ConnectionManager};
+pub use correlator::FrameMatcher;
+pub use load::LoadTests;
@@ -0,0 +1,184 @@
+//! `{{name}}` placeholders in payloads and field values, resolved when a message is sent.
+//!
+//! Names refer to variables of the active environment. Names starting with `$` are built-ins:
+//! `$timestamp`, `$timestamp_ms`, `$random_u8`, `$random_u16`, `$random_u32` and `$seq`
+//! (a counter per connection, starting at 1).
+
+use crate::models::ProtocolField;
+use chrono::Utc;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("Unknown variable '{0}'")]
+    UnknownVariable(String),
+    #[error("Unclosed '{{{{' in '{0}'")]
+    Unclosed(String),
+}
+
+pub type Result<T> = std::result::Result<T, TemplateError>;
+
+/// How a built-in number is written where its placeholder stood
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberStyle {
+    /// Decimal digits (text payloads, decimal fields)
+    Decimal,
+    /// Big-endian hex bytes; `Some(n)` writes exactly n bytes, None the built-in's own width
+    Hex(Option<usize>),
+    /// Binary digits for n bytes
+    Binary(usize),
+}
+
+/// Per-connection `{{$seq}}` counters
+#[derive(Default)]
+pub struct Sequences(Mutex<HashMap<String, u64>>);
+
+impl Sequences {
+    /// Value for the next message on `connection_id`
+    pub fn next(&self, connection_id: &str) -> u64 {
+        let mut counters = self.0.lock().unwrap();
+        let counter = counters.entry(connection_id.to_string()).or_insert(0);
+        *counter += 1;
+        *counter
+    }
+
+    /// Value the next message would get, without using it up
+    pub fn peek(&self, connection_id: &str) -> u64 {
+        self.0.lock().unwrap().get(connection_id).copied().unwrap_or(0) + 1
+    }
+
+    pub fn reset(&self, connection_id: &str) {
+        self.0.lock().unwrap().remove(connection_id);
+    }
+}
+
+/// Values available to one message. Built-ins are evaluated once per message,
+/// so repeated `{{$seq}}` or `{{$timestamp}}` placeholders agree.
+pub struct Context<'a> {
+    variables: &'a HashMap<String, String>,
+    timestamp_ms: i64,
+    next_seq: Box<dyn Fn() -> u64 + 'a>,
+    seq: Cell<Option<u64>>,
+}
+
+impl<'a> Context<'a> {
+    /// `next_seq` is only called if the message uses `{{$seq}}`
+    pub fn new(variables: &'a HashMap<String, String>, next_seq: impl Fn() -> u64 + 'a) -> Self {
+        Self {
+            variables,
+            timestamp_ms: Utc::now().timestamp_millis(),
+            next_seq: Box::new(next_seq),
+            seq: Cell::new(None),
+        }
+    }
+
+    /// Built-in value and its natural width in bytes
+    fn builtin(&self, name: &str) -> Option<(u64, usize)> {
+        match name {
+            "timestamp" => Some(((self.timestamp_ms / 1000) as u64, 4)),
+            "timestamp_ms" => Some((self.timestamp_ms as u64, 8)),
+            "random_u8" => Some((rand::random::<u8>() as u64, 1)),
+            "random_u16" => Some((rand::random::<u16>() as u64, 2)),
+            "random_u32" => Some((rand::random::<u32>() as u64, 4)),
+            "seq" => {
+                let seq = self.seq.get().unwrap_or_else(|| (self.next_seq)());
+                self.seq.set(Some(seq));
+                Some((seq, 4))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn has_placeholders(input: &str) -> bool {
+    input.contains("{{")
+}
+
+/// Replace every placeholder in `input`
+pub fn render(input: &str, context: &Context, style: NumberStyle) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or_else(|| TemplateError::Unclosed(input.to_string()))?;
+        let name = after[..end].trim();
+
+        if let Some(builtin) = name.strip_prefix('$') {
+            let (value, width) = context
+                .builtin(builtin)
+                .ok_or_else(|| TemplateError::UnknownVariable(name.to_string()))?;
+            output.push_str(&format_number(value, width, style));
+        } else {
+            let value = context
+                .variables
+                .get(name)
+                .ok_or_else(|| TemplateError::UnknownVariable(name.to_string()))?;
+            output.push_str(value);
+        }
+        rest = &after[end + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Resolve placeholders in the values of a field tree, in each field's value format
+pub fn render_fields(fields: &mut [ProtocolField], context: &Context) -> Result<()> {
+    for field in fields {
+        if has_placeholders(&field.value) {
+            field.value = render(&field.value, context, field_style(field))?;
+        }
+        render_fields(&mut field.fields, context)?;
+        if let Some(element) = field.element.as_deref_mut() {
+            render_fields(std::slice::from_mut(element), context)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn fields_have_placeholders(fields: &[ProtocolField]) -> bool {
+    fields
+        .iter()
+        .any(|f| {
+            has_placeholders(&f.value)
+                || fields_have_placeholders(&f.fields)
+                || f.element.as_deref().is_some_and(|e| fields_have_placeholders(std::slice::from_ref(e)))
+        })
+}
+
+fn field_style(field: &ProtocolField) -> NumberStyle {
+    let width = field.length.unwrap_or(1).max(1) as usize;
+    if field.is_variable {
+        return if field.value_type == "hex" { NumberStyle::Hex(None) } else { NumberStyle::Decimal };
+    }
+    // Without a format, the encoder reads a number-like value as hex
+    match field.value_format.as_deref() {
+        Some("hex") | None => NumberStyle::Hex(Some(width)),
+        Some("bin") => NumberStyle::Binary(width),
+        _ => NumberStyle::Decimal,
+    }
+}
+
+fn format_number(value: u64, width: usize, style: NumberStyle) -> String {
+    match style {
+        NumberStyle::Decimal => value.to_string(),
+        NumberStyle::Hex(forced) => {
+            // Byte i counted from the right; wide fields are zero filled
+            let width = forced.unwrap_or(width);
+            (0..width)
+                .rev()
+                .map(|i| format!("{:02X}", if i < 8 { (value >> (i * 8)) as u8 } else { 0 }))
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+        NumberStyle::Binary(width) => {
+            let bits = width * 8;
+            let masked = if bits >= 64 { value } else { value & ((1u64 << bits) - 1) };
+            format!("{:0bits$b}", masked)
+        }
+    }
+}
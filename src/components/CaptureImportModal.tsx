@@ -0,0 +1,205 @@
+import { useEffect, useState } from 'react';
+import { Button, Checkbox, Empty, Modal, Space, Table, Tag, message } from 'antd';
+import { captureService } from '../services/captureService';
+import type { CaptureImport, CaptureMessage, ReplayResult } from '../services/captureService';
+
+interface CaptureImportModalProps {
+  capture: CaptureImport | null;
+  connectionId: string;
+  isConnected: boolean;
+  onClose: () => void;
+  // Client messages to open as new message tabs, with the server they were sent to
+  onOpenMessages: (messages: CaptureMessage[], server: string) => void;
+}
+
+const byteCount = (hex: string) => hex.replace(/\s/g, '').length / 2;
+
+const preview = (hex: string) => (hex.length > 48 ? `${hex.slice(0, 48)}…` : hex);
+
+// Conversations of an imported capture: pick client messages to open as messages or replay
+export default function CaptureImportModal({ capture, connectionId, isConnected, onClose, onOpenMessages }: CaptureImportModalProps) {
+  const [conversationIndex, setConversationIndex] = useState(0);
+  const [selected, setSelected] = useState<number[]>([]);
+  const [keepTiming, setKeepTiming] = useState(false);
+  const [waitForResponse, setWaitForResponse] = useState(true);
+  const [results, setResults] = useState<Record<number, ReplayResult>>({});
+  const [replaying, setReplaying] = useState(false);
+
+  const conversation = capture?.conversations[conversationIndex];
+
+  useEffect(() => {
+    setConversationIndex(0);
+  }, [capture]);
+
+  // Every client message is selected at first
+  useEffect(() => {
+    setSelected(conversation?.messages.flatMap((m, i) => (m.fromClient ? [i] : [])) ?? []);
+    setResults({});
+  }, [conversation]);
+
+  const selectedMessages = () => [...selected].sort((a, b) => a - b);
+
+  const handleOpen = () => {
+    if (!conversation) return;
+    onOpenMessages(selectedMessages().map(i => conversation.messages[i]), conversation.server);
+  };
+
+  const handleReplay = async () => {
+    if (!conversation) return;
+    const indexes = selectedMessages();
+    let previous = conversation.messages[indexes[0]]?.offsetMs ?? 0;
+    const messages = indexes.map(i => {
+      const { data, offsetMs } = conversation.messages[i];
+      const delayMs = keepTiming ? Math.max(0, Math.round(offsetMs - previous)) : 0;
+      previous = offsetMs;
+      return { data, delayMs };
+    });
+
+    try {
+      setReplaying(true);
+      setResults({});
+      const replayed = await captureService.replay(connectionId, messages, waitForResponse);
+      setResults(Object.fromEntries(replayed.map((result, n) => [indexes[n], result])));
+      const failed = replayed.find(r => r.error);
+      if (failed) {
+        message.error(`Replay stopped: ${failed.error}`);
+      } else {
+        message.success(`Replayed ${replayed.length} messages`);
+      }
+    } catch (error) {
+      message.error('Replay failed: ' + error);
+    } finally {
+      setReplaying(false);
+    }
+  };
+
+  return (
+    <Modal
+      title={capture ? `Import Capture: ${capture.fileName}` : 'Import Capture'}
+      open={capture !== null}
+      onCancel={onClose}
+      footer={null}
+      width={960}
+    >
+      {!capture || capture.conversations.length === 0 ? (
+        <Empty description="No TCP or UDP conversations in this capture" />
+      ) : (
+        <div style={{ display: 'flex', flexDirection: 'column', gap: 12 }}>
+          <div style={{ color: '#858585', fontSize: 12 }}>
+            {capture.packets} packets, {capture.conversations.length} conversations
+            {capture.ignored > 0 && `, ${capture.ignored} packets ignored (not TCP or UDP over IP)`}
+          </div>
+          <Table
+            size="small"
+            pagination={false}
+            scroll={{ y: 160 }}
+            dataSource={capture.conversations.map((c, key) => ({ ...c, key }))}
+            onRow={record => ({ onClick: () => setConversationIndex(record.key), style: { cursor: 'pointer' } })}
+            rowClassName={record => (record.key === conversationIndex ? 'ant-table-row-selected' : '')}
+            columns={[
+              {
+                title: 'Transport',
+                dataIndex: 'transport',
+                key: 'transport',
+                width: 90,
+                render: (transport: string) => <Tag>{transport.toUpperCase()}</Tag>,
+              },
+              { title: 'Client', dataIndex: 'client', key: 'client' },
+              { title: 'Server', dataIndex: 'server', key: 'server' },
+              { title: 'Packets', dataIndex: 'packets', key: 'packets', width: 80 },
+              {
+                title: 'Bytes (C → S / S → C)',
+                key: 'bytes',
+                width: 170,
+                render: (_, record) => (
+                  <span>
+                    {record.clientBytes} / {record.serverBytes}
+                    {record.incomplete && <Tag color="orange" style={{ marginLeft: 6 }}>gaps</Tag>}
+                  </span>
+                ),
+              },
+            ]}
+          />
+          {conversation && (
+            <Table
+              size="small"
+              pagination={false}
+              scroll={{ y: 260 }}
+              dataSource={conversation.messages.map((m, key) => ({ ...m, key }))}
+              rowSelection={{
+                selectedRowKeys: selected,
+                onChange: keys => setSelected(keys.map(Number)),
+                // Only what the client sent can be sent again
+                getCheckboxProps: record => ({ disabled: !record.fromClient }),
+              }}
+              columns={[
+                {
+                  title: 'Dir',
+                  dataIndex: 'fromClient',
+                  key: 'fromClient',
+                  width: 56,
+                  render: (fromClient: boolean) => (
+                    <span style={{ color: fromClient ? '#ff6c37' : '#4ec9b0' }}>{fromClient ? 'C → S' : 'S → C'}</span>
+                  ),
+                },
+                {
+                  title: 'Time (ms)',
+                  dataIndex: 'offsetMs',
+                  key: 'offsetMs',
+                  width: 90,
+                  render: (offset: number) => offset.toFixed(1),
+                },
+                { title: 'Bytes', key: 'length', width: 70, render: (_, record) => byteCount(record.data) },
+                {
+                  title: 'Data',
+                  dataIndex: 'data',
+                  key: 'data',
+                  render: (data: string) => <span style={{ fontFamily: 'monospace', fontSize: 12 }}>{preview(data)}</span>,
+                },
+                {
+                  title: 'Replay',
+                  key: 'replay',
+                  width: 220,
+                  render: (_, record) => {
+                    const result = results[record.key];
+                    if (!result) return null;
+                    if (result.error) return <span style={{ color: '#f48771', fontSize: 12 }}>{result.error}</span>;
+                    return (
+                      <span style={{ fontFamily: 'monospace', fontSize: 12 }}>
+                        {result.responseData ? preview(result.responseData) : 'sent'} ({result.responseTimeMs} ms)
+                      </span>
+                    );
+                  },
+                },
+              ]}
+            />
+          )}
+          <div style={{ display: 'flex', alignItems: 'center', justifyContent: 'space-between' }}>
+            <Space>
+              <Checkbox checked={keepTiming} onChange={e => setKeepTiming(e.target.checked)}>
+                Keep original timing
+              </Checkbox>
+              <Checkbox checked={waitForResponse} onChange={e => setWaitForResponse(e.target.checked)}>
+                Wait for responses
+              </Checkbox>
+            </Space>
+            <Space>
+              <Button onClick={handleOpen} disabled={selected.length === 0}>
+                Open as Messages
+              </Button>
+              <Button
+                type="primary"
+                onClick={handleReplay}
+                loading={replaying}
+                disabled={selected.length === 0 || !isConnected}
+                title={isConnected ? undefined : 'Connect the current tab first'}
+              >
+                Replay on Current Connection
+              </Button>
+            </Space>
+          </div>
+        </div>
+      )}
+    </Modal>
+  );
+}
@@ -0,0 +1,115 @@
+import { Input, InputNumber, Select } from 'antd';
+import { useEffect, useState } from 'react';
+import ProtocolFieldEditor from './ProtocolFieldEditor';
+import TlvItemEditor from './TlvItemEditor';
+import { protocolService, Protocol as SavedProtocol } from '../services/protocolService';
+import type { ProtocolField, RepeatRule } from '../types/protocol-simple';
+import { cloneFieldsWithNewIds } from '../utils/protocolFields';
+import { useFontSize } from '../contexts/FontSizeContext';
+
+interface CompositeFieldPanelProps {
+  field: ProtocolField;
+  onChange: (updates: Partial<ProtocolField>) => void;
+}
+
+// Expanded row of a group, array or TLV field: structure settings plus a nested field editor
+export default function CompositeFieldPanel({ field, onChange }: CompositeFieldPanelProps) {
+  const { fontSize } = useFontSize();
+  const [savedProtocols, setSavedProtocols] = useState<SavedProtocol[]>([]);
+
+  useEffect(() => {
+    if (field.kind !== 'group') return;
+    protocolService.listProtocols()
+      .then(setSavedProtocols)
+      .catch(err => console.error('Failed to load protocols:', err));
+  }, [field.kind]);
+
+  const repeat: RepeatRule = field.repeat ?? { mode: 'untilEnd' };
+  const labelStyle = { color: '#858585', fontSize: fontSize - 1 };
+
+  const handleEmbedProtocol = (protocolId?: string) => {
+    const protocol = savedProtocols.find(p => p.id === protocolId);
+    onChange({
+      protocolRef: protocol?.id,
+      fields: protocol ? cloneFieldsWithNewIds(protocol.fields) : field.fields,
+    });
+  };
+
+  const handleRepeatModeChange = (mode: RepeatRule['mode']) => {
+    if (mode === 'fixed') {
+      onChange({ repeat: { mode, count: field.fields?.length ?? 0 } });
+    } else if (mode === 'fromField') {
+      onChange({ repeat: { mode, field: '' } });
+    } else {
+      onChange({ repeat: { mode } });
+    }
+  };
+
+  if (field.kind === 'tlv') {
+    return (
+      <div style={{ padding: '4px 0 4px 24px' }}>
+        <TlvItemEditor field={field} onChange={onChange} />
+      </div>
+    );
+  }
+
+  return (
+    <div style={{ display: 'flex', flexDirection: 'column', gap: 8, padding: '4px 0 4px 24px' }}>
+      <div style={{ display: 'flex', alignItems: 'center', gap: 8 }}>
+        {field.kind === 'group' ? (
+          <>
+            <span style={labelStyle}>Embed protocol</span>
+            <Select
+              size="small"
+              allowClear
+              value={field.protocolRef}
+              onChange={handleEmbedProtocol}
+              placeholder="Inline fields"
+              style={{ width: 220 }}
+              options={savedProtocols.map(p => ({ value: p.id, label: p.name }))}
+            />
+          </>
+        ) : (
+          <>
+            <span style={labelStyle}>Repeat</span>
+            <Select
+              size="small"
+              value={repeat.mode}
+              onChange={handleRepeatModeChange}
+              style={{ width: 140 }}
+              options={[
+                { value: 'fixed', label: 'Fixed count' },
+                { value: 'fromField', label: 'Count from field' },
+                { value: 'untilEnd', label: 'Until end' },
+              ]}
+            />
+            {repeat.mode === 'fixed' && (
+              <InputNumber
+                size="small"
+                min={0}
+                value={repeat.count}
+                onChange={(count) => onChange({ repeat: { mode: 'fixed', count: count ?? 0 } })}
+                style={{ width: 80 }}
+              />
+            )}
+            {repeat.mode === 'fromField' && (
+              <Input
+                size="small"
+                value={repeat.field}
+                onChange={(e) => onChange({ repeat: { mode: 'fromField', field: e.target.value } })}
+                placeholder="Earlier field name"
+                style={{ width: 180 }}
+              />
+            )}
+          </>
+        )}
+      </div>
+      <div style={{ height: 220 }}>
+        <ProtocolFieldEditor
+          fields={field.fields ?? []}
+          onChange={(fields) => onChange({ fields })}
+        />
+      </div>
+    </div>
+  );
+}
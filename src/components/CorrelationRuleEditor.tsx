@@ -0,0 +1,81 @@
+import { AutoComplete, InputNumber, Select, Space, Switch, Tooltip } from 'antd';
+import type { ProtocolField } from '../types/protocol-simple';
+import type { CorrelationRule, Protocol } from '../services/protocolService';
+
+interface CorrelationRuleEditorProps {
+  rule?: CorrelationRule;
+  onChange: (rule?: CorrelationRule) => void;
+  fields: ProtocolField[]; // fields of the protocol being edited
+  protocolId?: string;
+  protocols: Protocol[]; // saved protocols responses may be decoded with
+}
+
+const HELP = 'Match each response to its request by a field both carry (e.g. a transaction id), '
+  + 'so several requests can be in flight on one connection. Responses must be delimited by fixed sizes or length fields.';
+
+// Scalar field names anywhere in the tree, offered as correlation fields
+const fieldNames = (fields: ProtocolField[]): string[] =>
+  fields.flatMap(field => (field.kind && field.kind !== 'scalar' ? fieldNames(field.fields ?? []) : [field.name]));
+
+const nameOptions = (fields: ProtocolField[]) =>
+  [...new Set(fieldNames(fields).filter(Boolean))].map(name => ({ value: name }));
+
+// Request/response correlation settings of a protocol
+export default function CorrelationRuleEditor({ rule, onChange, fields, protocolId, protocols }: CorrelationRuleEditorProps) {
+  const responseFields = rule?.responseProtocolId && rule.responseProtocolId !== protocolId
+    ? protocols.find(p => p.id === rule.responseProtocolId)?.fields ?? []
+    : fields;
+
+  const update = (updates: Partial<CorrelationRule>) => rule && onChange({ ...rule, ...updates });
+
+  return (
+    <Space wrap size="small">
+      <Tooltip title={HELP}>
+        <Switch
+          size="small"
+          checked={!!rule}
+          onChange={(checked) => onChange(checked ? { requestField: '', responseField: '', timeoutMs: 5000 } : undefined)}
+        />
+      </Tooltip>
+      {rule && (
+        <>
+          <AutoComplete
+            size="small"
+            value={rule.requestField}
+            onChange={(requestField) => update({ requestField })}
+            options={nameOptions(fields)}
+            placeholder="Request field"
+            style={{ width: 150 }}
+          />
+          <span style={{ color: '#858585' }}>=</span>
+          <Select
+            size="small"
+            value={rule.responseProtocolId ?? ''}
+            onChange={(id) => update({ responseProtocolId: id || undefined })}
+            style={{ width: 160 }}
+            options={[
+              { value: '', label: 'This protocol' },
+              ...protocols.filter(p => p.id !== protocolId).map(p => ({ value: p.id, label: p.name })),
+            ]}
+          />
+          <AutoComplete
+            size="small"
+            value={rule.responseField}
+            onChange={(responseField) => update({ responseField })}
+            options={nameOptions(responseFields)}
+            placeholder="Response field"
+            style={{ width: 150 }}
+          />
+          <InputNumber
+            size="small"
+            min={1}
+            value={rule.timeoutMs}
+            onChange={(timeoutMs) => update({ timeoutMs: timeoutMs ?? 5000 })}
+            suffix="ms"
+            style={{ width: 110 }}
+          />
+        </>
+      )}
+    </Space>
+  );
+}
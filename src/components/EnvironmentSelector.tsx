@@ -0,0 +1,252 @@
+import { useEffect, useState } from 'react';
+import { Button, Empty, Input, Modal, Popconfirm, Select, Space, Tooltip, message } from 'antd';
+import { DeleteOutlined, PlusOutlined, SettingOutlined } from '@ant-design/icons';
+import { environmentService } from '../services/environmentService';
+import type { Environment, EnvironmentVariable } from '../services/environmentService';
+
+const BUILTINS_HELP = 'Use {{name}} in payloads and field values. Built-ins: {{$timestamp}}, {{$timestamp_ms}}, '
+  + '{{$random_u8}}, {{$random_u16}}, {{$random_u32}}, {{$seq}} (per connection)';
+
+interface EnvironmentSelectorProps {
+  // Called after the active environment or its variables change
+  onChange?: () => void;
+}
+
+// Active environment picker with a dialog to edit environments and their variables
+export default function EnvironmentSelector({ onChange }: EnvironmentSelectorProps) {
+  const [environments, setEnvironments] = useState<Environment[]>([]);
+  const [managerOpen, setManagerOpen] = useState(false);
+  const [editingId, setEditingId] = useState<string | null>(null);
+  const [draftName, setDraftName] = useState('');
+  const [draftVariables, setDraftVariables] = useState<EnvironmentVariable[]>([]);
+  const [sessionVariables, setSessionVariables] = useState<EnvironmentVariable[]>([]);
+
+  const loadEnvironments = async () => {
+    try {
+      const result = await environmentService.listEnvironments();
+      setEnvironments(result);
+      return result;
+    } catch (error) {
+      message.error('Failed to load environments: ' + error);
+      return [];
+    }
+  };
+
+  useEffect(() => {
+    loadEnvironments();
+  }, []);
+
+  const active = environments.find(env => env.active);
+
+  const handleSelect = async (id: string | undefined) => {
+    try {
+      await environmentService.setActiveEnvironment(id ?? null);
+      await loadEnvironments();
+      onChange?.();
+    } catch (error) {
+      message.error('Failed to switch environment: ' + error);
+    }
+  };
+
+  const startEditing = (env: Environment) => {
+    setEditingId(env.id);
+    setDraftName(env.name);
+    setDraftVariables(env.variables);
+  };
+
+  const handleAdd = async () => {
+    try {
+      const env = await environmentService.createEnvironment(`Environment ${environments.length + 1}`);
+      await loadEnvironments();
+      startEditing(env);
+    } catch (error) {
+      message.error('Failed to create environment: ' + error);
+    }
+  };
+
+  const handleSave = async () => {
+    if (!editingId) return;
+    if (!draftName.trim()) {
+      message.error('Environment name is required');
+      return;
+    }
+    try {
+      const variables = draftVariables.filter(v => v.key.trim()).map(v => ({ key: v.key.trim(), value: v.value }));
+      await environmentService.updateEnvironment(editingId, draftName.trim(), variables);
+      await loadEnvironments();
+      message.success('Environment saved');
+      onChange?.();
+    } catch (error) {
+      message.error('Failed to save environment: ' + error);
+    }
+  };
+
+  const handleDelete = async (id: string) => {
+    try {
+      await environmentService.deleteEnvironment(id);
+      const result = await loadEnvironments();
+      if (editingId === id) {
+        if (result.length > 0) {
+          startEditing(result[0]);
+        } else {
+          setEditingId(null);
+        }
+      }
+      onChange?.();
+    } catch (error) {
+      message.error('Failed to delete environment: ' + error);
+    }
+  };
+
+  const openManager = () => {
+    const first = active ?? environments[0];
+    if (first) {
+      startEditing(first);
+    } else {
+      setEditingId(null);
+    }
+    environmentService.listSessionVariables()
+      .then(setSessionVariables)
+      .catch(() => setSessionVariables([]));
+    setManagerOpen(true);
+  };
+
+  const handleClearSession = async () => {
+    try {
+      await environmentService.clearSessionVariables();
+      setSessionVariables([]);
+      onChange?.();
+    } catch (error) {
+      message.error('Failed to clear session variables: ' + error);
+    }
+  };
+
+  const updateVariable = (index: number, updates: Partial<EnvironmentVariable>) =>
+    setDraftVariables(prev => prev.map((v, i) => (i === index ? { ...v, ...updates } : v)));
+
+  return (
+    <>
+      <Space size={4}>
+        <Tooltip title={BUILTINS_HELP}>
+          <Select
+            value={active?.id}
+            onChange={handleSelect}
+            placeholder="No environment"
+            allowClear
+            size="small"
+            style={{ width: 160 }}
+            options={environments.map(env => ({ value: env.id, label: env.name }))}
+          />
+        </Tooltip>
+        <Button size="small" type="text" icon={<SettingOutlined />} onClick={openManager} style={{ color: '#cccccc' }} />
+      </Space>
+
+      <Modal
+        title="Environments"
+        open={managerOpen}
+        onCancel={() => setManagerOpen(false)}
+        footer={null}
+        width={760}
+      >
+        <div style={{ display: 'flex', gap: 16, minHeight: 320 }}>
+          <div style={{ width: 200, borderRight: '1px solid #3e3e42', paddingRight: 12 }}>
+            {environments.map(env => (
+              <div
+                key={env.id}
+                onClick={() => startEditing(env)}
+                style={{
+                  display: 'flex',
+                  justifyContent: 'space-between',
+                  alignItems: 'center',
+                  padding: '4px 8px',
+                  cursor: 'pointer',
+                  borderRadius: 4,
+                  background: env.id === editingId ? '#37373d' : 'transparent',
+                  color: env.active ? '#ff6c37' : '#cccccc',
+                }}
+              >
+                <span>{env.name}</span>
+                <Popconfirm
+                  title="Delete this environment?"
+                  onConfirm={() => handleDelete(env.id)}
+                  okText="Yes"
+                  cancelText="No"
+                >
+                  <Button size="small" type="text" danger icon={<DeleteOutlined />} onClick={e => e.stopPropagation()} />
+                </Popconfirm>
+              </div>
+            ))}
+            <Button size="small" type="dashed" icon={<PlusOutlined />} onClick={handleAdd} style={{ width: '100%', marginTop: 8 }}>
+              Add
+            </Button>
+          </div>
+
+          <div style={{ flex: 1 }}>
+            {editingId === null ? (
+              <Empty description="No environment selected" />
+            ) : (
+              <Space direction="vertical" style={{ width: '100%' }}>
+                <Input
+                  value={draftName}
+                  onChange={e => setDraftName(e.target.value)}
+                  placeholder="Environment name"
+                  style={{ maxWidth: 300 }}
+                />
+                {draftVariables.map((variable, index) => (
+                  <Space key={index} style={{ width: '100%' }}>
+                    <Input
+                      value={variable.key}
+                      onChange={e => updateVariable(index, { key: e.target.value })}
+                      placeholder="Name"
+                      style={{ width: 180, fontFamily: 'monospace' }}
+                    />
+                    <Input
+                      value={variable.value}
+                      onChange={e => updateVariable(index, { value: e.target.value })}
+                      placeholder="Value"
+                      style={{ width: 280, fontFamily: 'monospace' }}
+                    />
+                    <Button
+                      size="small"
+                      type="text"
+                      danger
+                      icon={<DeleteOutlined />}
+                      onClick={() => setDraftVariables(prev => prev.filter((_, i) => i !== index))}
+                    />
+                  </Space>
+                ))}
+                <Space>
+                  <Button
+                    size="small"
+                    type="dashed"
+                    icon={<PlusOutlined />}
+                    onClick={() => setDraftVariables(prev => [...prev, { key: '', value: '' }])}
+                  >
+                    Add variable
+                  </Button>
+                  <Button size="small" type="primary" onClick={handleSave}>
+                    Save
+                  </Button>
+                </Space>
+                <span style={{ color: '#858585', fontSize: 12 }}>{BUILTINS_HELP}</span>
+              </Space>
+            )}
+          </div>
+        </div>
+        {sessionVariables.length > 0 && (
+          <div style={{ borderTop: '1px solid #3e3e42', marginTop: 12, paddingTop: 12 }}>
+            <Space style={{ width: '100%', justifyContent: 'space-between' }}>
+              <span style={{ color: '#cccccc' }}>Session variables (set by protocol scripts, override the environment)</span>
+              <Button size="small" onClick={handleClearSession}>Clear</Button>
+            </Space>
+            {sessionVariables.map(variable => (
+              <div key={variable.key} style={{ fontFamily: 'monospace', color: '#cccccc', marginTop: 4 }}>
+                {variable.key} = {variable.value}
+              </div>
+            ))}
+          </div>
+        )}
+      </Modal>
+    </>
+  );
+}
@@ -0,0 +1,74 @@
+import { Button, Input, Select, Space } from 'antd';
+import { useState } from 'react';
+import type { FieldCondition, ProtocolField } from '../types/protocol-simple';
+import { formatHex, parseNumber } from '../utils/protocolFields';
+
+interface FieldConditionEditorProps {
+  condition?: FieldCondition;
+  earlierFields: ProtocolField[];
+  onChange: (condition?: FieldCondition) => void;
+}
+
+const conditionOperand = (condition?: FieldCondition): string => {
+  if (!condition) return '';
+  if (condition.op === 'equals') return formatHex(condition.value);
+  if (condition.op === 'bitmaskSet') return formatHex(condition.mask);
+  return condition.values.map(formatHex).join(', ');
+};
+
+// Popover content for editing the condition of a field
+export default function FieldConditionEditor({ condition, earlierFields, onChange }: FieldConditionEditorProps) {
+  const [field, setField] = useState(condition?.field ?? '');
+  const [op, setOp] = useState<FieldCondition['op']>(condition?.op ?? 'equals');
+  const [operand, setOperand] = useState(conditionOperand(condition));
+
+  const apply = () => {
+    if (!field) return;
+    if (op === 'inSet') {
+      const values = operand.split(',').map(parseNumber).filter((v): v is number => v !== undefined);
+      onChange({ op, field, values });
+      return;
+    }
+    const num = parseNumber(operand);
+    if (num === undefined) return;
+    onChange(op === 'equals' ? { op, field, value: num } : { op, field, mask: num });
+  };
+
+  return (
+    <Space direction="vertical" size="small" style={{ width: 260 }}>
+      <Select
+        size="small"
+        value={field || undefined}
+        onChange={setField}
+        placeholder="Earlier field"
+        style={{ width: '100%' }}
+        options={earlierFields.map(f => ({ value: f.name, label: f.name }))}
+      />
+      <Select
+        size="small"
+        value={op}
+        onChange={setOp}
+        style={{ width: '100%' }}
+        options={[
+          { value: 'equals', label: 'Equals' },
+          { value: 'bitmaskSet', label: 'Bitmask set' },
+          { value: 'inSet', label: 'In set' },
+        ]}
+      />
+      <Input
+        size="small"
+        value={operand}
+        onChange={(e) => setOperand(e.target.value)}
+        placeholder={op === 'inSet' ? 'e.g. 0x0F, 0x10' : 'e.g. 0x10 or 16'}
+      />
+      <Space>
+        <Button size="small" type="primary" onClick={apply} disabled={!field}>
+          Apply
+        </Button>
+        <Button size="small" onClick={() => onChange(undefined)} disabled={!condition}>
+          Clear
+        </Button>
+      </Space>
+    </Space>
+  );
+}
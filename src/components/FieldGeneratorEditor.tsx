@@ -0,0 +1,110 @@
+import { Button, Input, Select, Space } from 'antd';
+import { useState } from 'react';
+import type { FieldGenerator } from '../types/protocol-simple';
+import { formatHex, parseNumber } from '../utils/protocolFields';
+
+interface FieldGeneratorEditorProps {
+  generator?: FieldGenerator;
+  onChange: (generator?: FieldGenerator) => void;
+}
+
+type GeneratorType = FieldGenerator['type'];
+
+// Short description shown in place of the value of a generated field
+export const describeGenerator = (generator: FieldGenerator): string => {
+  switch (generator.type) {
+    case 'counter':
+      return `counter · from ${generator.start} step ${generator.step}${generator.wrap !== undefined ? ` wrap ${generator.wrap}` : ''}`;
+    case 'unixTime':
+      return `unix time · ${generator.unit === 'ms' ? 'milliseconds' : 'seconds'}`;
+    case 'randomBytes':
+      return 'random bytes';
+    case 'randomRange':
+      return `random · ${generator.min}..${generator.max}`;
+  }
+};
+
+// Popover content for choosing how a field's value is generated
+export default function FieldGeneratorEditor({ generator, onChange }: FieldGeneratorEditorProps) {
+  const [type, setType] = useState<GeneratorType>(generator?.type ?? 'counter');
+  const [start, setStart] = useState(generator?.type === 'counter' ? String(generator.start) : '1');
+  const [step, setStep] = useState(generator?.type === 'counter' ? String(generator.step) : '1');
+  const [wrap, setWrap] = useState(generator?.type === 'counter' && generator.wrap !== undefined ? formatHex(generator.wrap) : '');
+  const [unit, setUnit] = useState<'s' | 'ms'>(generator?.type === 'unixTime' ? generator.unit : 's');
+  const [min, setMin] = useState(generator?.type === 'randomRange' ? String(generator.min) : '0');
+  const [max, setMax] = useState(generator?.type === 'randomRange' ? String(generator.max) : '255');
+
+  const build = (): FieldGenerator | undefined => {
+    switch (type) {
+      case 'counter': {
+        const startValue = parseNumber(start);
+        const stepValue = parseNumber(step);
+        if (startValue === undefined || stepValue === undefined) return undefined;
+        const wrapValue = parseNumber(wrap);
+        return { type, start: startValue, step: stepValue, ...(wrapValue !== undefined ? { wrap: wrapValue } : {}) };
+      }
+      case 'unixTime':
+        return { type, unit };
+      case 'randomBytes':
+        return { type };
+      case 'randomRange': {
+        const minValue = parseNumber(min);
+        const maxValue = parseNumber(max);
+        if (minValue === undefined || maxValue === undefined || minValue > maxValue) return undefined;
+        return { type, min: minValue, max: maxValue };
+      }
+    }
+  };
+
+  const next = build();
+
+  return (
+    <Space direction="vertical" size="small" style={{ width: 240 }}>
+      <Select
+        size="small"
+        value={type}
+        onChange={setType}
+        style={{ width: '100%' }}
+        options={[
+          { value: 'counter', label: 'Counter' },
+          { value: 'unixTime', label: 'Unix time' },
+          { value: 'randomBytes', label: 'Random bytes' },
+          { value: 'randomRange', label: 'Random in range' },
+        ]}
+      />
+      {type === 'counter' && (
+        <>
+          <Input size="small" prefix="Start" value={start} onChange={(e) => setStart(e.target.value)} />
+          <Input size="small" prefix="Step" value={step} onChange={(e) => setStep(e.target.value)} />
+          <Input size="small" prefix="Wrap" value={wrap} onChange={(e) => setWrap(e.target.value)} placeholder="field maximum" />
+        </>
+      )}
+      {type === 'unixTime' && (
+        <Select
+          size="small"
+          value={unit}
+          onChange={setUnit}
+          style={{ width: '100%' }}
+          options={[
+            { value: 's', label: 'Seconds' },
+            { value: 'ms', label: 'Milliseconds' },
+          ]}
+        />
+      )}
+      {type === 'randomRange' && (
+        <>
+          <Input size="small" prefix="Min" value={min} onChange={(e) => setMin(e.target.value)} />
+          <Input size="small" prefix="Max" value={max} onChange={(e) => setMax(e.target.value)} />
+        </>
+      )}
+      <Space>
+        <Button size="small" type="primary" onClick={() => next && onChange(next)} disabled={!next}>
+          Apply
+        </Button>
+        <Button size="small" onClick={() => onChange(undefined)} disabled={!generator}>
+          Clear
+        </Button>
+      </Space>
+    </Space>
+  );
+}
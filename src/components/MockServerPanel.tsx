@@ -0,0 +1,115 @@
+import { useState, useEffect } from 'react';
+import { Button, Card, Input, InputNumber, Space, Tag, Tooltip, message } from 'antd';
+import { PlayCircleOutlined, StopOutlined } from '@ant-design/icons';
+import { mockServerService } from '../services/mockServerService';
+
+// localStorage key
+const MOCK_SERVER_KEY = 'tcp_sender_mock_server';
+const MAX_LOG_LINES = 200;
+
+const HELP = 'Runs tcp-test-server in mock mode: rules in a JSON file match requests by protocol, field values '
+  + 'or byte patterns and reply with a protocol, hex or text. Protocols named by the rules are taken from your saved protocols.';
+
+const loadSettings = () => {
+  try {
+    const saved = localStorage.getItem(MOCK_SERVER_KEY);
+    if (saved) {
+      // Older settings also held the server executable, which now ships with the app
+      const { configPath, port } = JSON.parse(saved) as { configPath: string; port: number };
+      return { configPath, port };
+    }
+  } catch {
+    // fall through to defaults
+  }
+  return { configPath: '', port: 18080 };
+};
+
+// Start and stop the mock test server and follow its output
+export default function MockServerPanel() {
+  const [settings, setSettings] = useState(loadSettings);
+  const [runningPort, setRunningPort] = useState<number | null>(null);
+  const [log, setLog] = useState<string[]>([]);
+
+  useEffect(() => {
+    mockServerService.status().then(status => setRunningPort(status.running ? status.port ?? null : null)).catch(() => {});
+    const unlisten = mockServerService.onLog((line) => {
+      setLog(prev => [...prev.slice(-(MAX_LOG_LINES - 1)), line]);
+    });
+    // The server exits by itself when its rules are invalid
+    const timer = setInterval(() => {
+      mockServerService.status().then(status => setRunningPort(status.running ? status.port ?? null : null)).catch(() => {});
+    }, 2000);
+    return () => {
+      unlisten.then(fn => fn());
+      clearInterval(timer);
+    };
+  }, []);
+
+  const update = (updates: Partial<typeof settings>) => {
+    const next = { ...settings, ...updates };
+    setSettings(next);
+    localStorage.setItem(MOCK_SERVER_KEY, JSON.stringify(next));
+  };
+
+  const handleStart = async () => {
+    if (!settings.configPath.trim()) {
+      message.warning('Enter the rules file');
+      return;
+    }
+    try {
+      setLog([]);
+      const status = await mockServerService.start(settings);
+      setRunningPort(status.port ?? settings.port);
+    } catch (error) {
+      message.error('Failed to start mock server: ' + error);
+    }
+  };
+
+  const handleStop = async () => {
+    try {
+      await mockServerService.stop();
+      setRunningPort(null);
+    } catch (error) {
+      message.error('Failed to stop mock server: ' + error);
+    }
+  };
+
+  return (
+    <Card
+      size="small"
+      title={<Tooltip title={HELP}><span>Mock server</span></Tooltip>}
+      extra={runningPort !== null ? <Tag color="green">127.0.0.1:{runningPort}</Tag> : <Tag>stopped</Tag>}
+      style={{ marginBottom: 16 }}
+    >
+      <Space direction="vertical" style={{ width: '100%' }}>
+        <Space.Compact style={{ width: '100%' }}>
+          <Input
+            value={settings.configPath}
+            onChange={(e) => update({ configPath: e.target.value })}
+            placeholder="Path to mock rules (.json)"
+            disabled={runningPort !== null}
+          />
+          <InputNumber
+            value={settings.port}
+            min={1}
+            max={65535}
+            onChange={(port) => update({ port: port ?? 18080 })}
+            prefix="Port"
+            disabled={runningPort !== null}
+            style={{ width: 140 }}
+          />
+          {runningPort !== null ? (
+            <Button danger icon={<StopOutlined />} onClick={handleStop}>Stop</Button>
+          ) : (
+            <Button type="primary" icon={<PlayCircleOutlined />} onClick={handleStart}>Start</Button>
+          )}
+        </Space.Compact>
+        {log.length > 0 && (
+          <pre style={{ maxHeight: 160, overflow: 'auto', margin: 0, fontSize: 12, color: '#cccccc', background: '#1e1e1e', padding: 8 }}>
+            {log.join('\n')}
+          </pre>
+        )}
+      </Space>
+    </Card>
+  );
+}
@@ -1,8 +1,53 @@
-import { Button, Input, InputNumber, Table, Popconfirm, Checkbox, Tooltip } from 'antd';
-import { PlusOutlined, DeleteOutlined, ArrowUpOutlined, ArrowDownOutlined, PlusCircleOutlined } from '@ant-design/icons';
-import type { ProtocolField } from '../types/protocol-simple';
+import { Button, Input, InputNumber, Table, Popconfirm, Checkbox, Tooltip, Popover, Select } from 'antd';
+import { PlusOutlined, DeleteOutlined, ArrowUpOutlined, ArrowDownOutlined, PlusCircleOutlined, BranchesOutlined, ColumnWidthOutlined, FontColorsOutlined, ThunderboltOutlined } from '@ant-design/icons';
+import type { FieldKind, LengthOf, ProtocolField, TextSpec, VarintFormat } from '../types/protocol-simple';
 import { useState, useRef, useEffect, useCallback, memo } from 'react';
 import { useFontSize } from '../contexts/FontSizeContext';
+import CompositeFieldPanel from './CompositeFieldPanel';
+import FieldConditionEditor from './FieldConditionEditor';
+import FieldGeneratorEditor, { describeGenerator } from './FieldGeneratorEditor';
+import TextOptionsEditor from './TextOptionsEditor';
+import { textByteLength } from '../utils/protocolFields';
+
+const COMPOSITE_KINDS: FieldKind[] = ['group', 'array', 'tlv'];
+
+const isComposite = (field: ProtocolField) => COMPOSITE_KINDS.includes(field.kind ?? 'scalar');
+
+const isVarint = (field: ProtocolField) => field.kind === 'varint';
+
+const isText = (field: ProtocolField) =>
+  field.isVariable ? field.valueType === 'text' : field.valueFormat === 'text';
+
+// Fixed-size numbers and varints can hold an auto length
+const canHoldLength = (field: ProtocolField) => isVarint(field) || (!isComposite(field) && !field.isVariable);
+
+const VARINT_FORMAT_OPTIONS: { value: VarintFormat; label: string }[] = [
+  { value: 'leb128', label: 'LEB128' },
+  { value: 'zigzag', label: 'Zigzag' },
+  { value: 'mqttLength', label: 'MQTT' },
+];
+
+// Select value for a length of the following fields (field names are user text)
+const FOLLOWING = '__following__';
+
+const describeLengthOf = (lengthOf: LengthOf) =>
+  lengthOf.mode === 'following' ? 'auto · length of following fields' : `auto · length of "${lengthOf.field}"`;
+
+// Short description of a group, array or TLV field shown in the Value column
+const describeComposite = (field: ProtocolField): string => {
+  const count = field.fields?.length ?? 0;
+  if (field.kind === 'group') {
+    return `Group · ${count} field${count === 1 ? '' : 's'}`;
+  }
+  if (field.kind === 'tlv') {
+    return `TLV · ${count} item${count === 1 ? '' : 's'}`;
+  }
+  const repeat = field.repeat ?? { mode: 'untilEnd' };
+  const rule = repeat.mode === 'fixed' ? `×${repeat.count}`
+    : repeat.mode === 'fromField' ? `count from "${repeat.field}"`
+    : 'until end';
+  return `Array · ${rule} · ${count} element${count === 1 ? '' : 's'}`;
+};
 
 interface ProtocolFieldEditorProps {
   fields: ProtocolField[];
@@ -249,6 +294,34 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
     onChangeRef.current([...currentFields, newField]);
   };
 
+  const addCompositeField = (kind: FieldKind) => {
+    const currentFields = fieldsRef.current;
+    const newField: ProtocolField = {
+      id: `field_${Date.now()}`,
+      name: `${kind === 'tlv' ? 'TLV' : kind[0].toUpperCase() + kind.slice(1)}${currentFields.length + 1}`,
+      enabled: true,
+      kind,
+      value: '',
+      fields: [],
+      repeat: kind === 'array' ? { mode: 'untilEnd' } : undefined,
+      tlv: kind === 'tlv' ? { tagWidth: 1, lengthWidth: 1, endianness: 'big' } : undefined,
+    };
+    onChangeRef.current([...currentFields, newField]);
+  };
+
+  const addVarintField = () => {
+    const currentFields = fieldsRef.current;
+    const newField: ProtocolField = {
+      id: `field_${Date.now()}`,
+      name: `Varint${currentFields.length + 1}`,
+      enabled: true,
+      kind: 'varint',
+      varintFormat: 'leb128',
+      value: '0',
+    };
+    onChangeRef.current([...currentFields, newField]);
+  };
+
   const updateField = useCallback((id: string, updates: Partial<ProtocolField>) => {
     onChangeRef.current(
       fieldsRef.current.map((field) =>
@@ -258,13 +331,13 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
   }, []);
 
   // 计算值的实际字节长度
-  const calculateByteLength = (value: string, valueType: 'text' | 'hex', isVariable: boolean): number => {
+  const calculateByteLength = (value: string, valueType: 'text' | 'hex', isVariable: boolean, text?: TextSpec): number => {
     if (!value) return 0;
 
     if (isVariable) {
       if (valueType === 'text') {
-        // Text 类型：直接返回字符数（UTF-8 字节数）
-        return new TextEncoder().encode(value).length;
+        // Text 类型：按字段编码计算字节数（含结束符）
+        return textByteLength(value, text);
       } else {
         // Hex 类型：返回 hex 字符串长度 / 2
         const cleanHex = value.replace(/\s/g, '');
@@ -275,6 +348,12 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
   };
 
   const handleValueChange = useCallback((id: string, inputValue: string, field: ProtocolField) => {
+    // {{variable}} placeholders are resolved at send time, keep them as typed
+    if (inputValue.includes('{{')) {
+      setEditingFields(prev => ({ ...prev, [id]: inputValue }));
+      updateField(id, { value: inputValue });
+      return;
+    }
     // For non-variable fields
     if (!field.isVariable) {
       const format = field.valueFormat ?? 'dec';
@@ -283,6 +362,10 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
         const filtered = inputValue.replace(/[^0-9]/g, '');
         setEditingFields(prev => ({ ...prev, [id]: filtered }));
         updateField(id, { value: filtered });
+      } else if (format === 'text') {
+        // TEXT 模式：接受任意输入，长度固定
+        setEditingFields(prev => ({ ...prev, [id]: inputValue }));
+        updateField(id, { value: inputValue });
       } else if (format === 'hex') {
         // HEX 模式：只接受 hex 字符
         const filtered = inputValue.replace(/[^0-9A-Fa-f\s]/g, '');
@@ -312,7 +395,7 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
       } else {
         // Text type - accept any input
         setEditingFields(prev => ({ ...prev, [id]: inputValue }));
-        const actualLength = calculateByteLength(inputValue, 'text', true);
+        const actualLength = calculateByteLength(inputValue, 'text', true, field.text);
         updateField(id, { value: inputValue, length: actualLength });
       }
     }
@@ -342,7 +425,7 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
       }
 
       // 更新长度为新类型的实际字节长度
-      const newLength = calculateByteLength(newValue, newType, true);
+      const newLength = calculateByteLength(newValue, newType, true, field.text);
       updateField(id, { valueType: newType, value: newValue, length: newLength });
     }
   }, []);
@@ -351,9 +434,9 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
     const field = fieldsRef.current.find(f => f.id === id);
     if (!field || field.isVariable) return;
 
-    // Handle undefined: default to 'dec', so dec → hex → bin → dec toggle works
+    // Handle undefined: default to 'dec', so dec → hex → bin → text → dec toggle works
     const currentFormat = field.valueFormat ?? 'dec';
-    const newFormat = currentFormat === 'dec' ? 'hex' : currentFormat === 'hex' ? 'bin' : 'dec';
+    const newFormat = currentFormat === 'dec' ? 'hex' : currentFormat === 'hex' ? 'bin' : currentFormat === 'bin' ? 'text' : 'dec';
     let newValue = field.value;
 
     if (newFormat === 'hex') {
@@ -362,9 +445,14 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
     } else if (newFormat === 'bin') {
       // Hex → Bin
       newValue = hexToBin(field.value, field.length || 1);
+    } else if (newFormat === 'text') {
+      // Bin → Text（去掉右侧补零）
+      newValue = hexToText(binToHex(field.value, field.length || 1)).replace(/\0+$/, '');
     } else {
-      // Bin → Dec
-      newValue = binToDec(field.value);
+      // Text → Dec（按 UTF-8 右侧补零到字段长度）
+      const length = field.length || 1;
+      const hex = textToHex(field.value).replace(/\s/g, '').padEnd(length * 2, '0').slice(0, length * 2);
+      newValue = BigInt('0x' + hex).toString();
     }
 
     updateField(id, { valueFormat: newFormat, value: newValue });
@@ -429,7 +517,17 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
       dataIndex: 'isVariable',
       key: 'isVariable',
       width: 100,
-      render: (isVariable: boolean, record: ProtocolField) => (
+      render: (isVariable: boolean, record: ProtocolField) => isComposite(record) ? (
+        <span style={{ color: '#858585', fontSize: fontSize, padding: 0 }}>{record.kind}</span>
+      ) : isVarint(record) ? (
+        <Select
+          size="small"
+          value={record.varintFormat ?? 'leb128'}
+          onChange={(varintFormat) => updateField(record.id, { varintFormat })}
+          options={VARINT_FORMAT_OPTIONS}
+          style={{ width: '100%' }}
+        />
+      ) : (
         <Checkbox
           checked={isVariable || false}
           onChange={(e) => {
@@ -449,13 +547,15 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
                   hexValue = decToHex(record.value, record.length || 1).replace(/\s/g, '');
                 } else if (format === 'hex') {
                   hexValue = record.value;
+                } else if (format === 'text') {
+                  hexValue = textToHex(record.value).replace(/\s/g, '');
                 } else {
                   // BIN → HEX
                   hexValue = binToHex(record.value, record.length || 1).replace(/\s/g, '');
                 }
                 const textValue = hexToText(hexValue);
                 updates.value = textValue;
-                updates.length = calculateByteLength(textValue, 'text', true);
+                updates.length = calculateByteLength(textValue, 'text', true, record.text);
               } else {
                 updates.value = '';
                 updates.length = 0;
@@ -482,7 +582,9 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
       key: 'length',
       width: 100,
       render: (length: number | undefined, record: ProtocolField) => (
-        record.isVariable ? (
+        isComposite(record) || isVarint(record) ? (
+          <span style={{ color: '#858585', fontSize: fontSize, padding: 0 }}>-</span>
+        ) : record.isVariable ? (
           <span style={{ color: '#858585', fontSize: fontSize, padding: 0 }}>{length || 0}</span>
         ) : (
           <InputNumber
@@ -503,6 +605,8 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
                 } else if (format === 'hex') {
                   // HEX 模式：前面补零或截断
                   updates.value = adjustHexToLength(record.value, newLength);
+                } else if (format === 'text') {
+                  // TEXT 模式：按文本选项补齐，值不变
                 } else {
                   // BIN 模式：调整位数
                   const cleanBin = record.value.replace(/\s/g, '');
@@ -531,13 +635,39 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
       dataIndex: 'value',
       key: 'value',
       render: (text: string, record: ProtocolField) => {
+        if (isComposite(record)) {
+          return (
+            <span style={{ color: '#858585', fontSize: fontSize, padding: 0 }}>{describeComposite(record)}</span>
+          );
+        }
+        if (record.lengthOf) {
+          return (
+            <span style={{ color: '#858585', fontSize: fontSize, padding: 0 }}>{describeLengthOf(record.lengthOf)}</span>
+          );
+        }
+        if (record.generator) {
+          return (
+            <span style={{ color: '#858585', fontSize: fontSize, padding: 0 }}>{describeGenerator(record.generator)}</span>
+          );
+        }
+        if (isVarint(record)) {
+          return (
+            <Input
+              value={text}
+              onChange={(e) => updateField(record.id, { value: e.target.value.replace(/[^0-9-]/g, '') })}
+              placeholder={record.varintFormat === 'zigzag' ? 'Enter signed integer' : 'Enter integer'}
+              className="protocol-field-input"
+              style={{ flex: 1, padding: '12px 8px', height: 24 }}
+            />
+          );
+        }
         const format = record.valueFormat ?? 'dec';
         const displayValue = editingFields[record.id] !== undefined
           ? editingFields[record.id]
           : (() => {
               if (!record.isVariable) {
-                if (format === 'dec') {
-                  // DEC 模式直接显示
+                if (format === 'dec' || format === 'text') {
+                  // DEC / TEXT 模式直接显示
                   return text;
                 } else if (format === 'hex') {
                   // HEX 模式格式化显示
@@ -570,6 +700,7 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
           ? (record.valueType === 'text' ? 'Enter text' : 'Enter hex (e.g., 01 02 03)')
           : (format === 'dec' ? `Enter decimal (0-${getMaxDecValue(record.length || 1)})`
             : format === 'hex' ? 'Enter hex (e.g., 01 02 03)'
+            : format === 'text' ? `Enter text (${record.length || 1} bytes)`
             : 'Enter binary (e.g., 10101010)');
 
         return (
@@ -614,9 +745,53 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
                   border: '1px solid #3e3e42',
                 }}
               >
-                {(record.valueFormat ?? 'dec') === 'dec' ? 'DEC' : (record.valueFormat ?? 'dec') === 'hex' ? 'HEX' : 'BIN'}
+                {(record.valueFormat ?? 'dec') === 'dec' ? 'DEC' : (record.valueFormat ?? 'dec') === 'hex' ? 'HEX' : (record.valueFormat ?? 'dec') === 'bin' ? 'BIN' : 'TXT'}
               </Button>
             )}
+            {!record.isVariable && format !== 'text' && (record.length ?? 1) > 1 && (
+              <Tooltip title={record.endianness === 'little' ? 'Little-endian' : 'Big-endian'}>
+                <Button
+                  type="text"
+                  size="small"
+                  onClick={() => updateField(record.id, {
+                    endianness: record.endianness === 'little' ? undefined : 'little',
+                  })}
+                  style={{
+                    padding: '0 6px',
+                    height: 24,
+                    fontSize: fontSize-2,
+                    color: record.endianness === 'little' ? '#ff6c37' : '#858585',
+                    border: '1px solid #3e3e42',
+                  }}
+                >
+                  {record.endianness === 'little' ? 'LE' : 'BE'}
+                </Button>
+              </Tooltip>
+            )}
+            {isText(record) && (
+              <Popover
+                trigger="click"
+                title="Text options"
+                content={
+                  <TextOptionsEditor
+                    text={record.text}
+                    fixed={!record.isVariable}
+                    onChange={(text) => updateField(record.id, record.isVariable
+                      ? { text, length: calculateByteLength(record.value, 'text', true, text) }
+                      : { text })}
+                  />
+                }
+              >
+                <Tooltip title={`Encoding: ${record.text?.encoding ?? 'utf8'}`}>
+                  <Button
+                    type="text"
+                    size="small"
+                    icon={<FontColorsOutlined />}
+                    style={{ color: record.text ? '#ff6c37' : '#858585' }}
+                  />
+                </Tooltip>
+              </Popover>
+            )}
           </div>
         );
       },
@@ -624,7 +799,7 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
     {
       title: 'Actions',
       key: 'action',
-      width: 120,
+      width: 200,
       render: (_: any, record: ProtocolField, index: number) => {
         const currentFields = fieldsRef.current;
         return (
@@ -636,6 +811,80 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
                 style={{ margin: '0px', paddingRight: '4px'}}
               />
             </Tooltip>
+            <Popover
+              trigger="click"
+              title="Include only when"
+              content={
+                <FieldConditionEditor
+                  key={JSON.stringify(record.condition ?? null)}
+                  condition={record.condition}
+                  earlierFields={currentFields.slice(0, index)}
+                  onChange={(condition) => updateField(record.id, { condition })}
+                />
+              }
+            >
+              <Tooltip title={record.condition ? `Condition on "${record.condition.field}"` : 'Condition'}>
+                <Button
+                  type="text"
+                  size="small"
+                  icon={<BranchesOutlined />}
+                  style={{ color: record.condition ? '#ff6c37' : '#858585' }}
+                />
+              </Tooltip>
+            </Popover>
+            <Popover
+              trigger="click"
+              title="Auto length"
+              content={
+                <Select
+                  size="small"
+                  allowClear
+                  value={record.lengthOf?.mode === 'field' ? record.lengthOf.field : record.lengthOf && FOLLOWING}
+                  onChange={(target?: string) => updateField(record.id, {
+                    lengthOf: !target ? undefined
+                      : target === FOLLOWING ? { mode: 'following' }
+                      : { mode: 'field', field: target },
+                  })}
+                  placeholder="Manual value"
+                  style={{ width: 220 }}
+                  options={[
+                    { value: FOLLOWING, label: 'All following fields' },
+                    ...currentFields.slice(index + 1).map(f => ({ value: f.name, label: `Field "${f.name}"` })),
+                  ]}
+                />
+              }
+            >
+              <Tooltip title={record.lengthOf ? describeLengthOf(record.lengthOf) : 'Auto length'}>
+                <Button
+                  type="text"
+                  size="small"
+                  icon={<ColumnWidthOutlined />}
+                  disabled={!canHoldLength(record)}
+                  style={{ color: record.lengthOf ? '#ff6c37' : canHoldLength(record) ? '#858585' : '#555555' }}
+                />
+              </Tooltip>
+            </Popover>
+            <Popover
+              trigger="click"
+              title="Generate value"
+              content={
+                <FieldGeneratorEditor
+                  key={JSON.stringify(record.generator ?? null)}
+                  generator={record.generator}
+                  onChange={(generator) => updateField(record.id, { generator })}
+                />
+              }
+            >
+              <Tooltip title={record.generator ? describeGenerator(record.generator) : 'Generator'}>
+                <Button
+                  type="text"
+                  size="small"
+                  icon={<ThunderboltOutlined />}
+                  disabled={isComposite(record)}
+                  style={{ color: record.generator ? '#ff6c37' : isComposite(record) ? '#555555' : '#858585' }}
+                />
+              </Tooltip>
+            </Popover>
             <Tooltip title="Insert Below">
               <Button
                 type="text"
@@ -707,6 +956,38 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
         >
           Add Field
         </Button>
+        <Button
+          icon={<PlusOutlined />}
+          onClick={() => addCompositeField('group')}
+          size="small"
+          style={{ marginLeft: 8 }}
+        >
+          Add Group
+        </Button>
+        <Button
+          icon={<PlusOutlined />}
+          onClick={() => addCompositeField('array')}
+          size="small"
+          style={{ marginLeft: 8 }}
+        >
+          Add Array
+        </Button>
+        <Button
+          icon={<PlusOutlined />}
+          onClick={() => addCompositeField('tlv')}
+          size="small"
+          style={{ marginLeft: 8 }}
+        >
+          Add TLV
+        </Button>
+        <Button
+          icon={<PlusOutlined />}
+          onClick={addVarintField}
+          size="small"
+          style={{ marginLeft: 8 }}
+        >
+          Add Varint
+        </Button>
       </div>
       <div
         style={{
@@ -731,7 +1012,7 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
           <div style={{ width: 100, padding: '8px', color: '#cccccc', fontSize, fontWeight: 500 }}>Variable</div>
           <div style={{ width: 80, padding: '8px', color: '#cccccc', fontSize, fontWeight: 500 }}>Length</div>
           <div style={{ flex: 1, padding: '8px', color: '#cccccc', fontSize, fontWeight: 500 }}>Value</div>
-          <div style={{ width: 120, padding: '8px', color: '#cccccc', fontSize, fontWeight: 500 }}>Actions</div>
+          <div style={{ width: 176, padding: '8px', color: '#cccccc', fontSize, fontWeight: 500 }}>Actions</div>
         </div>
         <div
           ref={listContainerRef}
@@ -757,6 +1038,15 @@ function ProtocolFieldEditor({ fields, onChange }: ProtocolFieldEditorProps) {
               background: '#252526',
             }}
             tableLayout="fixed"
+            expandable={{
+              rowExpandable: isComposite,
+              expandedRowRender: (record: ProtocolField) => (
+                <CompositeFieldPanel
+                  field={record}
+                  onChange={(updates) => updateField(record.id, updates)}
+                />
+              ),
+            }}
             onRow={(record, index) => ({
               style: {
                 opacity: record.enabled === false ? 0.4 : 1,
@@ -0,0 +1,182 @@
+import { useEffect, useState } from 'react';
+import { Button, Empty, Modal, Popconfirm, Select, Space, Table, Tag, message } from 'antd';
+import { protocolService } from '../services/protocolService';
+import type { Protocol, PropertyChange, ProtocolRevisionSummary, RevisionDiff } from '../services/protocolService';
+
+interface ProtocolHistoryModalProps {
+  protocol: Protocol | null;
+  onClose: () => void;
+  onRestored: () => void;
+}
+
+const KIND_COLORS = { added: 'green', removed: 'red', modified: 'orange' } as const;
+
+const formatValue = (value: unknown) =>
+  value === undefined ? '—' : typeof value === 'string' ? value : JSON.stringify(value);
+
+function ChangeList({ changes }: { changes: PropertyChange[] }) {
+  return (
+    <>
+      {changes.map(change => (
+        <div key={change.key} style={{ fontSize: 12 }}>
+          <span style={{ color: '#858585' }}>{change.key}: </span>
+          <span style={{ color: '#f48771', textDecoration: 'line-through' }}>{formatValue(change.before)}</span>
+          {' → '}
+          <span style={{ color: '#89d185' }}>{formatValue(change.after)}</span>
+        </div>
+      ))}
+    </>
+  );
+}
+
+// Save history of a protocol: pick a revision to see what it changed, or restore it
+export default function ProtocolHistoryModal({ protocol, onClose, onRestored }: ProtocolHistoryModalProps) {
+  const [revisions, setRevisions] = useState<ProtocolRevisionSummary[]>([]);
+  const [selected, setSelected] = useState<number | null>(null);
+  const [base, setBase] = useState<number | null>(null);
+  const [diff, setDiff] = useState<RevisionDiff | null>(null);
+
+  const loadRevisions = async (protocolId: string) => {
+    try {
+      const result = await protocolService.listRevisions(protocolId);
+      setRevisions(result);
+      select(result[0]?.revision ?? null, result);
+    } catch (error) {
+      message.error('Failed to load history: ' + error);
+    }
+  };
+
+  // Compare against the previous revision by default
+  const select = (revision: number | null, list = revisions) => {
+    setSelected(revision);
+    setBase(list.find(r => revision !== null && r.revision < revision)?.revision ?? null);
+  };
+
+  useEffect(() => {
+    setDiff(null);
+    if (protocol) {
+      loadRevisions(protocol.id);
+    } else {
+      setRevisions([]);
+      setSelected(null);
+    }
+  }, [protocol?.id]);
+
+  useEffect(() => {
+    if (!protocol || selected === null || base === null) {
+      setDiff(null);
+      return;
+    }
+    protocolService.diffRevisions(protocol.id, base, selected)
+      .then(setDiff)
+      .catch(error => message.error('Failed to compare revisions: ' + error));
+  }, [protocol?.id, selected, base]);
+
+  const handleRestore = async () => {
+    if (!protocol || selected === null) return;
+    try {
+      await protocolService.restoreRevision(protocol.id, selected);
+      message.success(`Restored revision ${selected}`);
+      onRestored();
+      loadRevisions(protocol.id);
+    } catch (error) {
+      message.error('Failed to restore revision: ' + error);
+    }
+  };
+
+  const latest = revisions[0]?.revision;
+
+  return (
+    <Modal
+      title={protocol ? `History: ${protocol.name}` : 'History'}
+      open={protocol !== null}
+      onCancel={onClose}
+      footer={null}
+      width={860}
+    >
+      {revisions.length === 0 ? (
+        <Empty description="No revisions yet" />
+      ) : (
+        <div style={{ display: 'flex', gap: 16, height: '55vh' }}>
+          <div style={{ width: 300, overflow: 'auto' }}>
+            <Table
+              size="small"
+              rowKey="revision"
+              pagination={false}
+              dataSource={revisions}
+              onRow={(record) => ({ onClick: () => select(record.revision), style: { cursor: 'pointer' } })}
+              rowClassName={(record) => (record.revision === selected ? 'ant-table-row-selected' : '')}
+              columns={[
+                { title: '#', dataIndex: 'revision', key: 'revision', width: 48 },
+                {
+                  title: 'Saved',
+                  dataIndex: 'createdAt',
+                  key: 'createdAt',
+                  render: (date: string, record: ProtocolRevisionSummary) => (
+                    <div>
+                      <div style={{ color: '#cccccc', fontSize: 12 }}>{new Date(date).toLocaleString()}</div>
+                      <div style={{ color: '#858585', fontSize: 12 }}>{record.name} · {record.fieldCount} fields</div>
+                    </div>
+                  ),
+                },
+              ]}
+            />
+          </div>
+
+          <div style={{ flex: 1, overflow: 'auto' }}>
+            {selected !== null && (
+              <Space style={{ marginBottom: 12 }}>
+                <span style={{ color: '#858585' }}>Compare</span>
+                <Select
+                  size="small"
+                  value={base ?? undefined}
+                  placeholder="Base"
+                  onChange={setBase}
+                  style={{ width: 110 }}
+                  options={revisions
+                    .filter(r => r.revision !== selected)
+                    .map(r => ({ value: r.revision, label: `Revision ${r.revision}` }))}
+                />
+                <span style={{ color: '#858585' }}>with revision {selected}</span>
+                <Popconfirm
+                  title={`Restore revision ${selected}? It will be saved as a new revision.`}
+                  onConfirm={handleRestore}
+                  okText="Restore"
+                  cancelText="Cancel"
+                  disabled={selected === latest}
+                >
+                  <Button size="small" disabled={selected === latest}>Restore</Button>
+                </Popconfirm>
+              </Space>
+            )}
+
+            {base === null ? (
+              <Empty description="First revision" />
+            ) : diff && diff.properties.length === 0 && diff.fields.length === 0 ? (
+              <Empty description="No changes" />
+            ) : diff && (
+              <>
+                {diff.properties.length > 0 && (
+                  <div style={{ marginBottom: 12 }}>
+                    <ChangeList changes={diff.properties} />
+                  </div>
+                )}
+                {diff.fields.map(field => (
+                  <div key={`${field.kind}:${field.path}`} style={{ marginBottom: 8 }}>
+                    <Tag color={KIND_COLORS[field.kind]}>{field.kind}</Tag>
+                    <span style={{ color: '#cccccc', fontFamily: 'monospace' }}>{field.path}</span>
+                    {field.changes && (
+                      <div style={{ paddingLeft: 16 }}>
+                        <ChangeList changes={field.changes} />
+                      </div>
+                    )}
+                  </div>
+                ))}
+              </>
+            )}
+          </div>
+        </div>
+      )}
+    </Modal>
+  );
+}
@@ -0,0 +1,57 @@
+import { useEffect, useState } from 'react';
+import { Input, Modal, Space, Typography } from 'antd';
+import type { ProtocolHooks } from '../services/protocolService';
+
+const { Text } = Typography;
+
+const PRE_SEND_HELP = '`payload` holds the encoded request; assign to it to change what is sent. '
+  + 'Example: payload = payload .. unhex(hex(hmac_sha256(vars.key, payload)))';
+const POST_RECEIVE_HELP = '`response` and `request` hold the raw bytes, `fields` the decoded response. '
+  + 'Raise an error to fail the exchange. Example: assert(fields.status == "0", "login failed"); vars.token = fields.token';
+const SHARED_HELP = 'Both read and write `vars`; values written there override environment variables in {{placeholders}} '
+  + 'until cleared. Helpers: hex, unhex, sha256, hmac_sha256, string.pack/unpack.';
+
+interface ProtocolHooksModalProps {
+  open: boolean;
+  hooks?: ProtocolHooks;
+  onClose: () => void;
+  onChange: (hooks?: ProtocolHooks) => void;
+}
+
+// Lua pre-send and post-receive scripts of a protocol
+export default function ProtocolHooksModal({ open, hooks, onClose, onChange }: ProtocolHooksModalProps) {
+  const [preSend, setPreSend] = useState('');
+  const [postReceive, setPostReceive] = useState('');
+
+  useEffect(() => {
+    if (open) {
+      setPreSend(hooks?.preSend ?? '');
+      setPostReceive(hooks?.postReceive ?? '');
+    }
+  }, [open, hooks]);
+
+  const handleOk = () => {
+    const next: ProtocolHooks = {
+      ...(preSend.trim() ? { preSend } : {}),
+      ...(postReceive.trim() ? { postReceive } : {}),
+    };
+    onChange(next.preSend || next.postReceive ? next : undefined);
+    onClose();
+  };
+
+  const editorStyle = { fontFamily: 'monospace', fontSize: 12 };
+
+  return (
+    <Modal title="Scripts (Lua)" open={open} onOk={handleOk} onCancel={onClose} okText="Apply" width={720}>
+      <Space direction="vertical" style={{ width: '100%' }}>
+        <Text style={{ color: '#cccccc' }}>Pre-send</Text>
+        <Input.TextArea value={preSend} onChange={(e) => setPreSend(e.target.value)} rows={6} style={editorStyle} />
+        <Text style={{ color: '#858585', fontSize: 12 }}>{PRE_SEND_HELP}</Text>
+        <Text style={{ color: '#cccccc' }}>Post-receive</Text>
+        <Input.TextArea value={postReceive} onChange={(e) => setPostReceive(e.target.value)} rows={6} style={editorStyle} />
+        <Text style={{ color: '#858585', fontSize: 12 }}>{POST_RECEIVE_HELP}</Text>
+        <Text style={{ color: '#858585', fontSize: 12 }}>{SHARED_HELP}</Text>
+      </Space>
+    </Modal>
+  );
+}
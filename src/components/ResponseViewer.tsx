@@ -1,9 +1,13 @@
 import { Button, Empty, message } from 'antd';
 import { CopyOutlined } from '@ant-design/icons';
 import { useFontSize } from '../contexts/FontSizeContext';
+import type { DecodedField, ProtocolSuggestion } from '../services/protocolService';
 
 interface ResponseViewerProps {
   data: string;
+  decoded?: DecodedField[]; // fields decoded with the selected protocol
+  suggestions?: ProtocolSuggestion[]; // what the data looks like, when not decoded
+  onDecodeAs?: (protocolId: string) => void;
 }
 
 interface HexLine {
@@ -79,9 +83,45 @@ function formatHexData(data: string): HexLine[] {
   return lines;
 }
 
-export default function ResponseViewer({ data }: ResponseViewerProps) {
+// One row per decoded field, nested members indented below their parent
+function DecodedFieldRows({ fields, depth }: { fields: DecodedField[]; depth: number }) {
+  return (
+    <>
+      {fields.map((field, index) => (
+        <div key={`${field.offset}-${index}`}>
+          <div style={{ display: 'flex', gap: '8px', paddingLeft: depth * 16 }}>
+            <span style={{ color: '#858585', userSelect: 'none' }}>{field.offset.toString(16).padStart(4, '0')}</span>
+            <span style={{ color: '#9cdcfe' }}>{field.name}</span>
+            <span style={{ color: '#27AE60', wordBreak: 'break-all' }}>{field.value}</span>
+          </div>
+          {field.fields && <DecodedFieldRows fields={field.fields} depth={depth + 1} />}
+        </div>
+      ))}
+    </>
+  );
+}
+
+// Values parsed by a well-known protocol, one row each
+function DetectedValueRows({ values }: { values: Record<string, unknown> }) {
+  return (
+    <>
+      {Object.entries(values).map(([name, value]) => (
+        <div key={name} style={{ display: 'flex', gap: '8px' }}>
+          <span style={{ color: '#9cdcfe' }}>{name}</span>
+          <span style={{ color: '#27AE60', wordBreak: 'break-all' }}>
+            {typeof value === 'string' ? value : JSON.stringify(value)}
+          </span>
+        </div>
+      ))}
+    </>
+  );
+}
+
+export default function ResponseViewer({ data, decoded, suggestions, onDecodeAs }: ResponseViewerProps) {
   const { fontSize } = useFontSize();
-  const textDisplay = hexToText(data);
+  const detected = decoded ? undefined : suggestions?.find(s => !s.protocolId);
+  const savedMatches = decoded ? [] : (suggestions ?? []).filter(s => s.protocolId);
+  const textDisplay = decoded ? JSON.stringify(decoded, null, 2) : hexToText(data);
   const hexLines = formatHexData(data);
   const isHexEmpty = !data || hexLines.length === 0;
   const isTextEmpty = !textDisplay;
@@ -133,7 +173,7 @@ export default function ResponseViewer({ data }: ResponseViewerProps) {
           justifyContent: 'space-between',
           alignItems: 'center',
         }}>
-          <span style={{ color: '#cccccc', fontSize, fontWeight: 500 }}>Decoded Text</span>
+          <span style={{ color: '#cccccc', fontSize, fontWeight: 500 }}>{decoded ? 'Decoded Fields' : 'Decoded Text'}</span>
           <Button
             type="text"
             size="small"
@@ -157,23 +197,74 @@ export default function ResponseViewer({ data }: ResponseViewerProps) {
              borderRadius: '4px 4px 0 0'
           }}
         >
+          {/* Suggestion: what the data looks like and saved protocols that decode it */}
+          {(detected || savedMatches.length > 0) && (
+            <div style={{
+              padding: '4px 8px',
+              marginBottom: 8,
+              fontSize: fontSize - 2,
+              color: '#cccccc',
+              background: '#1e3a5f',
+              borderRadius: 4,
+              display: 'flex',
+              flexWrap: 'wrap',
+              alignItems: 'center',
+              gap: '4px 12px',
+            }}>
+              {detected && <span>Looks like <b>{detected.name}</b> — {detected.summary}</span>}
+              {savedMatches.map(s => (
+                <Button
+                  key={s.protocolId}
+                  type="link"
+                  size="small"
+                  title={s.summary}
+                  onClick={() => onDecodeAs?.(s.protocolId!)}
+                  style={{ padding: 0, height: 'auto', fontSize: fontSize - 2 }}
+                >
+                  Decode as {s.name}
+                </Button>
+              ))}
+            </div>
+          )}
           {isTextEmpty ? (
             <Empty
               description="No data"
               image={Empty.PRESENTED_IMAGE_SIMPLE}
               style={{ color: '#858585', marginTop: 40 }}
             />
-          ) : (
-            <pre style={{
+          ) : decoded ? (
+            <div style={{
               fontFamily: 'JetBrains Mono, Consolas, Monaco, monospace',
               fontSize,
-              color: '#27AE60',
-              margin: 0,
-              whiteSpace: 'pre-wrap',
-              wordBreak: 'break-all',
+              lineHeight: 1.6,
             }}>
-              {textDisplay}
-            </pre>
+              <DecodedFieldRows fields={decoded} depth={0} />
+            </div>
+          ) : (
+            <>
+              {detected?.values && (
+                <div style={{
+                  fontFamily: 'JetBrains Mono, Consolas, Monaco, monospace',
+                  fontSize,
+                  lineHeight: 1.6,
+                  paddingBottom: 8,
+                  marginBottom: 8,
+                  borderBottom: '1px solid #2d2d30',
+                }}>
+                  <DetectedValueRows values={detected.values} />
+                </div>
+              )}
+              <pre style={{
+                fontFamily: 'JetBrains Mono, Consolas, Monaco, monospace',
+                fontSize,
+                color: '#27AE60',
+                margin: 0,
+                whiteSpace: 'pre-wrap',
+                wordBreak: 'break-all',
+              }}>
+                {textDisplay}
+              </pre>
+            </>
           )}
         </div>
 
@@ -189,7 +280,7 @@ export default function ResponseViewer({ data }: ResponseViewerProps) {
 		     paddingBottom: 20,
 			   borderRadius: '0 0 4px 4px'
           }}>
-		     {isHexEmpty ? ('') : (<span>Total: {textDisplay.length} chars</span>)}
+		     {isHexEmpty ? ('') : decoded ? (<span>Total: {decoded.length} fields</span>) : (<span>Total: {textDisplay.length} chars</span>)}
           </div>
         )}
       </div>
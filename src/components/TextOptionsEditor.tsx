@@ -0,0 +1,84 @@
+import { Input, Select, Space } from 'antd';
+import type { TextSpec } from '../types/protocol-simple';
+
+interface TextOptionsEditorProps {
+  text?: TextSpec;
+  fixed: boolean; // padding only applies to fixed-length fields
+  onChange: (text: TextSpec) => void;
+}
+
+const rowStyle = { display: 'flex', alignItems: 'center', justifyContent: 'space-between', gap: 8 };
+
+// Popover content for the encoding, padding and terminator of a text field
+export default function TextOptionsEditor({ text, fixed, onChange }: TextOptionsEditorProps) {
+  const spec: TextSpec = text ?? {};
+  const update = (updates: Partial<TextSpec>) => onChange({ ...spec, ...updates });
+
+  return (
+    <Space direction="vertical" size="small" style={{ width: 240 }}>
+      <div style={rowStyle}>
+        <span>Encoding</span>
+        <Select
+          size="small"
+          value={spec.encoding ?? 'utf8'}
+          onChange={(encoding) => update({ encoding })}
+          style={{ width: 140 }}
+          options={[
+            { value: 'utf8', label: 'UTF-8' },
+            { value: 'ascii', label: 'ASCII' },
+            { value: 'utf16le', label: 'UTF-16LE' },
+            { value: 'utf16be', label: 'UTF-16BE' },
+            { value: 'gbk', label: 'GBK' },
+            { value: 'latin1', label: 'Latin-1' },
+          ]}
+        />
+      </div>
+      <div style={rowStyle}>
+        <span>Terminator</span>
+        <Select
+          size="small"
+          value={spec.terminator ?? 'none'}
+          onChange={(terminator) => update({ terminator })}
+          style={{ width: 140 }}
+          options={[
+            { value: 'none', label: 'None' },
+            { value: 'nul', label: 'NUL' },
+            { value: 'crlf', label: 'CRLF' },
+            { value: 'pascal', label: 'Length prefix' },
+          ]}
+        />
+      </div>
+      {fixed && (
+        <>
+          <div style={rowStyle}>
+            <span>Pad byte</span>
+            <Input
+              size="small"
+              key={spec.padByte ?? 0}
+              defaultValue={(spec.padByte ?? 0).toString(16).toUpperCase().padStart(2, '0')}
+              onBlur={(e) => {
+                const padByte = parseInt(e.target.value, 16);
+                if (!isNaN(padByte) && padByte >= 0 && padByte <= 0xFF) update({ padByte });
+              }}
+              maxLength={2}
+              style={{ width: 140 }}
+            />
+          </div>
+          <div style={rowStyle}>
+            <span>Pad side</span>
+            <Select
+              size="small"
+              value={spec.padSide ?? 'right'}
+              onChange={(padSide) => update({ padSide })}
+              style={{ width: 140 }}
+              options={[
+                { value: 'right', label: 'Right' },
+                { value: 'left', label: 'Left' },
+              ]}
+            />
+          </div>
+        </>
+      )}
+    </Space>
+  );
+}
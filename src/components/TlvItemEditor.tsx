@@ -0,0 +1,206 @@
+import { Button, Checkbox, Input, Select } from 'antd';
+import { DeleteOutlined, PlusOutlined } from '@ant-design/icons';
+import type { ProtocolField, TlvSpec } from '../types/protocol-simple';
+import { formatHex, parseNumber } from '../utils/protocolFields';
+import { useFontSize } from '../contexts/FontSizeContext';
+
+interface TlvItemEditorProps {
+  field: ProtocolField;
+  onChange: (updates: Partial<ProtocolField>) => void;
+}
+
+let itemCounter = 0;
+
+const WIDTH_OPTIONS = [1, 2, 4].map(w => ({ value: w, label: `${w} byte${w === 1 ? '' : 's'}` }));
+
+// Expanded row of a TLV field: header layout, tag names and the list of items
+export default function TlvItemEditor({ field, onChange }: TlvItemEditorProps) {
+  const { fontSize } = useFontSize();
+  const spec: TlvSpec = field.tlv ?? { tagWidth: 1, lengthWidth: 1 };
+  const tags = spec.tags ?? [];
+  const items = field.fields ?? [];
+  const labelStyle = { color: '#858585', fontSize: fontSize - 1 };
+
+  const tagName = (tag?: number) =>
+    tag === undefined ? '' : tags.find(t => t.tag === tag)?.name ?? formatHex(tag);
+
+  const updateSpec = (updates: Partial<TlvSpec>) => {
+    const next = { ...spec, ...updates };
+    // Item names follow the tag registry
+    const renamed = updates.tags
+      ? items.map(item => ({
+          ...item,
+          name: item.tag === undefined ? item.name : next.tags?.find(t => t.tag === item.tag)?.name ?? formatHex(item.tag),
+        }))
+      : items;
+    onChange({ tlv: next, fields: renamed });
+  };
+
+  const updateTag = (index: number, updates: Partial<{ tag: number; name: string }>) => {
+    updateSpec({ tags: tags.map((t, i) => (i === index ? { ...t, ...updates } : t)) });
+  };
+
+  const updateItem = (id: string, updates: Partial<ProtocolField>) => {
+    onChange({ fields: items.map(item => (item.id === id ? { ...item, ...updates } : item)) });
+  };
+
+  const addItem = () => {
+    const tag = tags[0]?.tag ?? 0;
+    const item: ProtocolField = {
+      id: `field_${Date.now()}_${itemCounter++}`,
+      name: tagName(tag),
+      tag,
+      isVariable: true,
+      valueType: 'hex',
+      enabled: true,
+      value: '',
+    };
+    onChange({ fields: [...items, item] });
+  };
+
+  return (
+    <div style={{ display: 'flex', flexDirection: 'column', gap: 8 }}>
+      <div style={{ display: 'flex', alignItems: 'center', gap: 8 }}>
+        <span style={labelStyle}>Tag</span>
+        <Select
+          size="small"
+          value={spec.tagWidth}
+          onChange={(tagWidth) => updateSpec({ tagWidth })}
+          style={{ width: 90 }}
+          options={WIDTH_OPTIONS}
+        />
+        <span style={labelStyle}>Length</span>
+        <Select
+          size="small"
+          value={spec.lengthWidth}
+          onChange={(lengthWidth) => updateSpec({ lengthWidth })}
+          style={{ width: 90 }}
+          options={WIDTH_OPTIONS}
+        />
+        <Select
+          size="small"
+          value={spec.endianness ?? 'big'}
+          onChange={(endianness) => updateSpec({ endianness })}
+          style={{ width: 120 }}
+          options={[
+            { value: 'big', label: 'Big endian' },
+            { value: 'little', label: 'Little endian' },
+          ]}
+        />
+        <Checkbox
+          checked={spec.lengthIncludesHeader ?? false}
+          onChange={(e) => updateSpec({ lengthIncludesHeader: e.target.checked })}
+          style={labelStyle}
+        >
+          Length includes header
+        </Checkbox>
+      </div>
+
+      <span style={labelStyle}>Tag names</span>
+      {tags.map((t, index) => (
+        <div key={index} style={{ display: 'flex', alignItems: 'center', gap: 8 }}>
+          <Input
+            size="small"
+            key={`${index}-${t.tag}`}
+            defaultValue={formatHex(t.tag)}
+            onBlur={(e) => {
+              const tag = parseNumber(e.target.value);
+              if (tag !== undefined) updateTag(index, { tag });
+            }}
+            style={{ width: 90 }}
+          />
+          <Input
+            size="small"
+            value={t.name}
+            onChange={(e) => updateTag(index, { name: e.target.value })}
+            placeholder="Name"
+            style={{ width: 180 }}
+          />
+          <Button
+            type="text"
+            size="small"
+            danger
+            icon={<DeleteOutlined />}
+            onClick={() => updateSpec({ tags: tags.filter((_, i) => i !== index) })}
+          />
+        </div>
+      ))}
+      <div>
+        <Button
+          size="small"
+          icon={<PlusOutlined />}
+          onClick={() => updateSpec({ tags: [...tags, { tag: tags.length + 1, name: `Tag${tags.length + 1}` }] })}
+        >
+          Add Tag Name
+        </Button>
+      </div>
+
+      <span style={labelStyle}>Items</span>
+      {items.map(item => (
+        <div key={item.id} style={{ display: 'flex', alignItems: 'center', gap: 8 }}>
+          <Checkbox
+            checked={item.enabled ?? true}
+            onChange={(e) => updateItem(item.id, { enabled: e.target.checked })}
+          />
+          <Select
+            size="small"
+            showSearch
+            value={item.tag}
+            onChange={(tag) => updateItem(item.id, { tag, name: tagName(tag) })}
+            style={{ width: 180 }}
+            options={[
+              ...tags.map(t => ({ value: t.tag, label: `${formatHex(t.tag)} ${t.name}` })),
+              ...(item.tag !== undefined && !tags.some(t => t.tag === item.tag)
+                ? [{ value: item.tag, label: formatHex(item.tag) }]
+                : []),
+            ]}
+            popupRender={(menu) => (
+              <>
+                {menu}
+                <Input
+                  size="small"
+                  placeholder="Other tag, e.g. 0x1F"
+                  onKeyDown={(e) => e.stopPropagation()}
+                  onPressEnter={(e) => {
+                    const tag = parseNumber(e.currentTarget.value);
+                    if (tag !== undefined) updateItem(item.id, { tag, name: tagName(tag) });
+                  }}
+                  style={{ margin: 4, width: 'calc(100% - 8px)' }}
+                />
+              </>
+            )}
+          />
+          <Select
+            size="small"
+            value={item.valueType ?? 'hex'}
+            onChange={(valueType) => updateItem(item.id, { valueType })}
+            style={{ width: 80 }}
+            options={[
+              { value: 'hex', label: 'Hex' },
+              { value: 'text', label: 'Text' },
+            ]}
+          />
+          <Input
+            size="small"
+            value={item.value}
+            onChange={(e) => updateItem(item.id, { value: e.target.value })}
+            placeholder={item.valueType === 'text' ? 'Text value' : 'Hex value, e.g. 01 02'}
+            style={{ flex: 1 }}
+          />
+          <Button
+            type="text"
+            size="small"
+            danger
+            icon={<DeleteOutlined />}
+            onClick={() => onChange({ fields: items.filter(i => i.id !== item.id) })}
+          />
+        </div>
+      ))}
+      <div>
+        <Button size="small" icon={<PlusOutlined />} onClick={addItem}>
+          Add Item
+        </Button>
+      </div>
+    </div>
+  );
+}
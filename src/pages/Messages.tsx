@@ -1,13 +1,17 @@
 import { useState, useEffect, useRef } from 'react';
 import { Button, Select, Space, Input, Tabs, message as antMessage, Modal } from 'antd';
-import { SendOutlined } from '@ant-design/icons';
-import { connectionService } from '../services/connectionService';
+import { SendOutlined, VideoCameraOutlined, DownloadOutlined, ImportOutlined } from '@ant-design/icons';
+import { connectionService, CaptureStatus } from '../services/connectionService';
+import { captureService, CaptureImport, CaptureMessage } from '../services/captureService';
 import { messageService } from '../services/messageService';
-import { protocolService, Protocol as SavedProtocol } from '../services/protocolService';
+import { protocolService, Protocol as SavedProtocol, DecodedField, ProtocolSuggestion } from '../services/protocolService';
 import ProtocolFieldEditor from '../components/ProtocolFieldEditor';
 import ProtocolHexPreview from '../components/ProtocolHexPreview';
 import ResponseViewer from '../components/ResponseViewer';
+import EnvironmentSelector from '../components/EnvironmentSelector';
+import CaptureImportModal from '../components/CaptureImportModal';
 import type { ProtocolField } from '../types/protocol-simple';
+import { cloneFieldsWithNewIds, toSavedField } from '../utils/protocolFields';
 import { useFontSize } from '../contexts/FontSizeContext';
 
 const { TextArea } = Input;
@@ -160,6 +164,8 @@ interface TabData {
   responseTime: number;
   protocolFields: ProtocolField[];
   selectedProtocolPreset?: string;
+  responseProtocol?: string; // saved protocol used to decode the response
+  responseCorrelation?: string; // correlation value of the response, hex
 }
 
 export default function Messages() {
@@ -272,8 +278,124 @@ export default function Messages() {
   const connectionId = `conn_${activeTab}`;
   const currentTab = tabs.find(tab => tab.key === activeTab)!;
 
+  // Protocol fields are encoded by the backend (groups, arrays, embedded protocols and {{variables}})
+  const [protocolHex, setProtocolHex] = useState('');
+  const [environmentVersion, setEnvironmentVersion] = useState(0);
+  useEffect(() => {
+    if (currentTab.requestMode !== 'protocol') return;
+    let cancelled = false;
+    protocolService.encodeFields(currentTab.protocolFields, connectionId, true)
+      .then(hex => { if (!cancelled) setProtocolHex(hex); })
+      .catch(() => { if (!cancelled) setProtocolHex(''); });
+    return () => { cancelled = true; };
+  }, [currentTab.protocolFields, currentTab.requestMode, connectionId, environmentVersion]);
+
+  // Response fields decoded with the chosen protocol
+  const [decodedResponse, setDecodedResponse] = useState<DecodedField[] | undefined>();
+  useEffect(() => {
+    const protocolId = currentTab.responseProtocol;
+    if (!protocolId || !currentTab.responseData) {
+      setDecodedResponse(undefined);
+      return;
+    }
+    let cancelled = false;
+    protocolService.decodeData(protocolId, currentTab.responseData)
+      .then(fields => { if (!cancelled) setDecodedResponse(fields); })
+      .catch(() => { if (!cancelled) setDecodedResponse(undefined); });
+    return () => { cancelled = true; };
+  }, [currentTab.responseData, currentTab.responseProtocol]);
+
+  // Guesses at what an undecoded response is
+  const [responseSuggestions, setResponseSuggestions] = useState<ProtocolSuggestion[]>([]);
+  useEffect(() => {
+    if (currentTab.responseProtocol || !currentTab.responseData) {
+      setResponseSuggestions([]);
+      return;
+    }
+    let cancelled = false;
+    protocolService.detect(currentTab.responseData)
+      .then(suggestions => { if (!cancelled) setResponseSuggestions(suggestions); })
+      .catch(() => { if (!cancelled) setResponseSuggestions([]); });
+    return () => { cancelled = true; };
+  }, [currentTab.responseData, currentTab.responseProtocol]);
+
+  // Traffic recording of this tab's connection, kept by the backend across reconnects
+  const [captureStatus, setCaptureStatus] = useState<CaptureStatus | undefined>();
+  useEffect(() => {
+    let cancelled = false;
+    connectionService.getRecording(connectionId)
+      .then(status => { if (!cancelled) setCaptureStatus(status); })
+      .catch(() => { if (!cancelled) setCaptureStatus(undefined); });
+    return () => { cancelled = true; };
+  }, [connectionId, currentTab.isConnected, currentTab.responseData]);
+
+  const handleToggleRecording = async () => {
+    try {
+      setCaptureStatus(await connectionService.setRecording(connectionId, !captureStatus?.recording));
+    } catch (error) {
+      antMessage.error(`Recording failed: ${error}`);
+    }
+  };
+
+  const handleExportCapture = async () => {
+    try {
+      const packets = await connectionService.exportCapture(connectionId);
+      antMessage.success(`Exported ${packets} packets`);
+    } catch (error) {
+      // User cancelled or error occurred
+      if (error !== 'No file selected') {
+        antMessage.error(`Failed to export capture: ${error}`);
+      }
+    }
+  };
+
+  // Conversations of an imported PCAP/PCAPNG file
+  const [importedCapture, setImportedCapture] = useState<CaptureImport | null>(null);
+
+  const handleImportCapture = async () => {
+    try {
+      setImportedCapture(await captureService.importCapture());
+    } catch (error) {
+      // User cancelled or error occurred
+      if (error !== 'No file selected') {
+        antMessage.error(`Failed to import capture: ${error}`);
+      }
+    }
+  };
+
+  // One hex message tab per captured message, aimed at the server it was sent to
+  const handleOpenCaptureMessages = (messages: CaptureMessage[], server: string) => {
+    const separator = server.lastIndexOf(':');
+    const host = server.slice(0, separator).replace(/^\[|\]$/g, '');
+    const port = server.slice(separator + 1);
+    const maxKey = tabs.reduce((max, tab) => {
+      const numKey = parseInt(tab.key, 10);
+      return !isNaN(numKey) && numKey > max ? numKey : max;
+    }, 0);
+
+    const newTabs = messages.map((captured, i): TabData => ({
+      key: String(maxKey + 1 + i),
+      label: `${importedCapture?.fileName ?? 'Capture'} #${i + 1}`,
+      closable: true,
+      host,
+      port,
+      isConnected: false,
+      requestMode: 'hex',
+      requestData: captured.data,
+      responseData: '',
+      responseTime: 0,
+      protocolFields: [],
+    }));
+    if (newTabs.length === 0) return;
+
+    setTabs(prev => [...prev, ...newTabs]);
+    setActiveTab(newTabs[0].key);
+    setImportedCapture(null);
+    antMessage.success(`Opened ${newTabs.length} messages`);
+  };
+
   const updateTab = (key: string, updates: Partial<TabData>) => {
-    setTabs(tabs.map(tab => (tab.key === key ? { ...tab, ...updates } : tab)));
+    setTabs(prev => prev.map(tab => (tab.key === key ? { ...tab, ...updates } : tab)));
   };
 
   const handleProtocolPresetChange = async (protocolId: string) => {
@@ -301,9 +423,8 @@ export default function Messages() {
 
         // Convert saved protocol fields to tab fields with new IDs
         // Also recalculate length for variable fields
-        const fields = protocol.fields.map((field, index) => ({
+        const fields = cloneFieldsWithNewIds(protocol.fields).map(field => ({
           ...field,
-          id: `field_${Date.now()}_${index}`,
           // Recalculate length for variable fields based on current value
           length: field.isVariable
             ? calculateByteLength(field.value || '', field.valueType || 'text')
@@ -344,16 +465,7 @@ export default function Messages() {
       setIsSavingProtocol(true);
 
       // Prepare field data (generate new IDs)
-      const fields = currentTab.protocolFields.map((field, index) => ({
-        id: `field_${Date.now()}_${index}`,
-        name: field.name,
-        length: field.length,
-        isVariable: field.isVariable ?? false,
-        valueType: field.valueType ?? 'hex',
-        valueFormat: field.valueFormat,
-        value: field.value || '',
-        description: field.description,
-      }));
+      const fields = cloneFieldsWithNewIds(currentTab.protocolFields).map(toSavedField);
 
       const newProtocol = await protocolService.createProtocol({
         name: newProtocolName.trim(),
@@ -492,69 +604,6 @@ export default function Messages() {
     }
   };
 
-  const buildProtocolData = (): string => {
-    // Convert protocol fields to hex string
-    let hexData = '';
-    for (const field of currentTab.protocolFields) {
-      // Skip disabled fields
-      if (field.enabled === false) {
-        continue;
-      }
-
-      const value = field.value || '';
-
-
-      // Handle variable-length fields
-      if (field.isVariable) {
-        if (field.valueType === 'text') {
-          // Text type - convert to hex
-          const bytes = new TextEncoder().encode(value);
-          for (let i = 0; i < bytes.length; i++) {
-            hexData += bytes[i].toString(16).padStart(2, '0');
-          }
-        } else {
-          // Hex type (default) - just remove spaces and append
-          hexData += value.replace(/\s/g, '');
-        }
-      } else {
-        // Handle fixed-length fields
-        const fieldLength = field.length || 1;
-        let cleanHex = '';
-
-        if (field.valueFormat === 'dec') {
-          // DEC format: convert decimal to hex
-          const num = BigInt(value || '0');
-          cleanHex = num.toString(16).toUpperCase().padStart(fieldLength * 2, '0');
-          cleanHex = cleanHex.slice(-fieldLength * 2); // Keep only last N bytes
-        } else if (field.valueFormat === 'bin') {
-          // BIN format: convert binary to hex
-          const cleanBin = value.replace(/\s/g, '');
-          const num = BigInt('0b' + (cleanBin || '0'));
-          cleanHex = num.toString(16).toUpperCase().padStart(fieldLength * 2, '0');
-          cleanHex = cleanHex.slice(-fieldLength * 2); // Keep only last N bytes
-        } else if (/^[0-9A-Fa-f\s]+$/.test(value)) {
-          // It's hex
-          cleanHex = value.replace(/\s/g, '');
-        } else {
-          // It's text, convert to hex
-          const bytes = new TextEncoder().encode(value);
-          for (let i = 0; i < fieldLength; i++) {
-            if (i < bytes.length) {
-              cleanHex += bytes[i].toString(16).padStart(2, '0');
-            } else {
-              cleanHex += '00';
-            }
-          }
-        }
-
-        // Pad with zeros at the beginning (left padding) for hex values
-        const paddedHex = cleanHex.padStart(fieldLength * 2, '0');
-        hexData += paddedHex.slice(-fieldLength * 2);
-      }
-    }
-    return hexData;
-  };
-
   // Build hex data for preview in text/hex mode
   const buildPreviewHexData = (): string => {
     if (currentTab.requestMode === 'text') {
@@ -592,29 +641,61 @@ export default function Messages() {
         antMessage.warning('Please add protocol fields');
         return;
       }
-      dataToSend = buildProtocolData();
     } else if (!currentTab.requestData.trim()) {
       antMessage.warning('Please enter data to send');
       return;
     }
 
+    // Protocols with a correlation rule may have several requests in flight, so Send stays enabled
+    const correlated = currentTab.requestMode === 'protocol'
+      ? savedProtocols.find(p => p.id === currentTab.selectedProtocolPreset && p.correlation)
+      : undefined;
+    if (correlated) {
+      const tabKey = activeTab;
+      try {
+        dataToSend = await protocolService.encodeFields(currentTab.protocolFields, connectionId);
+        const response = await messageService.sendCorrelated({
+          connection_id: connectionId,
+          protocol_id: correlated.id,
+          data: dataToSend,
+        });
+        updateTab(tabKey, {
+          responseData: response.response_data,
+          responseTime: response.response_time_ms,
+          responseCorrelation: response.correlation_value,
+          responseProtocol: correlated.correlation?.responseProtocolId ?? correlated.id,
+        });
+        if (response.error) {
+          antMessage.error(response.error);
+        }
+      } catch (error) {
+        antMessage.error(`Send failed: ${error}`);
+      }
+      return;
+    }
+
     try {
       setIsLoading(true);
-      updateTab(activeTab, { responseData: '', responseTime: 0 });
+      updateTab(activeTab, { responseData: '', responseTime: 0, responseCorrelation: undefined });
+
+      if (currentTab.requestMode === 'protocol') {
+        dataToSend = await protocolService.encodeFields(currentTab.protocolFields, connectionId);
+      }
 
       const response = await messageService.sendMessage({
         connection_id: connectionId,
         data: dataToSend,
         mode: mode,
+        protocol_id: currentTab.requestMode === 'protocol' ? currentTab.selectedProtocolPreset : undefined,
       });
 
-      if (response.success) {
-        updateTab(activeTab, {
-          responseData: response.response_data,
-          responseTime: response.response_time_ms,
-        });
-      } else {
-        antMessage.error(`Send failed: ${response.error}`);
+      // A response rejected by a post-receive hook is still shown
+      updateTab(activeTab, {
+        responseData: response.response_data,
+        responseTime: response.response_time_ms,
+      });
+      if (!response.success) {
+        antMessage.error(`${response.error}`);
       }
     } catch (error) {
       antMessage.error(`Send failed: ${error}`);
@@ -764,6 +845,37 @@ export default function Messages() {
             </Button>
           </>
         )}
+        <Button
+          size="small"
+          danger={captureStatus?.recording}
+          icon={<VideoCameraOutlined />}
+          onClick={handleToggleRecording}
+          title={captureStatus?.recording
+            ? `Recording: ${captureStatus.bytesSent} bytes sent, ${captureStatus.bytesReceived} bytes received`
+            : 'Record sent and received bytes for export as a capture file'}
+        >
+          {captureStatus?.recording ? 'Stop Recording' : 'Record'}
+        </Button>
+        <Button
+          size="small"
+          icon={<DownloadOutlined />}
+          onClick={handleExportCapture}
+          disabled={!captureStatus?.sessions}
+          title="Save the recorded traffic as PCAPNG (or .pcap) for Wireshark"
+        >
+          Export Capture
+        </Button>
+        <Button
+          size="small"
+          icon={<ImportOutlined />}
+          onClick={handleImportCapture}
+          title="Open a PCAP/PCAPNG file to reuse or replay its payloads"
+        >
+          Import Capture
+        </Button>
+        <div style={{ marginLeft: 'auto' }}>
+          <EnvironmentSelector onChange={() => setEnvironmentVersion(v => v + 1)} />
+        </div>
       </div>
 
       {/* Split Workspace */}
@@ -970,7 +1082,7 @@ export default function Messages() {
 					}}>
                   <ProtocolHexPreview hexData={
                     currentTab.requestMode === 'protocol'
-                      ? buildProtocolData()
+                      ? protocolHex
                       : buildPreviewHexData()
                   } />
               </div>
@@ -1004,14 +1116,31 @@ export default function Messages() {
             <Space>
               <span style={{ color: '#cccccc', fontWeight: 500 }}>Response</span>
               <span style={{ color: '#858585' }}>{currentTab.responseTime} ms</span>
+              {currentTab.responseCorrelation && (
+                <span style={{ color: '#858585', fontFamily: 'monospace' }}>ID {currentTab.responseCorrelation}</span>
+              )}
             </Space>
             <Space>
+              <Select
+                size="small"
+                allowClear
+                value={currentTab.responseProtocol}
+                onChange={(responseProtocol) => updateTab(activeTab, { responseProtocol })}
+                placeholder="Decode as..."
+                style={{ width: 180 }}
+                options={savedProtocols.map(p => ({ value: p.id, label: p.name }))}
+              />
               <Button size="small">Copy</Button>
               <Button size="small">Save</Button>
             </Space>
           </div>
           <div style={{ flex: 1, overflow: 'hidden', minHeight: 0, height: '100%' }}>
-            <ResponseViewer data={currentTab.responseData} />
+            <ResponseViewer
+              data={currentTab.responseData}
+              decoded={decodedResponse}
+              suggestions={responseSuggestions}
+              onDecodeAs={(responseProtocol) => updateTab(activeTab, { responseProtocol })}
+            />
           </div>
         </div>
       </div>
@@ -1056,6 +1185,14 @@ export default function Messages() {
           </div>
         </Space>
       </Modal>
+
+      <CaptureImportModal
+        capture={importedCapture}
+        connectionId={connectionId}
+        isConnected={currentTab.isConnected}
+        onClose={() => setImportedCapture(null)}
+        onOpenMessages={handleOpenCaptureMessages}
+      />
     </div>
   );
 }
@@ -1,17 +1,29 @@
 import { useState, useEffect } from 'react';
-import { Button, Table, Modal, Input, Space, Popconfirm, message, Empty, Typography } from 'antd';
-import { PlusOutlined, EditOutlined, DeleteOutlined, CopyOutlined, ImportOutlined, DownloadOutlined } from '@ant-design/icons';
+import { Button, Table, Modal, Input, InputNumber, Select, Space, Popconfirm, message, Empty, Typography, Dropdown } from 'antd';
+import { PlusOutlined, EditOutlined, DeleteOutlined, CopyOutlined, ImportOutlined, DownloadOutlined, CodeOutlined, BookOutlined, HistoryOutlined } from '@ant-design/icons';
 import ProtocolFieldEditor from '../components/ProtocolFieldEditor';
-import { protocolService, Protocol } from '../services/protocolService';
+import ProtocolHistoryModal from '../components/ProtocolHistoryModal';
+import CorrelationRuleEditor from '../components/CorrelationRuleEditor';
+import ProtocolHooksModal from '../components/ProtocolHooksModal';
+import { protocolService, Protocol, ConflictResolution, CorrelationRule, LibraryConflict, LibraryPreview, ProtocolHooks } from '../services/protocolService';
 import type { ProtocolField } from '../types/protocol-simple';
+import { cloneFieldsWithNewIds, toSavedField } from '../utils/protocolFields';
 
 const { Title, Text } = Typography;
 
+const resolutionOptions: { value: ConflictResolution; label: string }[] = [
+  { value: 'skip', label: 'Skip' },
+  { value: 'rename', label: 'Rename' },
+  { value: 'overwrite', label: 'Overwrite' },
+];
+
 interface EditingProtocol {
   id?: string;
   name: string;
   description: string;
   fields: ProtocolField[];
+  correlation?: CorrelationRule;
+  hooks?: ProtocolHooks;
 }
 
 export default function Protocols() {
@@ -19,6 +31,14 @@ export default function Protocols() {
   const [loading, setLoading] = useState(false);
   const [modalVisible, setModalVisible] = useState(false);
   const [editingProtocol, setEditingProtocol] = useState<EditingProtocol | null>(null);
+  const [dissectorTarget, setDissectorTarget] = useState<string | null>(null);
+  const [dissectorTransport, setDissectorTransport] = useState<'tcp' | 'udp'>('tcp');
+  const [dissectorPort, setDissectorPort] = useState<number>(8080);
+  const [historyTarget, setHistoryTarget] = useState<Protocol | null>(null);
+  const [hooksOpen, setHooksOpen] = useState(false);
+  const [selectedIds, setSelectedIds] = useState<string[]>([]);
+  const [libraryPreview, setLibraryPreview] = useState<LibraryPreview | null>(null);
+  const [resolutions, setResolutions] = useState<Record<string, ConflictResolution>>({});
 
   const loadProtocols = async () => {
     setLoading(true);
@@ -50,16 +70,9 @@ export default function Protocols() {
       id: protocol.id,
       name: protocol.name,
       description: protocol.description || '',
-      fields: protocol.fields.map((f, i) => ({
-        id: `edit_${Date.now()}_${i}`,
-        name: f.name,
-        length: f.length,
-        isVariable: f.isVariable ?? false,
-        valueType: f.valueType ?? 'hex',
-        valueFormat: f.valueFormat,
-        value: f.value || '',
-        description: f.description,
-      })),
+      fields: cloneFieldsWithNewIds(protocol.fields, 'edit').map(toSavedField),
+      correlation: protocol.correlation,
+      hooks: protocol.hooks,
     });
     setModalVisible(true);
   };
@@ -67,21 +80,14 @@ export default function Protocols() {
   const handleDuplicate = async (protocol: Protocol) => {
     try {
       // Deep copy fields with new IDs
-      const fields = protocol.fields.map((field, index) => ({
-        id: `field_${Date.now()}_${index}`,
-        name: field.name,
-        length: field.length,
-        isVariable: field.isVariable ?? false,
-        valueType: field.valueType ?? 'hex',
-        valueFormat: field.valueFormat,
-        value: field.value || '',
-        description: field.description,
-      }));
+      const fields = cloneFieldsWithNewIds(protocol.fields).map(toSavedField);
 
       await protocolService.createProtocol({
         name: `${protocol.name}-copy`,
         description: protocol.description,
         fields,
+        correlation: protocol.correlation,
+        hooks: protocol.hooks,
       });
       message.success('Protocol duplicated successfully');
       loadProtocols();
@@ -112,6 +118,32 @@ export default function Protocols() {
     }
   };
 
+  const handleGenerateCode = async (id: string, language: 'c' | 'rust') => {
+    try {
+      await protocolService.exportProtocolCode(id, language);
+      message.success('Code generated successfully');
+    } catch (error) {
+      // User cancelled or error occurred
+      if (error !== 'No file selected') {
+        message.error('Failed to generate code: ' + error);
+      }
+    }
+  };
+
+  const handleExportDissector = async () => {
+    if (!dissectorTarget) return;
+    try {
+      await protocolService.exportProtocolDissector(dissectorTarget, dissectorTransport, dissectorPort);
+      message.success('Dissector exported successfully');
+      setDissectorTarget(null);
+    } catch (error) {
+      // User cancelled or error occurred
+      if (error !== 'No file selected') {
+        message.error('Failed to export dissector: ' + error);
+      }
+    }
+  };
+
   const handleImport = async () => {
     try {
       const imported = await protocolService.importProtocol();
@@ -120,19 +152,22 @@ export default function Protocols() {
       setEditingProtocol({
         name: imported.name,
         description: imported.description || '',
-        fields: imported.fields.map((f, i) => ({
-          id: `import_${Date.now()}_${i}`,
-          name: f.name,
-          length: f.length,
-          isVariable: f.isVariable ?? false,
-          valueType: f.valueType ?? 'hex',
-          valueFormat: f.valueFormat,
-          value: f.value || '',
-          description: f.description,
-        })),
+        fields: cloneFieldsWithNewIds(imported.fields, 'import').map(toSavedField),
       });
       setModalVisible(true);
-      message.success('Protocol imported successfully');
+      if (imported.warnings?.length) {
+        Modal.warning({
+          title: `Imported with ${imported.warnings.length} unsupported construct(s)`,
+          width: 560,
+          content: (
+            <ul style={{ paddingLeft: 20, maxHeight: 300, overflow: 'auto' }}>
+              {imported.warnings.map((warning, i) => <li key={i}>{warning}</li>)}
+            </ul>
+          ),
+        });
+      } else {
+        message.success('Protocol imported successfully');
+      }
     } catch (error) {
       // User cancelled or error occurred
       if (typeof error === 'string' && !error.startsWith('No file')) {
@@ -141,6 +176,45 @@ export default function Protocols() {
     }
   };
 
+  const handleExportLibrary = async () => {
+    try {
+      const count = await protocolService.exportLibrary(selectedIds);
+      message.success(`Exported ${count} protocol(s) to library`);
+    } catch (error) {
+      // User cancelled or error occurred
+      if (error !== 'No file selected') {
+        message.error('Failed to export library: ' + error);
+      }
+    }
+  };
+
+  const handleOpenLibrary = async () => {
+    try {
+      const preview = await protocolService.openLibrary();
+      setResolutions(Object.fromEntries(preview.conflicts.map(c => [c.id, 'skip' as ConflictResolution])));
+      setLibraryPreview(preview);
+    } catch (error) {
+      // User cancelled or error occurred
+      if (typeof error === 'string' && !error.startsWith('No file')) {
+        message.error('Failed to open library: ' + error);
+      }
+    }
+  };
+
+  const handleImportLibrary = async () => {
+    if (!libraryPreview) return;
+    try {
+      const summary = await protocolService.importLibrary(libraryPreview.library, resolutions);
+      message.success(
+        `Library imported: ${summary.created} new, ${summary.renamed} renamed, ${summary.overwritten} overwritten, ${summary.skipped} skipped`
+      );
+      setLibraryPreview(null);
+      loadProtocols();
+    } catch (error) {
+      message.error('Failed to import library: ' + error);
+    }
+  };
+
   const handleSave = async () => {
     if (!editingProtocol) return;
 
@@ -149,6 +223,12 @@ export default function Protocols() {
       return;
     }
 
+    const { correlation } = editingProtocol;
+    if (correlation && (!correlation.requestField.trim() || !correlation.responseField.trim())) {
+      message.error('Correlation needs a request field and a response field');
+      return;
+    }
+
     try {
       if (editingProtocol.id) {
         await protocolService.updateProtocol({
@@ -156,6 +236,8 @@ export default function Protocols() {
           name: editingProtocol.name,
           description: editingProtocol.description || undefined,
           fields: editingProtocol.fields,
+          correlation,
+          hooks: editingProtocol.hooks,
         });
         message.success('Protocol updated successfully');
       } else {
@@ -163,6 +245,8 @@ export default function Protocols() {
           name: editingProtocol.name,
           description: editingProtocol.description || undefined,
           fields: editingProtocol.fields,
+          correlation,
+          hooks: editingProtocol.hooks,
         });
         message.success('Protocol created successfully');
       }
@@ -208,7 +292,7 @@ export default function Protocols() {
     {
       title: 'Actions',
       key: 'actions',
-      width: 180,
+      width: 240,
       render: (_: any, record: Protocol) => (
         <Space size="small">
           <Button
@@ -232,6 +316,38 @@ export default function Protocols() {
             onClick={() => handleExport(record.id)}
             style={{ color: '#cccccc' }}
           />
+          <Button
+            type="text"
+            size="small"
+            icon={<HistoryOutlined />}
+            onClick={() => setHistoryTarget(record)}
+            style={{ color: '#cccccc' }}
+          />
+          <Dropdown
+            trigger={['click']}
+            menu={{
+              items: [
+                { key: 'c', label: 'C header (.h)' },
+                { key: 'rust', label: 'Rust struct (.rs)' },
+                { type: 'divider' },
+                { key: 'wireshark', label: 'Wireshark dissector (.lua)' },
+              ],
+              onClick: ({ key }) => {
+                if (key === 'wireshark') {
+                  setDissectorTarget(record.id);
+                } else {
+                  handleGenerateCode(record.id, key as 'c' | 'rust');
+                }
+              },
+            }}
+          >
+            <Button
+              type="text"
+              size="small"
+              icon={<CodeOutlined />}
+              style={{ color: '#cccccc' }}
+            />
+          </Dropdown>
           <Popconfirm
             title="Are you sure to delete this protocol?"
             onConfirm={() => handleDelete(record.id)}
@@ -257,6 +373,22 @@ export default function Protocols() {
           Protocol Templates
         </Title>
         <Space>
+          <Dropdown
+            trigger={['click']}
+            menu={{
+              items: [
+                { key: 'import', label: 'Import library...' },
+                {
+                  key: 'export',
+                  label: selectedIds.length ? `Export ${selectedIds.length} selected` : 'Export all',
+                  disabled: protocols.length === 0,
+                },
+              ],
+              onClick: ({ key }) => (key === 'import' ? handleOpenLibrary() : handleExportLibrary()),
+            }}
+          >
+            <Button icon={<BookOutlined />}>Library</Button>
+          </Dropdown>
           <Button
             icon={<ImportOutlined />}
             onClick={handleImport}
@@ -277,6 +409,10 @@ export default function Protocols() {
         columns={columns}
         dataSource={protocols}
         rowKey="id"
+        rowSelection={{
+          selectedRowKeys: selectedIds,
+          onChange: (keys) => setSelectedIds(keys as string[]),
+        }}
         loading={loading}
         pagination={false}
         locale={{
@@ -328,7 +464,28 @@ export default function Protocols() {
               style={{ maxWidth: 400 }}
             />
           </div>
-          <div style={{ height: 'calc(60vh - 100px)', display: 'flex', flexDirection: 'column' }}>
+          <div>
+            <Text style={{ color: '#cccccc', marginRight: 8 }}>Correlation:</Text>
+            {editingProtocol && (
+              <CorrelationRuleEditor
+                rule={editingProtocol.correlation}
+                onChange={(correlation) => setEditingProtocol(prev => prev ? { ...prev, correlation } : null)}
+                fields={editingProtocol.fields}
+                protocolId={editingProtocol.id}
+                protocols={protocols}
+              />
+            )}
+            <Button
+              size="small"
+              icon={<CodeOutlined />}
+              onClick={() => setHooksOpen(true)}
+              style={{ marginLeft: 16 }}
+              type={editingProtocol?.hooks ? 'primary' : 'default'}
+            >
+              Scripts
+            </Button>
+          </div>
+          <div style={{ height: 'calc(60vh - 140px)', display: 'flex', flexDirection: 'column' }}>
             <Text style={{ color: '#cccccc', marginBottom: 8 }}>Fields:</Text>
             {editingProtocol && (
               <ProtocolFieldEditor
@@ -339,6 +496,118 @@ export default function Protocols() {
           </div>
         </Space>
       </Modal>
+
+      <ProtocolHooksModal
+        open={hooksOpen}
+        hooks={editingProtocol?.hooks}
+        onClose={() => setHooksOpen(false)}
+        onChange={(hooks) => setEditingProtocol(prev => prev ? { ...prev, hooks } : null)}
+      />
+
+      <Modal
+        title="Export Wireshark Dissector"
+        open={dissectorTarget !== null}
+        onOk={handleExportDissector}
+        onCancel={() => setDissectorTarget(null)}
+        okText="Export"
+        cancelText="Cancel"
+        width={360}
+      >
+        <Space>
+          <Select
+            value={dissectorTransport}
+            onChange={setDissectorTransport}
+            style={{ width: 90 }}
+            options={[
+              { value: 'tcp', label: 'TCP' },
+              { value: 'udp', label: 'UDP' },
+            ]}
+          />
+          <InputNumber
+            value={dissectorPort}
+            onChange={(port) => port !== null && setDissectorPort(port)}
+            min={1}
+            max={65535}
+            precision={0}
+            prefix="Port"
+            style={{ width: 160 }}
+          />
+        </Space>
+      </Modal>
+
+      <ProtocolHistoryModal
+        protocol={historyTarget}
+        onClose={() => setHistoryTarget(null)}
+        onRestored={loadProtocols}
+      />
+
+      <Modal
+        title="Import Protocol Library"
+        open={libraryPreview !== null}
+        onOk={handleImportLibrary}
+        onCancel={() => setLibraryPreview(null)}
+        okText="Import"
+        cancelText="Cancel"
+        width={640}
+      >
+        {libraryPreview && (
+          <Space direction="vertical" style={{ width: '100%' }}>
+            <Text style={{ color: '#cccccc' }}>
+              {libraryPreview.library.protocols.length} protocol(s) in library
+              {libraryPreview.conflicts.length > 0 && `, ${libraryPreview.conflicts.length} already exist`}
+            </Text>
+            {libraryPreview.conflicts.length > 0 && (
+              <>
+                <Space>
+                  <Text style={{ color: '#858585' }}>Apply to all:</Text>
+                  <Select<ConflictResolution>
+                    placeholder="Choose"
+                    size="small"
+                    style={{ width: 120 }}
+                    options={resolutionOptions}
+                    onChange={(resolution) => setResolutions(
+                      Object.fromEntries(libraryPreview.conflicts.map(c => [c.id, resolution]))
+                    )}
+                  />
+                </Space>
+                <Table
+                  size="small"
+                  rowKey="id"
+                  pagination={false}
+                  scroll={{ y: 300 }}
+                  dataSource={libraryPreview.conflicts}
+                  columns={[
+                    { title: 'Protocol', dataIndex: 'name', key: 'name' },
+                    {
+                      title: 'Conflicts with',
+                      key: 'existing',
+                      render: (_: any, conflict: LibraryConflict) => (
+                        <span style={{ color: '#858585' }}>
+                          {conflict.existingName} (same {conflict.reason})
+                        </span>
+                      ),
+                    },
+                    {
+                      title: 'Action',
+                      key: 'action',
+                      width: 140,
+                      render: (_: any, conflict: LibraryConflict) => (
+                        <Select<ConflictResolution>
+                          size="small"
+                          value={resolutions[conflict.id] ?? 'skip'}
+                          onChange={(resolution) => setResolutions(prev => ({ ...prev, [conflict.id]: resolution }))}
+                          style={{ width: 120 }}
+                          options={resolutionOptions}
+                        />
+                      ),
+                    },
+                  ]}
+                />
+              </>
+            )}
+          </Space>
+        )}
+      </Modal>
     </div>
   );
 }
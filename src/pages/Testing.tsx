@@ -1,12 +1,284 @@
-import { Typography } from 'antd';
+import { useState, useEffect, useRef } from 'react';
+import { Button, Table, Input, InputNumber, Select, Space, Switch, Popconfirm, message, Typography, Statistic, Card, Row, Col, Tooltip } from 'antd';
+import { PlayCircleOutlined, StopOutlined, DeleteOutlined, ReloadOutlined } from '@ant-design/icons';
+import type { ColumnsType } from 'antd/es/table';
+import MockServerPanel from '../components/MockServerPanel';
+import { protocolService, Protocol } from '../services/protocolService';
+import { loadTestService, LoadTestConfig, LoadTestProgress, LoadTestRun, LoadTestSample, LoadTestSummary } from '../services/loadTestService';
 
-const { Title } = Typography;
+const { Title, Text } = Typography;
+
+const defaultConfig: LoadTestConfig = {
+  host: '127.0.0.1',
+  port: 8080,
+  connections: 10,
+  durationSecs: 30,
+  payload: '',
+  expectResponse: true,
+  timeoutSecs: 5,
+};
+
+const ms = (value: number) => `${value.toFixed(2)} ms`;
+
+const sampleColumns: ColumnsType<LoadTestSample> = [
+  { title: 'Second', dataIndex: 'second', width: 80 },
+  { title: 'Requests', dataIndex: 'requests' },
+  { title: 'Errors', dataIndex: 'errors' },
+  { title: 'p50', dataIndex: 'p50', render: ms },
+  { title: 'p99', dataIndex: 'p99', render: ms },
+];
+
+// Totals and percentiles of a finished run
+function SummaryView({ summary }: { summary: LoadTestSummary }) {
+  const { latency, connectTime, errors } = summary;
+  return (
+    <Space direction="vertical" style={{ width: '100%' }}>
+      <Row gutter={16}>
+        <Col span={4}><Statistic title="Requests" value={summary.requests} /></Col>
+        <Col span={4}><Statistic title="Throughput" value={summary.throughput} precision={1} suffix="req/s" /></Col>
+        <Col span={4}><Statistic title="Errors" value={errors.connect + errors.timeout + errors.io} /></Col>
+        <Col span={4}><Statistic title="Duration" value={summary.durationMs / 1000} precision={1} suffix="s" /></Col>
+        <Col span={4}><Statistic title="Sent" value={summary.bytesSent} suffix="B" /></Col>
+        <Col span={4}><Statistic title="Received" value={summary.bytesReceived} suffix="B" /></Col>
+      </Row>
+      <Text style={{ color: '#cccccc' }}>
+        Latency: p50 {ms(latency.p50)} · p90 {ms(latency.p90)} · p99 {ms(latency.p99)} · max {ms(latency.max)} · mean {ms(latency.mean)}
+      </Text>
+      <Text style={{ color: '#cccccc' }}>
+        Connect: {connectTime.count} opened · p50 {ms(connectTime.p50)} · p99 {ms(connectTime.p99)} · max {ms(connectTime.max)}
+      </Text>
+      <Text style={{ color: '#858585' }}>
+        Errors: {errors.connect} connect · {errors.timeout} timeout · {errors.io} I/O{summary.stopped ? ' · stopped early' : ''}
+      </Text>
+      <Table size="small" rowKey="second" columns={sampleColumns} dataSource={summary.timeline} pagination={{ pageSize: 10 }} />
+    </Space>
+  );
+}
 
 export default function Testing() {
+  const [config, setConfig] = useState<LoadTestConfig>(defaultConfig);
+  const [useProtocol, setUseProtocol] = useState(false);
+  const [protocols, setProtocols] = useState<Protocol[]>([]);
+  const [runId, setRunId] = useState<string | null>(null);
+  const [progress, setProgress] = useState<LoadTestProgress[]>([]);
+  const [runs, setRuns] = useState<LoadTestRun[]>([]);
+  const [selectedRun, setSelectedRun] = useState<LoadTestRun | null>(null);
+  const runIdRef = useRef<string | null>(null);
+
+  const loadRuns = async () => {
+    try {
+      setRuns(await loadTestService.listRuns());
+    } catch (error) {
+      message.error('Failed to load test runs: ' + error);
+    }
+  };
+
+  useEffect(() => {
+    loadRuns();
+    protocolService.listProtocols().then(setProtocols).catch(() => setProtocols([]));
+
+    const unlistenProgress = loadTestService.onProgress((update) => {
+      if (update.runId === runIdRef.current) {
+        setProgress(prev => [...prev, update]);
+      }
+    });
+    const unlistenFinished = loadTestService.onFinished(({ run, error }) => {
+      if (run.id !== runIdRef.current) return;
+      runIdRef.current = null;
+      setRunId(null);
+      setSelectedRun(run);
+      if (error) {
+        message.warning('Load test finished but could not be saved: ' + error);
+      }
+      loadRuns();
+    });
+    return () => {
+      unlistenProgress.then(unlisten => unlisten());
+      unlistenFinished.then(unlisten => unlisten());
+    };
+  }, []);
+
+  const update = (updates: Partial<LoadTestConfig>) => setConfig(prev => ({ ...prev, ...updates }));
+
+  const handleStart = async () => {
+    if (useProtocol ? !config.protocolId : !config.payload.trim()) {
+      message.warning(useProtocol ? 'Select a protocol to send' : 'Enter a payload to send');
+      return;
+    }
+    try {
+      setProgress([]);
+      setSelectedRun(null);
+      const id = await loadTestService.startLoadTest({
+        ...config,
+        protocolId: useProtocol ? config.protocolId : undefined,
+      });
+      runIdRef.current = id;
+      setRunId(id);
+    } catch (error) {
+      message.error('Failed to start load test: ' + error);
+    }
+  };
+
+  const handleStop = async () => {
+    if (!runId) return;
+    try {
+      await loadTestService.stopLoadTest(runId);
+    } catch (error) {
+      message.error('Failed to stop load test: ' + error);
+    }
+  };
+
+  const handleDelete = async (id: string) => {
+    try {
+      await loadTestService.deleteRun(id);
+      if (selectedRun?.id === id) setSelectedRun(null);
+      loadRuns();
+    } catch (error) {
+      message.error('Failed to delete run: ' + error);
+    }
+  };
+
+  const latest = progress[progress.length - 1];
+
+  const runColumns: ColumnsType<LoadTestRun> = [
+    { title: 'Started', dataIndex: 'startedAt', render: (value: string) => new Date(value).toLocaleString() },
+    { title: 'Target', render: (_, run) => `${run.config.host}:${run.config.port}` },
+    { title: 'Connections', render: (_, run) => run.config.connections },
+    { title: 'Rate', render: (_, run) => (run.config.rate ? `${run.config.rate}/s` : 'max') },
+    { title: 'Throughput', render: (_, run) => `${run.summary.throughput.toFixed(1)} req/s` },
+    { title: 'p99', render: (_, run) => ms(run.summary.latency.p99) },
+    {
+      title: 'Errors',
+      render: (_, run) => run.summary.errors.connect + run.summary.errors.timeout + run.summary.errors.io,
+    },
+    {
+      title: '',
+      width: 50,
+      render: (_, run) => (
+        <Popconfirm title="Delete this run?" onConfirm={(e) => { e?.stopPropagation(); handleDelete(run.id); }}>
+          <Button size="small" type="text" danger icon={<DeleteOutlined />} onClick={(e) => e.stopPropagation()} />
+        </Popconfirm>
+      ),
+    },
+  ];
+
   return (
     <div style={{ padding: '24px' }}>
-      <Title level={2}>Test Suite</Title>
-      <p>Test suite feature is under development...</p>
+      <Title level={2}>Testing</Title>
+
+      <MockServerPanel />
+
+      <Card size="small" title="Load test" style={{ marginBottom: 16 }}>
+        <Space direction="vertical" style={{ width: '100%' }}>
+          <Space wrap>
+            <Input
+              value={config.host}
+              onChange={(e) => update({ host: e.target.value })}
+              placeholder="Host"
+              style={{ width: 180 }}
+            />
+            <InputNumber value={config.port} min={1} max={65535} onChange={(port) => update({ port: port ?? 1 })} prefix="Port" />
+            <InputNumber
+              value={config.connections}
+              min={1}
+              max={1000}
+              onChange={(connections) => update({ connections: connections ?? 1 })}
+              prefix="Connections"
+            />
+            <InputNumber
+              value={config.durationSecs}
+              min={1}
+              onChange={(durationSecs) => update({ durationSecs: durationSecs ?? 1 })}
+              prefix="Duration"
+              suffix="s"
+            />
+            <Tooltip title="Requests per second over all connections; leave empty to send as fast as the target answers">
+              <InputNumber
+                value={config.rate}
+                min={1}
+                onChange={(rate) => update({ rate: rate ?? undefined })}
+                prefix="Rate"
+                suffix="req/s"
+                placeholder="max"
+              />
+            </Tooltip>
+            <InputNumber
+              value={config.timeoutSecs}
+              min={1}
+              onChange={(timeoutSecs) => update({ timeoutSecs: timeoutSecs ?? 1 })}
+              prefix="Timeout"
+              suffix="s"
+            />
+            <Space>
+              <Switch size="small" checked={config.expectResponse} onChange={(expectResponse) => update({ expectResponse })} />
+              <Text style={{ color: '#cccccc' }}>Wait for responses</Text>
+            </Space>
+          </Space>
+          <Space.Compact style={{ width: '100%' }}>
+            <Select
+              value={useProtocol ? 'protocol' : 'hex'}
+              onChange={(mode) => setUseProtocol(mode === 'protocol')}
+              style={{ width: 120 }}
+              options={[{ value: 'hex', label: 'Hex' }, { value: 'protocol', label: 'Protocol' }]}
+            />
+            {useProtocol ? (
+              <Select
+                value={config.protocolId}
+                onChange={(protocolId) => update({ protocolId })}
+                placeholder="Protocol, encoded once at the start"
+                style={{ flex: 1 }}
+                options={protocols.map(p => ({ value: p.id, label: p.name }))}
+              />
+            ) : (
+              <Input
+                value={config.payload}
+                onChange={(e) => update({ payload: e.target.value })}
+                placeholder="Payload in hex, e.g. 01 03 00 00 00 0A"
+                style={{ fontFamily: 'monospace' }}
+              />
+            )}
+            {runId ? (
+              <Button danger icon={<StopOutlined />} onClick={handleStop}>Stop</Button>
+            ) : (
+              <Button type="primary" icon={<PlayCircleOutlined />} onClick={handleStart}>Start</Button>
+            )}
+          </Space.Compact>
+        </Space>
+      </Card>
+
+      {runId && (
+        <Card size="small" title="Running" style={{ marginBottom: 16 }}>
+          <Row gutter={16}>
+            <Col span={4}><Statistic title="Elapsed" value={(latest?.elapsedMs ?? 0) / 1000} precision={0} suffix={`/ ${config.durationSecs} s`} /></Col>
+            <Col span={4}><Statistic title="Requests" value={latest?.requests ?? 0} /></Col>
+            <Col span={4}><Statistic title="Last second" value={latest?.sample.requests ?? 0} suffix="req/s" /></Col>
+            <Col span={4}><Statistic title="p50 / p99" value={latest ? `${latest.sample.p50.toFixed(1)} / ${latest.sample.p99.toFixed(1)}` : '-'} suffix="ms" /></Col>
+            <Col span={4}><Statistic title="Errors" value={latest?.errors ?? 0} /></Col>
+            <Col span={4}><Statistic title="Open connections" value={latest?.activeConnections ?? 0} /></Col>
+          </Row>
+        </Card>
+      )}
+
+      {selectedRun && (
+        <Card size="small" title={`Result · ${new Date(selectedRun.startedAt).toLocaleString()}`} style={{ marginBottom: 16 }}>
+          <SummaryView summary={selectedRun.summary} />
+        </Card>
+      )}
+
+      <Card
+        size="small"
+        title="Previous runs"
+        extra={<Button size="small" type="text" icon={<ReloadOutlined />} onClick={loadRuns} />}
+      >
+        <Table
+          size="small"
+          rowKey="id"
+          columns={runColumns}
+          dataSource={runs}
+          pagination={{ pageSize: 10 }}
+          onRow={(run) => ({ onClick: () => setSelectedRun(run), style: { cursor: 'pointer' } })}
+        />
+      </Card>
     </div>
   );
 }
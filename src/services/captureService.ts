@@ -0,0 +1,48 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export interface CaptureMessage {
+  fromClient: boolean;
+  offsetMs: number; // since the first packet of the conversation
+  data: string; // hex
+}
+
+export interface Conversation {
+  transport: 'tcp' | 'udp';
+  client: string; // host:port
+  server: string;
+  packets: number;
+  clientBytes: number;
+  serverBytes: number;
+  incomplete: boolean; // some stream data was never captured
+  messages: CaptureMessage[];
+}
+
+export interface CaptureImport {
+  fileName: string;
+  packets: number;
+  ignored: number; // not TCP or UDP over IP
+  conversations: Conversation[];
+}
+
+export interface ReplayMessage {
+  data: string; // hex
+  delayMs?: number; // pause before sending
+}
+
+export interface ReplayResult {
+  responseData: string; // hex
+  responseTimeMs: number;
+  error?: string;
+}
+
+export const captureService = {
+  // Pick a PCAP/PCAPNG file and list its conversations
+  async importCapture(): Promise<CaptureImport> {
+    return await invoke<CaptureImport>('import_capture');
+  },
+
+  // Send messages in order on a connection; stops at the first failure
+  async replay(connectionId: string, messages: ReplayMessage[], waitForResponse: boolean): Promise<ReplayResult[]> {
+    return await invoke<ReplayResult[]>('replay_capture', { connectionId, messages, waitForResponse });
+  },
+};
@@ -13,6 +13,13 @@ export interface CommandResult {
   message: string;
 }
 
+export interface CaptureStatus {
+  recording: boolean;
+  sessions: number;
+  bytesSent: number;
+  bytesReceived: number;
+}
+
 export const connectionService = {
   async createConnection(config: ConnectionConfig): Promise<CommandResult> {
     return await invoke<CommandResult>('create_connection', { config });
@@ -37,4 +44,17 @@ export const connectionService = {
   async listConnections(): Promise<string[]> {
     return await invoke<string[]>('list_connections');
   },
+
+  async setRecording(connectionId: string, enabled: boolean): Promise<CaptureStatus> {
+    return await invoke<CaptureStatus>('set_connection_recording', { connectionId, enabled });
+  },
+
+  async getRecording(connectionId: string): Promise<CaptureStatus> {
+    return await invoke<CaptureStatus>('get_connection_recording', { connectionId });
+  },
+
+  // Returns the number of packets written
+  async exportCapture(connectionId: string): Promise<number> {
+    return await invoke<number>('export_connection_capture', { connectionId });
+  },
 };
@@ -0,0 +1,48 @@
+import { invoke } from '@tauri-apps/api/core';
+
+// Substituted for {{key}} in payloads and field values when sending
+export interface EnvironmentVariable {
+  key: string;
+  value: string;
+}
+
+export interface Environment {
+  id: string;
+  name: string;
+  variables: EnvironmentVariable[];
+  active: boolean;
+  createdAt: string;
+  updatedAt: string;
+}
+
+export const environmentService = {
+  async listEnvironments(): Promise<Environment[]> {
+    return await invoke<Environment[]>('list_environments');
+  },
+
+  async createEnvironment(name: string, variables: EnvironmentVariable[] = []): Promise<Environment> {
+    return await invoke<Environment>('create_environment', { request: { name, variables } });
+  },
+
+  async updateEnvironment(id: string, name: string, variables: EnvironmentVariable[]): Promise<Environment> {
+    return await invoke<Environment>('update_environment', { request: { id, name, variables } });
+  },
+
+  async deleteEnvironment(id: string): Promise<void> {
+    return await invoke<void>('delete_environment', { id });
+  },
+
+  // null sends without an environment
+  async setActiveEnvironment(id: string | null): Promise<void> {
+    return await invoke<void>('set_active_environment', { id });
+  },
+
+  // Variables set by protocol scripts; they override environment variables until the app exits
+  async listSessionVariables(): Promise<EnvironmentVariable[]> {
+    return await invoke<EnvironmentVariable[]>('list_session_variables');
+  },
+
+  async clearSessionVariables(): Promise<void> {
+    return await invoke<void>('clear_session_variables');
+  },
+};
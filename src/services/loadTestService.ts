@@ -0,0 +1,99 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export interface LoadTestConfig {
+  host: string;
+  port: number;
+  connections: number;
+  durationSecs: number;
+  rate?: number; // requests/sec over all connections; as fast as possible when unset
+  payload: string; // hex, ignored when protocolId is set
+  protocolId?: string;
+  expectResponse: boolean;
+  timeoutSecs: number;
+}
+
+// Milliseconds
+export interface LatencyStats {
+  count: number;
+  mean: number;
+  p50: number;
+  p90: number;
+  p99: number;
+  max: number;
+}
+
+export interface LoadTestErrors {
+  connect: number;
+  timeout: number;
+  io: number;
+}
+
+export interface LoadTestSample {
+  second: number;
+  requests: number;
+  errors: number;
+  p50: number;
+  p99: number;
+}
+
+export interface LoadTestSummary {
+  durationMs: number;
+  requests: number;
+  throughput: number;
+  bytesSent: number;
+  bytesReceived: number;
+  latency: LatencyStats;
+  connectTime: LatencyStats;
+  errors: LoadTestErrors;
+  timeline: LoadTestSample[];
+  stopped: boolean;
+}
+
+export interface LoadTestProgress {
+  runId: string;
+  elapsedMs: number;
+  requests: number;
+  errors: number;
+  activeConnections: number;
+  sample: LoadTestSample;
+}
+
+export interface LoadTestRun {
+  id: string;
+  config: LoadTestConfig;
+  summary: LoadTestSummary;
+  startedAt: string;
+}
+
+export interface LoadTestFinished {
+  run: LoadTestRun;
+  error?: string; // the run couldn't be stored
+}
+
+export const loadTestService = {
+  // Returns the run id; progress and the result arrive as events
+  async startLoadTest(config: LoadTestConfig): Promise<string> {
+    return await invoke<string>('start_load_test', { config });
+  },
+
+  async stopLoadTest(runId: string): Promise<void> {
+    return await invoke<void>('stop_load_test', { runId });
+  },
+
+  async listRuns(): Promise<LoadTestRun[]> {
+    return await invoke<LoadTestRun[]>('list_load_test_runs');
+  },
+
+  async deleteRun(id: string): Promise<void> {
+    return await invoke<void>('delete_load_test_run', { id });
+  },
+
+  onProgress(handler: (progress: LoadTestProgress) => void): Promise<UnlistenFn> {
+    return listen<LoadTestProgress>('load-test-progress', (event) => handler(event.payload));
+  },
+
+  onFinished(handler: (finished: LoadTestFinished) => void): Promise<UnlistenFn> {
+    return listen<LoadTestFinished>('load-test-finished', (event) => handler(event.payload));
+  },
+};
@@ -1,16 +1,32 @@
 import { invoke } from '@tauri-apps/api/core';
+import type { DecodedField } from './protocolService';
 
 export interface SendMessageRequest {
   connection_id: string;
   data: string;
   mode: 'text' | 'hex';
+  protocol_id?: string; // protocol the data was encoded with; its hooks run around the exchange
 }
 
 export interface SendMessageResponse {
   success: boolean;
   response_data: string;
   response_time_ms: number;
-  error?: string;
+  error?: string; // set when a post-receive hook rejected the response
+}
+
+export interface CorrelatedSendRequest {
+  connection_id: string;
+  protocol_id: string; // protocol whose correlation rule matches the response
+  data: string; // encoded request, hex
+}
+
+export interface CorrelatedSendResponse {
+  response_data: string;
+  response_time_ms: number;
+  correlation_value: string; // hex
+  fields: DecodedField[];
+  error?: string; // set when a post-receive hook rejected the response
 }
 
 export const messageService = {
@@ -22,6 +38,11 @@ export const messageService = {
     return await invoke<SendMessageResponse>('send_only', { request });
   },
 
+  // Several correlated requests may be in flight on one connection
+  async sendCorrelated(request: CorrelatedSendRequest): Promise<CorrelatedSendResponse> {
+    return await invoke<CorrelatedSendResponse>('send_correlated', { request });
+  },
+
   async receiveOnly(
     connectionId: string,
     mode: 'text' | 'hex'
@@ -0,0 +1,31 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export interface StartMockServerRequest {
+  configPath: string; // mock rules (JSON)
+  port: number;
+}
+
+export interface MockServerStatus {
+  running: boolean;
+  port?: number;
+}
+
+export const mockServerService = {
+  // Protocols the rules name but don't define are taken from the saved protocols
+  async start(request: StartMockServerRequest): Promise<MockServerStatus> {
+    return await invoke<MockServerStatus>('start_mock_server', { request });
+  },
+
+  async stop(): Promise<void> {
+    return await invoke<void>('stop_mock_server');
+  },
+
+  async status(): Promise<MockServerStatus> {
+    return await invoke<MockServerStatus>('mock_server_status');
+  },
+
+  onLog(handler: (line: string) => void): Promise<UnlistenFn> {
+    return listen<string>('mock-server-log', (event) => handler(event.payload));
+  },
+};
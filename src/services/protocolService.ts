@@ -1,11 +1,27 @@
 import { invoke } from '@tauri-apps/api/core';
 import type { ProtocolField } from '../types/protocol-simple';
 
+// Pairs a response with the request whose requestField bytes equal its responseField bytes
+export interface CorrelationRule {
+  requestField: string;
+  responseField: string;
+  responseProtocolId?: string; // default: the request's own protocol
+  timeoutMs: number;
+}
+
+// Lua scripts run around every exchange using the protocol
+export interface ProtocolHooks {
+  preSend?: string; // may replace `payload` and set `vars`
+  postReceive?: string; // may check `response`/`fields` and set `vars`; an error fails the exchange
+}
+
 export interface Protocol {
   id: string;
   name: string;
   description?: string;
   fields: ProtocolField[];
+  correlation?: CorrelationRule;
+  hooks?: ProtocolHooks;
   createdAt: string;
   updatedAt: string;
 }
@@ -14,6 +30,8 @@ export interface CreateProtocolRequest {
   name: string;
   description?: string;
   fields: ProtocolField[];
+  correlation?: CorrelationRule;
+  hooks?: ProtocolHooks;
 }
 
 export interface UpdateProtocolRequest {
@@ -21,12 +39,102 @@ export interface UpdateProtocolRequest {
   name: string;
   description?: string;
   fields: ProtocolField[];
+  correlation?: CorrelationRule;
+  hooks?: ProtocolHooks;
 }
 
 export interface ProtocolImport {
   name: string;
   description?: string;
   fields: ProtocolField[];
+  warnings?: string[]; // constructs the source format had that couldn't be imported
+}
+
+// Many protocols shared as one file
+export interface ProtocolLibrary {
+  version: number;
+  exportedAt?: string;
+  protocols: { id: string; name: string; description?: string; fields: ProtocolField[]; correlation?: CorrelationRule; hooks?: ProtocolHooks }[];
+}
+
+export type ConflictResolution = 'skip' | 'rename' | 'overwrite';
+
+// A bundled protocol whose id or name is already used by a saved one
+export interface LibraryConflict {
+  id: string;
+  name: string;
+  existingId: string;
+  existingName: string;
+  reason: 'id' | 'name';
+}
+
+export interface LibraryPreview {
+  library: ProtocolLibrary;
+  conflicts: LibraryConflict[];
+}
+
+export interface LibraryImportSummary {
+  created: number;
+  renamed: number;
+  overwritten: number;
+  skipped: number;
+}
+
+// Entry of a protocol's save history
+export interface ProtocolRevisionSummary {
+  revision: number;
+  name: string;
+  fieldCount: number;
+  createdAt: string;
+}
+
+export interface ProtocolRevision {
+  protocolId: string;
+  revision: number;
+  name: string;
+  description?: string;
+  fields: ProtocolField[];
+  createdAt: string;
+}
+
+// A missing side means the property is unset there
+export interface PropertyChange {
+  key: string;
+  before?: unknown;
+  after?: unknown;
+}
+
+export interface FieldChange {
+  path: string; // field names joined by '.', '[]' for array elements
+  kind: 'added' | 'removed' | 'modified';
+  changes?: PropertyChange[];
+}
+
+export interface RevisionDiff {
+  from: number;
+  to: number;
+  properties: PropertyChange[];
+  fields: FieldChange[];
+}
+
+// A field read back from received data
+export interface DecodedField {
+  name: string;
+  offset: number;
+  length: number;
+  hex: string;
+  value: string;
+  fields?: DecodedField[];
+  tag?: number; // TLV items only
+}
+
+// What received data looks like: a well-known protocol, or a saved protocol that decodes it
+export interface ProtocolSuggestion {
+  name: string;
+  protocolId?: string; // saved protocols only
+  summary: string;
+  values?: Record<string, unknown>; // parsed by a well-known protocol
+  decoded?: DecodedField[]; // decoded with the saved protocol
 }
 
 export const protocolService = {
@@ -50,11 +158,63 @@ export const protocolService = {
     return await invoke<void>('delete_protocol', { id });
   },
 
+  async listRevisions(protocolId: string): Promise<ProtocolRevisionSummary[]> {
+    return await invoke<ProtocolRevisionSummary[]>('list_protocol_revisions', { protocolId });
+  },
+
+  async getRevision(protocolId: string, revision: number): Promise<ProtocolRevision | null> {
+    return await invoke<ProtocolRevision | null>('get_protocol_revision', { protocolId, revision });
+  },
+
+  async diffRevisions(protocolId: string, from: number, to: number): Promise<RevisionDiff> {
+    return await invoke<RevisionDiff>('diff_protocol_revisions', { protocolId, from, to });
+  },
+
+  // Saved as a new revision
+  async restoreRevision(protocolId: string, revision: number): Promise<Protocol> {
+    return await invoke<Protocol>('restore_protocol_revision', { protocolId, revision });
+  },
+
   async exportProtocol(protocolId: string): Promise<void> {
     return await invoke<void>('export_protocol_to_file', { protocolId });
   },
 
+  async exportProtocolCode(protocolId: string, language: 'c' | 'rust'): Promise<void> {
+    return await invoke<void>('export_protocol_code', { protocolId, language });
+  },
+
+  async exportProtocolDissector(protocolId: string, transport: 'tcp' | 'udp', port: number): Promise<void> {
+    return await invoke<void>('export_protocol_dissector', { protocolId, transport, port });
+  },
+
   async importProtocol(): Promise<ProtocolImport> {
     return await invoke<ProtocolImport>('import_protocol_from_file');
   },
+
+  // All protocols when `protocolIds` is empty; returns how many were written
+  async exportLibrary(protocolIds: string[] = []): Promise<number> {
+    return await invoke<number>('export_protocol_library', { protocolIds });
+  },
+
+  async openLibrary(): Promise<LibraryPreview> {
+    return await invoke<LibraryPreview>('open_protocol_library');
+  },
+
+  // Conflicts missing from `resolutions` are skipped
+  async importLibrary(library: ProtocolLibrary, resolutions: Record<string, ConflictResolution>): Promise<LibraryImportSummary> {
+    return await invoke<LibraryImportSummary>('import_protocol_library', { request: { library, resolutions } });
+  },
+
+  // Placeholders are resolved with the active environment; a preview doesn't advance {{$seq}}
+  async encodeFields(fields: ProtocolField[], connectionId?: string, preview = false): Promise<string> {
+    return await invoke<string>('encode_protocol_fields', { fields, connectionId, preview });
+  },
+
+  async decodeData(protocolId: string, data: string): Promise<DecodedField[]> {
+    return await invoke<DecodedField[]>('decode_protocol_data', { protocolId, data });
+  },
+
+  async detect(data: string): Promise<ProtocolSuggestion[]> {
+    return await invoke<ProtocolSuggestion[]>('detect_protocols', { data });
+  },
 };
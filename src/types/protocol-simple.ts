@@ -1,13 +1,76 @@
+export type FieldKind = 'scalar' | 'group' | 'array' | 'tlv' | 'varint';
+
+// leb128: unsigned (protobuf varint), zigzag: signed (protobuf sint), mqttLength: at most 4 bytes
+export type VarintFormat = 'leb128' | 'zigzag' | 'mqttLength';
+
+export type TextEncoding = 'ascii' | 'utf8' | 'utf16le' | 'utf16be' | 'gbk' | 'latin1';
+
+// Encoding options of a text field (padding only applies to fixed-length fields)
+export interface TextSpec {
+  encoding?: TextEncoding; // default: 'utf8'
+  padByte?: number; // default: 0
+  padSide?: 'left' | 'right'; // default: 'right'
+  terminator?: 'none' | 'nul' | 'crlf' | 'pascal'; // pascal: one-byte length prefix
+}
+
+// Auto-length: the value is the encoded byte length of other fields
+export type LengthOf =
+  | { mode: 'field'; field: string } // a later field
+  | { mode: 'following' }; // everything after this field in the same structure
+
+export type Endianness = 'big' | 'little';
+
+// Value produced anew on every send (state is kept per connection)
+export type FieldGenerator =
+  | { type: 'counter'; start: number; step: number; wrap?: number } // back to start once past wrap (or the field maximum)
+  | { type: 'unixTime'; unit: 's' | 'ms' } // written with the field's width and byte order
+  | { type: 'randomBytes' }
+  | { type: 'randomRange'; min: number; max: number };
+
+// Item header layout of a TLV field
+export interface TlvSpec {
+  tagWidth: number; // bytes
+  lengthWidth: number; // bytes
+  endianness?: Endianness; // default: 'big'
+  lengthIncludesHeader?: boolean; // length counts tag and length bytes too
+  tags?: { tag: number; name: string }[]; // known tags, used to name decoded items
+}
+
+// How many elements an array field holds
+export type RepeatRule =
+  | { mode: 'fixed'; count: number }
+  | { mode: 'fromField'; field: string } // count is the value of an earlier field
+  | { mode: 'untilEnd' };
+
+// Includes a field only when an earlier field matches (values compared as unsigned integers)
+export type FieldCondition =
+  | { op: 'equals'; field: string; value: number }
+  | { op: 'bitmaskSet'; field: string; mask: number } // every bit of mask is set
+  | { op: 'inSet'; field: string; values: number[] };
+
 export interface ProtocolField {
   id: string;
   name: string;
   length?: number; // in bytes (optional for variable-length fields)
   isVariable?: boolean; // true if field has variable length
   valueType?: 'text' | 'hex'; // for variable fields: how to interpret the value (default: 'hex')
-  valueFormat?: 'dec' | 'hex' | 'bin'; // for non-variable fields: display as decimal, hex, or binary (default: 'dec')
+  valueFormat?: 'dec' | 'hex' | 'bin' | 'text'; // for non-variable fields: display as decimal, hex, binary or text (default: 'dec')
   enabled?: boolean; // whether to include this field in the assembled message (default: true)
   value: string;
   description?: string;
+  kind?: FieldKind; // default: 'scalar'
+  protocolRef?: string; // group: id of the saved protocol it embeds
+  fields?: ProtocolField[]; // group members, or array elements
+  element?: ProtocolField; // array: shape of one element, used when decoding
+  repeat?: RepeatRule; // array: element count rule
+  condition?: FieldCondition; // field is omitted unless the condition holds
+  tlv?: TlvSpec; // tlv: item header layout
+  tag?: number; // tag of an item inside a TLV field
+  varintFormat?: VarintFormat; // varint: wire format (default: 'leb128')
+  lengthOf?: LengthOf; // value is filled in with the length of other fields
+  text?: TextSpec; // text fields: encoding, padding and terminator
+  endianness?: Endianness; // fixed-size numbers; big-endian when unset
+  generator?: FieldGenerator; // value is generated on every send
 }
 
 export interface Protocol {
@@ -0,0 +1,75 @@
+import type { ProtocolField, TextSpec } from '../types/protocol-simple';
+
+let idCounter = 0;
+
+// Copy a field tree with fresh ids (field ids are unique across all saved protocols)
+export function cloneFieldWithNewIds(field: ProtocolField, prefix = 'field'): ProtocolField {
+  return {
+    ...field,
+    id: `${prefix}_${Date.now()}_${idCounter++}`,
+    fields: field.fields?.map(child => cloneFieldWithNewIds(child, prefix)),
+    element: field.element ? cloneFieldWithNewIds(field.element, prefix) : undefined,
+  };
+}
+
+export function cloneFieldsWithNewIds(fields: ProtocolField[], prefix = 'field'): ProtocolField[] {
+  return fields.map(field => cloneFieldWithNewIds(field, prefix));
+}
+
+// Fields sent to the backend when saving a protocol
+export function toSavedField(field: ProtocolField): ProtocolField {
+  return {
+    id: field.id,
+    name: field.name,
+    length: field.length,
+    isVariable: field.isVariable ?? false,
+    valueType: field.valueType ?? 'hex',
+    valueFormat: field.valueFormat,
+    value: field.value || '',
+    description: field.description,
+    kind: field.kind,
+    protocolRef: field.protocolRef,
+    fields: field.fields?.map(toSavedField),
+    element: field.element ? toSavedField(field.element) : undefined,
+    repeat: field.repeat,
+    condition: field.condition,
+    tlv: field.tlv,
+    tag: field.tag,
+    varintFormat: field.varintFormat,
+    lengthOf: field.lengthOf,
+    text: field.text,
+    endianness: field.endianness,
+    generator: field.generator,
+  };
+}
+
+// Accepts decimal ("16") or hex ("0x10")
+export function parseNumber(text: string): number | undefined {
+  const trimmed = text.trim();
+  if (!trimmed) return undefined;
+  const num = /^0x/i.test(trimmed) ? parseInt(trimmed.slice(2), 16) : parseInt(trimmed, 10);
+  return isNaN(num) ? undefined : num;
+}
+
+export const formatHex = (num: number) => `0x${num.toString(16).toUpperCase()}`;
+
+// Encoded size of a text value, terminator or length prefix included
+export function textByteLength(value: string, spec?: TextSpec): number {
+  const encoding = spec?.encoding ?? 'utf8';
+  const unit = encoding === 'utf16le' || encoding === 'utf16be' ? 2 : 1;
+  let length: number;
+  if (unit === 2) {
+    length = value.length * 2; // JS strings are UTF-16 code units
+  } else if (encoding === 'ascii' || encoding === 'latin1') {
+    length = value.length;
+  } else if (encoding === 'gbk') {
+    length = Array.from(value).reduce((n, c) => n + (c.charCodeAt(0) < 0x80 ? 1 : 2), 0);
+  } else {
+    length = new TextEncoder().encode(value).length;
+  }
+  const terminator = spec?.terminator ?? 'none';
+  if (terminator === 'nul') return length + unit;
+  if (terminator === 'crlf') return length + unit * 2;
+  if (terminator === 'pascal') return length + 1;
+  return length;
+}
@@ -0,0 +1,196 @@
+//! Listeners to start, from the command line or a JSON config file.
+//!
+//! ```json
+//! {
+//!   "listeners": [
+//!     { "port": 18080 },
+//!     { "address": "0.0.0.0", "port": 1502, "handler": "modbus" },
+//!     { "port": 15020, "handler": "mock", "mock": "rules.json" },
+//!     { "port": 15021, "handler": "modbus-slave", "registers": "registers.json" },
+//!     { "port": 16379, "handler": "redis-server" },
+//!     { "port": 10021, "handler": "ftp-server" },
+//!     { "port": 10025, "handler": "smtp-server", "mailDir": "mail" },
+//!     { "port": 18443, "handler": "http", "tls": { "cert": "cert.pem", "key": "key.pem" } }
+//!   ]
+//! }
+//! ```
+//!
+//! Port 0 binds any free port; the address actually bound is printed as
+//! `🚀 TCP Test Server [<handler>] listening on <address>` so callers can pick it up from stdout.
+
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+const USAGE: &str = "usage: tcp-test-server [--config server.json] [--listen [ADDR:]PORT[/HANDLER]]... \
+[--bind ADDR] [--port N] [--mock rules.json] [--registers registers.json] [--mail-dir DIR]";
+
+#[derive(Debug, Deserialize)]
+pub struct ServerConfig {
+    pub listeners: Vec<ListenerConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenerConfig {
+    #[serde(default = "localhost")]
+    pub address: String,
+    /// 0 for any free port
+    #[serde(default)]
+    pub port: u16,
+    #[serde(default)]
+    pub handler: Handler,
+    /// Rules file of a `mock` listener
+    #[serde(default)]
+    pub mock: Option<String>,
+    /// Initial values of a `modbus-slave` listener
+    #[serde(default)]
+    pub registers: Option<String>,
+    /// Where an `smtp-server` listener saves delivered mail
+    #[serde(default)]
+    pub mail_dir: Option<String>,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+fn localhost() -> String {
+    "127.0.0.1".to_string()
+}
+
+/// PEM files of a TLS listener
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(not(feature = "tls"), allow(dead_code))] // only read when built with TLS
+pub struct TlsConfig {
+    pub cert: String,
+    pub key: String,
+}
+
+/// What a listener does with its connections
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Handler {
+    /// Detect the protocol of every message and answer with the parse result
+    #[default]
+    Auto,
+    Modbus,
+    Http,
+    Redis,
+    Ftp,
+    Smtp,
+    Websocket,
+    Telnet,
+    Custom,
+    /// Answer with mock rules
+    Mock,
+    /// Emulate a Modbus TCP slave
+    ModbusSlave,
+    /// Emulate an in-memory Redis server
+    RedisServer,
+    /// Hold FTP control-channel sessions
+    FtpServer,
+    /// Hold SMTP sessions and accept mail
+    SmtpServer,
+}
+
+impl Handler {
+    /// Whether a listener with this handler parses messages as `protocol`
+    pub fn accepts(self, protocol: Handler) -> bool {
+        self == Handler::Auto || self == protocol
+    }
+}
+
+impl fmt::Display for Handler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Handler::Auto => "auto",
+            Handler::Modbus => "modbus",
+            Handler::Http => "http",
+            Handler::Redis => "redis",
+            Handler::Ftp => "ftp",
+            Handler::Smtp => "smtp",
+            Handler::Websocket => "websocket",
+            Handler::Telnet => "telnet",
+            Handler::Custom => "custom",
+            Handler::Mock => "mock",
+            Handler::ModbusSlave => "modbus-slave",
+            Handler::RedisServer => "redis-server",
+            Handler::FtpServer => "ftp-server",
+            Handler::SmtpServer => "smtp-server",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Handler {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        serde_json::from_value(serde_json::Value::String(s.to_lowercase()))
+            .map_err(|_| anyhow::anyhow!("unknown handler '{}'", s))
+    }
+}
+
+pub fn load(path: &str) -> anyhow::Result<ServerConfig> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+    serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("{}: {}", path, e))
+}
+
+/// Listeners from the arguments: `--config` and `--listen` add listeners; without either,
+/// `--bind`, `--port` and `--mock` describe the single default one.
+/// `--mock`, `--registers` and `--mail-dir` also apply to `--listen` listeners of that kind that have none.
+pub fn from_args(args: impl IntoIterator<Item = String>) -> anyhow::Result<ServerConfig> {
+    let mut listeners = Vec::new();
+    let mut address = localhost();
+    let mut port = 18080;
+    let mut mock_file = None;
+    let mut registers_file = None;
+    let mut mail_dir = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| anyhow::anyhow!("{} needs a value ({})", arg, USAGE));
+        match arg.as_str() {
+            "--config" => listeners.extend(load(&value()?)?.listeners),
+            "--listen" => listeners.push(parse_listen(&value()?)?),
+            "--bind" => address = value()?,
+            "--port" => port = value()?.parse().map_err(|_| anyhow::anyhow!("--port needs a number"))?,
+            "--mock" => mock_file = Some(value()?),
+            "--registers" => registers_file = Some(value()?),
+            "--mail-dir" => mail_dir = Some(value()?),
+            other => anyhow::bail!("unknown argument '{}' ({})", other, USAGE),
+        }
+    }
+
+    if listeners.is_empty() {
+        let handler = if mock_file.is_some() { Handler::Mock } else { Handler::Auto };
+        listeners.push(ListenerConfig { address, port, handler, mock: None, registers: None, mail_dir: None, tls: None });
+    }
+    for listener in &mut listeners {
+        if listener.handler == Handler::Mock && listener.mock.is_none() {
+            listener.mock = mock_file.clone();
+        }
+        if listener.handler == Handler::ModbusSlave && listener.registers.is_none() {
+            listener.registers = registers_file.clone();
+        }
+        if listener.handler == Handler::SmtpServer && listener.mail_dir.is_none() {
+            listener.mail_dir = mail_dir.clone();
+        }
+    }
+    Ok(ServerConfig { listeners })
+}
+
+/// `[ADDR:]PORT[/HANDLER]`, e.g. `18080`, `0.0.0.0:1502/modbus` or `[::1]:0/http`
+fn parse_listen(spec: &str) -> anyhow::Result<ListenerConfig> {
+    let (endpoint, handler) = match spec.rsplit_once('/') {
+        Some((endpoint, handler)) => (endpoint, handler.parse()?),
+        None => (spec, Handler::Auto),
+    };
+    let (address, port) = match endpoint.rsplit_once(':') {
+        Some((address, port)) => (address.trim_start_matches('[').trim_end_matches(']').to_string(), port),
+        None => (localhost(), endpoint),
+    };
+    let port = port
+        .parse()
+        .map_err(|_| anyhow::anyhow!("--listen {}: bad port '{}'", spec, port))?;
+    Ok(ListenerConfig { address, port, handler, mock: None, registers: None, mail_dir: None, tls: None })
+}
@@ -0,0 +1,142 @@
+//! Deliberate misbehaviour of mock replies, to exercise client framing and timeout paths.
+//!
+//! A rule lists faults, each firing independently with its `probability` (1 when unset):
+//!
+//! ```json
+//! "faults": [
+//!   { "kind": "delay", "ms": 3000, "probability": 0.1 },
+//!   { "kind": "split", "chunks": 4, "gapMs": 20 },
+//!   { "kind": "coalesce" },
+//!   { "kind": "truncate", "keep": 3 },
+//!   { "kind": "corrupt", "bytes": 2 },
+//!   { "kind": "closeMidFrame" },
+//!   { "kind": "reset" },
+//!   { "kind": "noResponse", "probability": 0.05 }
+//! ]
+//! ```
+
+use rand::Rng;
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Fault {
+    #[serde(default = "always")]
+    pub probability: f64,
+    #[serde(flatten)]
+    pub kind: FaultKind,
+}
+
+fn always() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FaultKind {
+    /// Wait before replying, on top of the rule's delay
+    Delay { ms: u64 },
+    /// Write the reply in `chunks` pieces, `gapMs` apart
+    #[serde(rename_all = "camelCase")]
+    Split { chunks: usize, #[serde(default)] gap_ms: u64 },
+    /// Hold the reply back and send it in one write with the next one
+    Coalesce,
+    /// Send only the first `keep` bytes (half when unset)
+    Truncate { #[serde(default)] keep: Option<usize> },
+    /// Flip `bytes` random bytes (one when unset)
+    Corrupt { #[serde(default)] bytes: Option<usize> },
+    /// Send the first half of the reply, then close the connection
+    CloseMidFrame,
+    /// Abort the connection with a TCP reset instead of replying
+    Reset,
+    /// Swallow the request
+    NoResponse,
+}
+
+impl fmt::Display for FaultKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FaultKind::Delay { ms } => write!(f, "delay {} ms", ms),
+            FaultKind::Split { chunks, gap_ms } => write!(f, "split in {} ({} ms gaps)", chunks, gap_ms),
+            FaultKind::Coalesce => write!(f, "coalesce"),
+            FaultKind::Truncate { .. } => write!(f, "truncate"),
+            FaultKind::Corrupt { .. } => write!(f, "corrupt"),
+            FaultKind::CloseMidFrame => write!(f, "close mid-frame"),
+            FaultKind::Reset => write!(f, "reset"),
+            FaultKind::NoResponse => write!(f, "no response"),
+        }
+    }
+}
+
+/// What the faults that fired do to one reply
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub delay_ms: u64,
+    pub split: Option<(usize, u64)>,
+    pub coalesce: bool,
+    pub truncate: Option<Option<usize>>,
+    pub corrupt: usize,
+    pub close_mid_frame: bool,
+    pub reset: bool,
+    pub no_response: bool,
+    /// Faults that fired, for the log
+    pub fired: Vec<String>,
+}
+
+pub fn check(faults: &[Fault]) -> Result<(), String> {
+    for fault in faults {
+        if !(0.0..=1.0).contains(&fault.probability) {
+            return Err(format!("{}: probability must be between 0 and 1", fault.kind));
+        }
+        if let FaultKind::Split { chunks: 0, .. } = fault.kind {
+            return Err("split: chunks must be at least 1".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Roll each fault
+pub fn plan(faults: &[Fault]) -> Plan {
+    let mut rng = rand::thread_rng();
+    let mut plan = Plan::default();
+    for fault in faults {
+        if !rng.gen_bool(fault.probability) {
+            continue;
+        }
+        plan.fired.push(fault.kind.to_string());
+        match fault.kind {
+            FaultKind::Delay { ms } => plan.delay_ms += ms,
+            FaultKind::Split { chunks, gap_ms } => plan.split = Some((chunks, gap_ms)),
+            FaultKind::Coalesce => plan.coalesce = true,
+            FaultKind::Truncate { keep } => plan.truncate = Some(keep),
+            FaultKind::Corrupt { bytes } => plan.corrupt += bytes.unwrap_or(1),
+            FaultKind::CloseMidFrame => plan.close_mid_frame = true,
+            FaultKind::Reset => plan.reset = true,
+            FaultKind::NoResponse => plan.no_response = true,
+        }
+    }
+    plan
+}
+
+impl Plan {
+    /// Truncate and corrupt the reply
+    pub fn damage(&self, data: &mut Vec<u8>) {
+        if let Some(keep) = self.truncate {
+            data.truncate(keep.unwrap_or(data.len() / 2));
+        }
+        if data.is_empty() {
+            return;
+        }
+        let mut rng = rand::thread_rng();
+        for _ in 0..self.corrupt {
+            let i = rng.gen_range(0..data.len());
+            data[i] ^= rng.gen_range(1..=255u8);
+        }
+    }
+}
+
+/// Split `data` into `chunks` nearly equal, non-empty pieces
+pub fn chunks(data: &[u8], chunks: usize) -> Vec<&[u8]> {
+    let size = data.len().div_ceil(chunks.max(1)).max(1);
+    data.chunks(size).collect()
+}
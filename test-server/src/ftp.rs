@@ -0,0 +1,98 @@
+//! FTP control channel: login with `USER`/`PASS` (any password), then the commands that need
+//! no data connection. Transfers are refused with 425 since there is no data channel.
+
+use crate::line::{self, Reply};
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Connected,
+    /// `USER` given, waiting for `PASS`
+    User(String),
+    LoggedIn,
+}
+
+#[derive(Default)]
+pub struct Session {
+    state: State,
+    cwd: String,
+}
+
+impl line::Session for Session {
+    fn banner(&self) -> Reply {
+        Reply::new(220, "tcp-test-server FTP ready")
+    }
+
+    fn line(&mut self, line: &str) -> Reply {
+        let (verb, arg) = line::split_command(line);
+        println!("   📨 {}", if verb == "PASS" { "PASS ****" } else { line });
+
+        match verb.as_str() {
+            "USER" if arg.is_empty() => return Reply::new(501, "Syntax error in parameters or arguments"),
+            "USER" => {
+                self.state = State::User(arg.to_string());
+                return Reply::new(331, "User name okay, need password");
+            }
+            "PASS" => {
+                return match std::mem::take(&mut self.state) {
+                    State::User(user) => {
+                        let reply = Reply::new(230, &format!("User {} logged in", user));
+                        self.state = State::LoggedIn;
+                        self.cwd = "/".to_string();
+                        reply
+                    }
+                    state @ State::LoggedIn => {
+                        self.state = state;
+                        Reply::new(503, "Already logged in")
+                    }
+                    State::Connected => Reply::new(503, "Login with USER first"),
+                };
+            }
+            "QUIT" => return Reply::new(221, "Goodbye").and_close(),
+            "NOOP" => return Reply::new(200, "NOOP ok"),
+            "SYST" => return Reply::new(215, "UNIX Type: L8"),
+            "FEAT" => return Reply::multi(211, &["Features:", " UTF8", "End"]),
+            _ => {}
+        }
+
+        if !matches!(self.state, State::LoggedIn) {
+            return Reply::new(530, "Please login with USER and PASS");
+        }
+        match verb.as_str() {
+            "PWD" | "XPWD" => Reply::new(257, &format!("\"{}\" is the current directory", self.cwd)),
+            "CWD" if arg.is_empty() => Reply::new(501, "Syntax error in parameters or arguments"),
+            "CWD" => {
+                self.cwd = resolve(&self.cwd, arg);
+                Reply::new(250, "Directory successfully changed")
+            }
+            "CDUP" => {
+                self.cwd = resolve(&self.cwd, "..");
+                Reply::new(250, "Directory successfully changed")
+            }
+            "TYPE" => match arg.to_ascii_uppercase().as_str() {
+                "A" | "A N" => Reply::new(200, "Switching to ASCII mode"),
+                "I" | "L 8" => Reply::new(200, "Switching to Binary mode"),
+                _ => Reply::new(504, "Command not implemented for that parameter"),
+            },
+            "MODE" | "STRU" => Reply::new(200, "OK"),
+            "LIST" | "NLST" | "MLSD" | "RETR" | "STOR" | "APPE" => Reply::new(425, "Use PORT or PASV first"),
+            "PASV" | "EPSV" | "PORT" | "EPRT" => Reply::new(502, "Data connections are not supported"),
+            _ => Reply::new(502, "Command not implemented"),
+        }
+    }
+}
+
+/// Apply a `CWD` argument to the current directory
+fn resolve(cwd: &str, path: &str) -> String {
+    let mut parts: Vec<&str> = if path.starts_with('/') { Vec::new() } else { cwd.split('/').filter(|p| !p.is_empty()).collect() };
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    format!("/{}", parts.join("/"))
+}
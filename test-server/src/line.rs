@@ -0,0 +1,106 @@
+//! Command/reply sessions over text lines, as spoken on FTP and SMTP control channels:
+//! a banner when the connection opens, then numbered replies to one command per line.
+
+use crate::Stream;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Longest line accepted before the connection is dropped
+const MAX_LINE: usize = 64 * 1024;
+
+/// What to send back for one line
+#[derive(Default)]
+pub struct Reply {
+    pub text: String,
+    /// Close the connection after sending
+    pub close: bool,
+}
+
+impl Reply {
+    /// `<code> <text>`
+    pub fn new(code: u16, text: &str) -> Reply {
+        Reply { text: format!("{} {}\r\n", code, text), close: false }
+    }
+
+    /// A reply over several lines: `<code>-<line>` for all but the last
+    pub fn multi(code: u16, lines: &[&str]) -> Reply {
+        let last = lines.len().saturating_sub(1);
+        let text = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| format!("{}{}{}\r\n", code, if i == last { ' ' } else { '-' }, line))
+            .collect();
+        Reply { text, close: false }
+    }
+
+    /// Nothing to send, e.g. while a mail body is coming in
+    pub fn none() -> Reply {
+        Reply::default()
+    }
+
+    pub fn and_close(mut self) -> Reply {
+        self.close = true;
+        self
+    }
+}
+
+/// The state of one session
+pub trait Session: Send {
+    fn banner(&self) -> Reply;
+    /// Handle one line, without its line ending
+    fn line(&mut self, line: &str) -> Reply;
+}
+
+pub async fn handle_connection(mut stream: Box<dyn Stream>, mut session: impl Session) {
+    let banner = session.banner();
+    if let Err(e) = stream.write_all(banner.text.as_bytes()).await {
+        eprintln!("   ❌ Send error: {}", e);
+        return;
+    }
+
+    let mut buffer = Vec::new();
+    let mut chunk = vec![0u8; 4096];
+    loop {
+        match stream.read(&mut chunk).await {
+            Ok(0) => break,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            Err(e) => {
+                eprintln!("   ❌ Read error: {}", e);
+                break;
+            }
+        }
+
+        // Pipelined commands are answered in order, in one write
+        let mut out = String::new();
+        let mut close = false;
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let raw: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let reply = session.line(line.trim_end_matches(['\r', '\n']));
+            out.push_str(&reply.text);
+            if reply.close {
+                close = true;
+                break;
+            }
+        }
+        if buffer.len() > MAX_LINE {
+            eprintln!("   ❌ Line longer than {} bytes, closing", MAX_LINE);
+            close = true;
+        }
+
+        if !out.is_empty() {
+            if let Err(e) = stream.write_all(out.as_bytes()).await {
+                eprintln!("   ❌ Send error: {}", e);
+                break;
+            }
+        }
+        if close {
+            break;
+        }
+    }
+}
+
+/// Split a command line into its upper-cased verb and the rest
+pub fn split_command(line: &str) -> (String, &str) {
+    let (verb, rest) = line.split_once(' ').unwrap_or((line, ""));
+    (verb.to_ascii_uppercase(), rest.trim())
+}
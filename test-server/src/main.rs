@@ -1,79 +1,160 @@
-mod protocols;
+mod config;
+mod faults;
+mod ftp;
+mod line;
+mod mock;
+mod modbus;
+mod redis;
+mod smtp;
+#[cfg(feature = "tls")]
+mod tls;
 
-use protocols::*;
-use std::collections::HashMap;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpListener;
+use config::{Handler, ListenerConfig};
+use packetforge_core::detect as protocols;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 
 #[derive(Debug, serde::Serialize)]
 struct ServerResponse {
     protocol: String,
     protocol_display: String,
     operation: String,
-    fields: HashMap<String, serde_json::Value>,
+    fields: protocols::Fields,
     raw_hex: String,
     raw_ascii: String,
     message: String,
 }
 
+/// A client connection, plain or TLS
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+#[cfg(feature = "tls")]
+type Acceptor = tokio_rustls::TlsAcceptor;
+#[cfg(not(feature = "tls"))]
+type Acceptor = std::convert::Infallible;
+
+/// What a listener does with its connections
+enum Service {
+    Parse(Handler),
+    Mock(Arc<mock::Mock>),
+    ModbusSlave(Arc<modbus::Slave>),
+    RedisServer(Arc<redis::Store>),
+    FtpServer,
+    SmtpServer(Arc<smtp::Mailbox>),
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let listener = TcpListener::bind("127.0.0.1:18080").await?;
-    println!("🚀 TCP Test Server listening on 127.0.0.1:18080");
-    println!("📋 Supported protocols: Modbus TCP, HTTP GET/POST, FTP, SMTP, WebSocket, Redis RESP, Telnet");
+    let config = config::from_args(std::env::args().skip(1))?;
+
+    // Bind every listener before serving any, so a bad one stops the server right away
+    let mut servers = Vec::new();
+    for listener in &config.listeners {
+        servers.push(bind(listener).await?);
+    }
     println!("📨 Waiting for connections...\n");
 
+    let tasks: Vec<_> = servers
+        .into_iter()
+        .map(|(listener, service, tls)| tokio::spawn(serve(listener, service, tls)))
+        .collect();
+    for task in tasks {
+        task.await?;
+    }
+    Ok(())
+}
+
+async fn bind(config: &ListenerConfig) -> anyhow::Result<(TcpListener, Arc<Service>, Option<Acceptor>)> {
+    let service = match config.handler {
+        Handler::Mock => {
+            let path = config.mock.as_deref().ok_or_else(|| {
+                anyhow::anyhow!("mock listener on port {} needs a rules file (--mock or \"mock\")", config.port)
+            })?;
+            Service::Mock(mock::prepare(mock::load(path)?)?)
+        }
+        // Without a file every register starts at zero
+        Handler::ModbusSlave => Service::ModbusSlave(modbus::prepare(match config.registers.as_deref() {
+            Some(path) => modbus::load(path)?,
+            None => Default::default(),
+        })?),
+        Handler::RedisServer => Service::RedisServer(redis::prepare()),
+        Handler::FtpServer => Service::FtpServer,
+        Handler::SmtpServer => Service::SmtpServer(smtp::prepare(config.mail_dir.as_deref())?),
+        handler => Service::Parse(handler),
+    };
+    let tls = match &config.tls {
+        #[cfg(feature = "tls")]
+        Some(tls) => Some(tls::acceptor(tls)?),
+        #[cfg(not(feature = "tls"))]
+        Some(_) => anyhow::bail!("TLS listeners need tcp-test-server built with --features tls"),
+        None => None,
+    };
+
+    let listener = TcpListener::bind((config.address.as_str(), config.port))
+        .await
+        .map_err(|e| anyhow::anyhow!("{}:{}: {}", config.address, config.port, e))?;
+    println!(
+        "🚀 TCP Test Server [{}{}] listening on {}",
+        config.handler,
+        if tls.is_some() { ", TLS" } else { "" },
+        listener.local_addr()?
+    );
+    if config.handler == Handler::Auto {
+        println!("📋 Supported protocols: Modbus TCP, HTTP GET/POST, FTP, SMTP, WebSocket, Redis RESP, Telnet");
+    }
+    Ok((listener, Arc::new(service), tls))
+}
+
+async fn serve(listener: TcpListener, service: Arc<Service>, tls: Option<Acceptor>) {
+    let tls = Arc::new(tls);
     loop {
         match listener.accept().await {
-            Ok((mut socket, addr)) => {
+            Ok((socket, addr)) => {
                 println!("📥 New connection from: {}", addr);
-
+                let service = service.clone();
+                let tls = tls.clone();
                 tokio::spawn(async move {
-                    let mut buf = vec![0u8; 4096];
-                    let mut response_count = 0;
-
-                    loop {
-                        match socket.read(&mut buf).await {
-                            Ok(0) => {
-                                println!("   Connection closed by client");
-                                break;
-                            }
-                            Ok(n) => {
-                                response_count += 1;
-                                let data = &buf[..n];
-
-                                // 检测协议类型并解析
-                                let response = parse_and_respond(data, response_count);
-
-                                // 打印到控制台
-                                println!("   📨 [{}] {} - {}", response_count, response.protocol, response.operation);
-
-                                // 发送JSON响应回客户端
-                                let json_response = serde_json::to_string_pretty(&response)
-                                    .unwrap_or_else(|_| "Error formatting response".to_string());
-
-                                if let Err(e) = socket.write_all(json_response.as_bytes()).await {
-                                    eprintln!("   ❌ Send error: {}", e);
-                                    break;
-                                }
-                                if let Err(e) = socket.write_all(b"\n\n").await {
-                                    eprintln!("   ❌ Send error: {}", e);
-                                    break;
-                                }
-
-                                // 打印详细字段信息
-                                for (key, value) in &response.fields {
-                                    println!("      {} = {}", key, value);
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("   ❌ Read error: {}", e);
-                                break;
-                            }
+                    // The mock resets connections through its own handle to the socket
+                    let control = match socket2::SockRef::from(&socket).try_clone() {
+                        Ok(control) => control,
+                        Err(e) => {
+                            eprintln!("   ❌ Socket error: {}", e);
+                            return;
+                        }
+                    };
+                    let stream = match secure(socket, &tls).await {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            eprintln!("   ❌ TLS handshake failed: {}", e);
+                            return;
+                        }
+                    };
+                    match &*service {
+                        Service::Parse(handler) => answer(stream, *handler).await,
+                        Service::Mock(mock) => {
+                            mock::handle_connection(stream, control, mock.clone()).await;
+                            println!("   ✅ Connection from {} closed\n", addr);
+                        }
+                        Service::ModbusSlave(slave) => {
+                            modbus::handle_connection(stream, slave.clone()).await;
+                            println!("   ✅ Connection from {} closed\n", addr);
+                        }
+                        Service::RedisServer(store) => {
+                            redis::handle_connection(stream, store.clone()).await;
+                            println!("   ✅ Connection from {} closed\n", addr);
+                        }
+                        Service::FtpServer => {
+                            line::handle_connection(stream, ftp::Session::default()).await;
+                            println!("   ✅ Connection from {} closed\n", addr);
+                        }
+                        Service::SmtpServer(mailbox) => {
+                            line::handle_connection(stream, smtp::Session::new(mailbox.clone())).await;
+                            println!("   ✅ Connection from {} closed\n", addr);
                         }
                     }
-
-                    println!("   ✅ Connection closed (total messages: {})\n", response_count);
                 });
             }
             Err(e) => {
@@ -83,12 +164,73 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
-fn parse_and_respond(data: &[u8], msg_id: usize) -> ServerResponse {
+/// Complete the TLS handshake on listeners with TLS
+async fn secure(socket: TcpStream, tls: &Option<Acceptor>) -> std::io::Result<Box<dyn Stream>> {
+    match tls {
+        #[cfg(feature = "tls")]
+        Some(acceptor) => Ok(Box::new(acceptor.accept(socket).await?)),
+        #[cfg(not(feature = "tls"))]
+        Some(never) => match *never {},
+        None => Ok(Box::new(socket)),
+    }
+}
+
+/// Answer every message with its parse result as JSON
+async fn answer(mut socket: Box<dyn Stream>, handler: Handler) {
+    let mut buf = vec![0u8; 4096];
+    let mut response_count = 0;
+
+    loop {
+        match socket.read(&mut buf).await {
+            Ok(0) => {
+                println!("   Connection closed by client");
+                break;
+            }
+            Ok(n) => {
+                response_count += 1;
+                let data = &buf[..n];
+
+                // 检测协议类型并解析
+                let response = parse_and_respond(data, handler);
+
+                // 打印到控制台
+                println!("   📨 [{}] {} - {}", response_count, response.protocol, response.operation);
+
+                // 发送JSON响应回客户端
+                let json_response = serde_json::to_string_pretty(&response)
+                    .unwrap_or_else(|_| "Error formatting response".to_string());
+
+                if let Err(e) = socket.write_all(json_response.as_bytes()).await {
+                    eprintln!("   ❌ Send error: {}", e);
+                    break;
+                }
+                if let Err(e) = socket.write_all(b"\n\n").await {
+                    eprintln!("   ❌ Send error: {}", e);
+                    break;
+                }
+
+                // 打印详细字段信息
+                for (key, value) in &response.fields {
+                    println!("      {} = {}", key, value);
+                }
+            }
+            Err(e) => {
+                eprintln!("   ❌ Read error: {}", e);
+                break;
+            }
+        }
+    }
+
+    println!("   ✅ Connection closed (total messages: {})\n", response_count);
+}
+
+/// Parse with the listener's protocol, or try every protocol in order when it detects
+fn parse_and_respond(data: &[u8], handler: Handler) -> ServerResponse {
     let hex_string: String = data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
     let ascii_string = bytes_to_ascii(data);
 
     // 尝试按协议优先级解析
-    if let Some(result) = protocols::parse_modbus_tcp(data) {
+    if let Some(result) = protocols::parse_modbus_tcp(data).filter(|_| handler.accepts(Handler::Modbus)) {
         return ServerResponse {
             protocol: "Modbus TCP".to_string(),
             protocol_display: "Modbus TCP (MBAP Header + PDU)".to_string(),
@@ -101,7 +243,7 @@ fn parse_and_respond(data: &[u8], msg_id: usize) -> ServerResponse {
         };
     }
 
-    if let Some(result) = protocols::parse_http(data) {
+    if let Some(result) = protocols::parse_http(data).filter(|_| handler.accepts(Handler::Http)) {
         return ServerResponse {
             protocol: result.method.clone(),
             protocol_display: format!("HTTP/1.1 {}", result.method),
@@ -113,7 +255,7 @@ fn parse_and_respond(data: &[u8], msg_id: usize) -> ServerResponse {
         };
     }
 
-    if let Some(result) = protocols::parse_redis_resp(data) {
+    if let Some(result) = protocols::parse_redis_resp(data).filter(|_| handler.accepts(Handler::Redis)) {
         return ServerResponse {
             protocol: "Redis RESP".to_string(),
             protocol_display: "Redis Serialization Protocol (RESP)".to_string(),
@@ -125,7 +267,7 @@ fn parse_and_respond(data: &[u8], msg_id: usize) -> ServerResponse {
         };
     }
 
-    if let Some(result) = protocols::parse_ftp(data) {
+    if let Some(result) = protocols::parse_ftp(data).filter(|_| handler.accepts(Handler::Ftp)) {
         return ServerResponse {
             protocol: "FTP".to_string(),
             protocol_display: "File Transfer Protocol".to_string(),
@@ -137,7 +279,7 @@ fn parse_and_respond(data: &[u8], msg_id: usize) -> ServerResponse {
         };
     }
 
-    if let Some(result) = protocols::parse_smtp(data) {
+    if let Some(result) = protocols::parse_smtp(data).filter(|_| handler.accepts(Handler::Smtp)) {
         return ServerResponse {
             protocol: "SMTP".to_string(),
             protocol_display: "Simple Mail Transfer Protocol".to_string(),
@@ -149,7 +291,7 @@ fn parse_and_respond(data: &[u8], msg_id: usize) -> ServerResponse {
         };
     }
 
-    if let Some(result) = protocols::parse_websocket(data) {
+    if let Some(result) = protocols::parse_websocket(data).filter(|_| handler.accepts(Handler::Websocket)) {
         return ServerResponse {
             protocol: "WebSocket".to_string(),
             protocol_display: "WebSocket Handshake (RFC 6455)".to_string(),
@@ -161,7 +303,7 @@ fn parse_and_respond(data: &[u8], msg_id: usize) -> ServerResponse {
         };
     }
 
-    if let Some(result) = protocols::parse_telnet(data) {
+    if let Some(result) = protocols::parse_telnet(data).filter(|_| handler.accepts(Handler::Telnet)) {
         return ServerResponse {
             protocol: "Telnet".to_string(),
             protocol_display: "Telnet Protocol (RFC 854)".to_string(),
@@ -173,7 +315,7 @@ fn parse_and_respond(data: &[u8], msg_id: usize) -> ServerResponse {
         };
     }
 
-    if let Some(result) = protocols::parse_custom_header(data) {
+    if let Some(result) = protocols::parse_custom_header(data).filter(|_| handler.accepts(Handler::Custom)) {
         return ServerResponse {
             protocol: "Custom Header".to_string(),
             protocol_display: "自定义协议头".to_string(),
@@ -192,7 +334,7 @@ fn parse_and_respond(data: &[u8], msg_id: usize) -> ServerResponse {
         protocol_display: "未知协议".to_string(),
         operation: "原始数据".to_string(),
         fields: {
-            let mut f = HashMap::new();
+            let mut f = protocols::Fields::new();
             f.insert("data_length".to_string(), serde_json::json!(data.len()));
             f.insert("preview".to_string(), serde_json::json!(ascii_string));
             f
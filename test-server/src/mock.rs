@@ -0,0 +1,515 @@
+//! Mock mode: impersonate a device with rules loaded from a JSON file.
+//!
+//! Each rule matches the data at the front of a connection's buffer, by decoding it with a
+//! protocol, by field values of that decode, or by a byte pattern, and answers with a reply
+//! built from a protocol (optionally copying request fields with `{{request.<field>}}`), raw hex
+//! or text. Timers send unsolicited replies on every connection. Rules can also list `faults`
+//! that make their replies misbehave (see `faults`).
+//!
+//! ```json
+//! {
+//!   "protocols": [ ...protocols as exported from the app's protocol library... ],
+//!   "greeting": { "text": "READY\r\n" },
+//!   "rules": [{
+//!     "name": "read holding registers",
+//!     "match": { "protocol": "Modbus Request", "fields": { "function": "03" } },
+//!     "reply": { "protocol": "Modbus Response", "fields": { "transaction_id": "{{request.transaction_id}}" } },
+//!     "delayMs": 20
+//!   }, {
+//!     "match": { "bytes": "AA ?? 01" },
+//!     "reply": { "hex": "AA 00 81" }
+//!   }],
+//!   "timers": [{ "intervalMs": 5000, "reply": { "text": "heartbeat\r\n" } }]
+//! }
+//! ```
+
+use crate::faults::{self, Fault};
+use packetforge_core::model::{FieldKind, ProtocolField};
+use packetforge_core::{DecodedField, ProtocolError};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::Stream;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, WriteHalf};
+use tokio::sync::Mutex;
+
+/// A protocol as exported from the app's protocol library
+#[derive(Debug, Clone, Deserialize)]
+pub struct Protocol {
+    pub name: String,
+    pub fields: Vec<ProtocolField>,
+}
+
+/// A decoded request field: raw bytes and the value in the field's own format
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+/// Decoded fields by name, nested ones included; a name used twice keeps its first value
+pub type Values = HashMap<String, Value>;
+
+#[derive(Debug, Deserialize)]
+pub struct MockConfig {
+    #[serde(default)]
+    pub protocols: Vec<Protocol>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Sent to every client as soon as it connects
+    #[serde(default)]
+    pub greeting: Option<Reply>,
+    #[serde(default)]
+    pub timers: Vec<Timer>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "match")]
+    pub matcher: Match,
+    /// Matching data is swallowed without an answer when unset
+    #[serde(default)]
+    pub reply: Option<Reply>,
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Misbehaviour of the reply, see `faults`
+    #[serde(default)]
+    pub faults: Vec<Fault>,
+}
+
+/// All given conditions must hold
+#[derive(Debug, Default, Deserialize)]
+pub struct Match {
+    /// Protocol the request must decode with; the decode also delimits the request
+    #[serde(default)]
+    pub protocol: Option<String>,
+    /// Field values of the decode, compared ignoring case, spaces and `0x`
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+    /// Hex the request must start with; `??` matches any byte
+    #[serde(default)]
+    pub bytes: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Reply {
+    /// Protocol to encode, with its field values replaced by `fields`
+    #[serde(default)]
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+    #[serde(default)]
+    pub hex: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Timer {
+    pub interval_ms: u64,
+    pub reply: Reply,
+}
+
+/// Byte pattern with wildcards
+type Pattern = Vec<Option<u8>>;
+
+/// Configuration checked and indexed for serving
+pub struct Mock {
+    config: MockConfig,
+    protocols: HashMap<String, Protocol>,
+    patterns: Vec<Option<Pattern>>,
+}
+
+enum Outcome {
+    /// Rule index and the size of the request it matched
+    Matched(usize, usize, Values),
+    Incomplete,
+    NoMatch,
+}
+
+impl Mock {
+    fn new(config: MockConfig) -> anyhow::Result<Self> {
+        let mut protocols = HashMap::new();
+        for protocol in &config.protocols {
+            protocols.insert(protocol.name.clone(), protocol.clone());
+        }
+
+        let mut patterns = Vec::new();
+        for (i, rule) in config.rules.iter().enumerate() {
+            let label = rule_label(rule, i);
+            if let Some(name) = &rule.matcher.protocol {
+                if !protocols.contains_key(name) {
+                    anyhow::bail!("{}: unknown protocol '{}'", label, name);
+                }
+            } else if !rule.matcher.fields.is_empty() {
+                anyhow::bail!("{}: matching fields needs a protocol", label);
+            }
+            if let Some(reply) = &rule.reply {
+                check_reply(reply, &protocols).map_err(|e| anyhow::anyhow!("{}: {}", label, e))?;
+            }
+            faults::check(&rule.faults).map_err(|e| anyhow::anyhow!("{}: {}", label, e))?;
+            patterns.push(rule.matcher.bytes.as_deref().map(parse_pattern).transpose().map_err(|e| anyhow::anyhow!("{}: {}", label, e))?);
+        }
+        for timer in &config.timers {
+            check_reply(&timer.reply, &protocols)?;
+        }
+        if let Some(greeting) = &config.greeting {
+            check_reply(greeting, &protocols)?;
+        }
+
+        Ok(Self { config, protocols, patterns })
+    }
+
+    /// First rule matching the front of `buffer`. When none matches but a rule might once
+    /// more data arrives, the buffer is kept.
+    fn match_request(&self, buffer: &[u8]) -> Outcome {
+        let mut incomplete = false;
+        for (i, rule) in self.config.rules.iter().enumerate() {
+            let (values, used) = match &rule.matcher.protocol {
+                Some(name) => match packetforge_core::decode_prefix(&self.protocols[name].fields, buffer) {
+                    Ok((decoded, used)) => {
+                        let mut values = Values::new();
+                        collect_values(&decoded, &mut values);
+                        (values, used)
+                    }
+                    Err(ProtocolError::UnexpectedEnd(_)) => {
+                        incomplete = true;
+                        continue;
+                    }
+                    Err(_) => continue,
+                },
+                None => (Values::new(), buffer.len()),
+            };
+
+            if let Some(pattern) = &self.patterns[i] {
+                if pattern.len() > used {
+                    if pattern.len() > buffer.len() && prefix_matches(pattern, buffer) {
+                        incomplete = true;
+                    }
+                    continue;
+                }
+                if !prefix_matches(pattern, &buffer[..used]) {
+                    continue;
+                }
+            }
+
+            let fields_match = rule.matcher.fields.iter().all(|(name, expected)| {
+                values.get(name).is_some_and(|value| same_value(&value.text, expected))
+            });
+            if fields_match {
+                return Outcome::Matched(i, used, values);
+            }
+        }
+
+        if incomplete { Outcome::Incomplete } else { Outcome::NoMatch }
+    }
+
+    fn build_reply(&self, reply: &Reply, request: &Values) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        if let Some(name) = &reply.protocol {
+            let mut values = HashMap::new();
+            let mut raw = HashMap::new();
+            for (field, value) in &reply.fields {
+                match value.trim().strip_prefix("{{request.").and_then(|v| v.strip_suffix("}}")) {
+                    Some(source) => {
+                        let copied = request
+                            .get(source.trim())
+                            .ok_or_else(|| format!("request has no field '{}'", source.trim()))?;
+                        raw.insert(field.clone(), copied.bytes.clone());
+                    }
+                    None => {
+                        values.insert(field.clone(), value.clone());
+                    }
+                }
+            }
+            let fields = with_values(&self.protocols[name].fields, &values, &raw);
+            data.extend(packetforge_core::encode(&fields).map_err(|e| e.to_string())?);
+        }
+        if let Some(hex) = &reply.hex {
+            data.extend(packetforge_core::hex_string_to_bytes(hex)?);
+        }
+        if let Some(text) = &reply.text {
+            data.extend_from_slice(text.as_bytes());
+        }
+        Ok(data)
+    }
+}
+
+fn collect_values(fields: &[DecodedField], values: &mut Values) {
+    for field in fields {
+        if !values.contains_key(&field.name) {
+            let bytes = packetforge_core::hex_string_to_bytes(&field.hex).unwrap_or_default();
+            values.insert(field.name.clone(), Value { bytes, text: field.value.clone() });
+        }
+        collect_values(&field.fields, values);
+    }
+}
+
+/// Fields with their configured values replaced by `values` (in the field's own format) or
+/// `raw` (bytes) where given, at any depth
+fn with_values(fields: &[ProtocolField], values: &HashMap<String, String>, raw: &HashMap<String, Vec<u8>>) -> Vec<ProtocolField> {
+    fields
+        .iter()
+        .map(|field| {
+            let mut field = field.clone();
+            if let Some(bytes) = raw.get(&field.name).filter(|_| field.kind == FieldKind::Scalar) {
+                // Copied bytes go out as they came in, whatever the field's format
+                if field.is_text() {
+                    field.value = String::from_utf8_lossy(bytes).into_owned();
+                } else if field.is_variable {
+                    field.value_type = "hex".to_string();
+                    field.value = packetforge_core::hex::bytes_to_plain_hex(bytes);
+                } else {
+                    field.value_format = Some("hex".to_string());
+                    field.endianness = None;
+                    field.value = packetforge_core::hex::bytes_to_plain_hex(bytes);
+                }
+            } else if let Some(value) = values.get(&field.name) {
+                field.value = value.clone();
+            }
+            field.fields = with_values(&field.fields, values, raw);
+            field
+        })
+        .collect()
+}
+
+fn rule_label(rule: &Rule, index: usize) -> String {
+    if rule.name.is_empty() {
+        format!("rule {}", index + 1)
+    } else {
+        format!("rule '{}'", rule.name)
+    }
+}
+
+fn check_reply(reply: &Reply, protocols: &HashMap<String, Protocol>) -> anyhow::Result<()> {
+    match &reply.protocol {
+        Some(name) if !protocols.contains_key(name) => anyhow::bail!("unknown reply protocol '{}'", name),
+        None if !reply.fields.is_empty() => anyhow::bail!("reply fields need a protocol"),
+        _ => Ok(()),
+    }
+}
+
+fn parse_pattern(text: &str) -> anyhow::Result<Pattern> {
+    let tokens: Vec<String> = if text.contains(char::is_whitespace) {
+        text.split_whitespace().map(str::to_string).collect()
+    } else {
+        text.as_bytes().chunks(2).map(|c| String::from_utf8_lossy(c).into_owned()).collect()
+    };
+    tokens
+        .iter()
+        .map(|token| match token.as_str() {
+            "??" => Ok(None),
+            _ => u8::from_str_radix(token, 16)
+                .map(Some)
+                .map_err(|_| anyhow::anyhow!("bad byte '{}' in pattern", token)),
+        })
+        .collect()
+}
+
+fn prefix_matches(pattern: &[Option<u8>], data: &[u8]) -> bool {
+    pattern.iter().zip(data).all(|(p, b)| p.is_none_or(|p| p == *b))
+}
+
+fn same_value(actual: &str, expected: &str) -> bool {
+    let normalize = |s: &str| {
+        let s = s.trim();
+        let s = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+        s.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase()
+    };
+    normalize(actual) == normalize(expected)
+}
+
+fn hex_preview(data: &[u8]) -> String {
+    data.iter().take(32).map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+        + if data.len() > 32 { " …" } else { "" }
+}
+
+pub fn load(path: &str) -> anyhow::Result<MockConfig> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+    serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("{}: {}", path, e))
+}
+
+/// Check the rules once for a listener
+pub fn prepare(config: MockConfig) -> anyhow::Result<Arc<Mock>> {
+    let mock = Mock::new(config)?;
+    println!(
+        "🎭 Mock mode: {} rules, {} protocols, {} timers",
+        mock.config.rules.len(),
+        mock.protocols.len(),
+        mock.config.timers.len()
+    );
+    Ok(Arc::new(mock))
+}
+
+/// Write side of a connection, with replies held back by a coalesce fault
+struct Link {
+    writer: WriteHalf<Box<dyn Stream>>,
+    /// The TCP socket under the stream, to reset it
+    socket: socket2::Socket,
+    held: Vec<u8>,
+}
+
+/// Whether the connection stays open after a reply
+#[derive(PartialEq)]
+enum Flow {
+    Open,
+    Closed,
+}
+
+pub async fn handle_connection(stream: Box<dyn Stream>, socket: socket2::Socket, mock: Arc<Mock>) {
+    let (mut reader, writer) = tokio::io::split(stream);
+    let link = Arc::new(Mutex::new(Link { writer, socket, held: Vec::new() }));
+
+    if let Some(greeting) = &mock.config.greeting {
+        if send_reply(&mock, Some(greeting), &Values::new(), &link, "greeting", &[]).await == Flow::Closed {
+            return;
+        }
+    }
+
+    let timers: Vec<_> = (0..mock.config.timers.len())
+        .map(|i| {
+            let mock = mock.clone();
+            let link = link.clone();
+            tokio::spawn(async move {
+                let timer = &mock.config.timers[i];
+                let mut interval = tokio::time::interval(Duration::from_millis(timer.interval_ms.max(1)));
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    if send_reply(&mock, Some(&timer.reply), &Values::new(), &link, "timer", &[]).await == Flow::Closed {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let mut buffer = Vec::new();
+    let mut chunk = vec![0u8; 4096];
+    'read: loop {
+        match reader.read(&mut chunk).await {
+            Ok(0) => break,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            Err(e) => {
+                eprintln!("   ❌ Read error: {}", e);
+                break;
+            }
+        }
+
+        while !buffer.is_empty() {
+            match mock.match_request(&buffer) {
+                Outcome::Matched(i, used, values) => {
+                    let rule = &mock.config.rules[i];
+                    let label = rule_label(rule, i);
+                    println!("   📨 {} matched {}", label, hex_preview(&buffer[..used]));
+                    buffer.drain(..used);
+                    if rule.delay_ms > 0 {
+                        tokio::time::sleep(Duration::from_millis(rule.delay_ms)).await;
+                    }
+                    let flow = send_reply(&mock, rule.reply.as_ref(), &values, &link, &label, &rule.faults).await;
+                    if flow == Flow::Closed {
+                        break 'read;
+                    }
+                }
+                Outcome::Incomplete => break,
+                Outcome::NoMatch => {
+                    println!("   ⚠️  No rule matched {}", hex_preview(&buffer));
+                    buffer.clear();
+                }
+            }
+        }
+    }
+
+    for timer in timers {
+        timer.abort();
+    }
+}
+
+/// Build a reply, apply the faults that fire and write it
+async fn send_reply(
+    mock: &Mock,
+    reply: Option<&Reply>,
+    request: &Values,
+    link: &Mutex<Link>,
+    label: &str,
+    faults: &[Fault],
+) -> Flow {
+    let mut data = match reply.map(|r| mock.build_reply(r, request)).transpose() {
+        Ok(data) => data.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("   ❌ {}: cannot build reply: {}", label, e);
+            return Flow::Open;
+        }
+    };
+
+    let plan = faults::plan(faults);
+    if !plan.fired.is_empty() {
+        println!("   💥 {} faults: {}", label, plan.fired.join(", "));
+    }
+    if plan.no_response {
+        return Flow::Open;
+    }
+    if plan.delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(plan.delay_ms)).await;
+    }
+    plan.damage(&mut data);
+
+    let mut link = link.lock().await;
+    if plan.reset {
+        // Closing with a zero linger time sends RST instead of FIN
+        if let Err(e) = link.socket.set_linger(Some(Duration::ZERO)) {
+            eprintln!("   ❌ Cannot reset: {}", e);
+        }
+        return Flow::Closed;
+    }
+    if plan.coalesce {
+        link.held.extend_from_slice(&data);
+        return Flow::Open;
+    }
+    if reply.is_none() && link.held.is_empty() && !plan.close_mid_frame {
+        return Flow::Open;
+    }
+
+    let mut out = std::mem::take(&mut link.held);
+    out.extend_from_slice(&data);
+    if plan.close_mid_frame {
+        out.truncate(out.len() / 2);
+    }
+
+    let result = match plan.split {
+        Some((chunks, gap_ms)) => {
+            let mut result = Ok(());
+            for (i, piece) in faults::chunks(&out, chunks).into_iter().enumerate() {
+                if i > 0 && gap_ms > 0 {
+                    tokio::time::sleep(Duration::from_millis(gap_ms)).await;
+                }
+                result = link.writer.write_all(piece).await;
+                if result.is_err() {
+                    break;
+                }
+            }
+            result
+        }
+        None => link.writer.write_all(&out).await,
+    };
+
+    match result {
+        Ok(()) => {
+            println!("   📤 {} replied {}", label, hex_preview(&out));
+            if plan.close_mid_frame {
+                let _ = link.writer.shutdown().await;
+                return Flow::Closed;
+            }
+            Flow::Open
+        }
+        Err(e) => {
+            eprintln!("   ❌ Send error: {}", e);
+            Flow::Closed
+        }
+    }
+}
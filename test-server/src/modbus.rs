@@ -0,0 +1,279 @@
+//! Modbus TCP slave: a register map shared by all connections of a listener, read and written
+//! with function codes 01–06, 0F and 10. Anything else gets an exception response.
+//!
+//! Initial values come from a JSON file, keyed by start address; a list fills consecutive addresses:
+//!
+//! ```json
+//! {
+//!   "size": 1000,
+//!   "coils": { "0": true, "8": [1, 0, 1] },
+//!   "discreteInputs": { "0": [true, false] },
+//!   "holdingRegisters": { "0": 1234, "100": [1, 2, 3] },
+//!   "inputRegisters": { "0": [42, 43] }
+//! }
+//! ```
+//!
+//! `size` is the number of addresses in each table (65536 when unset); requests beyond it
+//! get exception 02.
+
+use crate::Stream;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const ILLEGAL_FUNCTION: u8 = 0x01;
+const ILLEGAL_DATA_ADDRESS: u8 = 0x02;
+const ILLEGAL_DATA_VALUE: u8 = 0x03;
+
+const MBAP_LEN: usize = 7;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterFile {
+    #[serde(default)]
+    pub size: Option<usize>,
+    #[serde(default)]
+    pub coils: BTreeMap<u16, Entry<Bit>>,
+    #[serde(default)]
+    pub discrete_inputs: BTreeMap<u16, Entry<Bit>>,
+    #[serde(default)]
+    pub holding_registers: BTreeMap<u16, Entry<u16>>,
+    #[serde(default)]
+    pub input_registers: BTreeMap<u16, Entry<u16>>,
+}
+
+/// One value, or values for consecutive addresses
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Entry<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+/// A coil or discrete input, as `true`/`false` or `1`/`0`
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum Bit {
+    Bool(bool),
+    Number(u8),
+}
+
+impl From<Bit> for bool {
+    fn from(bit: Bit) -> bool {
+        match bit {
+            Bit::Bool(value) => value,
+            Bit::Number(value) => value != 0,
+        }
+    }
+}
+
+struct RegisterMap {
+    coils: Vec<bool>,
+    discrete_inputs: Vec<bool>,
+    holding_registers: Vec<u16>,
+    input_registers: Vec<u16>,
+}
+
+/// The slave of one listener
+pub struct Slave {
+    map: Mutex<RegisterMap>,
+}
+
+pub fn load(path: &str) -> anyhow::Result<RegisterFile> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+    serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("{}: {}", path, e))
+}
+
+/// Build the register map for a listener
+pub fn prepare(file: RegisterFile) -> anyhow::Result<Arc<Slave>> {
+    let size = file.size.unwrap_or(0x10000);
+    if !(1..=0x10000).contains(&size) {
+        anyhow::bail!("size must be between 1 and 65536");
+    }
+    let map = RegisterMap {
+        coils: fill(size, &file.coils, "coils")?,
+        discrete_inputs: fill(size, &file.discrete_inputs, "discreteInputs")?,
+        holding_registers: fill(size, &file.holding_registers, "holdingRegisters")?,
+        input_registers: fill(size, &file.input_registers, "inputRegisters")?,
+    };
+    println!("🏭 Modbus slave: {} addresses per table", size);
+    Ok(Arc::new(Slave { map: Mutex::new(map) }))
+}
+
+fn fill<T, V>(size: usize, entries: &BTreeMap<u16, Entry<V>>, table: &str) -> anyhow::Result<Vec<T>>
+where
+    T: Default + Clone + From<V>,
+    V: Copy,
+{
+    let mut values = vec![T::default(); size];
+    for (&start, entry) in entries {
+        let list = match entry {
+            Entry::One(value) => std::slice::from_ref(value),
+            Entry::Many(list) => list.as_slice(),
+        };
+        let start = start as usize;
+        if start + list.len() > size {
+            anyhow::bail!("{}: values at {} go past the {} addresses", table, start, size);
+        }
+        for (slot, &value) in values[start..].iter_mut().zip(list) {
+            *slot = value.into();
+        }
+    }
+    Ok(values)
+}
+
+pub async fn handle_connection(mut stream: Box<dyn Stream>, slave: Arc<Slave>) {
+    let mut buffer = Vec::new();
+    let mut chunk = vec![0u8; 4096];
+    loop {
+        match stream.read(&mut chunk).await {
+            Ok(0) => break,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            Err(e) => {
+                eprintln!("   ❌ Read error: {}", e);
+                break;
+            }
+        }
+
+        while buffer.len() >= MBAP_LEN {
+            let length = u16::from_be_bytes([buffer[4], buffer[5]]) as usize;
+            if !(2..=254).contains(&length) {
+                eprintln!("   ❌ Bad MBAP length {}, closing", length);
+                return;
+            }
+            if buffer.len() < 6 + length {
+                break;
+            }
+            let frame: Vec<u8> = buffer.drain(..6 + length).collect();
+            if frame[2..4] != [0, 0] {
+                println!("   ⚠️  Ignoring frame with protocol id {:02X}{:02X}", frame[2], frame[3]);
+                continue;
+            }
+
+            let (unit, pdu) = (frame[6], &frame[MBAP_LEN..]);
+            let reply = match slave.execute(pdu) {
+                Ok(reply) => {
+                    println!("   📨 [unit {}] FC 0x{:02X} ok", unit, pdu[0]);
+                    reply
+                }
+                Err(code) => {
+                    println!("   📨 [unit {}] FC 0x{:02X} exception 0x{:02X}", unit, pdu[0], code);
+                    vec![pdu[0] | 0x80, code]
+                }
+            };
+
+            let mut response = Vec::with_capacity(MBAP_LEN + reply.len());
+            response.extend_from_slice(&frame[..4]);
+            response.extend_from_slice(&(reply.len() as u16 + 1).to_be_bytes());
+            response.push(unit);
+            response.extend_from_slice(&reply);
+            if let Err(e) = stream.write_all(&response).await {
+                eprintln!("   ❌ Send error: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+impl Slave {
+    /// Run one request PDU, returning the response PDU or an exception code
+    fn execute(&self, pdu: &[u8]) -> Result<Vec<u8>, u8> {
+        let function = pdu[0];
+        let word = |i: usize| {
+            pdu.get(i..i + 2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .ok_or(ILLEGAL_DATA_VALUE)
+        };
+        let mut map = self.map.lock().unwrap();
+
+        match function {
+            0x01 | 0x02 => {
+                let (start, count) = (word(1)?, word(3)?);
+                check_count(count, 2000)?;
+                let table = if function == 0x01 { &map.coils } else { &map.discrete_inputs };
+                let bits = range(table, start, count)?;
+                let mut reply = vec![function, bits.len().div_ceil(8) as u8];
+                reply.extend(pack_bits(bits));
+                Ok(reply)
+            }
+            0x03 | 0x04 => {
+                let (start, count) = (word(1)?, word(3)?);
+                check_count(count, 125)?;
+                let table = if function == 0x03 { &map.holding_registers } else { &map.input_registers };
+                let registers = range(table, start, count)?;
+                let mut reply = vec![function, (registers.len() * 2) as u8];
+                reply.extend(registers.iter().flat_map(|r| r.to_be_bytes()));
+                Ok(reply)
+            }
+            0x05 => {
+                let (address, value) = (word(1)?, word(3)?);
+                let on = match value {
+                    0xFF00 => true,
+                    0x0000 => false,
+                    _ => return Err(ILLEGAL_DATA_VALUE),
+                };
+                range_mut(&mut map.coils, address, 1)?[0] = on;
+                Ok(pdu[..5].to_vec())
+            }
+            0x06 => {
+                let (address, value) = (word(1)?, word(3)?);
+                range_mut(&mut map.holding_registers, address, 1)?[0] = value;
+                Ok(pdu[..5].to_vec())
+            }
+            0x0F => {
+                let (start, count) = (word(1)?, word(3)?);
+                check_count(count, 1968)?;
+                let data = pdu.get(6..).ok_or(ILLEGAL_DATA_VALUE)?;
+                if pdu[5] as usize != (count as usize).div_ceil(8) || data.len() != pdu[5] as usize {
+                    return Err(ILLEGAL_DATA_VALUE);
+                }
+                for (i, coil) in range_mut(&mut map.coils, start, count)?.iter_mut().enumerate() {
+                    *coil = data[i / 8] & (1 << (i % 8)) != 0;
+                }
+                Ok(pdu[..5].to_vec())
+            }
+            0x10 => {
+                let (start, count) = (word(1)?, word(3)?);
+                check_count(count, 123)?;
+                let data = pdu.get(6..).ok_or(ILLEGAL_DATA_VALUE)?;
+                if pdu[5] as usize != count as usize * 2 || data.len() != pdu[5] as usize {
+                    return Err(ILLEGAL_DATA_VALUE);
+                }
+                let registers = range_mut(&mut map.holding_registers, start, count)?;
+                for (register, bytes) in registers.iter_mut().zip(data.chunks(2)) {
+                    *register = u16::from_be_bytes([bytes[0], bytes[1]]);
+                }
+                Ok(pdu[..5].to_vec())
+            }
+            _ => Err(ILLEGAL_FUNCTION),
+        }
+    }
+}
+
+fn check_count(count: u16, max: u16) -> Result<(), u8> {
+    if (1..=max).contains(&count) {
+        Ok(())
+    } else {
+        Err(ILLEGAL_DATA_VALUE)
+    }
+}
+
+fn range<T>(table: &[T], start: u16, count: u16) -> Result<&[T], u8> {
+    table
+        .get(start as usize..start as usize + count as usize)
+        .ok_or(ILLEGAL_DATA_ADDRESS)
+}
+
+fn range_mut<T>(table: &mut [T], start: u16, count: u16) -> Result<&mut [T], u8> {
+    table
+        .get_mut(start as usize..start as usize + count as usize)
+        .ok_or(ILLEGAL_DATA_ADDRESS)
+}
+
+/// Coils and inputs are packed eight to a byte, lowest address in the lowest bit
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|byte| byte.iter().enumerate().fold(0u8, |acc, (i, &on)| acc | ((on as u8) << i)))
+        .collect()
+}
@@ -0,0 +1,683 @@
+//! Redis server: an in-memory keyspace shared by all connections of a listener, speaking RESP2
+//! (RESP3 after `HELLO 3`). Commands arrive as RESP arrays or inline text lines, so both the
+//! app's hex and text modes get real replies, and pipelined commands are answered in order.
+//!
+//! Supported: strings (`GET`, `SET` with `EX`/`PX`/`NX`/`XX`/`KEEPTTL`, `INCR`…), lists
+//! (`LPUSH`, `LRANGE`…), hashes (`HSET`, `HGETALL`…), expiry (`EXPIRE`, `TTL`…), key
+//! commands and `PING`/`ECHO`/`HELLO`/`SELECT`/`QUIT`. Expired keys are dropped when touched.
+
+use crate::Stream;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Longest bulk string accepted
+const MAX_BULK: usize = 64 * 1024 * 1024;
+const MAX_INLINE: usize = 64 * 1024;
+
+const WRONG_TYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+const NOT_INTEGER: &str = "ERR value is not an integer or out of range";
+const SYNTAX: &str = "ERR syntax error";
+
+type List = VecDeque<Vec<u8>>;
+type Hash = BTreeMap<Vec<u8>, Vec<u8>>;
+/// Command name and arguments
+type Command = Vec<Vec<u8>>;
+
+enum Value {
+    String(Vec<u8>),
+    List(List),
+    Hash(Hash),
+}
+
+struct Entry {
+    value: Value,
+    expires: Option<Instant>,
+}
+
+impl Entry {
+    fn expired(&self, now: Instant) -> bool {
+        self.expires.is_some_and(|t| t <= now)
+    }
+}
+
+type Keys = HashMap<Vec<u8>, Entry>;
+
+/// The keyspace of one listener
+#[derive(Default)]
+pub struct Store {
+    keys: Mutex<Keys>,
+}
+
+enum Reply {
+    Simple(&'static str),
+    Error(String),
+    Integer(i64),
+    Bulk(Vec<u8>),
+    Null,
+    NullArray,
+    Array(Vec<Reply>),
+    /// A flat array in RESP2
+    Map(Vec<(Reply, Reply)>),
+}
+
+impl Reply {
+    fn error(message: &str) -> Reply {
+        Reply::Error(message.to_string())
+    }
+
+    fn bulk_or_null(value: Option<impl AsRef<[u8]>>) -> Reply {
+        value.map_or(Reply::Null, |v| Reply::Bulk(v.as_ref().to_vec()))
+    }
+
+    fn encode(&self, resp3: bool, out: &mut Vec<u8>) {
+        match self {
+            Reply::Simple(text) => out.extend_from_slice(format!("+{}\r\n", text).as_bytes()),
+            Reply::Error(text) => out.extend_from_slice(format!("-{}\r\n", text).as_bytes()),
+            Reply::Integer(n) => out.extend_from_slice(format!(":{}\r\n", n).as_bytes()),
+            Reply::Bulk(data) => {
+                out.extend_from_slice(format!("${}\r\n", data.len()).as_bytes());
+                out.extend_from_slice(data);
+                out.extend_from_slice(b"\r\n");
+            }
+            Reply::Null if resp3 => out.extend_from_slice(b"_\r\n"),
+            Reply::Null => out.extend_from_slice(b"$-1\r\n"),
+            Reply::NullArray if resp3 => out.extend_from_slice(b"_\r\n"),
+            Reply::NullArray => out.extend_from_slice(b"*-1\r\n"),
+            Reply::Array(items) => {
+                out.extend_from_slice(format!("*{}\r\n", items.len()).as_bytes());
+                for item in items {
+                    item.encode(resp3, out);
+                }
+            }
+            Reply::Map(pairs) => {
+                let header = if resp3 { format!("%{}\r\n", pairs.len()) } else { format!("*{}\r\n", pairs.len() * 2) };
+                out.extend_from_slice(header.as_bytes());
+                for (key, value) in pairs {
+                    key.encode(resp3, out);
+                    value.encode(resp3, out);
+                }
+            }
+        }
+    }
+}
+
+/// Per-connection protocol state
+#[derive(Default)]
+struct Session {
+    resp3: bool,
+}
+
+pub fn prepare() -> Arc<Store> {
+    println!("🗄️  Redis server: in-memory keyspace, RESP2/RESP3");
+    Arc::new(Store::default())
+}
+
+pub async fn handle_connection(mut stream: Box<dyn Stream>, store: Arc<Store>) {
+    let mut session = Session::default();
+    let mut buffer = Vec::new();
+    let mut chunk = vec![0u8; 4096];
+    loop {
+        match stream.read(&mut chunk).await {
+            Ok(0) => break,
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            Err(e) => {
+                eprintln!("   ❌ Read error: {}", e);
+                break;
+            }
+        }
+
+        // Answer every complete command in one write, keeping pipelined replies in order
+        let mut out = Vec::new();
+        let mut quit = false;
+        loop {
+            let (args, used) = match parse_command(&buffer) {
+                Ok(Some(command)) => command,
+                Ok(None) => break,
+                Err(e) => {
+                    Reply::Error(format!("ERR Protocol error: {}", e)).encode(session.resp3, &mut out);
+                    quit = true;
+                    break;
+                }
+            };
+            buffer.drain(..used);
+            if args.is_empty() {
+                continue;
+            }
+            println!("   📨 {}", preview(&args));
+            let reply = store.execute(&mut session, &args);
+            reply.encode(session.resp3, &mut out);
+            if args[0].eq_ignore_ascii_case(b"QUIT") {
+                quit = true;
+                break;
+            }
+        }
+
+        if !out.is_empty() {
+            if let Err(e) = stream.write_all(&out).await {
+                eprintln!("   ❌ Send error: {}", e);
+                break;
+            }
+        }
+        if quit {
+            break;
+        }
+    }
+}
+
+fn preview(args: &[Vec<u8>]) -> String {
+    let text = args.iter().map(|a| String::from_utf8_lossy(a)).collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(60) {
+        Some((i, _)) => format!("{} …", &text[..i]),
+        None => text,
+    }
+}
+
+/// The command at the front of `buffer` and the bytes it takes, `None` while incomplete
+fn parse_command(buffer: &[u8]) -> Result<Option<(Command, usize)>, String> {
+    if buffer.is_empty() {
+        return Ok(None);
+    }
+    if buffer[0] != b'*' {
+        // Inline command, as typed in a terminal
+        let Some(end) = buffer.iter().position(|&b| b == b'\n') else {
+            return if buffer.len() > MAX_INLINE { Err("too big inline request".into()) } else { Ok(None) };
+        };
+        let line = String::from_utf8_lossy(&buffer[..end]);
+        let args = line.split_whitespace().map(|a| a.as_bytes().to_vec()).collect();
+        return Ok(Some((args, end + 1)));
+    }
+
+    let Some((count, mut pos)) = number_line(buffer, 1)? else {
+        return Ok(None);
+    };
+    let mut args = Vec::new();
+    for _ in 0..count.max(0) {
+        if pos >= buffer.len() {
+            return Ok(None);
+        }
+        if buffer[pos] != b'$' {
+            return Err(format!("expected '$', got '{}'", buffer[pos] as char));
+        }
+        let Some((len, start)) = number_line(buffer, pos + 1)? else {
+            return Ok(None);
+        };
+        let len = usize::try_from(len).ok().filter(|&l| l <= MAX_BULK).ok_or("invalid bulk length")?;
+        if buffer.len() < start + len + 2 {
+            return Ok(None);
+        }
+        if &buffer[start + len..start + len + 2] != b"\r\n" {
+            return Err("bulk string not terminated by CRLF".into());
+        }
+        args.push(buffer[start..start + len].to_vec());
+        pos = start + len + 2;
+    }
+    Ok(Some((args, pos)))
+}
+
+/// A number ending in CRLF at `start`, and where the next line begins
+fn number_line(buffer: &[u8], start: usize) -> Result<Option<(i64, usize)>, String> {
+    let Some(end) = buffer[start..].windows(2).position(|w| w == b"\r\n") else {
+        return if buffer.len() - start > 32 { Err("invalid multibulk length".into()) } else { Ok(None) };
+    };
+    let text = std::str::from_utf8(&buffer[start..start + end]).map_err(|_| "invalid length")?;
+    let n = text.parse().map_err(|_| format!("invalid length '{}'", text))?;
+    Ok(Some((n, start + end + 2)))
+}
+
+impl Store {
+    fn execute(&self, session: &mut Session, args: &[Vec<u8>]) -> Reply {
+        let name = String::from_utf8_lossy(&args[0]).to_lowercase();
+        let mut keys = self.keys.lock().unwrap();
+        run(&mut keys, session, &name, &args[1..]).unwrap_or_else(|e| e)
+    }
+}
+
+fn run(keys: &mut Keys, session: &mut Session, name: &str, a: &[Vec<u8>]) -> Result<Reply, Reply> {
+    let wrong_args = || Reply::Error(format!("ERR wrong number of arguments for '{}' command", name));
+    let arity = |min: usize, max: Option<usize>| {
+        if a.len() < min || max.is_some_and(|max| a.len() > max) {
+            Err(wrong_args())
+        } else {
+            Ok(())
+        }
+    };
+
+    let reply = match name {
+        // Connection
+        "ping" => {
+            arity(0, Some(1))?;
+            match a.first() {
+                Some(message) => Reply::Bulk(message.clone()),
+                None => Reply::Simple("PONG"),
+            }
+        }
+        "echo" => {
+            arity(1, Some(1))?;
+            Reply::Bulk(a[0].clone())
+        }
+        "hello" => {
+            if let Some(version) = a.first() {
+                match int(version)? {
+                    2 => session.resp3 = false,
+                    3 => session.resp3 = true,
+                    _ => return Err(Reply::error("NOPROTO unsupported protocol version")),
+                }
+            }
+            let text = |s: &str| Reply::Bulk(s.as_bytes().to_vec());
+            Reply::Map(vec![
+                (text("server"), text("redis")),
+                (text("version"), text("7.0.0")),
+                (text("proto"), Reply::Integer(if session.resp3 { 3 } else { 2 })),
+                (text("id"), Reply::Integer(1)),
+                (text("mode"), text("standalone")),
+                (text("role"), text("master")),
+                (text("modules"), Reply::Array(Vec::new())),
+            ])
+        }
+        "select" => {
+            arity(1, Some(1))?;
+            match int(&a[0])? {
+                0 => Reply::Simple("OK"),
+                _ => return Err(Reply::error("ERR DB index is out of range")),
+            }
+        }
+        "quit" | "client" => Reply::Simple("OK"),
+        "command" => Reply::Array(Vec::new()),
+
+        // Keys
+        "del" | "unlink" => {
+            arity(1, None)?;
+            let removed = a.iter().filter(|k| live(keys, k).is_some() && keys.remove(*k).is_some()).count();
+            Reply::Integer(removed as i64)
+        }
+        "exists" => {
+            arity(1, None)?;
+            Reply::Integer(a.iter().filter(|k| live(keys, k).is_some()).count() as i64)
+        }
+        "type" => {
+            arity(1, Some(1))?;
+            Reply::Simple(match live(keys, &a[0]).map(|e| &e.value) {
+                Some(Value::String(_)) => "string",
+                Some(Value::List(_)) => "list",
+                Some(Value::Hash(_)) => "hash",
+                None => "none",
+            })
+        }
+        "keys" => {
+            arity(1, Some(1))?;
+            let now = Instant::now();
+            let mut found: Vec<&Vec<u8>> = keys
+                .iter()
+                .filter(|(k, e)| !e.expired(now) && glob(&a[0], k))
+                .map(|(k, _)| k)
+                .collect();
+            found.sort();
+            Reply::Array(found.into_iter().map(|k| Reply::Bulk(k.clone())).collect())
+        }
+        "dbsize" => {
+            let now = Instant::now();
+            keys.retain(|_, e| !e.expired(now));
+            Reply::Integer(keys.len() as i64)
+        }
+        "flushdb" | "flushall" => {
+            keys.clear();
+            Reply::Simple("OK")
+        }
+        "expire" | "pexpire" => {
+            arity(2, Some(2))?;
+            let amount = int(&a[1])?;
+            let after = if name == "expire" { amount.saturating_mul(1000) } else { amount };
+            match live(keys, &a[0]) {
+                Some(_) if after <= 0 => {
+                    keys.remove(&a[0]);
+                    Reply::Integer(1)
+                }
+                Some(entry) => {
+                    entry.expires = Some(expire_at(amount, name == "expire", name)?);
+                    Reply::Integer(1)
+                }
+                None => Reply::Integer(0),
+            }
+        }
+        "ttl" | "pttl" => {
+            arity(1, Some(1))?;
+            match live(keys, &a[0]) {
+                Some(Entry { expires: Some(at), .. }) => {
+                    let left = at.saturating_duration_since(Instant::now()).as_millis() as i64;
+                    Reply::Integer(if name == "ttl" { (left + 500) / 1000 } else { left })
+                }
+                Some(_) => Reply::Integer(-1),
+                None => Reply::Integer(-2),
+            }
+        }
+        "persist" => {
+            arity(1, Some(1))?;
+            match live(keys, &a[0]) {
+                Some(entry) => Reply::Integer(entry.expires.take().is_some() as i64),
+                None => Reply::Integer(0),
+            }
+        }
+
+        // Strings
+        "get" => {
+            arity(1, Some(1))?;
+            Reply::bulk_or_null(string(keys, &a[0])?)
+        }
+        "getdel" => {
+            arity(1, Some(1))?;
+            let value = string(keys, &a[0])?.cloned();
+            keys.remove(&a[0]);
+            Reply::bulk_or_null(value)
+        }
+        "set" => {
+            arity(2, None)?;
+            let (mut expires, mut nx, mut xx, mut keep_ttl) = (None, false, false, false);
+            let mut options = a[2..].iter();
+            while let Some(option) = options.next() {
+                match option.to_ascii_uppercase().as_slice() {
+                    b"NX" => nx = true,
+                    b"XX" => xx = true,
+                    b"KEEPTTL" => keep_ttl = true,
+                    unit @ (b"EX" | b"PX") => {
+                        let amount = int(options.next().ok_or_else(|| Reply::error(SYNTAX))?)?;
+                        if amount <= 0 {
+                            return Err(Reply::error("ERR invalid expire time in 'set' command"));
+                        }
+                        expires = Some(expire_at(amount, unit == b"EX", name)?);
+                    }
+                    _ => return Err(Reply::error(SYNTAX)),
+                }
+            }
+            if (nx && xx) || (keep_ttl && expires.is_some()) {
+                return Err(Reply::error(SYNTAX));
+            }
+            let existing = live(keys, &a[0]);
+            if (nx && existing.is_some()) || (xx && existing.is_none()) {
+                Reply::Null
+            } else {
+                if keep_ttl {
+                    expires = existing.and_then(|e| e.expires);
+                }
+                keys.insert(a[0].clone(), Entry { value: Value::String(a[1].clone()), expires });
+                Reply::Simple("OK")
+            }
+        }
+        "setex" => {
+            arity(3, Some(3))?;
+            let seconds = int(&a[1])?;
+            if seconds <= 0 {
+                return Err(Reply::error("ERR invalid expire time in 'setex' command"));
+            }
+            let expires = Some(expire_at(seconds, true, name)?);
+            keys.insert(a[0].clone(), Entry { value: Value::String(a[2].clone()), expires });
+            Reply::Simple("OK")
+        }
+        "mget" => {
+            arity(1, None)?;
+            let values = a.iter().map(|k| match live(keys, k).map(|e| &e.value) {
+                Some(Value::String(v)) => Reply::Bulk(v.clone()),
+                _ => Reply::Null,
+            });
+            Reply::Array(values.collect())
+        }
+        "mset" => {
+            if a.is_empty() || !a.len().is_multiple_of(2) {
+                return Err(wrong_args());
+            }
+            for pair in a.chunks(2) {
+                keys.insert(pair[0].clone(), Entry { value: Value::String(pair[1].clone()), expires: None });
+            }
+            Reply::Simple("OK")
+        }
+        "incr" | "decr" | "incrby" | "decrby" => {
+            let by_arg = name.ends_with("by");
+            arity(if by_arg { 2 } else { 1 }, Some(if by_arg { 2 } else { 1 }))?;
+            let step = if by_arg { int(&a[1])? } else { 1 };
+            let step = if name.starts_with("decr") { step.checked_neg().ok_or_else(|| Reply::error(NOT_INTEGER))? } else { step };
+            let current = match string(keys, &a[0])? {
+                Some(value) => int(value)?,
+                None => 0,
+            };
+            let next = current
+                .checked_add(step)
+                .ok_or_else(|| Reply::error("ERR increment or decrement would overflow"))?;
+            let expires = live(keys, &a[0]).and_then(|e| e.expires);
+            keys.insert(a[0].clone(), Entry { value: Value::String(next.to_string().into_bytes()), expires });
+            Reply::Integer(next)
+        }
+        "append" => {
+            arity(2, Some(2))?;
+            string(keys, &a[0])?;
+            let entry = keys
+                .entry(a[0].clone())
+                .or_insert_with(|| Entry { value: Value::String(Vec::new()), expires: None });
+            let Value::String(value) = &mut entry.value else { unreachable!() };
+            value.extend_from_slice(&a[1]);
+            Reply::Integer(value.len() as i64)
+        }
+        "strlen" => {
+            arity(1, Some(1))?;
+            Reply::Integer(string(keys, &a[0])?.map_or(0, |v| v.len()) as i64)
+        }
+
+        // Lists
+        "lpush" | "rpush" => {
+            arity(2, None)?;
+            let list = list_or_new(keys, &a[0])?;
+            for value in &a[1..] {
+                if name == "lpush" {
+                    list.push_front(value.clone());
+                } else {
+                    list.push_back(value.clone());
+                }
+            }
+            Reply::Integer(list.len() as i64)
+        }
+        "lpop" | "rpop" => {
+            arity(1, Some(2))?;
+            let count = a.get(1).map(|c| int(c)).transpose()?;
+            if count.is_some_and(|c| c < 0) {
+                return Err(Reply::error("ERR value is out of range, must be positive"));
+            }
+            let Some(list) = list(keys, &a[0])? else {
+                return Ok(if count.is_some() { Reply::NullArray } else { Reply::Null });
+            };
+            let mut pop = || if name == "lpop" { list.pop_front() } else { list.pop_back() };
+            let reply = match count {
+                Some(count) => Reply::Array((0..count).map_while(|_| pop()).map(Reply::Bulk).collect()),
+                None => pop().map_or(Reply::Null, Reply::Bulk),
+            };
+            remove_if_empty(keys, &a[0]);
+            reply
+        }
+        "llen" => {
+            arity(1, Some(1))?;
+            Reply::Integer(list(keys, &a[0])?.map_or(0, |l| l.len()) as i64)
+        }
+        "lrange" => {
+            arity(3, Some(3))?;
+            let (start, stop) = (int(&a[1])?, int(&a[2])?);
+            let items = match list(keys, &a[0])? {
+                Some(list) => match span(list.len(), start, stop) {
+                    Some((from, to)) => list.range(from..=to).map(|v| Reply::Bulk(v.clone())).collect(),
+                    None => Vec::new(),
+                },
+                None => Vec::new(),
+            };
+            Reply::Array(items)
+        }
+        "lindex" => {
+            arity(2, Some(2))?;
+            let index = int(&a[1])?;
+            let value = list(keys, &a[0])?.and_then(|list| {
+                let i = if index < 0 { list.len() as i64 + index } else { index };
+                usize::try_from(i).ok().and_then(|i| list.get(i)).cloned()
+            });
+            Reply::bulk_or_null(value)
+        }
+
+        // Hashes
+        "hset" | "hmset" => {
+            if a.len() < 3 || a.len().is_multiple_of(2) {
+                return Err(wrong_args());
+            }
+            let hash = hash_or_new(keys, &a[0])?;
+            let added = a[1..].chunks(2).filter(|pair| hash.insert(pair[0].clone(), pair[1].clone()).is_none()).count();
+            if name == "hmset" { Reply::Simple("OK") } else { Reply::Integer(added as i64) }
+        }
+        "hget" => {
+            arity(2, Some(2))?;
+            Reply::bulk_or_null(hash(keys, &a[0])?.and_then(|h| h.get(&a[1])))
+        }
+        "hdel" => {
+            arity(2, None)?;
+            let removed = match hash(keys, &a[0])? {
+                Some(hash) => a[1..].iter().filter(|f| hash.remove(*f).is_some()).count(),
+                None => 0,
+            };
+            remove_if_empty(keys, &a[0]);
+            Reply::Integer(removed as i64)
+        }
+        "hexists" => {
+            arity(2, Some(2))?;
+            Reply::Integer(hash(keys, &a[0])?.is_some_and(|h| h.contains_key(&a[1])) as i64)
+        }
+        "hlen" => {
+            arity(1, Some(1))?;
+            Reply::Integer(hash(keys, &a[0])?.map_or(0, |h| h.len()) as i64)
+        }
+        "hgetall" => {
+            arity(1, Some(1))?;
+            let pairs = hash(keys, &a[0])?
+                .map(|h| h.iter().map(|(k, v)| (Reply::Bulk(k.clone()), Reply::Bulk(v.clone()))).collect())
+                .unwrap_or_default();
+            Reply::Map(pairs)
+        }
+        "hkeys" | "hvals" => {
+            arity(1, Some(1))?;
+            let items = match hash(keys, &a[0])? {
+                Some(h) if name == "hkeys" => h.keys().map(|k| Reply::Bulk(k.clone())).collect(),
+                Some(h) => h.values().map(|v| Reply::Bulk(v.clone())).collect(),
+                None => Vec::new(),
+            };
+            Reply::Array(items)
+        }
+        "hincrby" => {
+            arity(3, Some(3))?;
+            let step = int(&a[2])?;
+            let hash = hash_or_new(keys, &a[0])?;
+            let current = hash.get(&a[1]).map(|v| int(v)).transpose()?.unwrap_or(0);
+            let next = current
+                .checked_add(step)
+                .ok_or_else(|| Reply::error("ERR increment or decrement would overflow"))?;
+            hash.insert(a[1].clone(), next.to_string().into_bytes());
+            Reply::Integer(next)
+        }
+
+        _ => {
+            let args = a.iter().map(|arg| format!("'{}' ", String::from_utf8_lossy(arg))).collect::<String>();
+            Reply::Error(format!("ERR unknown command '{}', with args beginning with: {}", name, args))
+        }
+    };
+    Ok(reply)
+}
+
+fn int(arg: &[u8]) -> Result<i64, Reply> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| Reply::error(NOT_INTEGER))
+}
+
+/// When a key given a positive `amount` of seconds or milliseconds to live expires
+fn expire_at(amount: i64, seconds: bool, command: &str) -> Result<Instant, Reply> {
+    let ms = if seconds { amount.checked_mul(1000) } else { Some(amount) };
+    ms.and_then(|ms| Instant::now().checked_add(Duration::from_millis(ms as u64)))
+        .ok_or_else(|| Reply::error(&format!("ERR invalid expire time in '{}' command", command)))
+}
+
+/// The entry under `key`, dropping it first when it has expired
+fn live<'a>(keys: &'a mut Keys, key: &[u8]) -> Option<&'a mut Entry> {
+    if keys.get(key).is_some_and(|e| e.expired(Instant::now())) {
+        keys.remove(key);
+    }
+    keys.get_mut(key)
+}
+
+fn string<'a>(keys: &'a mut Keys, key: &[u8]) -> Result<Option<&'a mut Vec<u8>>, Reply> {
+    match live(keys, key).map(|e| &mut e.value) {
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(Reply::error(WRONG_TYPE)),
+        None => Ok(None),
+    }
+}
+
+fn list<'a>(keys: &'a mut Keys, key: &[u8]) -> Result<Option<&'a mut List>, Reply> {
+    match live(keys, key).map(|e| &mut e.value) {
+        Some(Value::List(list)) => Ok(Some(list)),
+        Some(_) => Err(Reply::error(WRONG_TYPE)),
+        None => Ok(None),
+    }
+}
+
+fn hash<'a>(keys: &'a mut Keys, key: &[u8]) -> Result<Option<&'a mut Hash>, Reply> {
+    match live(keys, key).map(|e| &mut e.value) {
+        Some(Value::Hash(hash)) => Ok(Some(hash)),
+        Some(_) => Err(Reply::error(WRONG_TYPE)),
+        None => Ok(None),
+    }
+}
+
+fn list_or_new<'a>(keys: &'a mut Keys, key: &[u8]) -> Result<&'a mut List, Reply> {
+    live(keys, key);
+    let entry = keys
+        .entry(key.to_vec())
+        .or_insert_with(|| Entry { value: Value::List(List::new()), expires: None });
+    match &mut entry.value {
+        Value::List(list) => Ok(list),
+        _ => Err(Reply::error(WRONG_TYPE)),
+    }
+}
+
+fn hash_or_new<'a>(keys: &'a mut Keys, key: &[u8]) -> Result<&'a mut Hash, Reply> {
+    live(keys, key);
+    let entry = keys
+        .entry(key.to_vec())
+        .or_insert_with(|| Entry { value: Value::Hash(Hash::new()), expires: None });
+    match &mut entry.value {
+        Value::Hash(hash) => Ok(hash),
+        _ => Err(Reply::error(WRONG_TYPE)),
+    }
+}
+
+/// Lists and hashes disappear with their last element
+fn remove_if_empty(keys: &mut Keys, key: &[u8]) {
+    let empty = match keys.get(key).map(|e| &e.value) {
+        Some(Value::List(list)) => list.is_empty(),
+        Some(Value::Hash(hash)) => hash.is_empty(),
+        _ => false,
+    };
+    if empty {
+        keys.remove(key);
+    }
+}
+
+/// Inclusive index range of `LRANGE`, with negative indexes counted from the end
+fn span(len: usize, start: i64, stop: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { (len + start).max(0) } else { start };
+    let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
+
+/// `KEYS` pattern with `*` and `?`
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+        Some((b'?', rest)) => !text.is_empty() && glob(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
@@ -0,0 +1,172 @@
+//! SMTP session: `HELO`/`EHLO`, then `MAIL FROM`, `RCPT TO` and `DATA` in that order.
+//! Delivered mail is written to the mail directory as `.eml` files with the envelope
+//! in `Return-Path` and `Delivered-To` headers; without a directory it is only logged.
+
+use crate::line::{self, Reply};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Biggest message accepted, announced as `SIZE`
+const MAX_MESSAGE: usize = 10 * 1024 * 1024;
+
+/// Where the mail of one listener goes
+pub struct Mailbox {
+    dir: Option<PathBuf>,
+    delivered: AtomicU64,
+}
+
+pub fn prepare(dir: Option<&str>) -> anyhow::Result<Arc<Mailbox>> {
+    let dir = dir.map(PathBuf::from);
+    if let Some(dir) = &dir {
+        std::fs::create_dir_all(dir).map_err(|e| anyhow::anyhow!("{}: {}", dir.display(), e))?;
+        println!("📬 SMTP server: saving mail to {}", dir.display());
+    } else {
+        println!("📬 SMTP server: mail is logged, not saved (no mail directory)");
+    }
+    Ok(Arc::new(Mailbox { dir, delivered: AtomicU64::new(0) }))
+}
+
+impl Mailbox {
+    /// Save a message, returning its queue id
+    fn deliver(&self, from: &str, to: &[String], body: &str) -> std::io::Result<String> {
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let id = format!("{}-{}", millis, self.delivered.fetch_add(1, Ordering::Relaxed) + 1);
+        if let Some(dir) = &self.dir {
+            let mut mail = format!("Return-Path: <{}>\r\n", from);
+            for rcpt in to {
+                mail.push_str(&format!("Delivered-To: {}\r\n", rcpt));
+            }
+            mail.push_str(body);
+            std::fs::write(dir.join(format!("{}.eml", id)), mail)?;
+        }
+        Ok(id)
+    }
+}
+
+enum State {
+    Connected,
+    Greeted,
+    /// `MAIL FROM` accepted, collecting recipients
+    Mail { from: String, to: Vec<String> },
+    /// After `DATA`, until the line with a single dot
+    Data { from: String, to: Vec<String>, body: String },
+}
+
+pub struct Session {
+    mailbox: Arc<Mailbox>,
+    state: State,
+}
+
+impl Session {
+    pub fn new(mailbox: Arc<Mailbox>) -> Session {
+        Session { mailbox, state: State::Connected }
+    }
+
+    fn data_line(&mut self, line: &str) -> Reply {
+        let State::Data { from, to, body } = &mut self.state else {
+            unreachable!()
+        };
+        if line != "." {
+            // Past the limit the rest is read but dropped, and the message is refused at the end
+            if body.len() <= MAX_MESSAGE {
+                // Lines starting with a dot arrive with it doubled
+                body.push_str(line.strip_prefix('.').filter(|l| l.starts_with('.')).unwrap_or(line));
+                body.push_str("\r\n");
+            }
+            return Reply::none();
+        }
+
+        let (from, to, body) = (std::mem::take(from), std::mem::take(to), std::mem::take(body));
+        self.state = State::Greeted;
+        if body.len() > MAX_MESSAGE {
+            return Reply::new(552, "5.3.4 Message size exceeds fixed limit");
+        }
+        match self.mailbox.deliver(&from, &to, &body) {
+            Ok(id) => {
+                println!("   📧 Mail {} from <{}> to {} ({} bytes)", id, from, to.join(", "), body.len());
+                Reply::new(250, &format!("2.0.0 Ok: queued as {}", id))
+            }
+            Err(e) => {
+                eprintln!("   ❌ Cannot save mail: {}", e);
+                Reply::new(451, "4.3.0 Local error in processing")
+            }
+        }
+    }
+}
+
+impl line::Session for Session {
+    fn banner(&self) -> Reply {
+        Reply::new(220, "tcp-test-server ESMTP ready")
+    }
+
+    fn line(&mut self, line: &str) -> Reply {
+        if matches!(self.state, State::Data { .. }) {
+            return self.data_line(line);
+        }
+        println!("   📨 {}", line);
+
+        let (verb, arg) = line::split_command(line);
+        match verb.as_str() {
+            "HELO" | "EHLO" if arg.is_empty() => Reply::new(501, "5.5.4 Syntax: HELO hostname"),
+            "HELO" => {
+                self.state = State::Greeted;
+                Reply::new(250, "tcp-test-server")
+            }
+            "EHLO" => {
+                self.state = State::Greeted;
+                let size = format!("SIZE {}", MAX_MESSAGE);
+                Reply::multi(250, &[&format!("tcp-test-server Hello {}", arg), &size, "8BITMIME", "HELP"])
+            }
+            "MAIL" => match (&self.state, address(arg, "FROM:")) {
+                (State::Connected, _) => Reply::new(503, "5.5.1 Error: send HELO/EHLO first"),
+                (State::Mail { .. }, _) => Reply::new(503, "5.5.1 Error: nested MAIL command"),
+                (_, None) => Reply::new(501, "5.5.4 Syntax: MAIL FROM:<address>"),
+                (_, Some(from)) => {
+                    self.state = State::Mail { from, to: Vec::new() };
+                    Reply::new(250, "2.1.0 Ok")
+                }
+            },
+            "RCPT" => match (&mut self.state, address(arg, "TO:")) {
+                (State::Mail { .. }, None) => Reply::new(501, "5.5.4 Syntax: RCPT TO:<address>"),
+                (State::Mail { .. }, Some(rcpt)) if rcpt.is_empty() => {
+                    Reply::new(501, "5.1.3 Bad recipient address syntax")
+                }
+                (State::Mail { to, .. }, Some(rcpt)) => {
+                    to.push(rcpt);
+                    Reply::new(250, "2.1.5 Ok")
+                }
+                _ => Reply::new(503, "5.5.1 Error: need MAIL command"),
+            },
+            "DATA" => match &mut self.state {
+                State::Mail { to, .. } if to.is_empty() => Reply::new(503, "5.5.1 Error: need RCPT command"),
+                State::Mail { from, to } => {
+                    let (from, to) = (std::mem::take(from), std::mem::take(to));
+                    self.state = State::Data { from, to, body: String::new() };
+                    Reply::new(354, "End data with <CR><LF>.<CR><LF>")
+                }
+                _ => Reply::new(503, "5.5.1 Error: need RCPT command"),
+            },
+            "RSET" => {
+                if !matches!(self.state, State::Connected) {
+                    self.state = State::Greeted;
+                }
+                Reply::new(250, "2.0.0 Ok")
+            }
+            "NOOP" => Reply::new(250, "2.0.0 Ok"),
+            "VRFY" => Reply::new(252, "2.0.0 Cannot VRFY user"),
+            "QUIT" => Reply::new(221, "2.0.0 Bye").and_close(),
+            _ => Reply::new(500, "5.5.2 Error: command not recognized"),
+        }
+    }
+}
+
+/// The address in `FROM:<a@b>` or `TO:<a@b>`, ignoring parameters after it
+fn address(arg: &str, prefix: &str) -> Option<String> {
+    let rest = arg.get(..prefix.len()).filter(|p| p.eq_ignore_ascii_case(prefix)).map(|_| arg[prefix.len()..].trim())?;
+    let inner = rest.strip_prefix('<')?.split_once('>')?.0;
+    Some(inner.to_string())
+}
@@ -0,0 +1,27 @@
+//! TLS for listeners with a `tls` section (built with `--features tls`).
+
+use crate::config::TlsConfig;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+
+pub fn acceptor(config: &TlsConfig) -> anyhow::Result<TlsAcceptor> {
+    let open = |path: &str| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path, e))
+    };
+    let certs = rustls_pemfile::certs(&mut open(&config.cert)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("{}: {}", config.cert, e))?;
+    let key = rustls_pemfile::private_key(&mut open(&config.key)?)
+        .map_err(|e| anyhow::anyhow!("{}: {}", config.key, e))?
+        .ok_or_else(|| anyhow::anyhow!("{}: no private key", config.key))?;
+
+    let server = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(server)))
+}